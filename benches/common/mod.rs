@@ -0,0 +1,50 @@
+// Fixtures shared by the benches
+
+use bevy::core::TaskPoolOptions;
+use bevy::prelude::*;
+use bevy_mujoco::*;
+
+/// Free spheres in a grid above a floor, `bodies` independent bodies with contacts
+pub fn crowd(bodies: usize) -> MuJoCoSimulation {
+    let mut builder = MjcfBuilder::new()
+        .worldbody()
+        .add_geom("floor", "plane", &[50.0, 50.0, 0.1]);
+    for i in 0..bodies {
+        let (x, y) = ((i % 32) as f64 * 0.5, (i / 32) as f64 * 0.5);
+        builder = builder
+            .add_body(&format!("body_{i}"), [x, y, 0.5])
+            .add_freejoint()
+            .add_geom(&format!("geom_{i}"), "sphere", &[0.1])
+            .end();
+    }
+    builder.load().expect("benchmark model compiles")
+}
+
+/// Headless app stepping once per frame, so every frame publishes a state and syncs bodies.
+/// The scene is spawned before it's returned
+pub fn app(simulation: MuJoCoSimulation, task_pool_options: TaskPoolOptions) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins.set(TaskPoolPlugin { task_pool_options }))
+        .add_plugins((
+            AssetPlugin::default(),
+            TransformPlugin,
+            HierarchyPlugin,
+            bevy::gizmos::GizmoPlugin,
+        ))
+        .init_asset::<Mesh>()
+        .init_asset::<StandardMaterial>()
+        .insert_resource(MuJoCoPluginSettings {
+            spawn_mode: SpawnMode::TransformOnly,
+            step_mode: StepMode::Manual,
+            ..default()
+        })
+        .insert_resource(simulation)
+        .add_plugins(MuJoCoPlugin)
+        .add_systems(Update, |mut steps: EventWriter<MuJoCoStepRequest>| {
+            steps.send(MuJoCoStepRequest { n_steps: 1 });
+        });
+    for _ in 0..10 {
+        app.update();
+    }
+    app
+}
@@ -0,0 +1,54 @@
+// Measures the time of a MuJoCo step for scenes made of many independent piles of boxes,
+// stepping on one thread and with a thread pool solving constraint islands in parallel.
+// Needs the mujoco3 feature, without it every run is single-threaded.
+//
+// cargo bench --bench physics_threads --features mujoco3
+
+use bevy_mujoco::*;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+/// Piles of boxes that only touch the floor, each pile is one constraint island
+fn piles(count: usize) -> MuJoCoSimulation {
+    let mut builder = MjcfBuilder::new()
+        .worldbody()
+        .add_geom("floor", "plane", &[50.0, 50.0, 0.1]);
+    for pile in 0..count {
+        let (x, y) = ((pile % 16) as f64 * 1.0, (pile / 16) as f64 * 1.0);
+        for level in 0..4 {
+            builder = builder
+                .add_body(
+                    &format!("box_{pile}_{level}"),
+                    [x, y, 0.1 + level as f64 * 0.21],
+                )
+                .add_freejoint()
+                .add_geom(&format!("geom_{pile}_{level}"), "box", &[0.1, 0.1, 0.1])
+                .end();
+        }
+    }
+    builder.load().expect("benchmark model compiles")
+}
+
+fn physics_threads(c: &mut Criterion) {
+    let threads = std::thread::available_parallelism().map_or(4, |threads| threads.get());
+    let mut group = c.benchmark_group("physics_threads");
+    for count in [16, 64, 256] {
+        for threads in [1, threads] {
+            let mujoco = piles(count);
+            mujoco.set_physics_threads(threads);
+            let simulation = mujoco.lock().unwrap();
+            // let the piles settle into contact
+            for _ in 0..100 {
+                simulation.step();
+            }
+
+            let label = format!("{threads} threads");
+            group.bench_function(BenchmarkId::new(label, count), |b| {
+                b.iter(|| simulation.step())
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, physics_threads);
+criterion_main!(benches);
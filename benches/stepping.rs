@@ -0,0 +1,86 @@
+// Baselines for the cost of simulating with the plugin, on synthetic models of growing size:
+// raw `mj_step` throughput, extracting the state into caller-owned buffers, and a plugin
+// frame (one step plus transform sync) per body count.
+//
+// cargo bench --bench stepping
+
+mod common;
+
+use bevy::core::TaskPoolOptions;
+use bevy_mujoco::*;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use common::{app, crowd};
+
+/// A hinged chain of `links` capsules, one deep kinematic tree without contacts
+fn chain(links: usize) -> MuJoCoSimulation {
+    let mut builder = MjcfBuilder::new().worldbody();
+    for i in 0..links {
+        let z = if i == 0 { 2.0 } else { -0.1 };
+        builder = builder
+            .add_body(&format!("link_{i}"), [0.0, 0.0, z])
+            .add_joint(&format!("hinge_{i}"), "hinge", [0.0, 1.0, 0.0])
+            .add_geom(&format!("geom_{i}"), "capsule", &[0.02, 0.05]);
+    }
+    builder.load().expect("benchmark model compiles")
+}
+
+/// Models by name and size
+fn models() -> Vec<(&'static str, usize, fn(usize) -> MuJoCoSimulation)> {
+    vec![
+        ("crowd", 10, crowd),
+        ("crowd", 100, crowd),
+        ("crowd", 1000, crowd),
+        ("chain", 10, chain),
+        ("chain", 100, chain),
+    ]
+}
+
+fn mj_step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mj_step");
+    group.throughput(Throughput::Elements(1));
+    for (name, size, model) in models() {
+        let mujoco = model(size);
+        let simulation = mujoco.lock().unwrap();
+        group.bench_function(BenchmarkId::new(name, size), |b| {
+            b.iter(|| simulation.step())
+        });
+    }
+    group.finish();
+}
+
+fn state_extraction(c: &mut Criterion) {
+    let mut group = c.benchmark_group("state_extraction");
+    for (name, size, model) in models() {
+        let mujoco = model(size);
+        for _ in 0..10 {
+            mujoco.lock().unwrap().step();
+        }
+
+        let (mut qpos, mut qvel) = (vec![0.0; 7 * size], vec![0.0; 6 * size]);
+        let mut cfrc_ext = vec![[0.0; 6]; size + 1];
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_function(BenchmarkId::new(name, size), |b| {
+            b.iter(|| {
+                mujoco.copy_qpos_into(&mut qpos);
+                mujoco.copy_qvel_into(&mut qvel);
+                mujoco.copy_cfrc_ext_into(&mut cfrc_ext);
+            })
+        });
+    }
+    group.finish();
+}
+
+fn transform_sync(c: &mut Criterion) {
+    let mut group = c.benchmark_group("frame");
+    group.sample_size(30);
+    for (name, size, model) in models() {
+        let mut app = app(model(size), TaskPoolOptions::default());
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_function(BenchmarkId::new(name, size), |b| b.iter(|| app.update()));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, mj_step, state_extraction, transform_sync);
+criterion_main!(benches);
@@ -0,0 +1,40 @@
+// Measures frame time of the plugin's update systems for models with many free bodies.
+// Bevy's task pools are created once per process, so compare the serial and the parallel
+// (par_iter) sync in two runs, the first limited to one compute thread:
+//
+// TRANSFORM_SYNC_THREADS=1 cargo bench --bench transform_sync
+// cargo bench --bench transform_sync
+
+mod common;
+
+use bevy::core::TaskPoolOptions;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use common::{app, crowd};
+
+fn transform_sync(c: &mut Criterion) {
+    let threads = std::env::var("TRANSFORM_SYNC_THREADS")
+        .ok()
+        .and_then(|threads| threads.parse().ok());
+    let (label, task_pool_options) = match threads {
+        Some(threads) => (
+            format!("{threads} threads"),
+            TaskPoolOptions::with_num_threads(threads),
+        ),
+        None => ("all cores".to_string(), TaskPoolOptions::default()),
+    };
+
+    let mut group = c.benchmark_group("transform_sync");
+    group.sample_size(30);
+    for bodies in [100, 500, 1000] {
+        let mut app = app(crowd(bodies), task_pool_options.clone());
+        group.throughput(Throughput::Elements(bodies as u64));
+        group.bench_function(BenchmarkId::new(&label, bodies), |b| {
+            b.iter(|| app.update())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, transform_sync);
+criterion_main!(benches);
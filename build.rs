@@ -13,7 +13,61 @@ fn get_output_path() -> PathBuf {
     path
 }
 
+/// `mjVERSION_HEADER` of the installed headers, e.g. 235 for 2.3.5 and 312 for 3.1.2
+fn header_version(mj_root: &Path) -> Option<u32> {
+    let header =
+        fs::read_to_string(mj_root.join("include").join("mujoco").join("mujoco.h")).ok()?;
+    header.lines().find_map(|line| {
+        line.trim()
+            .strip_prefix("#define mjVERSION_HEADER")
+            .and_then(|version| version.trim().parse().ok())
+    })
+}
+
+/// Bindings to the MuJoCo 3.x headers, `mujoco-rs-sys` only describes the 2.x structs. They
+/// link the library in `lib` next to the headers
+#[cfg(feature = "mujoco3")]
+fn generate_bindings(mj_root: &Path) {
+    let include = mj_root.join("include");
+    bindgen::Builder::default()
+        .header(include.join("mujoco").join("mujoco.h").to_string_lossy())
+        .clang_arg(format!("-I{}", include.display()))
+        .allowlist_function("mj.*")
+        .allowlist_type("mj.*")
+        .allowlist_var("mj.*")
+        .prepend_enum_name(false)
+        .layout_tests(false)
+        .generate()
+        .expect("Unable to generate bindings for the MuJoCo 3.x headers")
+        .write_to_file(Path::new(&env::var("OUT_DIR").unwrap()).join("mujoco3.rs"))
+        .expect("Unable to write the MuJoCo 3.x bindings");
+
+    println!(
+        "cargo:rustc-link-search=native={}",
+        mj_root.join("lib").display()
+    );
+    println!("cargo:rustc-link-lib=dylib=mujoco");
+}
+
+#[cfg(not(feature = "mujoco3"))]
+fn generate_bindings(_mj_root: &Path) {}
+
 fn main() {
+    // `native` builds link MuJoCo; without it (`wasm` feature) the crate only plays back replays
+    println!("cargo:rustc-check-cfg=cfg(native)");
+    let native = env::var("CARGO_FEATURE_WASM").is_err()
+        && env::var("CARGO_CFG_TARGET_ARCH").map_or(true, |arch| arch != "wasm32");
+    if native {
+        println!("cargo:rustc-cfg=native");
+    }
+
+    // `mujoco3` is set when the feature of the same name generated bindings for MuJoCo 3.x
+    println!("cargo:rustc-check-cfg=cfg(mujoco3)");
+    let mut mujoco3 = false;
+    // `mujoco31` additionally when the headers are MuJoCo 3.1+, which added thread pools
+    println!("cargo:rustc-check-cfg=cfg(mujoco31)");
+    let mut mujoco31 = false;
+
     let (_, _, default_install) = match env::var("CARGO_CFG_UNIX") {
         Ok(_) => ("", "", ""),
         _ => match env::var("CARGO_CFG_WINDOWS") {
@@ -22,12 +76,29 @@ fn main() {
         },
     };
 
-    if option_env!("DOCS_RS").is_none() {
+    if native && option_env!("DOCS_RS").is_none() {
         let mj_root = match (env::var("MUJOCO_DIR"), env::var("MUJOCO_PREFIX")) {
             (Ok(dir), _) | (Err(..), Ok(dir)) => dir,
             (Err(..), Err(..)) => default_install.to_string(),
         };
         let mj_root = PathBuf::from_str(&mj_root).expect("Unable to get path");
+        let version = header_version(&mj_root);
+        if env::var("CARGO_FEATURE_MUJOCO3").is_ok() {
+            match version {
+                Some(version) if version >= 300 => {}
+                _ => panic!(
+                    "the mujoco3 feature needs MuJoCo 3.x headers in {}, found version {version:?}",
+                    mj_root.display()
+                ),
+            }
+            generate_bindings(&mj_root);
+            mujoco3 = true;
+            mujoco31 = version >= Some(310);
+        } else if let Some(version) = version.filter(|version| *version >= 300) {
+            println!(
+                "cargo:warning=MuJoCo {version} headers found, enable the mujoco3 feature to build for them"
+            );
+        }
         let mj_lib_windows = mj_root.join("bin");
 
         // Copy mujoco.dll to target directory on Windows targets
@@ -44,4 +115,11 @@ fn main() {
             std::fs::copy(src, dest).unwrap();
         }
     }
+
+    if mujoco3 {
+        println!("cargo:rustc-cfg=mujoco3");
+    }
+    if mujoco31 {
+        println!("cargo:rustc-cfg=mujoco31");
+    }
 }
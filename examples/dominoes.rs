@@ -0,0 +1,82 @@
+// This example simulates a scene of passive free bodies (dominoes, a stack of boxes and a ball)
+// without any actuators. Press D to push the first domino and B to throw the ball at the stack
+
+use bevy::prelude::*;
+use bevy_flycam::*;
+use bevy_mujoco::*;
+
+fn setup(mut commands: Commands) {
+    commands.spawn((
+        PointLight {
+            intensity: 9000.0,
+            range: 100.,
+            shadows_enabled: true,
+            ..default()
+        },
+        Transform::from_xyz(2.0, 6.0, 4.0),
+    ));
+
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(0.0, 2.0, 3.5).looking_at(Vec3::ZERO, Vec3::Y),
+        FlyCam,
+    ));
+}
+
+fn push(keys: Res<ButtonInput<KeyCode>>, mut mujoco: MuJoCoCommands) {
+    // impulses and points are in the bevy scene frame, MuJoCo's x axis is bevy's x axis
+    if keys.just_pressed(KeyCode::KeyD) {
+        mujoco.apply_impulse(
+            "domino_0",
+            Vec3::new(0.01, 0.0, 0.0),
+            Some(Vec3::new(-1.5, 0.18, 0.0)),
+        );
+    }
+    if keys.just_pressed(KeyCode::KeyB) {
+        mujoco.apply_impulse("ball", Vec3::new(3.0, 1.0, -3.0), None);
+    }
+}
+
+/// Log how many dominoes fell whenever the count changes
+fn count_fallen_dominoes(mujoco: Res<MuJoCoSimulation>, mut fallen: Local<usize>) {
+    let count = mujoco
+        .free_bodies()
+        .iter()
+        .filter(|body| body.name.starts_with("domino_"))
+        .filter(|body| {
+            // z component of the body's z axis
+            let [_, x, y, _] = body.quaternion;
+            1.0 - 2.0 * (x * x + y * y) < 0.7
+        })
+        .count();
+    if count != *fallen {
+        *fallen = count;
+        info!("{count} dominoes down");
+    }
+}
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .insert_resource(MuJoCoPluginSettings {
+            model_xml_path: "assets/mjcf/dominoes.xml".to_string(),
+            ..default()
+        })
+        .add_plugins(NoCameraPlayerPlugin)
+        .insert_resource(MovementSettings {
+            speed: 1.0,
+            ..default()
+        })
+        .add_plugins(MuJoCoPlugin)
+        .add_systems(Startup, setup)
+        .add_systems(
+            Update,
+            (
+                push.before(MuJoCoSystemSet::Control),
+                count_fallen_dominoes
+                    .after(MuJoCoSystemSet::Step)
+                    .run_if(resource_exists::<MuJoCoResources>),
+            ),
+        )
+        .run();
+}
@@ -49,8 +49,8 @@ fn main() {
             // model_xml_path: "assets/mujoco_menagerie/agility_cassie/scene.xml".to_string(),
             // pause_simulation: false,
             pause_simulation: false,
-            // * TODO: FPS not correct / no synchronization with physics time
-            target_fps: 600.0,
+            time_scale: 1.0,
+            ..default()
         })
         .add_plugins(NoCameraPlayerPlugin)
         .insert_resource(MovementSettings {
@@ -59,6 +59,6 @@ fn main() {
         })
         .add_plugins(MuJoCoPlugin)
         .add_systems(Startup, setup)
-        .add_systems(Update, robot_control_loop.after(simulate_physics))
+        .add_systems(Update, robot_control_loop.before(MuJoCoSystemSet::Control))
         .run();
 }
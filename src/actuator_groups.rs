@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{wrappers, ActuatorRef, Backend, MjcfElement, MuJoCoResources, MuJoCoSimulation};
+
+/// Named sets of actuators commanded together, e.g. the three actuators of a leg.
+/// Starts with one group per MJCF actuator `group` attribute value, named `group0`,
+/// `group1`, ...; more are added with `define` or `add_classes`.
+/// Group targets are kept until changed and written to the controls every frame
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MuJoCoActuatorGroups {
+    pub groups: HashMap<String, Vec<ActuatorRef>>,
+    pub targets: HashMap<String, Vec<f64>>,
+}
+
+impl MuJoCoActuatorGroups {
+    pub(crate) fn from_simulation(simulation: &impl Backend) -> Self {
+        let model = wrappers::model(simulation);
+        let actuator_group = unsafe { wrappers::slice(model.actuator_group, model.nu as usize) };
+
+        let mut groups: HashMap<String, Vec<ActuatorRef>> = HashMap::new();
+        for (id, group) in actuator_group.iter().enumerate() {
+            groups
+                .entry(format!("group{group}"))
+                .or_default()
+                .push(ActuatorRef::Id(id));
+        }
+        MuJoCoActuatorGroups {
+            groups,
+            targets: HashMap::new(),
+        }
+    }
+
+    pub fn define(&mut self, group: &str, actuators: &[&str]) {
+        self.groups.insert(
+            group.to_string(),
+            actuators
+                .iter()
+                .map(|name| ActuatorRef::from(*name))
+                .collect(),
+        );
+    }
+
+    /// Add a group per default class of the named actuators in an MJCF document, holding the
+    /// actuators with that `class` attribute. Actuators without one belong to `main`
+    pub fn add_classes(&mut self, mjcf: &MjcfElement) {
+        for section in mjcf.children.iter().filter(|child| child.tag == "actuator") {
+            for actuator in section.children.iter() {
+                let Some(name) = actuator.attribute("name") else {
+                    continue;
+                };
+                let class = actuator.attribute("class").unwrap_or("main");
+                self.groups
+                    .entry(class.to_string())
+                    .or_default()
+                    .push(ActuatorRef::from(name));
+            }
+        }
+    }
+
+    /// Set the controls of a group's actuators, in the order they were defined
+    pub fn set_group_targets(&mut self, group: &str, targets: &[f64]) {
+        self.targets.insert(group.to_string(), targets.to_vec());
+    }
+
+    pub fn clear_group_targets(&mut self, group: &str) {
+        self.targets.remove(group);
+    }
+}
+
+/// Write group targets into `MuJoCoResources::control`
+pub fn apply_actuator_groups(
+    mujoco: Res<MuJoCoSimulation>,
+    actuator_groups: Res<MuJoCoActuatorGroups>,
+    mut mujoco_resources: ResMut<MuJoCoResources>,
+) {
+    if actuator_groups.targets.is_empty() {
+        return;
+    }
+
+    let mujoco = mujoco.lock().unwrap();
+    let model = wrappers::model(&mujoco);
+    let control = &mut mujoco_resources.control;
+    control.data.resize(control.number_of_controls, 0.0);
+
+    for (group, targets) in actuator_groups.targets.iter() {
+        let Some(actuators) = actuator_groups.groups.get(group) else {
+            warn_once!("actuator group {group} is not defined");
+            continue;
+        };
+        if actuators.len() != targets.len() {
+            warn_once!(
+                "actuator group {group} has {} actuators, got {} targets",
+                actuators.len(),
+                targets.len()
+            );
+        }
+        for (actuator, target) in actuators.iter().zip(targets) {
+            match actuator.resolve(model) {
+                Some(id) => control.data[id] = *target,
+                None => warn_once!("actuator {actuator:?} of group {group} not found"),
+            }
+        }
+    }
+}
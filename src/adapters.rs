@@ -1,11 +1,17 @@
 use bevy::{
     prelude::*,
-    render::{mesh::Indices, render_asset::RenderAssetUsages, render_resource::PrimitiveTopology},
+    render::{
+        mesh::{Indices, VertexAttributeValues},
+        primitives::Aabb,
+        render_asset::RenderAssetUsages,
+        render_resource::PrimitiveTopology,
+    },
 };
-use mujoco_rust::{Body, Geom, GeomType};
-use nalgebra::{ArrayStorage, Const, Matrix, Quaternion};
+use nalgebra::Matrix3;
 use trees::Tree;
 
+use crate::{wrappers, Backend, Body, Geom, GeomMesh, GeomType, UpAxis};
+
 // use crate::mujoco_shape;
 
 /// BodyTree restructures bodie list into a tree structure
@@ -45,28 +51,80 @@ pub(crate) fn body_tree(bodies: &[Body]) -> Vec<BodyTree> {
     trees
 }
 
-/// Make a bevy mesh from exported MuJoCo mesh
-pub(crate) fn mesh_mujoco_2_bevy(mj_mesh: mujoco_rust::Mesh) -> Mesh {
+/// Make a bevy mesh from exported MuJoCo mesh.
+/// Vertex and face arrays come from the compiled model, so any format MuJoCo loads (STL, OBJ, MSH) works
+pub(crate) fn mesh_mujoco_2_bevy(mj_mesh: GeomMesh) -> Mesh {
     let mut mesh = Mesh::new(
         PrimitiveTopology::TriangleList,
         RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
     );
+    let degenerate_normals = normals_degenerate(&mj_mesh.normals, mj_mesh.vertices.len());
+
     mesh.insert_indices(Indices::U32(mj_mesh.indices));
     mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, mj_mesh.vertices);
-    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, mj_mesh.normals);
+    if degenerate_normals {
+        // STL files carry face normals only, MuJoCo may leave vertex normals empty or zeroed
+        mesh.compute_smooth_normals();
+    } else {
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, mj_mesh.normals);
+    }
     mesh
 }
 
-/// Make bevy quaternion from MuJoCo quaternion
-pub(crate) fn quat_mujoco_2_bevy(quat: Quaternion<f64>) -> Quat {
-    Quat::from_xyzw(quat.i as f32, quat.j as f32, quat.k as f32, quat.w as f32)
+/// Check whether MuJoCo mesh normals are unusable for shading
+fn normals_degenerate(normals: &[[f32; 3]], vertex_count: usize) -> bool {
+    normals.len() != vertex_count
+        || normals.iter().any(|normal| {
+            let normal = Vec3::from(*normal);
+            !normal.is_finite() || normal.length_squared() < 1e-6
+        })
+}
+
+/// Make bevy vector from a MuJoCo vector stored as an array, without changing axes
+pub(crate) fn array_mujoco_2_bevy(vec: [f64; 3]) -> Vec3 {
+    Vec3::new(vec[0] as f32, vec[1] as f32, vec[2] as f32)
 }
 
-/// Make bevy vector from MuJoCo vector
-pub(crate) fn vec3_mujoco_2_bevy(
-    vec: Matrix<f64, Const<3>, Const<1>, ArrayStorage<f64, 3, 1>>,
-) -> Vec3 {
-    Vec3::new(vec.x as f32, vec.y as f32, vec.z as f32)
+/// Convert a point or direction in MuJoCo world coordinates to the bevy scene frame
+pub(crate) fn position_mujoco_2_bevy(up_axis: UpAxis, position: [f64; 3]) -> Vec3 {
+    up_axis.basis().mul_vec3(Vec3::new(
+        position[0] as f32,
+        position[1] as f32,
+        position[2] as f32,
+    ))
+}
+
+/// Convert a point or direction in the bevy scene frame to MuJoCo world coordinates
+pub(crate) fn position_bevy_2_mujoco(up_axis: UpAxis, position: Vec3) -> [f64; 3] {
+    let position = up_axis.basis().inverse().mul_vec3(position);
+    [position.x as f64, position.y as f64, position.z as f64]
+}
+
+/// Convert a MuJoCo world frame, a position and a row-major rotation matrix (`xpos` / `xmat`,
+/// `geom_xpos` / `geom_xmat`, ...), to a pose in the bevy scene frame with `up_axis`, e.g.
+/// `MuJoCoSimulation::up_axis`
+pub fn pose_mujoco_2_bevy(up_axis: UpAxis, xpos: &[f64], xmat: &[f64]) -> Transform {
+    Transform {
+        translation: position_mujoco_2_bevy(up_axis, [xpos[0], xpos[1], xpos[2]]),
+        rotation: (up_axis.basis() * rotation_from_xmat(xmat)).normalize(),
+        ..default()
+    }
+}
+
+/// Convert a pose in the bevy scene frame with `up_axis` to a MuJoCo position and quaternion
+/// (w, x, y, z)
+pub fn pose_bevy_2_mujoco(up_axis: UpAxis, pose: &Transform) -> ([f64; 3], [f64; 4]) {
+    let basis = up_axis.basis();
+    let rotation = basis.inverse() * pose.rotation * basis;
+    (
+        position_bevy_2_mujoco(up_axis, pose.translation),
+        [
+            rotation.w as f64,
+            rotation.x as f64,
+            rotation.y as f64,
+            rotation.z as f64,
+        ],
+    )
 }
 
 /// Make bevy material from MuJoCo description
@@ -77,21 +135,22 @@ pub(crate) fn geom_material(geom: &Geom) -> StandardMaterial {
     }
 }
 
-/// Return mesh for a given geometry (PLANE; BOX; SPHERE; CAPSULE; ELLIPSOID; CYLINDER; MESH)
-pub(crate) fn geom_mesh(geom: &Geom) -> Mesh {
-    let size = &mut [geom.size.x as f32, geom.size.z as f32, geom.size.y as f32];
-
-    match geom.geom_type {
-        GeomType::PLANE => {
-            let _plane_size = if size[0] > 0.0 {
-                size[0]
-            } else {
-                // MuJoCo size 0 means infinite
-                1e6
-            };
-            Mesh::from(Plane3d::default())
-        }
-        GeomType::BOX => Mesh::from(Cuboid::new(size[0], size[1], size[2])),
+/// Return mesh for a given geometry (PLANE; BOX; SPHERE; CAPSULE; ELLIPSOID; CYLINDER; MESH).
+/// Meshes are centered on the geom frame origin like MuJoCo's; primitives have their axis
+/// along bevy's Y, turned onto MuJoCo's Z by `geom_transforms`.
+/// Infinite planes are drawn `plane_size` wide.
+/// Geom types that can't be rendered yet return `None` and are listed in `MuJoCoUnsupportedFeatures`
+pub(crate) fn geom_mesh(geom: &Geom, plane_size: f32) -> Option<Mesh> {
+    let size = &mut [
+        geom.size[0] as f32,
+        geom.size[2] as f32,
+        geom.size[1] as f32,
+    ];
+
+    let mesh = match geom.geom_type {
+        GeomType::PLANE => plane_mesh(size[0], size[2], plane_size),
+        // MuJoCo sizes are half extents
+        GeomType::BOX => Mesh::from(Cuboid::new(2.0 * size[0], 2.0 * size[1], 2.0 * size[2])),
         GeomType::SPHERE => Mesh::from(Sphere {
             radius: size[0],
             ..default()
@@ -101,53 +160,122 @@ pub(crate) fn geom_mesh(geom: &Geom) -> Mesh {
             half_length: size[2],
             ..default()
         }),
-        GeomType::ELLIPSOID => todo!(),
+        // unit sphere stretched to the semi-axes, normals are transformed along
+        GeomType::ELLIPSOID => Mesh::from(Sphere {
+            radius: 1.0,
+            ..default()
+        })
+        .scaled_by(Vec3::from(*size)),
         GeomType::CYLINDER => Mesh::from(Cylinder {
             radius: size[0],
             half_height: size[2],
             ..default()
         }),
 
-        GeomType::MESH => mesh_mujoco_2_bevy(geom.mesh.clone().unwrap()),
+        GeomType::MESH => mesh_mujoco_2_bevy(geom.mesh.clone()?),
         // --- NOT IMPLEMENTED ---
-        _ => todo!(),
-    }
-}
+        _ => return None,
+    };
 
-/// bevy and mujoco treat object frame differently, this function converts
-pub(crate) fn geom_correction(geom: &Geom) -> Vec3 {
-    let size = &mut [geom.size.x, geom.size.z, geom.size.y];
+    Some(mesh)
+}
 
-    match geom.geom_type {
-        GeomType::BOX => Vec3::new(0.0, (size[1] / 2.0) as f32, 0.0),
-        GeomType::CAPSULE => Vec3::new(0.0, (size[1] * 2.0) as f32, 0.0),
-        GeomType::CYLINDER => Vec3::new(0.0, (size[2] * 2.0) as f32, 0.0),
+/// Plane with MuJoCo half extents along x and y, where 0 means infinite and is clamped
+/// to `plane_size`. UVs repeat once per scene unit so textures tile instead of stretching
+fn plane_mesh(half_x: f32, half_y: f32, plane_size: f32) -> Mesh {
+    let extent = |half: f32| if half > 0.0 { 2.0 * half } else { plane_size };
+    let (width, depth) = (extent(half_x), extent(half_y));
 
-        _ => Vec3::ZERO,
+    let mut mesh = Plane3d::default().mesh().size(width, depth).build();
+    if let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute_mut(Mesh::ATTRIBUTE_UV_0) {
+        for uv in uvs.iter_mut() {
+            uv[0] *= width;
+            uv[1] *= depth;
+        }
     }
+    mesh
 }
 
-/// Return bevy transform for a given geometry from MuJoCo model
-pub(crate) fn geom_transform(geom: &Geom) -> Transform {
-    let mut transform = Transform {
-        translation: vec3_mujoco_2_bevy(geom.pos),
+/// Bounds of a mesh asset for frustum culling. Inserted together with the mesh because
+/// bevy only computes bounds for entities that have none, so a replaced mesh keeps stale ones
+pub(crate) fn mesh_aabb(meshes: &Assets<Mesh>, mesh: &Handle<Mesh>) -> Option<Aabb> {
+    meshes.get(mesh).and_then(Mesh::compute_aabb)
+}
 
-        rotation: quat_mujoco_2_bevy(geom.quat),
-        ..default()
-    };
+/// Bevy transforms of all geoms relative to their bodies, from the world frames `geom_xmat` /
+/// `geom_xpos` and `xmat` / `xpos` computed by `mj_forward`. These don't depend on how
+/// `geom_quat` is converted, so geoms given with `fromto` or turned arbitrarily keep their
+/// orientation
+pub(crate) fn geom_transforms(simulation: &impl Backend) -> Vec<Transform> {
+    let model = wrappers::model(simulation);
+    let data = wrappers::data(simulation);
+    let (nbody, ngeom) = (model.nbody as usize, model.ngeom as usize);
+    let xpos = unsafe { wrappers::slice(data.xpos, 3 * nbody) };
+    let xmat = unsafe { wrappers::slice(data.xmat, 9 * nbody) };
+    let geom_xpos = unsafe { wrappers::slice(data.geom_xpos, 3 * ngeom) };
+    let geom_xmat = unsafe { wrappers::slice(data.geom_xmat, 9 * ngeom) };
+    let geom_bodyid = unsafe { wrappers::slice(model.geom_bodyid, ngeom) };
+    let geom_type = unsafe { wrappers::slice(model.geom_type, ngeom) };
 
-    if geom.geom_type != GeomType::MESH {
-        transform.rotation *= Quat::from_rotation_x(std::f32::consts::FRAC_PI_2);
-    }
+    (0..ngeom)
+        .map(|geom| {
+            let body = geom_bodyid[geom] as usize;
+            let body_rotation = rotation_from_xmat(&xmat[9 * body..9 * body + 9]);
+            let geom_rotation = rotation_from_xmat(&geom_xmat[9 * geom..9 * geom + 9]);
+            let offset = array_mujoco_2_bevy([
+                geom_xpos[3 * geom] - xpos[3 * body],
+                geom_xpos[3 * geom + 1] - xpos[3 * body + 1],
+                geom_xpos[3 * geom + 2] - xpos[3 * body + 2],
+            ]);
 
-    transform
+            let mut rotation = body_rotation.inverse() * geom_rotation;
+            // primitives are built along bevy's Y axis
+            if GeomType::from_raw(geom_type[geom]) != GeomType::MESH {
+                rotation *= Quat::from_rotation_x(std::f32::consts::FRAC_PI_2);
+            }
+            Transform {
+                translation: body_rotation.inverse().mul_vec3(offset),
+                rotation: rotation.normalize(),
+                ..default()
+            }
+        })
+        .collect()
 }
 
-/// Return bevy transform for a given body from MuJoCo model
-pub(crate) fn body_transform(body: &Body) -> Transform {
-    Transform {
-        translation: vec3_mujoco_2_bevy(body.pos),
-        rotation: quat_mujoco_2_bevy(body.quat),
-        ..default()
-    }
+/// Rotation of a row-major MuJoCo matrix (`xmat`, `geom_xmat`, `site_xmat`).
+/// Orientations are read from rotation matrices rather than `xquat` / `body_quat`, so there is
+/// no quaternion component order or sign to get wrong
+pub(crate) fn rotation_from_xmat(xmat: &[f64]) -> Quat {
+    // nalgebra and glam both store matrices column major
+    let matrix = Matrix3::from_row_slice(&xmat[..9]).cast::<f32>();
+    Quat::from_mat3(&Mat3::from_cols_slice(matrix.as_slice())).normalize()
+}
+
+/// Bevy transforms of all bodies relative to their parents, from the world frames `xpos` /
+/// `xmat` computed by `mj_forward`. Root bodies are relative to MuJoCo's world frame
+pub(crate) fn body_transforms(simulation: &impl Backend) -> Vec<Transform> {
+    let model = wrappers::model(simulation);
+    let data = wrappers::data(simulation);
+    let nbody = model.nbody as usize;
+    let xpos = unsafe { wrappers::slice(data.xpos, 3 * nbody) };
+    let xmat = unsafe { wrappers::slice(data.xmat, 9 * nbody) };
+    let body_parentid = unsafe { wrappers::slice(model.body_parentid, nbody) };
+
+    (0..nbody)
+        .map(|body| {
+            let parent = body_parentid[body] as usize;
+            let parent_rotation = rotation_from_xmat(&xmat[9 * parent..9 * parent + 9]);
+            let rotation = rotation_from_xmat(&xmat[9 * body..9 * body + 9]);
+            let offset = array_mujoco_2_bevy([
+                xpos[3 * body] - xpos[3 * parent],
+                xpos[3 * body + 1] - xpos[3 * parent + 1],
+                xpos[3 * body + 2] - xpos[3 * parent + 2],
+            ]);
+            Transform {
+                translation: parent_rotation.inverse().mul_vec3(offset),
+                rotation: (parent_rotation.inverse() * rotation).normalize(),
+                ..default()
+            }
+        })
+        .collect()
 }
@@ -0,0 +1,198 @@
+//! Alignment checks between the rendered geoms and MuJoCo's geom poses. Every frame the pose
+//! of each geom entity is compared with `geom_xpos` / `geom_xmat`, so errors in the coordinate
+//! conversion show up as misaligned geoms instead of as robots that look slightly off
+
+use std::collections::HashSet;
+
+use bevy::{prelude::*, transform::TransformSystem};
+
+use crate::{
+    adapters::pose_mujoco_2_bevy, wrappers, GeomType, MuJoCoGeom, MuJoCoResources, MuJoCoSceneRoot,
+    MuJoCoSimulation, UpAxis,
+};
+
+/// Compare rendered geoms with the simulation while the resource exists, optionally drawing the
+/// collision geoms over the visual ones
+#[derive(Resource, Debug, Clone)]
+pub struct MuJoCoAlignmentCheck {
+    /// Distance in meters above which a geom is reported
+    pub position_tolerance: f32,
+    /// Angle in radians above which a geom is reported
+    pub rotation_tolerance: f32,
+    /// Draw collision geoms (non-zero `contype` or `conaffinity`) as gizmos at their MuJoCo poses
+    pub overlay: bool,
+    pub overlay_color: Color,
+    /// Errors of the rendered geoms from the last check
+    pub geoms: Vec<GeomAlignment>,
+    /// Geoms already logged as misaligned, each is logged once
+    reported: HashSet<i32>,
+}
+
+impl Default for MuJoCoAlignmentCheck {
+    fn default() -> Self {
+        MuJoCoAlignmentCheck {
+            position_tolerance: 1e-3,
+            rotation_tolerance: 1e-2,
+            overlay: true,
+            overlay_color: Color::srgb(1.0, 0.2, 0.8),
+            geoms: vec![],
+            reported: HashSet::new(),
+        }
+    }
+}
+
+impl MuJoCoAlignmentCheck {
+    /// Geoms whose errors exceed the tolerances
+    pub fn misaligned(&self) -> impl Iterator<Item = &GeomAlignment> {
+        self.geoms.iter().filter(|geom| {
+            geom.position_error > self.position_tolerance
+                || geom.rotation_error > self.rotation_tolerance
+        })
+    }
+}
+
+/// Difference between the rendered pose of a geom and its pose in the simulation, in the scene
+/// root frame
+#[derive(Debug, Clone)]
+pub struct GeomAlignment {
+    pub id: i32,
+    pub name: String,
+    /// Distance between the rendered and the simulated geom frame origins
+    pub position_error: f32,
+    /// Angle of the rotation between the rendered and the simulated geom frames
+    pub rotation_error: f32,
+}
+
+pub struct MuJoCoAlignmentPlugin;
+
+impl Plugin for MuJoCoAlignmentPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            check_alignment
+                .after(TransformSystem::TransformPropagate)
+                .run_if(resource_exists::<MuJoCoAlignmentCheck>)
+                .run_if(resource_exists::<MuJoCoResources>),
+        );
+    }
+}
+
+/// Pose of a geom in the bevy scene frame, with the rotation of bevy's primitive meshes (Y axis)
+/// to MuJoCo's (Z axis) applied to non-mesh geoms like the render entities do
+fn geom_pose(up_axis: UpAxis, xpos: &[f64], xmat: &[f64], mesh: bool) -> Transform {
+    let mut pose = pose_mujoco_2_bevy(up_axis, xpos, xmat);
+    if !mesh {
+        pose.rotation =
+            (pose.rotation * Quat::from_rotation_x(std::f32::consts::FRAC_PI_2)).normalize();
+    }
+    pose
+}
+
+fn check_alignment(
+    mut check: ResMut<MuJoCoAlignmentCheck>,
+    mujoco: Res<MuJoCoSimulation>,
+    mujoco_resources: Res<MuJoCoResources>,
+    scene_roots: Query<&GlobalTransform, With<MuJoCoSceneRoot>>,
+    geoms: Query<(&MuJoCoGeom, &GlobalTransform)>,
+    mut gizmos: Gizmos,
+) {
+    let Ok(root) = scene_roots.get_single() else {
+        return;
+    };
+
+    let simulation = mujoco.lock().unwrap();
+    let model = wrappers::model(&simulation);
+    let ngeom = model.ngeom as usize;
+    let data = wrappers::data(&simulation);
+    let xpos = unsafe { wrappers::slice(data.geom_xpos, 3 * ngeom) };
+    let xmat = unsafe { wrappers::slice(data.geom_xmat, 9 * ngeom) };
+    let geom_type = unsafe { wrappers::slice(model.geom_type, ngeom) };
+    let pose = |id: usize| {
+        geom_pose(
+            simulation.up_axis,
+            &xpos[3 * id..3 * id + 3],
+            &xmat[9 * id..9 * id + 9],
+            GeomType::from_raw(geom_type[id]) == GeomType::MESH,
+        )
+    };
+
+    let mut alignments = vec![];
+    for (geom, global_transform) in geoms.iter() {
+        let id = geom.id as usize;
+        if id >= ngeom {
+            continue;
+        }
+        let expected = pose(id);
+        let rendered = global_transform.reparented_to(root);
+        alignments.push(GeomAlignment {
+            id: geom.id,
+            name: mujoco_resources.geoms[id].name.clone(),
+            position_error: rendered.translation.distance(expected.translation),
+            rotation_error: rendered.rotation.angle_between(expected.rotation),
+        });
+    }
+    check.geoms = alignments;
+
+    let misaligned: Vec<GeomAlignment> = check.misaligned().cloned().collect();
+    for geom in misaligned {
+        if check.reported.insert(geom.id) {
+            warn!(
+                "geom {} (id {}) is rendered {:.4} m and {:.4} rad away from its simulated pose",
+                geom.name, geom.id, geom.position_error, geom.rotation_error
+            );
+        }
+    }
+
+    if !check.overlay {
+        return;
+    }
+    let contype = unsafe { wrappers::slice(model.geom_contype, ngeom) };
+    let conaffinity = unsafe { wrappers::slice(model.geom_conaffinity, ngeom) };
+    let size = unsafe { wrappers::slice(model.geom_size, 3 * ngeom) };
+    let rbound = unsafe { wrappers::slice(model.geom_rbound, ngeom) };
+    let color = check.overlay_color;
+    // bevy's capsules and cylinders are along Y, MuJoCo's along Z
+    let z_axis = Quat::from_rotation_x(std::f32::consts::FRAC_PI_2);
+    for id in (0..ngeom).filter(|&id| contype[id] != 0 || conaffinity[id] != 0) {
+        let pose = geom_pose(
+            simulation.up_axis,
+            &xpos[3 * id..3 * id + 3],
+            &xmat[9 * id..9 * id + 9],
+            true,
+        );
+        let (translation, rotation) = (
+            root.transform_point(pose.translation),
+            root.rotation() * pose.rotation,
+        );
+        let isometry = Isometry3d::new(translation, rotation);
+        let size = [
+            size[3 * id] as f32,
+            size[3 * id + 1] as f32,
+            size[3 * id + 2] as f32,
+        ];
+        match GeomType::from_raw(geom_type[id]) {
+            GeomType::SPHERE | GeomType::ELLIPSOID => {
+                gizmos.sphere(isometry, size.iter().copied().fold(0.0, f32::max), color);
+            }
+            GeomType::CAPSULE => {
+                let isometry = Isometry3d::new(translation, rotation * z_axis);
+                gizmos.primitive_3d(&Capsule3d::new(size[0], 2.0 * size[1]), isometry, color);
+            }
+            GeomType::CYLINDER => {
+                let isometry = Isometry3d::new(translation, rotation * z_axis);
+                gizmos.primitive_3d(&Cylinder::new(size[0], 2.0 * size[1]), isometry, color);
+            }
+            // half sizes along the geom frame axes
+            GeomType::BOX => {
+                let cuboid = Cuboid::new(2.0 * size[0], 2.0 * size[1], 2.0 * size[2]);
+                gizmos.primitive_3d(&cuboid, isometry, color);
+            }
+            // bounding sphere
+            GeomType::MESH => {
+                gizmos.sphere(isometry, rbound[id] as f32, color);
+            }
+            // planes and height fields
+            _ => {}
+        }
+    }
+}
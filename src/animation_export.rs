@@ -0,0 +1,206 @@
+//! Recording of body poses while the simulation runs, exported as keyframed animation: glTF
+//! animation channels (`MuJoCoSceneExporter::export_gltf_animation`) or BVH motion capture
+
+use std::fmt::Write;
+use std::path::Path;
+
+use bevy::prelude::*;
+
+use crate::{MuJoCoBody, MuJoCoTime};
+
+/// Local transforms of body entities, recorded every frame that stepped the simulation while
+/// `recording` is set. Insert the resource to record
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MuJoCoAnimationRecorder {
+    pub recording: bool,
+    /// Simulation time of each recorded frame
+    pub times: Vec<f64>,
+    pub tracks: Vec<BodyTrack>,
+}
+
+/// Poses of one body entity, one per recorded frame, relative to its parent entity
+#[derive(Debug, Clone)]
+pub struct BodyTrack {
+    pub entity: Entity,
+    pub name: String,
+    /// Track of the parent body, `None` for bodies attached to the scene root
+    pub parent: Option<usize>,
+    pub transforms: Vec<Transform>,
+}
+
+impl MuJoCoAnimationRecorder {
+    /// A recorder that starts recording right away
+    pub fn new() -> Self {
+        MuJoCoAnimationRecorder {
+            recording: true,
+            ..default()
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.times.clear();
+        self.tracks.clear();
+    }
+
+    /// Seconds between the first and the last recorded frame
+    pub fn duration(&self) -> f64 {
+        match (self.times.first(), self.times.last()) {
+            (Some(first), Some(last)) => last - first,
+            _ => 0.0,
+        }
+    }
+
+    /// Pose of a track at a time relative to the first frame, interpolated between frames
+    pub fn sample(&self, track: usize, time: f64) -> Option<Transform> {
+        let transforms = &self.tracks.get(track)?.transforms;
+        let start = *self.times.first()?;
+        let next = self.times.iter().position(|t| *t - start > time);
+        let (a, b, s) = match next {
+            None => (transforms.len() - 1, transforms.len() - 1, 0.0),
+            Some(0) => (0, 0, 0.0),
+            Some(next) => {
+                let (t0, t1) = (self.times[next - 1] - start, self.times[next] - start);
+                (next - 1, next, ((time - t0) / (t1 - t0)) as f32)
+            }
+        };
+        let (a, b) = (transforms.get(a)?, transforms.get(b)?);
+        Some(Transform {
+            translation: a.translation.lerp(b.translation, s),
+            rotation: a.rotation.slerp(b.rotation, s),
+            scale: a.scale.lerp(b.scale, s),
+        })
+    }
+
+    /// Write the subtree of body `root` as BVH motion capture sampled at `frame_rate`. The root
+    /// gets position and rotation channels, other bodies rotations only, so slide joints
+    /// below the root aren't kept. Angles are ZXY Euler angles in degrees, units are meters
+    pub fn export_bvh(
+        &self,
+        path: impl AsRef<Path>,
+        root: &str,
+        frame_rate: f64,
+    ) -> std::io::Result<()> {
+        let not_found =
+            |message: String| std::io::Error::new(std::io::ErrorKind::NotFound, message);
+        let root = self
+            .tracks
+            .iter()
+            .position(|track| track.name == root)
+            .ok_or_else(|| not_found(format!("body {root} wasn't recorded")))?;
+        if self.times.is_empty() || frame_rate <= 0.0 {
+            return Err(not_found("no frames recorded".to_string()));
+        }
+
+        // joints in depth-first order, the order of their channels in each motion line
+        let mut joints = vec![];
+        let mut bvh = String::from("HIERARCHY\n");
+        self.write_joint(&mut bvh, &mut joints, root, 0);
+
+        let frames = (self.duration() * frame_rate).floor() as usize + 1;
+        let _ = write!(
+            bvh,
+            "MOTION\nFrames: {frames}\nFrame Time: {:.6}\n",
+            1.0 / frame_rate
+        );
+        for frame in 0..frames {
+            let time = frame as f64 / frame_rate;
+            let mut values: Vec<String> = vec![];
+            for &track in joints.iter() {
+                let transform = self.sample(track, time).unwrap_or_default();
+                if track == root {
+                    let t = transform.translation;
+                    values.extend([t.x, t.y, t.z].map(|value| format!("{value:.6}")));
+                }
+                let (z, x, y) = transform.rotation.to_euler(EulerRot::ZXY);
+                values.extend([z, x, y].map(|angle| format!("{:.4}", angle.to_degrees())));
+            }
+            bvh.push_str(&values.join(" "));
+            bvh.push('\n');
+        }
+        std::fs::write(path, bvh)
+    }
+
+    fn write_joint(&self, bvh: &mut String, joints: &mut Vec<usize>, track: usize, depth: usize) {
+        let indent = "  ".repeat(depth);
+        let name = self.tracks[track].name.replace(char::is_whitespace, "_");
+        let offset = match depth {
+            // the root's position is animated
+            0 => Vec3::ZERO,
+            _ => self.tracks[track].transforms[0].translation,
+        };
+        joints.push(track);
+
+        let _ = writeln!(
+            bvh,
+            "{indent}{} {name}",
+            if depth == 0 { "ROOT" } else { "JOINT" }
+        );
+        let _ = writeln!(bvh, "{indent}{{");
+        let _ = writeln!(
+            bvh,
+            "{indent}  OFFSET {:.6} {:.6} {:.6}",
+            offset.x, offset.y, offset.z
+        );
+        if depth == 0 {
+            let _ = writeln!(
+                bvh,
+                "{indent}  CHANNELS 6 Xposition Yposition Zposition Zrotation Xrotation Yrotation"
+            );
+        } else {
+            let _ = writeln!(bvh, "{indent}  CHANNELS 3 Zrotation Xrotation Yrotation");
+        }
+        let children: Vec<usize> = (0..self.tracks.len())
+            .filter(|child| self.tracks[*child].parent == Some(track))
+            .collect();
+        for child in children.iter() {
+            self.write_joint(bvh, joints, *child, depth + 1);
+        }
+        if children.is_empty() {
+            let _ = writeln!(
+                bvh,
+                "{indent}  End Site\n{indent}  {{\n{indent}    OFFSET 0 0 0\n{indent}  }}"
+            );
+        }
+        let _ = writeln!(bvh, "{indent}}}");
+    }
+}
+
+/// Append the current body poses to `MuJoCoAnimationRecorder`
+pub(crate) fn record_animation(
+    mut recorder: ResMut<MuJoCoAnimationRecorder>,
+    sim_time: Res<MuJoCoTime>,
+    bodies: Query<(Entity, &Transform, Option<&Name>, Option<&Parent>), With<MuJoCoBody>>,
+) {
+    if !recorder.recording || sim_time.steps == 0 {
+        return;
+    }
+
+    // tracks are set up on the first frame, bodies spawned later aren't recorded
+    if recorder.tracks.is_empty() {
+        let entities: Vec<Entity> = bodies.iter().map(|(entity, ..)| entity).collect();
+        recorder.tracks = bodies
+            .iter()
+            .map(|(entity, _, name, parent)| BodyTrack {
+                entity,
+                name: name.map_or(format!("body_{}", entity.index()), |name| {
+                    let name = name.as_str();
+                    name.strip_prefix("MuJoCo::body_")
+                        .unwrap_or(name)
+                        .to_string()
+                }),
+                parent: parent.and_then(|parent| entities.iter().position(|e| *e == parent.get())),
+                transforms: vec![],
+            })
+            .collect();
+    }
+
+    recorder.times.push(sim_time.elapsed);
+    for track in recorder.tracks.iter_mut() {
+        let transform = bodies
+            .get(track.entity)
+            .map(|(_, transform, ..)| *transform)
+            // despawned bodies hold their last pose
+            .unwrap_or_else(|_| track.transforms.last().copied().unwrap_or_default());
+        track.transforms.push(transform);
+    }
+}
@@ -0,0 +1,160 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    adapters::*, sys::mjModel, terrain::model_hfield_mesh, wrappers, Backend, Geom, GeomType,
+};
+
+/// Mesh and material handles of rendered geoms keyed by their content, so identical geoms
+/// share assets and can be drawn instanced. Kept across reloads: loading the same model again
+/// or several robots built from the same meshes reuses the existing handles.
+/// The cache holds strong handles, `clear` it to let unused assets be freed
+#[derive(Resource, Default)]
+pub struct MuJoCoAssetCache {
+    meshes: HashMap<GeomMeshKey, Handle<Mesh>>,
+    materials: HashMap<[u32; 4], Handle<StandardMaterial>>,
+}
+
+impl MuJoCoAssetCache {
+    pub fn mesh_count(&self) -> usize {
+        self.meshes.len()
+    }
+
+    pub fn material_count(&self) -> usize {
+        self.materials.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.meshes.clear();
+        self.materials.clear();
+    }
+
+    /// Mesh of a geom, built on first use. `None` for geoms that can't be rendered
+    pub(crate) fn geom_mesh(
+        &mut self,
+        simulation: &impl Backend,
+        geom: &Geom,
+        plane_size: f32,
+        meshes: &mut Assets<Mesh>,
+    ) -> Option<Handle<Mesh>> {
+        let model = wrappers::model(simulation);
+        let key = GeomMeshKey::new(model, geom.id as usize, plane_size);
+        if let Some(mesh) = self.meshes.get(&key) {
+            return Some(mesh.clone());
+        }
+
+        let mesh = match geom.geom_type {
+            GeomType::HFIELD => {
+                let geom_dataid =
+                    unsafe { wrappers::slice(model.geom_dataid, model.ngeom as usize) };
+                Some(model_hfield_mesh(
+                    simulation,
+                    geom_dataid[geom.id as usize] as usize,
+                ))
+            }
+            _ => geom_mesh(geom, plane_size),
+        }?;
+        let mesh = meshes.add(mesh);
+        self.meshes.insert(key, mesh.clone());
+        Some(mesh)
+    }
+
+    /// Material of a geom, built on first use
+    pub(crate) fn geom_material(
+        &mut self,
+        geom: &Geom,
+        materials: &mut Assets<StandardMaterial>,
+    ) -> Handle<StandardMaterial> {
+        let key = [
+            geom.color[0].to_bits(),
+            geom.color[1].to_bits(),
+            geom.color[2].to_bits(),
+            geom.color[3].to_bits(),
+        ];
+        self.materials
+            .entry(key)
+            .or_insert_with(|| materials.add(geom_material(geom)))
+            .clone()
+    }
+}
+
+/// What the rendered mesh of a geom is built from: type and size for primitives, vertices
+/// and faces for meshes, samples and size for height fields. Floats are kept as bits so keys
+/// compare exactly. Independent of object ids, so equal geoms of different models are equal
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum GeomMeshKey {
+    Primitive {
+        geom_type: GeomType,
+        size: [u64; 3],
+        /// Width infinite planes are drawn with
+        plane_size: Option<u32>,
+    },
+    Mesh {
+        vertices: Vec<u32>,
+        faces: Vec<i32>,
+    },
+    HeightField {
+        nrow: usize,
+        ncol: usize,
+        size: [u64; 4],
+        data: Vec<u32>,
+    },
+}
+
+fn to_bits(values: &[f32]) -> Vec<u32> {
+    values.iter().map(|value| value.to_bits()).collect()
+}
+
+impl GeomMeshKey {
+    pub(crate) fn new(model: &mjModel, geom_id: usize, plane_size: f32) -> Self {
+        let ngeom = model.ngeom as usize;
+        let geom_type =
+            GeomType::from_raw(unsafe { wrappers::slice(model.geom_type, ngeom) }[geom_id]);
+        let dataid = unsafe { wrappers::slice(model.geom_dataid, ngeom) }[geom_id];
+
+        match geom_type {
+            GeomType::HFIELD if dataid >= 0 => {
+                let hfield = dataid as usize;
+                let nhfield = model.nhfield as usize;
+                let nrow = unsafe { wrappers::slice(model.hfield_nrow, nhfield) }[hfield] as usize;
+                let ncol = unsafe { wrappers::slice(model.hfield_ncol, nhfield) }[hfield] as usize;
+                let adr = unsafe { wrappers::slice(model.hfield_adr, nhfield) }[hfield] as usize;
+                let size = &unsafe { wrappers::slice(model.hfield_size, 4 * nhfield) }
+                    [4 * hfield..4 * hfield + 4];
+                let data =
+                    unsafe { wrappers::slice(model.hfield_data, model.nhfielddata as usize) };
+                GeomMeshKey::HeightField {
+                    nrow,
+                    ncol,
+                    size: std::array::from_fn(|i| size[i].to_bits()),
+                    data: to_bits(&data[adr..adr + nrow * ncol]),
+                }
+            }
+            GeomType::MESH if dataid >= 0 => {
+                let mesh = dataid as usize;
+                let nmesh = model.nmesh as usize;
+                let vertadr = unsafe { wrappers::slice(model.mesh_vertadr, nmesh) }[mesh] as usize;
+                let vertnum = unsafe { wrappers::slice(model.mesh_vertnum, nmesh) }[mesh] as usize;
+                let faceadr = unsafe { wrappers::slice(model.mesh_faceadr, nmesh) }[mesh] as usize;
+                let facenum = unsafe { wrappers::slice(model.mesh_facenum, nmesh) }[mesh] as usize;
+                let vert =
+                    unsafe { wrappers::slice(model.mesh_vert, 3 * model.nmeshvert as usize) };
+                let face =
+                    unsafe { wrappers::slice(model.mesh_face, 3 * model.nmeshface as usize) };
+                GeomMeshKey::Mesh {
+                    vertices: to_bits(&vert[3 * vertadr..3 * (vertadr + vertnum)]),
+                    faces: face[3 * faceadr..3 * (faceadr + facenum)].to_vec(),
+                }
+            }
+            _ => {
+                let size = &unsafe { wrappers::slice(model.geom_size, 3 * ngeom) }
+                    [3 * geom_id..3 * geom_id + 3];
+                GeomMeshKey::Primitive {
+                    geom_type,
+                    size: std::array::from_fn(|i| size[i].to_bits()),
+                    // infinite planes are drawn `plane_size` wide
+                    plane_size: (geom_type == GeomType::PLANE).then_some(plane_size.to_bits()),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Resolve a model path so that MuJoCo sees an absolute path to the model file.
+///
+/// MuJoCo resolves `<include>`, `meshdir` and `texturedir` relative to the directory
+/// of the model file, so once the model path is absolute those resolve correctly no
+/// matter where the binary runs. Relative paths are looked up in order:
+/// `assets_path`, the current directory, the executable directory and the crate
+/// directory of a `cargo run` invocation.
+pub fn resolve_asset_path(assets_path: Option<&Path>, path: &str) -> PathBuf {
+    let path = Path::new(path);
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+
+    let mut roots: Vec<PathBuf> = vec![];
+    if let Some(assets_path) = assets_path {
+        roots.push(assets_path.to_path_buf());
+    }
+    if let Ok(current_dir) = env::current_dir() {
+        roots.push(current_dir);
+    }
+    if let Some(exe_dir) = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+    {
+        roots.push(exe_dir);
+    }
+    if let Ok(manifest_dir) = env::var("CARGO_MANIFEST_DIR") {
+        roots.push(PathBuf::from(manifest_dir));
+    }
+
+    let candidates = roots.iter().map(|root| root.join(path));
+    for candidate in candidates.clone() {
+        if candidate.is_file() {
+            return candidate.canonicalize().unwrap_or(candidate);
+        }
+    }
+
+    // Nothing found: report the path relative to the preferred root
+    candidates
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| path.to_path_buf())
+}
@@ -0,0 +1,210 @@
+//! The MuJoCo bindings the crate talks to. Everything past loading goes through raw
+//! `mjModel` / `mjData` pointers (see `wrappers`), which is all a `Backend` provides, so the
+//! plugin runs on either `mujoco_rust` or `RawSimulation`
+
+use std::mem::ManuallyDrop;
+use std::sync::MutexGuard;
+
+use crate::{sys::no_render, wrappers, SimulationHandle};
+
+/// Model and data types of the bindings the crate is built with, see the `mujoco3` feature
+pub use crate::sys::{mjData, mjModel};
+
+/// A compiled model and its data. The plugin runs on `PluginBackend`, selected by cargo
+/// features; the provided methods step and read any backend headless
+///
+/// # Safety
+///
+/// `model_ptr` and `data_ptr` must return valid, non-null pointers to a compiled model and to
+/// data made for that model, which stay valid and at the same address for as long as the
+/// backend is alive, and no one else may write through them while the backend is borrowed.
+/// The provided methods only write to the data through `&mut self`, or through MuJoCo calls
+/// that don't hand out references
+pub unsafe trait Backend {
+    fn model_ptr(&self) -> *mut mjModel;
+    fn data_ptr(&self) -> *mut mjData;
+
+    fn step(&self) {
+        unsafe { no_render::mj_step(self.model_ptr(), self.data_ptr()) };
+    }
+
+    fn forward(&self) {
+        unsafe { no_render::mj_forward(self.model_ptr(), self.data_ptr()) };
+    }
+
+    /// Reset the data to the model's initial state
+    fn reset(&self) {
+        unsafe { no_render::mj_resetData(self.model_ptr(), self.data_ptr()) };
+    }
+
+    fn time(&self) -> f64 {
+        wrappers::data(self).time
+    }
+
+    /// Set actuator controls, values past `nu` are ignored
+    fn control(&mut self, ctrl: &[f64]) {
+        let nu = wrappers::model(self).nu as usize;
+        let len = ctrl.len().min(nu);
+        let target = unsafe { wrappers::slice_mut(wrappers::data(self).ctrl, nu) };
+        target[..len].copy_from_slice(&ctrl[..len]);
+    }
+
+    fn qpos(&self) -> Vec<f64> {
+        let nq = wrappers::model(self).nq as usize;
+        unsafe { wrappers::slice(wrappers::data(self).qpos, nq) }.to_vec()
+    }
+
+    fn qvel(&self) -> Vec<f64> {
+        let nv = wrappers::model(self).nv as usize;
+        unsafe { wrappers::slice(wrappers::data(self).qvel, nv) }.to_vec()
+    }
+
+    fn sensordata(&self) -> Vec<f64> {
+        let nsensordata = wrappers::model(self).nsensordata as usize;
+        unsafe { wrappers::slice(wrappers::data(self).sensordata, nsensordata) }.to_vec()
+    }
+
+    /// Set joint positions, call `forward` to update the world frames
+    fn set_qpos(&mut self, qpos: &[f64]) {
+        let nq = wrappers::model(self).nq as usize;
+        let len = qpos.len().min(nq);
+        let target = unsafe { wrappers::slice_mut(wrappers::data(self).qpos, nq) };
+        target[..len].copy_from_slice(&qpos[..len]);
+    }
+
+    fn set_qvel(&mut self, qvel: &[f64]) {
+        let nv = wrappers::model(self).nv as usize;
+        let len = qvel.len().min(nv);
+        let target = unsafe { wrappers::slice_mut(wrappers::data(self).qvel, nv) };
+        target[..len].copy_from_slice(&qvel[..len]);
+    }
+}
+
+#[cfg(not(any(feature = "mujoco-rust", feature = "raw-backend")))]
+compile_error!("enable the `mujoco-rust` or the `raw-backend` feature to pick MuJoCo bindings");
+
+/// The bindings the plugin loads models with: `mujoco_rust` by default, `RawSimulation` with
+/// the `raw-backend` feature
+#[cfg(not(feature = "raw-backend"))]
+pub type PluginBackend = mujoco_rust::Simulation;
+#[cfg(feature = "raw-backend")]
+pub type PluginBackend = RawSimulation;
+
+/// Load and compile an MJCF/URDF model with the plugin's backend
+#[cfg(not(feature = "raw-backend"))]
+pub(crate) fn load_plugin_backend(path: &str) -> Result<PluginBackend, String> {
+    mujoco_rust::Model::from_xml(path)
+        .map(mujoco_rust::Simulation::new)
+        .map_err(|error| error.to_string())
+}
+
+#[cfg(feature = "raw-backend")]
+pub(crate) fn load_plugin_backend(path: &str) -> Result<PluginBackend, String> {
+    RawSimulation::load(path)
+}
+
+/// Free the model and data of the plugin's backend. `mujoco_rust` wrappers don't free their
+/// pointers, so they are deleted here and the wrapper itself is never dropped
+#[cfg(not(feature = "raw-backend"))]
+pub(crate) fn free_plugin_backend(simulation: &mut ManuallyDrop<PluginBackend>) {
+    unsafe {
+        no_render::mj_deleteData(simulation.data_ptr());
+        no_render::mj_deleteModel(simulation.model_ptr());
+    }
+}
+
+#[cfg(feature = "raw-backend")]
+pub(crate) fn free_plugin_backend(simulation: &mut ManuallyDrop<PluginBackend>) {
+    unsafe { ManuallyDrop::drop(simulation) };
+}
+
+/// The `mujoco_rust` bindings, used by the plugin unless `raw-backend` is enabled. They only
+/// bind MuJoCo 2.x, `mujoco3` builds run on `RawSimulation`
+#[cfg(all(feature = "mujoco-rust", not(mujoco3)))]
+unsafe impl Backend for mujoco_rust::Simulation {
+    fn model_ptr(&self) -> *mut mjModel {
+        self.model.ptr()
+    }
+
+    fn data_ptr(&self) -> *mut mjData {
+        self.state.ptr()
+    }
+}
+
+unsafe impl Backend for SimulationHandle {
+    fn model_ptr(&self) -> *mut mjModel {
+        (**self).model_ptr()
+    }
+
+    fn data_ptr(&self) -> *mut mjData {
+        (**self).data_ptr()
+    }
+}
+
+/// A locked `MuJoCoSimulation`
+unsafe impl Backend for MutexGuard<'_, SimulationHandle> {
+    fn model_ptr(&self) -> *mut mjModel {
+        (**self).model_ptr()
+    }
+
+    fn data_ptr(&self) -> *mut mjData {
+        (**self).data_ptr()
+    }
+}
+
+/// Model and data loaded through the raw bindings directly, for MuJoCo versions `mujoco_rust`
+/// doesn't support yet. The plugin runs on it with the `raw-backend` feature, it also steps
+/// headless through the `Backend` methods. Both are freed when dropped
+#[cfg(feature = "raw-backend")]
+pub struct RawSimulation {
+    model: *mut mjModel,
+    data: *mut mjData,
+}
+
+// MuJoCo doesn't keep thread-local state for a model or its data
+#[cfg(feature = "raw-backend")]
+unsafe impl Send for RawSimulation {}
+
+#[cfg(feature = "raw-backend")]
+impl RawSimulation {
+    /// Load and compile an MJCF/URDF model, returning the compiler error on failure
+    pub fn load(path: &str) -> Result<Self, String> {
+        let path = std::ffi::CString::new(path).map_err(|error| error.to_string())?;
+        let mut error = [0 as std::ffi::c_char; 1000];
+        let model = unsafe {
+            no_render::mj_loadXML(
+                path.as_ptr(),
+                std::ptr::null(),
+                error.as_mut_ptr(),
+                error.len() as i32,
+            )
+        };
+        if model.is_null() {
+            let error = unsafe { std::ffi::CStr::from_ptr(error.as_ptr()) };
+            return Err(error.to_string_lossy().into_owned());
+        }
+        let data = unsafe { no_render::mj_makeData(model) };
+        Ok(RawSimulation { model, data })
+    }
+}
+
+#[cfg(feature = "raw-backend")]
+unsafe impl Backend for RawSimulation {
+    fn model_ptr(&self) -> *mut mjModel {
+        self.model
+    }
+
+    fn data_ptr(&self) -> *mut mjData {
+        self.data
+    }
+}
+
+#[cfg(feature = "raw-backend")]
+impl Drop for RawSimulation {
+    fn drop(&mut self) {
+        unsafe {
+            no_render::mj_deleteData(self.data);
+            no_render::mj_deleteModel(self.model);
+        }
+    }
+}
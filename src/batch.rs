@@ -0,0 +1,423 @@
+//! Batched simulation: copies of the loaded model that share its `mjModel`, each with its own
+//! `mjData`, stepped in parallel on bevy's compute task pool and rendered on a grid next to the
+//! main scene. Meshes and materials are shared between copies so bevy draws them instanced
+
+use bevy::{prelude::*, tasks::ComputeTaskPool};
+
+use crate::sys::{mjData, mjModel, no_render};
+use crate::{
+    adapters::*, simulate_physics, wrappers, GeomType, MuJoCoAssetCache, MuJoCoModelInfo,
+    MuJoCoPluginSettings, MuJoCoResources, MuJoCoSceneRoot, MuJoCoSimulation, MuJoCoState,
+    MuJoCoStepRequest, MuJoCoTime, SpawnMode, StepMode,
+};
+
+/// Step `n_envs` copies of the model next to the main simulation
+#[derive(Clone)]
+pub struct MuJoCoBatchPlugin {
+    pub n_envs: usize,
+    /// Distance between neighbouring envs on the grid, in scene units
+    pub spacing: f32,
+}
+
+impl Default for MuJoCoBatchPlugin {
+    fn default() -> Self {
+        MuJoCoBatchPlugin {
+            n_envs: 16,
+            spacing: 2.0,
+        }
+    }
+}
+
+impl Plugin for MuJoCoBatchPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(BatchSettings(self.clone()));
+        app.add_systems(
+            Update,
+            (
+                setup_batch.run_if(resource_added::<MuJoCoResources>),
+                (step_batch, sync_batch)
+                    .chain()
+                    .after(simulate_physics)
+                    .run_if(resource_exists::<MuJoCoBatch>),
+            )
+                .chain()
+                .run_if(resource_exists::<MuJoCoResources>),
+        );
+        app.add_systems(
+            Update,
+            unload_batch.run_if(resource_removed::<MuJoCoResources>),
+        );
+    }
+}
+
+#[derive(Resource)]
+struct BatchSettings(MuJoCoBatchPlugin);
+
+/// `mjData` of a single env, freed with `mj_deleteData` when dropped
+struct EnvData(*mut mjData);
+
+// each env is only touched by one task at a time, `MuJoCoBatch` hands out `&mut` per env
+unsafe impl Send for EnvData {}
+unsafe impl Sync for EnvData {}
+
+impl Drop for EnvData {
+    fn drop(&mut self) {
+        unsafe { no_render::mj_deleteData(self.0) };
+    }
+}
+
+/// Model shared by every env, read-only while stepping
+#[derive(Clone, Copy)]
+struct ModelPtr(*const mjModel);
+
+unsafe impl Send for ModelPtr {}
+unsafe impl Sync for ModelPtr {}
+
+/// Per-env state and control of the batch. Controls are applied before every step,
+/// states are read back once stepping for the frame is done
+#[derive(Resource)]
+pub struct MuJoCoBatch {
+    envs: Vec<EnvData>,
+    /// `nu` controls per env
+    pub controls: Vec<Vec<f64>>,
+    pub states: Vec<MuJoCoState>,
+    /// Simulation time per env
+    pub times: Vec<f64>,
+    resets: Vec<bool>,
+    spacing: f32,
+    root: Entity,
+    time_budget: f64,
+    /// Stepping time of the envs, limited by `MuJoCoPluginSettings::step_budget` like the
+    /// main simulation's
+    time: MuJoCoTime,
+}
+
+impl MuJoCoBatch {
+    pub fn len(&self) -> usize {
+        self.envs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.envs.is_empty()
+    }
+
+    /// Reset an env to the model's initial state before the next step
+    pub fn reset(&mut self, env: usize) {
+        if let Some(reset) = self.resets.get_mut(env) {
+            *reset = true;
+        }
+    }
+
+    /// Offset of an env from the main scene, in the bevy scene frame.
+    /// The main simulation takes the first grid cell
+    pub fn offset(&self, env: usize) -> Vec3 {
+        let columns = ((self.envs.len() + 1) as f32).sqrt().ceil() as usize;
+        let cell = env + 1;
+        Vec3::new(
+            (cell % columns) as f32 * self.spacing,
+            0.0,
+            (cell / columns) as f32 * self.spacing,
+        )
+    }
+
+    fn data(&self, env: usize) -> &mjData {
+        unsafe { &*self.envs[env].0 }
+    }
+
+    /// Copy the env states into `states`, reusing their allocations
+    fn read_states(&mut self, model: &mjModel) {
+        for env in 0..self.envs.len() {
+            let data = unsafe { &*self.envs[env].0 };
+            let state = &mut self.states[env];
+            state.qpos.clear();
+            state
+                .qpos
+                .extend_from_slice(unsafe { wrappers::slice(data.qpos, model.nq as usize) });
+            state.qvel.clear();
+            state
+                .qvel
+                .extend_from_slice(unsafe { wrappers::slice(data.qvel, model.nv as usize) });
+            state.sensor_data.clear();
+            state.sensor_data.extend_from_slice(unsafe {
+                wrappers::slice(data.sensordata, model.nsensordata as usize)
+            });
+            state.cfrc_ext.clear();
+            state.cfrc_ext.extend(
+                unsafe { wrappers::slice(data.cfrc_ext, 6 * model.nbody as usize) }
+                    .chunks_exact(6)
+                    .map(|wrench| {
+                        let mut out = [0.0; 6];
+                        out.copy_from_slice(wrench);
+                        out
+                    }),
+            );
+            state.act.clear();
+            state
+                .act
+                .extend_from_slice(unsafe { wrappers::slice(data.act, model.na as usize) });
+            state.actuator_force.clear();
+            state.actuator_force.extend_from_slice(unsafe {
+                wrappers::slice(data.actuator_force, model.nu as usize)
+            });
+            state.qfrc_actuator.clear();
+            state.qfrc_actuator.extend_from_slice(unsafe {
+                wrappers::slice(data.qfrc_actuator, model.nv as usize)
+            });
+            self.times[env] = data.time;
+        }
+    }
+}
+
+/// Entity of a geom rendered for one env of the batch
+#[derive(Component)]
+pub struct MuJoCoBatchGeom {
+    pub env: usize,
+    pub id: i32,
+    /// Rotation from the MuJoCo geom frame to the bevy mesh frame
+    pub(crate) mesh_rotation: Quat,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn setup_batch(
+    mut commands: Commands,
+    mujoco: Res<MuJoCoSimulation>,
+    batch_settings: Res<BatchSettings>,
+    settings: Res<MuJoCoPluginSettings>,
+    mujoco_resources: Res<MuJoCoResources>,
+    previous: Option<Res<MuJoCoBatch>>,
+    scene_roots: Query<Entity, With<MuJoCoSceneRoot>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut asset_cache: ResMut<MuJoCoAssetCache>,
+) {
+    if let Some(previous) = previous {
+        if let Some(mut root) = commands.get_entity(previous.root) {
+            root.despawn_recursive();
+        }
+    }
+    let Ok(scene_root) = scene_roots.get_single() else {
+        warn!("MuJoCo batch needs a single MuJoCoSceneRoot");
+        return;
+    };
+
+    let mujoco = mujoco.lock().unwrap();
+    let model = wrappers::model(&mujoco);
+    let main_data = wrappers::data(&mujoco);
+    let n_envs = batch_settings.0.n_envs;
+
+    // every env starts from the current state of the main simulation
+    let envs: Vec<EnvData> = (0..n_envs)
+        .map(|_| {
+            let data = unsafe { no_render::mj_makeData(model) };
+            let env = EnvData(data);
+            let data = unsafe { &mut *data };
+            unsafe {
+                wrappers::slice_mut(data.qpos, model.nq as usize)
+                    .copy_from_slice(wrappers::slice(main_data.qpos, model.nq as usize))
+            };
+            unsafe {
+                wrappers::slice_mut(data.qvel, model.nv as usize)
+                    .copy_from_slice(wrappers::slice(main_data.qvel, model.nv as usize))
+            };
+            data.time = main_data.time;
+            unsafe { no_render::mj_forward(model, data) };
+            env
+        })
+        .collect();
+
+    // world geoms such as the floor are shared with the main scene
+    let geom_bodyid = unsafe { wrappers::slice(model.geom_bodyid, model.ngeom as usize) };
+    let geom_group = unsafe { wrappers::slice(model.geom_group, model.ngeom as usize) };
+    let geom_type = unsafe { wrappers::slice(model.geom_type, model.ngeom as usize) };
+    let visuals: Vec<(i32, Quat, Option<(Handle<Mesh>, Handle<StandardMaterial>)>)> =
+        mujoco_resources
+            .geoms
+            .iter()
+            .filter(|geom| geom_bodyid[geom.id as usize] != 0)
+            .filter_map(|geom| {
+                let handles = match settings.spawn_mode {
+                    // shared with the main scene, bevy draws the copies instanced
+                    SpawnMode::Full => Some((
+                        asset_cache.geom_mesh(&mujoco, geom, settings.plane_size, &mut meshes)?,
+                        asset_cache.geom_material(geom, &mut materials),
+                    )),
+                    SpawnMode::TransformOnly => {
+                        geom_mesh(geom, settings.plane_size)?;
+                        None
+                    }
+                };
+                let mesh_rotation = if geom.geom_type == GeomType::MESH {
+                    Quat::IDENTITY
+                } else {
+                    Quat::from_rotation_x(std::f32::consts::FRAC_PI_2)
+                };
+                Some((geom.id, mesh_rotation, handles))
+            })
+            .collect();
+
+    let mut batch = MuJoCoBatch {
+        controls: vec![vec![0.0; model.nu as usize]; n_envs],
+        states: vec![MuJoCoState::default(); n_envs],
+        times: vec![0.0; n_envs],
+        resets: vec![false; n_envs],
+        envs,
+        spacing: batch_settings.0.spacing,
+        root: Entity::PLACEHOLDER,
+        time_budget: 0.0,
+        time: MuJoCoTime::default(),
+    };
+    batch.read_states(model);
+
+    let root = commands
+        .spawn((Name::new("MuJoCo::batch"), SpatialBundle::default()))
+        .with_children(|children| {
+            for env in 0..n_envs {
+                children
+                    .spawn((
+                        Name::new(format!("MuJoCo::env_{env}")),
+                        SpatialBundle {
+                            transform: Transform::from_translation(batch.offset(env)),
+                            ..default()
+                        },
+                    ))
+                    .with_children(|children| {
+                        for (id, mesh_rotation, handles) in visuals.iter() {
+                            let mut geom = children.spawn((
+                                MuJoCoBatchGeom {
+                                    env,
+                                    id: *id,
+                                    mesh_rotation: *mesh_rotation,
+                                },
+                                SpatialBundle::default(),
+                            ));
+                            // identical mesh and material handles are drawn instanced
+                            if let Some((mesh, material)) = handles {
+                                if let Some(aabb) = mesh_aabb(&meshes, mesh) {
+                                    geom.insert(aabb);
+                                }
+                                geom.insert((
+                                    Mesh3d(mesh.clone()),
+                                    MeshMaterial3d(material.clone()),
+                                ));
+                                settings.shadows.apply(
+                                    &mut geom,
+                                    geom_group[*id as usize],
+                                    GeomType::from_raw(geom_type[*id as usize]) == GeomType::PLANE,
+                                );
+                            }
+                        }
+                    });
+            }
+        })
+        .set_parent(scene_root)
+        .id();
+    batch.root = root;
+
+    commands.insert_resource(batch);
+}
+
+fn step_batch(
+    mut batch: ResMut<MuJoCoBatch>,
+    mujoco: Res<MuJoCoSimulation>,
+    settings: Res<MuJoCoPluginSettings>,
+    time: Res<Time>,
+    mut step_requests: EventReader<MuJoCoStepRequest>,
+) {
+    // the lock keeps the shared model from being changed while the envs step
+    let mujoco = mujoco.lock().unwrap();
+    let model = wrappers::model(&mujoco);
+    let timestep = model.opt.timestep;
+
+    let n_steps = match settings.step_mode {
+        StepMode::Realtime if settings.pause_simulation => 0,
+        StepMode::Realtime => {
+            batch.time_budget += time.delta_secs_f64() * settings.time_scale;
+            let n_steps = settings.step_budget.realtime_steps(
+                batch.time_budget,
+                timestep,
+                batch.time.average_step_time,
+            );
+            batch.time_budget -= n_steps as f64 * timestep;
+            settings.step_budget.carry_over(&mut batch.time_budget);
+            n_steps
+        }
+        StepMode::Manual => step_requests.read().map(|request| request.n_steps).sum(),
+    };
+
+    let has_resets = batch.resets.iter().any(|reset| *reset);
+    if n_steps == 0 && !has_resets {
+        return;
+    }
+
+    let model_ptr = ModelPtr(model);
+    let batch = &mut *batch;
+    let stepping_started = std::time::Instant::now();
+    ComputeTaskPool::get().scope(|scope| {
+        for ((env, control), reset) in batch
+            .envs
+            .iter_mut()
+            .zip(batch.controls.iter())
+            .zip(batch.resets.iter_mut())
+        {
+            scope.spawn(async move {
+                let model = model_ptr;
+                let data = unsafe { &mut *env.0 };
+                if std::mem::take(reset) {
+                    unsafe {
+                        no_render::mj_resetData(model.0, data);
+                        no_render::mj_forward(model.0, data);
+                    }
+                }
+                let ctrl = unsafe { wrappers::slice_mut(data.ctrl, (*model.0).nu as usize) };
+                let len = ctrl.len().min(control.len());
+                ctrl[..len].copy_from_slice(&control[..len]);
+                for _ in 0..n_steps {
+                    unsafe { no_render::mj_step(model.0, data) };
+                }
+            });
+        }
+    });
+    let elapsed = batch.times.first().copied().unwrap_or_default();
+    batch.time.record(
+        elapsed,
+        n_steps as f64 * timestep,
+        n_steps,
+        stepping_started.elapsed(),
+    );
+
+    batch.read_states(model);
+}
+
+fn sync_batch(
+    batch: Res<MuJoCoBatch>,
+    model_info: Res<MuJoCoModelInfo>,
+    mut geoms: Query<(&mut Transform, &MuJoCoBatchGeom)>,
+) {
+    if !batch.is_changed() {
+        return;
+    }
+
+    geoms.par_iter_mut().for_each(|(mut transform, geom)| {
+        let data = batch.data(geom.env);
+        let id = geom.id as usize;
+        let xpos = unsafe { wrappers::slice(data.geom_xpos, 3 * (id + 1)) };
+        let xmat = unsafe { wrappers::slice(data.geom_xmat, 9 * (id + 1)) };
+        let (Some(xpos), Some(xmat)) = (xpos.get(3 * id..), xmat.get(9 * id..)) else {
+            return;
+        };
+
+        let pose = pose_mujoco_2_bevy(model_info.up_axis, xpos, xmat);
+        transform.translation = pose.translation;
+        transform.rotation = (pose.rotation * geom.mesh_rotation).normalize();
+    });
+}
+
+fn unload_batch(mut commands: Commands, batch: Option<Res<MuJoCoBatch>>) {
+    let Some(batch) = batch else {
+        return;
+    };
+    if let Some(mut root) = commands.get_entity(batch.root) {
+        root.despawn_recursive();
+    }
+    commands.remove_resource::<MuJoCoBatch>();
+}
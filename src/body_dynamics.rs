@@ -0,0 +1,96 @@
+use crate::{wrappers, Backend, MuJoCoSimulation};
+
+/// `mjtObj::mjOBJ_BODY`
+const OBJ_BODY: i32 = 1;
+
+/// Spatial velocity or acceleration of a body at its frame origin, in the MuJoCo world frame
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SpatialVector {
+    pub angular: [f64; 3],
+    pub linear: [f64; 3],
+}
+
+impl SpatialVector {
+    fn from_raw(raw: [f64; 6]) -> Self {
+        SpatialVector {
+            angular: [raw[0], raw[1], raw[2]],
+            linear: [raw[3], raw[4], raw[5]],
+        }
+    }
+}
+
+impl MuJoCoSimulation {
+    /// Id of a body by name
+    pub fn body_id(&self, name: &str) -> Option<usize> {
+        let simulation = self.lock().unwrap();
+        wrappers::body_id(wrappers::model(&simulation), name)
+    }
+
+    /// Mass of a body
+    pub fn body_mass(&self, body_id: usize) -> Option<f64> {
+        let simulation = self.lock().unwrap();
+        let model = wrappers::model(&simulation);
+        unsafe {
+            wrappers::slice(model.body_mass, model.nbody as usize)
+                .get(body_id)
+                .copied()
+        }
+    }
+
+    /// Diagonal inertia of a body in its inertial frame
+    pub fn body_inertia(&self, body_id: usize) -> Option<[f64; 3]> {
+        let simulation = self.lock().unwrap();
+        let model = wrappers::model(&simulation);
+        let inertia = unsafe { wrappers::slice(model.body_inertia, 3 * model.nbody as usize) };
+        let inertia = inertia.get(3 * body_id..3 * body_id + 3)?;
+        Some([inertia[0], inertia[1], inertia[2]])
+    }
+
+    /// Velocity of a body frame (from `cvel`)
+    pub fn body_velocity(&self, body_id: usize) -> Option<SpatialVector> {
+        let simulation = self.lock().unwrap();
+        let model = wrappers::model(&simulation);
+        if body_id >= model.nbody as usize {
+            return None;
+        }
+
+        let mut velocity = [0.0; 6];
+        unsafe {
+            crate::sys::no_render::mj_objectVelocity(
+                simulation.model_ptr(),
+                simulation.data_ptr(),
+                OBJ_BODY,
+                body_id as i32,
+                velocity.as_mut_ptr(),
+                0,
+            );
+        }
+        Some(SpatialVector::from_raw(velocity))
+    }
+
+    /// Acceleration of a body frame (from `cacc`), computed with `mj_rnePostConstraint`
+    pub fn body_acceleration(&self, body_id: usize) -> Option<SpatialVector> {
+        let simulation = self.lock().unwrap();
+        let model = wrappers::model(&simulation);
+        if body_id >= model.nbody as usize {
+            return None;
+        }
+
+        let mut acceleration = [0.0; 6];
+        unsafe {
+            crate::sys::no_render::mj_rnePostConstraint(
+                simulation.model_ptr(),
+                simulation.data_ptr(),
+            );
+            crate::sys::no_render::mj_objectAcceleration(
+                simulation.model_ptr(),
+                simulation.data_ptr(),
+                OBJ_BODY,
+                body_id as i32,
+                acceleration.as_mut_ptr(),
+                0,
+            );
+        }
+        Some(SpatialVector::from_raw(acceleration))
+    }
+}
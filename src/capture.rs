@@ -0,0 +1,196 @@
+//! Video capture in simulation time: a frame is due every `1 / fps` simulated seconds, whatever
+//! the wall clock does, so captures of the same run are the same video
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+
+use bevy::{
+    prelude::*,
+    render::view::screenshot::{Screenshot, ScreenshotCaptured},
+};
+
+use crate::{
+    wrappers, MuJoCoPluginSettings, MuJoCoSimulation, MuJoCoStepRequest, MuJoCoTime, StepMode,
+};
+
+/// Where captured frames go
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CaptureFormat {
+    /// `frame_000000.png`, `frame_000001.png`, ... in the `path` directory
+    #[default]
+    PngSequence,
+    /// Raw frames piped to an `ffmpeg` process encoding the `path` video file
+    Ffmpeg,
+}
+
+/// Capture the primary window while the resource exists. Frames are taken once simulation
+/// time reaches them; when a frame steps past several, the screenshot is repeated for each
+#[derive(Resource)]
+pub struct MuJoCoCapture {
+    pub fps: f64,
+    pub path: PathBuf,
+    pub format: CaptureFormat,
+    /// With `StepMode::Manual`, request the steps of one video frame after every capture so
+    /// each video frame is rendered, however slow rendering is
+    pub step_simulation: bool,
+    /// Video frames written or requested so far
+    frames: u64,
+    start: Option<f64>,
+    sink: Arc<Mutex<CaptureSink>>,
+}
+
+impl MuJoCoCapture {
+    pub fn new(fps: f64, path: impl Into<PathBuf>, format: CaptureFormat) -> Self {
+        let path = path.into();
+        MuJoCoCapture {
+            fps,
+            path: path.clone(),
+            format,
+            step_simulation: false,
+            frames: 0,
+            start: None,
+            sink: Arc::new(Mutex::new(CaptureSink {
+                path,
+                format,
+                fps,
+                ffmpeg: None,
+            })),
+        }
+    }
+
+    pub fn with_step_simulation(mut self) -> Self {
+        self.step_simulation = true;
+        self
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+}
+
+struct CaptureSink {
+    path: PathBuf,
+    format: CaptureFormat,
+    fps: f64,
+    ffmpeg: Option<Child>,
+}
+
+impl CaptureSink {
+    fn write(&mut self, image: &Image, first: u64, copies: u64) {
+        let Ok(image) = image.clone().try_into_dynamic() else {
+            error!("can't convert captured frame {first}");
+            return;
+        };
+        let image = image.to_rgba8();
+        match self.format {
+            CaptureFormat::PngSequence => {
+                if let Err(error) = std::fs::create_dir_all(&self.path) {
+                    error!("can't create {}: {error}", self.path.display());
+                    return;
+                }
+                for frame in first..first + copies {
+                    let path = self.path.join(format!("frame_{frame:06}.png"));
+                    if let Err(error) = image.save(&path) {
+                        error!("can't write {}: {error}", path.display());
+                    }
+                }
+            }
+            CaptureFormat::Ffmpeg => {
+                let Some(stdin) = self
+                    .ffmpeg(image.width(), image.height())
+                    .and_then(|ffmpeg| ffmpeg.stdin.as_mut())
+                else {
+                    return;
+                };
+                for _ in 0..copies {
+                    if let Err(error) = stdin.write_all(image.as_raw()) {
+                        error!("can't pipe frame to ffmpeg: {error}");
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Encoder for frames of the given size, started with the first frame
+    fn ffmpeg(&mut self, width: u32, height: u32) -> Option<&mut Child> {
+        if self.ffmpeg.is_none() {
+            let (fps, path) = (self.fps.to_string(), &self.path);
+            let child = Command::new("ffmpeg")
+                .args([
+                    "-y",
+                    "-loglevel",
+                    "error",
+                    "-f",
+                    "rawvideo",
+                    "-pix_fmt",
+                    "rgba",
+                ])
+                .args(["-s", &format!("{width}x{height}"), "-r", &fps, "-i", "-"])
+                .args(["-pix_fmt", "yuv420p"])
+                .arg(path)
+                .stdin(Stdio::piped())
+                .spawn();
+            match child {
+                Ok(child) => self.ffmpeg = Some(child),
+                Err(error) => error!("can't start ffmpeg: {error}"),
+            }
+        }
+        self.ffmpeg.as_mut()
+    }
+}
+
+impl Drop for CaptureSink {
+    fn drop(&mut self) {
+        // closing stdin lets ffmpeg finish the file
+        if let Some(mut ffmpeg) = self.ffmpeg.take() {
+            drop(ffmpeg.stdin.take());
+            let _ = ffmpeg.wait();
+        }
+    }
+}
+
+/// Take a screenshot when simulation time reached the next video frame
+pub(crate) fn capture_frames(
+    mut commands: Commands,
+    mut capture: ResMut<MuJoCoCapture>,
+    sim_time: Res<MuJoCoTime>,
+    settings: Res<MuJoCoPluginSettings>,
+    mujoco: Res<MuJoCoSimulation>,
+    mut step_requests: EventWriter<MuJoCoStepRequest>,
+) {
+    if capture.fps <= 0.0 {
+        return;
+    }
+
+    let start = *capture.start.get_or_insert(sim_time.elapsed);
+    // frames whose time has come, with some slack for accumulated step times
+    let due = ((sim_time.elapsed - start) * capture.fps + 1e-6).floor() as u64 + 1;
+    if due <= capture.frames {
+        return;
+    }
+    let (first, copies) = (capture.frames, due - capture.frames);
+    capture.frames = due;
+
+    let sink = capture.sink.clone();
+    {
+        // an encoder already running keeps its settings
+        let mut sink = sink.lock().unwrap();
+        sink.path.clone_from(&capture.path);
+        sink.format = capture.format;
+        sink.fps = capture.fps;
+    }
+    commands.spawn(Screenshot::primary_window()).observe(
+        move |captured: Trigger<ScreenshotCaptured>| {
+            sink.lock().unwrap().write(&captured.0, first, copies);
+        },
+    );
+
+    if capture.step_simulation && settings.step_mode == StepMode::Manual {
+        let timestep = wrappers::model(&mujoco.lock().unwrap()).opt.timestep;
+        let n_steps = (1.0 / (capture.fps * timestep)).round().max(1.0) as usize;
+        step_requests.send(MuJoCoStepRequest { n_steps });
+    }
+}
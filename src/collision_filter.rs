@@ -0,0 +1,119 @@
+use bevy::prelude::*;
+
+use crate::{wrappers, Backend, MuJoCoSimulation};
+
+/// Collision bitmasks of the loaded model's geoms, indexed by geom id. Two geoms collide when
+/// `contype` of one and `conaffinity` of the other share a bit. Disabled geoms keep their
+/// masks here and get zero masks in the model until enabled again.
+/// Modifying this resource writes the changed geoms into the model before the next step
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct MuJoCoCollisionFilter {
+    pub contype: Vec<i32>,
+    pub conaffinity: Vec<i32>,
+    pub enabled: Vec<bool>,
+    geom_names: Vec<String>,
+    body_names: Vec<String>,
+    geom_bodyid: Vec<usize>,
+    /// Masks last written into the model, other geoms are left alone so masks set elsewhere
+    /// (e.g. by the free body pool) aren't overwritten
+    written: Vec<(i32, i32)>,
+}
+
+impl MuJoCoCollisionFilter {
+    pub(crate) fn from_simulation(simulation: &impl Backend) -> Self {
+        let model = wrappers::model(simulation);
+        let ngeom = model.ngeom as usize;
+        let contype = unsafe { wrappers::slice(model.geom_contype, ngeom) }.to_vec();
+        let conaffinity = unsafe { wrappers::slice(model.geom_conaffinity, ngeom) }.to_vec();
+
+        MuJoCoCollisionFilter {
+            written: contype
+                .iter()
+                .copied()
+                .zip(conaffinity.iter().copied())
+                .collect(),
+            contype,
+            conaffinity,
+            enabled: vec![true; ngeom],
+            geom_names: wrappers::names(model, model.name_geomadr, model.ngeom),
+            body_names: wrappers::names(model, model.name_bodyadr, model.nbody),
+            geom_bodyid: unsafe { wrappers::slice(model.geom_bodyid, ngeom) }
+                .iter()
+                .map(|id| *id as usize)
+                .collect(),
+        }
+    }
+
+    fn geom_id(&self, geom: &str) -> Option<usize> {
+        self.geom_names.iter().position(|name| name == geom)
+    }
+
+    pub fn set_geom_contype(&mut self, geom: &str, contype: i32) -> bool {
+        let Some(id) = self.geom_id(geom) else {
+            return false;
+        };
+        self.contype[id] = contype;
+        true
+    }
+
+    pub fn set_geom_conaffinity(&mut self, geom: &str, conaffinity: i32) -> bool {
+        let Some(id) = self.geom_id(geom) else {
+            return false;
+        };
+        self.conaffinity[id] = conaffinity;
+        true
+    }
+
+    /// Enable or disable collisions of a geom, or of every geom of a body, by name.
+    /// Geom names are looked up first
+    pub fn set_collisions_enabled(&mut self, geom_or_body: &str, enabled: bool) -> bool {
+        if let Some(id) = self.geom_id(geom_or_body) {
+            self.enabled[id] = enabled;
+            return true;
+        }
+        let Some(body) = self.body_names.iter().position(|name| name == geom_or_body) else {
+            return false;
+        };
+        for (geom, geom_body) in self.geom_bodyid.iter().enumerate() {
+            if *geom_body == body {
+                self.enabled[geom] = enabled;
+            }
+        }
+        true
+    }
+
+    /// Write the masks of geoms that changed since the last write
+    pub(crate) fn write(&mut self, simulation: &impl Backend) {
+        let model = wrappers::model(simulation);
+        let ngeom = model.ngeom as usize;
+        let geom_contype = unsafe { wrappers::slice_mut(model.geom_contype, ngeom) };
+        let geom_conaffinity = unsafe { wrappers::slice_mut(model.geom_conaffinity, ngeom) };
+
+        for geom in 0..ngeom.min(self.written.len()) {
+            let masks = if self.enabled[geom] {
+                (self.contype[geom], self.conaffinity[geom])
+            } else {
+                (0, 0)
+            };
+            if masks != self.written[geom] {
+                geom_contype[geom] = masks.0;
+                geom_conaffinity[geom] = masks.1;
+                self.written[geom] = masks;
+            }
+        }
+    }
+}
+
+/// Write changed `MuJoCoCollisionFilter` masks into the model
+pub fn apply_collision_filter(
+    mujoco: Res<MuJoCoSimulation>,
+    mut collision_filter: ResMut<MuJoCoCollisionFilter>,
+) {
+    if !collision_filter.is_changed() {
+        return;
+    }
+
+    let mujoco = mujoco.lock().unwrap();
+    // don't mark the resource changed again
+    collision_filter.bypass_change_detection().write(&mujoco);
+}
@@ -0,0 +1,311 @@
+//! Collision-only proxies: bevy gameplay entities mirrored into the simulation as kinematic
+//! geoms. MuJoCo bodies collide with them and every contact is reported back to the game, so
+//! bevy content can push robots and objects around without being simulated itself
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    adapters::*, wrappers, Backend, FreeBodyShape, MuJoCoEntityMap, MuJoCoResources,
+    MuJoCoSceneRoot, MuJoCoSimulation, MuJoCoSystemSet,
+};
+
+/// Mocap bodies whose name starts with this prefix and that have a geom are used as a pool for
+/// `MuJoCoCollisionProxy` entities, e.g.
+/// `<body name="bevy_proxy_0" mocap="true"><geom type="sphere" size="0.1"/></body>`
+pub const COLLISION_PROXY_PREFIX: &str = "bevy_proxy_";
+
+/// Where unused proxies wait in MuJoCo world coordinates, with collisions disabled
+const PARKING_POSITION: [f64; 3] = [0.0, 0.0, -2000.0];
+
+/// Mirror this entity into the simulation as a kinematic geom of `shape`, following its
+/// `GlobalTransform` relative to the scene root. The geom pushes simulated bodies but is never
+/// pushed back; contacts are sent as `MuJoCoProxyContact` events and triggered on the entity.
+/// Changing the shape reshapes the geom, removing the component frees it
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct MuJoCoCollisionProxy {
+    pub shape: FreeBodyShape,
+}
+
+impl MuJoCoCollisionProxy {
+    pub fn new(shape: FreeBodyShape) -> Self {
+        MuJoCoCollisionProxy { shape }
+    }
+}
+
+/// A pre-allocated mocap body
+#[derive(Debug, Clone, Copy)]
+struct ProxySlot {
+    body: usize,
+    geom: usize,
+    mocap: usize,
+    contype: i32,
+    conaffinity: i32,
+}
+
+/// Pool of mocap bodies declared in the MJCF (see `COLLISION_PROXY_PREFIX`) and the entities
+/// using them
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MuJoCoCollisionProxies {
+    parked: Vec<ProxySlot>,
+    assigned: HashMap<Entity, ProxySlot>,
+}
+
+impl MuJoCoCollisionProxies {
+    /// Find pool bodies and park them
+    pub(crate) fn from_simulation(simulation: &impl Backend) -> Self {
+        let model = wrappers::model(simulation);
+        let (nbody, ngeom) = (model.nbody as usize, model.ngeom as usize);
+        let body_names = wrappers::names(model, model.name_bodyadr, model.nbody);
+        let body_mocapid = unsafe { wrappers::slice(model.body_mocapid, nbody) };
+        let body_geomadr = unsafe { wrappers::slice(model.body_geomadr, nbody) };
+        let geom_contype = unsafe { wrappers::slice(model.geom_contype, ngeom) };
+        let geom_conaffinity = unsafe { wrappers::slice(model.geom_conaffinity, ngeom) };
+
+        let parked: Vec<ProxySlot> = (0..nbody)
+            .filter(|&body| body_names[body].starts_with(COLLISION_PROXY_PREFIX))
+            .filter_map(|body| {
+                let (mocap, geom) = (body_mocapid[body], body_geomadr[body]);
+                if mocap < 0 || geom < 0 {
+                    warn!(
+                        "{} needs mocap=\"true\" and a geom to be used as a collision proxy",
+                        body_names[body]
+                    );
+                    return None;
+                }
+                let geom = geom as usize;
+                Some(ProxySlot {
+                    body,
+                    geom,
+                    mocap: mocap as usize,
+                    contype: geom_contype[geom],
+                    conaffinity: geom_conaffinity[geom],
+                })
+            })
+            .collect();
+
+        for (i, slot) in parked.iter().enumerate() {
+            park(simulation, slot, i);
+        }
+        MuJoCoCollisionProxies {
+            parked,
+            assigned: HashMap::new(),
+        }
+    }
+
+    /// Number of proxies that can still be added
+    pub fn available(&self) -> usize {
+        self.parked.len()
+    }
+
+    /// Geom mirroring an entity
+    pub fn geom(&self, entity: Entity) -> Option<usize> {
+        self.assigned.get(&entity).map(|slot| slot.geom)
+    }
+
+    /// Entity mirrored by a geom
+    pub fn entity(&self, geom: usize) -> Option<Entity> {
+        self.assigned
+            .iter()
+            .find(|(_, slot)| slot.geom == geom)
+            .map(|(entity, _)| *entity)
+    }
+}
+
+/// Disable collisions of a pool body and move it out of the way, spread apart by `index`
+fn park(simulation: &impl Backend, slot: &ProxySlot, index: usize) {
+    let model = wrappers::model(simulation);
+    let (ngeom, nmocap) = (model.ngeom as usize, model.nmocap as usize);
+    unsafe { wrappers::slice_mut(model.geom_contype, ngeom)[slot.geom] = 0 };
+    unsafe { wrappers::slice_mut(model.geom_conaffinity, ngeom)[slot.geom] = 0 };
+
+    let data = wrappers::data(simulation);
+    let position = [
+        PARKING_POSITION[0] + index as f64,
+        PARKING_POSITION[1],
+        PARKING_POSITION[2],
+    ];
+    unsafe {
+        wrappers::slice_mut(data.mocap_pos, 3 * nmocap)[3 * slot.mocap..3 * slot.mocap + 3]
+            .copy_from_slice(&position)
+    };
+}
+
+/// Give a pool body the shape of a proxy and enable its collisions
+fn shape(simulation: &impl Backend, slot: &ProxySlot, shape: FreeBodyShape) {
+    let model = wrappers::model(simulation);
+    let ngeom = model.ngeom as usize;
+    unsafe { wrappers::slice_mut(model.geom_type, ngeom)[slot.geom] = shape.geom_type().to_raw() };
+    unsafe {
+        wrappers::slice_mut(model.geom_size, 3 * ngeom)[3 * slot.geom..3 * slot.geom + 3]
+            .copy_from_slice(&shape.geom_size())
+    };
+    unsafe { wrappers::slice_mut(model.geom_rbound, ngeom)[slot.geom] = shape.bounding_radius() };
+    unsafe { wrappers::slice_mut(model.geom_contype, ngeom)[slot.geom] = slot.contype };
+    unsafe { wrappers::slice_mut(model.geom_conaffinity, ngeom)[slot.geom] = slot.conaffinity };
+}
+
+/// A contact between a collision proxy and another geom in the last step. Sent as an event
+/// and triggered on the proxy entity, so observers can react per entity
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct MuJoCoProxyContact {
+    pub proxy: Entity,
+    pub other_geom: i32,
+    /// -1 for flex contacts
+    pub other_body: i32,
+    /// Entity of the other body, or the other proxy's entity for contacts between proxies
+    pub other: Option<Entity>,
+    /// Contact point in the bevy scene frame
+    pub position: Vec3,
+    /// Contact normal pointing away from the proxy, in the bevy scene frame
+    pub normal: Vec3,
+    /// Penetration depth, positive when the geoms overlap
+    pub depth: f32,
+}
+
+/// Add `MuJoCoCollisionProxy` support: a proxy pool is created for every loaded model, proxy
+/// entities are mirrored before each step and their contacts are reported after it
+pub struct MuJoCoCollisionProxyPlugin;
+
+impl Plugin for MuJoCoCollisionProxyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<MuJoCoProxyContact>();
+        app.add_systems(
+            PreUpdate,
+            init_collision_proxies.run_if(resource_added::<MuJoCoSimulation>),
+        );
+        app.add_systems(
+            Update,
+            (
+                mirror_collision_proxies.in_set(MuJoCoSystemSet::Control),
+                report_proxy_contacts.after(MuJoCoSystemSet::Step),
+            )
+                .run_if(resource_exists::<MuJoCoCollisionProxies>)
+                .run_if(resource_exists::<MuJoCoResources>),
+        );
+    }
+}
+
+fn init_collision_proxies(mut commands: Commands, mujoco: Res<MuJoCoSimulation>) {
+    let mujoco = mujoco.lock().unwrap();
+    commands.insert_resource(MuJoCoCollisionProxies::from_simulation(&mujoco));
+}
+
+/// Assign, reshape and free pool bodies, then move the assigned ones to their entities' poses.
+/// Poses are the entities' `GlobalTransform`, propagated at the end of the previous frame
+#[allow(clippy::too_many_arguments)]
+fn mirror_collision_proxies(
+    mujoco: Res<MuJoCoSimulation>,
+    mut pool: ResMut<MuJoCoCollisionProxies>,
+    entity_map: Res<MuJoCoEntityMap>,
+    changed: Query<(Entity, &MuJoCoCollisionProxy), Changed<MuJoCoCollisionProxy>>,
+    proxies: Query<&GlobalTransform, With<MuJoCoCollisionProxy>>,
+    mut removed: RemovedComponents<MuJoCoCollisionProxy>,
+    scene_roots: Query<&GlobalTransform, With<MuJoCoSceneRoot>>,
+    mut visibilities: Query<&mut Visibility>,
+) {
+    let mujoco = mujoco.lock().unwrap();
+
+    for entity in removed.read() {
+        if let Some(slot) = pool.assigned.remove(&entity) {
+            park(&mujoco, &slot, pool.parked.len());
+            pool.parked.push(slot);
+        }
+    }
+
+    for (entity, proxy) in changed.iter() {
+        let slot = match pool.assigned.get(&entity) {
+            Some(slot) => *slot,
+            None => {
+                let Some(slot) = pool.parked.pop() else {
+                    warn_once!(
+                        "no collision proxies left in the model, add mocap bodies named \
+                         {COLLISION_PROXY_PREFIX}*"
+                    );
+                    continue;
+                };
+                pool.assigned.insert(entity, slot);
+                slot
+            }
+        };
+        shape(&mujoco, &slot, proxy.shape);
+    }
+
+    let model = wrappers::model(&mujoco);
+    let nmocap = model.nmocap as usize;
+    let data = wrappers::data(&mujoco);
+    let mocap_pos = unsafe { wrappers::slice_mut(data.mocap_pos, 3 * nmocap) };
+    let mocap_quat = unsafe { wrappers::slice_mut(data.mocap_quat, 4 * nmocap) };
+    let scene_transform = scene_roots.get_single().copied().unwrap_or_default();
+    for (entity, slot) in pool.assigned.iter() {
+        let Ok(transform) = proxies.get(*entity) else {
+            continue;
+        };
+        // poses are given in the bevy scene frame
+        let (position, quat) =
+            pose_bevy_2_mujoco(mujoco.up_axis, &transform.reparented_to(&scene_transform));
+        mocap_pos[3 * slot.mocap..3 * slot.mocap + 3].copy_from_slice(&position);
+        mocap_quat[4 * slot.mocap..4 * slot.mocap + 4].copy_from_slice(&quat);
+    }
+
+    // the gameplay entities are what's seen, never the pool bodies
+    for slot in pool.parked.iter().chain(pool.assigned.values()) {
+        let Some(body) = entity_map.body(slot.body as i32) else {
+            continue;
+        };
+        if let Ok(mut visibility) = visibilities.get_mut(body) {
+            visibility.set_if_neq(Visibility::Hidden);
+        }
+    }
+}
+
+fn report_proxy_contacts(
+    mut commands: Commands,
+    mujoco: Res<MuJoCoSimulation>,
+    pool: Res<MuJoCoCollisionProxies>,
+    entity_map: Res<MuJoCoEntityMap>,
+    mut contacts: EventWriter<MuJoCoProxyContact>,
+) {
+    if pool.assigned.is_empty() {
+        return;
+    }
+
+    let mujoco = mujoco.lock().unwrap();
+    let model = wrappers::model(&mujoco);
+    let data = wrappers::data(&mujoco);
+    let geom_bodyid = unsafe { wrappers::slice(model.geom_bodyid, model.ngeom as usize) };
+    let proxy_of = |geom: i32| {
+        usize::try_from(geom)
+            .ok()
+            .and_then(|geom| pool.entity(geom))
+    };
+
+    for contact in unsafe { wrappers::slice(data.contact, data.ncon as usize) } {
+        let geoms = wrappers::contact_geoms(contact);
+        // the contact normal points from the first geom to the second
+        for (side, sign) in [(0, 1.0), (1, -1.0)] {
+            let Some(proxy) = proxy_of(geoms[side]) else {
+                continue;
+            };
+            let other_geom = geoms[1 - side];
+            let other_body = match other_geom {
+                geom if geom >= 0 => geom_bodyid[geom as usize],
+                _ => -1,
+            };
+            let other = proxy_of(other_geom).or_else(|| entity_map.body(other_body));
+            let normal = [contact.frame[0], contact.frame[1], contact.frame[2]];
+            let event = MuJoCoProxyContact {
+                proxy,
+                other_geom,
+                other_body,
+                other,
+                position: position_mujoco_2_bevy(mujoco.up_axis, contact.pos),
+                normal: sign * position_mujoco_2_bevy(mujoco.up_axis, normal),
+                depth: -contact.dist as f32,
+            };
+            commands.trigger_targets(event.clone(), proxy);
+            contacts.send(event);
+        }
+    }
+}
@@ -0,0 +1,317 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::{
+    adapters::{mesh_aabb, position_bevy_2_mujoco},
+    equality::weld_current_pose,
+    free_body::show_free_body,
+    kinematics::teleport_body,
+    rigid_body::apply_impulse,
+    terrain::{model_hfield_mesh, write_hfield},
+    wrappers, FreeBodyShape, GeomType, HeightField, MuJoCoActuatorGroups, MuJoCoBody,
+    MuJoCoCollisionFilter, MuJoCoCollisionProxies, MuJoCoControlLimits, MuJoCoCustomData,
+    MuJoCoDiagnostics, MuJoCoEntityMap, MuJoCoEqualityConstraints, MuJoCoFreeBodyPool,
+    MuJoCoGeomClasses, MuJoCoJointControl, MuJoCoMjcf, MuJoCoModelInfo, MuJoCoOptions,
+    MuJoCoPdController, MuJoCoPhysicalParameters, MuJoCoPluginSettings, MuJoCoResources,
+    MuJoCoSimulation, MuJoCoStateBuffer, MuJoCoStateScratch, MuJoCoTime, MuJoCoUnsupportedFeatures,
+    FREE_BODY_POOL_PREFIX,
+};
+
+/// A request to change the simulation, applied right before the next step
+#[derive(Event, Debug, Clone)]
+pub enum MuJoCoCommand {
+    Weld {
+        body_a: String,
+        body_b: String,
+    },
+    Unweld {
+        body_a: String,
+        body_b: String,
+    },
+    SpawnFreeBody {
+        shape: FreeBodyShape,
+        mass: f64,
+        pose: Transform,
+    },
+    SetHeightField {
+        name: String,
+        field: HeightField,
+    },
+    TeleportBody {
+        body: String,
+        pose: Transform,
+    },
+    ApplyImpulse {
+        body: String,
+        impulse: Vec3,
+        point: Option<Vec3>,
+    },
+    Pause,
+    Resume,
+    /// Pause and advance the simulation by exactly one `mj_step`
+    StepOnce,
+    Unload,
+}
+
+/// High-level control of the simulation from gameplay systems
+#[derive(SystemParam)]
+pub struct MuJoCoCommands<'w> {
+    commands: EventWriter<'w, MuJoCoCommand>,
+}
+
+impl MuJoCoCommands<'_> {
+    /// Attach `body_b` to `body_a` at their current relative pose by activating the weld
+    /// equality between them. The weld has to exist in the MJCF, usually declared
+    /// with `active="false"`
+    pub fn weld(&mut self, body_a: &str, body_b: &str) {
+        self.commands.send(MuJoCoCommand::Weld {
+            body_a: body_a.to_string(),
+            body_b: body_b.to_string(),
+        });
+    }
+
+    /// Detach two bodies welded with `weld`
+    pub fn unweld(&mut self, body_a: &str, body_b: &str) {
+        self.commands.send(MuJoCoCommand::Unweld {
+            body_a: body_a.to_string(),
+            body_b: body_b.to_string(),
+        });
+    }
+
+    /// Drop a free body into the simulation at `pose` in the bevy scene frame. Bodies come
+    /// from a pool declared in the MJCF (see `FREE_BODY_POOL_PREFIX`), when the pool is
+    /// exhausted the oldest spawned body is reused
+    pub fn spawn_free_body(&mut self, shape: FreeBodyShape, mass: f64, pose: Transform) {
+        self.commands
+            .send(MuJoCoCommand::SpawnFreeBody { shape, mass, pose });
+    }
+
+    /// Replace the data of a height field by name and update the rendered terrain
+    pub fn set_hfield(&mut self, name: &str, field: HeightField) {
+        self.commands.send(MuJoCoCommand::SetHeightField {
+            name: name.to_string(),
+            field,
+        });
+    }
+
+    /// Move a free or mocap body to `pose` in the bevy scene frame with zero velocity, e.g. to
+    /// respawn a robot or randomize object placement. Entity transforms follow in the same frame
+    pub fn teleport_body(&mut self, body: &str, pose: Transform) {
+        self.commands.send(MuJoCoCommand::TeleportBody {
+            body: body.to_string(),
+            pose,
+        });
+    }
+
+    /// Hit a body with a linear impulse (N s) at `point`, or at its center of mass when `None`,
+    /// both in the bevy scene frame. The body's velocity changes before the next step
+    pub fn apply_impulse(&mut self, body: &str, impulse: Vec3, point: Option<Vec3>) {
+        self.commands.send(MuJoCoCommand::ApplyImpulse {
+            body: body.to_string(),
+            impulse,
+            point,
+        });
+    }
+
+    /// Stop stepping, transforms keep following poses set while paused
+    pub fn pause(&mut self) {
+        self.commands.send(MuJoCoCommand::Pause);
+    }
+
+    pub fn resume(&mut self) {
+        self.commands.send(MuJoCoCommand::Resume);
+    }
+
+    /// Pause and advance the simulation by exactly one `mj_step` with the current controls
+    pub fn step_once(&mut self) {
+        self.commands.send(MuJoCoCommand::StepOnce);
+    }
+
+    /// Despawn the scene and drop the simulation with all plugin resources. Insert a new
+    /// `MuJoCoSimulation` (e.g. from `MuJoCoSimulation::load`) afterwards to load another model
+    pub fn unload(&mut self) {
+        self.commands.send(MuJoCoCommand::Unload);
+    }
+}
+
+/// Apply `MuJoCoCommand`s sent this frame
+#[allow(clippy::too_many_arguments)]
+pub fn apply_mujoco_commands(
+    mujoco: Res<MuJoCoSimulation>,
+    mut commands: EventReader<MuJoCoCommand>,
+    mut equality_constraints: ResMut<MuJoCoEqualityConstraints>,
+    mut free_body_pool: ResMut<MuJoCoFreeBodyPool>,
+    mut physical_parameters: ResMut<MuJoCoPhysicalParameters>,
+    mut settings: ResMut<MuJoCoPluginSettings>,
+    entity_map: Res<MuJoCoEntityMap>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    bodies: Query<(Entity, &MuJoCoBody)>,
+    mut world_commands: Commands,
+) {
+    if commands.is_empty() {
+        return;
+    }
+
+    let mujoco = mujoco.lock().unwrap();
+    for command in commands.read() {
+        match command {
+            MuJoCoCommand::Weld { body_a, body_b } | MuJoCoCommand::Unweld { body_a, body_b } => {
+                let weld = matches!(command, MuJoCoCommand::Weld { .. });
+                let model = wrappers::model(&mujoco);
+                let (Some(a), Some(b)) = (
+                    wrappers::body_id(model, body_a),
+                    wrappers::body_id(model, body_b),
+                ) else {
+                    warn!("can't weld unknown bodies {body_a} and {body_b}");
+                    continue;
+                };
+
+                let Some(constraint) = equality_constraints.find_weld(a as i32, b as i32).cloned()
+                else {
+                    warn!("no weld equality between {body_a} and {body_b} in the model");
+                    continue;
+                };
+
+                if weld {
+                    weld_current_pose(&mujoco, &constraint);
+                }
+                equality_constraints.constraints[constraint.id].active = weld;
+            }
+            MuJoCoCommand::SpawnFreeBody { shape, mass, pose } => {
+                let Some(pooled) = free_body_pool.spawn(&mujoco, *shape, *mass, *pose) else {
+                    warn!("no free bodies in the model, add bodies named {FREE_BODY_POOL_PREFIX}*");
+                    continue;
+                };
+                physical_parameters.body_mass[pooled.body] = *mass;
+                show_free_body(
+                    &mut world_commands,
+                    &mut meshes,
+                    &entity_map,
+                    &pooled,
+                    *shape,
+                );
+            }
+            MuJoCoCommand::SetHeightField { name, field } => {
+                let model = wrappers::model(&mujoco);
+                let Some(hfield) =
+                    wrappers::name_to_id(model, model.name_hfieldadr, model.nhfield, name)
+                else {
+                    warn!("no height field named {name} in the model");
+                    continue;
+                };
+                if !write_hfield(&mujoco, hfield, field) {
+                    continue;
+                }
+
+                // borrowed again after the write
+                let model = wrappers::model(&mujoco);
+                let ngeom = model.ngeom as usize;
+                let geom_type = unsafe { wrappers::slice(model.geom_type, ngeom) };
+                let geom_dataid = unsafe { wrappers::slice(model.geom_dataid, ngeom) };
+                for geom in 0..ngeom {
+                    if GeomType::from_raw(geom_type[geom]) != GeomType::HFIELD
+                        || geom_dataid[geom] as usize != hfield
+                    {
+                        continue;
+                    }
+                    if let Some(entity) = entity_map.geom(geom as i32) {
+                        let mesh = meshes.add(model_hfield_mesh(&mujoco, hfield));
+                        let mut entity = world_commands.entity(entity);
+                        if let Some(aabb) = mesh_aabb(&meshes, &mesh) {
+                            entity.insert(aabb);
+                        }
+                        entity.insert(Mesh3d(mesh));
+                    }
+                }
+            }
+            MuJoCoCommand::TeleportBody { body, pose } => {
+                let Some(id) = wrappers::body_id(wrappers::model(&mujoco), body) else {
+                    warn!("can't teleport unknown body {body}");
+                    continue;
+                };
+                if !teleport_body(&mujoco, id, pose) {
+                    warn!(
+                        "can't teleport body {body}, it has no free joint and isn't a mocap body"
+                    );
+                }
+            }
+            MuJoCoCommand::ApplyImpulse {
+                body,
+                impulse,
+                point,
+            } => {
+                let model = wrappers::model(&mujoco);
+                let Some(id) = wrappers::body_id(model, body) else {
+                    warn!("can't push unknown body {body}");
+                    continue;
+                };
+                let point = match point {
+                    Some(point) => position_bevy_2_mujoco(mujoco.up_axis, *point),
+                    None => {
+                        let xipos = unsafe {
+                            wrappers::slice(wrappers::data(&mujoco).xipos, 3 * model.nbody as usize)
+                        };
+                        [xipos[3 * id], xipos[3 * id + 1], xipos[3 * id + 2]]
+                    }
+                };
+                let impulse = position_bevy_2_mujoco(mujoco.up_axis, *impulse);
+                apply_impulse(&mujoco, id, impulse, [0.0; 3], point);
+            }
+            MuJoCoCommand::Pause => settings.pause_simulation = true,
+            MuJoCoCommand::Resume => settings.pause_simulation = false,
+            MuJoCoCommand::StepOnce => {
+                // `simulate_physics` steps with this frame's controls, then stays paused
+                settings.pause_simulation = true;
+                settings.step_once = true;
+            }
+            MuJoCoCommand::Unload => {
+                unload(&mut world_commands, &entity_map, &bodies);
+                // later commands refer to the unloaded model
+                return;
+            }
+        }
+    }
+}
+
+/// Despawn plugin entities and remove plugin resources. The model and data are freed once
+/// the last `MuJoCoSimulation` handle is dropped, which happens when the commands are applied
+/// unless other plugins (e.g. IPC threads) still hold a clone
+fn unload(
+    commands: &mut Commands,
+    entity_map: &MuJoCoEntityMap,
+    bodies: &Query<(Entity, &MuJoCoBody)>,
+) {
+    match entity_map.world {
+        Some(world) => commands.entity(world).despawn_recursive(),
+        // keep the user's scene root, remove only the bodies spawned under it
+        None => {
+            for (entity, body) in bodies.iter() {
+                if body.root_body {
+                    commands.entity(entity).despawn_recursive();
+                }
+            }
+        }
+    }
+
+    commands.remove_resource::<MuJoCoSimulation>();
+    commands.remove_resource::<MuJoCoResources>();
+    commands.remove_resource::<MuJoCoEntityMap>();
+    commands.remove_resource::<MuJoCoOptions>();
+    commands.remove_resource::<MuJoCoPhysicalParameters>();
+    commands.remove_resource::<MuJoCoCollisionFilter>();
+    commands.remove_resource::<MuJoCoFreeBodyPool>();
+    commands.remove_resource::<MuJoCoCollisionProxies>();
+    commands.remove_resource::<MuJoCoStateBuffer>();
+    commands.remove_resource::<MuJoCoStateScratch>();
+    commands.remove_resource::<MuJoCoEqualityConstraints>();
+    commands.remove_resource::<MuJoCoJointControl>();
+    commands.remove_resource::<MuJoCoControlLimits>();
+    commands.remove_resource::<MuJoCoActuatorGroups>();
+    commands.remove_resource::<MuJoCoCustomData>();
+    commands.remove_resource::<MuJoCoModelInfo>();
+    commands.remove_resource::<MuJoCoPdController>();
+    commands.remove_resource::<MuJoCoTime>();
+    commands.remove_resource::<MuJoCoUnsupportedFeatures>();
+    commands.remove_resource::<MuJoCoGeomClasses>();
+    commands.remove_resource::<MuJoCoMjcf>();
+    commands.remove_resource::<MuJoCoDiagnostics>();
+}
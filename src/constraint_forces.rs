@@ -0,0 +1,133 @@
+use crate::{
+    physics_diagnostics::{constraint_violation, solver_iterations},
+    wrappers, Backend, MuJoCoSimulation,
+};
+
+/// `mjtCone::mjCONE_PYRAMIDAL`
+const CONE_PYRAMIDAL: i32 = 0;
+
+/// A contact of the last step with the force the solver found for it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContactForce {
+    pub geom1: i32,
+    pub geom2: i32,
+    /// Bodies of the geoms, -1 for flex contacts
+    pub body1: i32,
+    pub body2: i32,
+    /// Contact point in the world frame, MuJoCo coordinates
+    pub position: [f64; 3],
+    /// Contact frame, row-major, the first row is the contact normal
+    pub frame: [f64; 9],
+    /// Penetration distance, negative when penetrating
+    pub dist: f64,
+    /// Normal force, tangential friction and torsional/rolling friction in the contact frame
+    /// (`mj_contactForce`)
+    pub force: [f64; 6],
+    /// Solver impulses of the contact's constraint rows in `efc_force`, one per friction
+    /// pyramid edge or elliptic cone dimension
+    pub efc_force: Vec<f64>,
+}
+
+/// Statistics of the constraint solver for the last step
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SolverStatistics {
+    pub ncon: usize,
+    pub nefc: usize,
+    pub iterations: usize,
+    /// Largest equality residual or penetration over the active constraints
+    pub constraint_violation: f64,
+}
+
+impl MuJoCoSimulation {
+    /// Generalized constraint forces, `nv` values
+    pub fn qfrc_constraint(&self) -> Vec<f64> {
+        let simulation = self.lock().unwrap();
+        let nv = wrappers::model(&simulation).nv as usize;
+        unsafe { wrappers::slice(wrappers::data(&simulation).qfrc_constraint, nv).to_vec() }
+    }
+
+    /// Forces of every active constraint row, `nefc` values
+    pub fn efc_force(&self) -> Vec<f64> {
+        let simulation = self.lock().unwrap();
+        let data = wrappers::data(&simulation);
+        unsafe { wrappers::slice(data.efc_force, data.nefc as usize).to_vec() }
+    }
+
+    /// Contacts of the last step with their forces
+    pub fn contact_forces(&self) -> Vec<ContactForce> {
+        let simulation = self.lock().unwrap();
+        let model = wrappers::model(&simulation);
+        let data = wrappers::data(&simulation);
+        let geom_bodyid = unsafe { wrappers::slice(model.geom_bodyid, model.ngeom as usize) };
+        let efc_force = unsafe { wrappers::slice(data.efc_force, data.nefc as usize) };
+        let body = |geom: i32| {
+            if geom >= 0 {
+                geom_bodyid[geom as usize]
+            } else {
+                -1
+            }
+        };
+
+        let contacts = unsafe { wrappers::slice(data.contact, data.ncon as usize) };
+        contacts
+            .iter()
+            .enumerate()
+            .map(|(id, contact)| {
+                let mut force = [0.0; 6];
+                unsafe {
+                    crate::sys::no_render::mj_contactForce(
+                        simulation.model_ptr(),
+                        simulation.data_ptr(),
+                        id as i32,
+                        force.as_mut_ptr(),
+                    );
+                }
+                let [geom1, geom2] = wrappers::contact_geoms(contact);
+                // excluded contacts have no constraint rows
+                let rows = match contact.efc_address {
+                    adr if adr >= 0 => {
+                        let adr = adr as usize;
+                        // pyramidal cones have 2 (dim - 1) rows, elliptic cones dim rows
+                        let count = if model.opt.cone == CONE_PYRAMIDAL && contact.dim > 1 {
+                            2 * (contact.dim as usize - 1)
+                        } else {
+                            contact.dim as usize
+                        };
+                        efc_force.get(adr..adr + count).unwrap_or_default().to_vec()
+                    }
+                    _ => vec![],
+                };
+                ContactForce {
+                    geom1,
+                    geom2,
+                    body1: body(geom1),
+                    body2: body(geom2),
+                    position: contact.pos,
+                    frame: contact.frame,
+                    dist: contact.dist,
+                    force,
+                    efc_force: rows,
+                }
+            })
+            .collect()
+    }
+
+    /// Contacts of the last step involving a body
+    pub fn body_contact_forces(&self, body_id: usize) -> Vec<ContactForce> {
+        self.contact_forces()
+            .into_iter()
+            .filter(|contact| contact.body1 == body_id as i32 || contact.body2 == body_id as i32)
+            .collect()
+    }
+
+    pub fn solver_statistics(&self) -> SolverStatistics {
+        let simulation = self.lock().unwrap();
+        let data = wrappers::data(&simulation);
+        SolverStatistics {
+            ncon: data.ncon as usize,
+            nefc: data.nefc as usize,
+            iterations: solver_iterations(data) as usize,
+            constraint_violation: constraint_violation(data),
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::{
+    wrappers, Backend, BiasType, MuJoCoPdController, MuJoCoResources, MuJoCoSimulation,
+    TransmissionType,
+};
+
+/// Command for a single joint, converted to the `ctrl` of the actuator driving it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MuJoCoControlMode {
+    /// Track a joint position with PD gains
+    Position { target: f64, kp: f64, kd: f64 },
+    /// Track a joint velocity with a damping gain
+    Velocity { target: f64, kd: f64 },
+    /// Apply a joint torque (or force for slide joints) directly
+    Torque(f64),
+}
+
+/// Actuator driving a joint through a joint transmission
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct JointActuator {
+    pub(crate) actuator: usize,
+    pub(crate) qpos_adr: usize,
+    pub(crate) dof_adr: usize,
+}
+
+/// Per-joint control targets keyed by joint name. The commands are turned into controls of
+/// the actuators driving those joints before every step, from that step's qpos/qvel, and
+/// into `MuJoCoResources::control` once a frame. Joints without a joint-transmission actuator
+/// are ignored
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MuJoCoJointControl {
+    pub modes: HashMap<String, MuJoCoControlMode>,
+    pub(crate) actuators: HashMap<String, JointActuator>,
+}
+
+impl MuJoCoJointControl {
+    pub(crate) fn from_simulation(simulation: &impl Backend) -> Self {
+        MuJoCoJointControl {
+            modes: HashMap::new(),
+            actuators: joint_actuators(simulation),
+        }
+    }
+
+    pub fn set(&mut self, joint: &str, mode: MuJoCoControlMode) {
+        self.modes.insert(joint.to_string(), mode);
+    }
+
+    /// Names of joints that can be commanded
+    pub fn joints(&self) -> impl Iterator<Item = &String> {
+        self.actuators.keys()
+    }
+}
+
+/// Map joint names to the actuators driving them
+pub(crate) fn joint_actuators(simulation: &impl Backend) -> HashMap<String, JointActuator> {
+    let model = wrappers::model(simulation);
+    let (nu, njnt) = (model.nu as usize, model.njnt as usize);
+    let trntype = unsafe { wrappers::slice(model.actuator_trntype, nu) };
+    let trnid = unsafe { wrappers::slice(model.actuator_trnid, 2 * nu) };
+    let qpos_adr = unsafe { wrappers::slice(model.jnt_qposadr, njnt) };
+    let dof_adr = unsafe { wrappers::slice(model.jnt_dofadr, njnt) };
+    let joint_names = wrappers::names(model, model.name_jntadr, model.njnt);
+
+    (0..nu)
+        .filter(|&actuator| {
+            TransmissionType::from_raw(trntype[actuator]) == TransmissionType::JOINT
+        })
+        .map(|actuator| {
+            let joint = trnid[2 * actuator] as usize;
+            let joint_actuator = JointActuator {
+                actuator,
+                qpos_adr: qpos_adr[joint] as usize,
+                dof_adr: dof_adr[joint] as usize,
+            };
+            (joint_names[joint].clone(), joint_actuator)
+        })
+        .collect()
+}
+
+/// Control value that makes an actuator apply `torque` to its joint, inverting the
+/// actuator model `force = gain * ctrl + bias` with the MJCF gain/bias parameters
+/// and the transmission gear. Activation dynamics are not taken into account
+pub(crate) fn ctrl_for_torque(simulation: &impl Backend, actuator: usize, torque: f64) -> f64 {
+    let model = wrappers::model(simulation);
+    let data = wrappers::data(simulation);
+    let nu = model.nu as usize;
+
+    // mjNGAIN = mjNBIAS = 10, gear has 6 entries
+    let gain = unsafe { wrappers::slice(model.actuator_gainprm, 10 * nu) }[10 * actuator];
+    let bias = &unsafe { wrappers::slice(model.actuator_biasprm, 10 * nu) }
+        [10 * actuator..10 * actuator + 3];
+    let biastype = unsafe { wrappers::slice(model.actuator_biastype, nu) }[actuator];
+    let gear = unsafe { wrappers::slice(model.actuator_gear, 6 * nu) }[6 * actuator];
+    let length = unsafe { wrappers::slice(data.actuator_length, nu) }[actuator];
+    let velocity = unsafe { wrappers::slice(data.actuator_velocity, nu) }[actuator];
+
+    let force = if gear != 0.0 { torque / gear } else { torque };
+    let bias = if BiasType::from_raw(biastype) == BiasType::AFFINE {
+        bias[0] + bias[1] * length + bias[2] * velocity
+    } else {
+        0.0
+    };
+
+    if gain != 0.0 {
+        (force - bias) / gain
+    } else {
+        0.0
+    }
+}
+
+/// Turn `MuJoCoJointControl` commands into actuator controls, from the state at the start of
+/// the frame. `simulate_physics` evaluates them again before every step
+pub fn apply_joint_control(
+    mujoco: Res<MuJoCoSimulation>,
+    joint_control: Res<MuJoCoJointControl>,
+    mut mujoco_resources: ResMut<MuJoCoResources>,
+) {
+    if joint_control.modes.is_empty() {
+        return;
+    }
+
+    let mujoco = mujoco.lock().unwrap();
+    let control = &mut mujoco_resources.control;
+    control.data.resize(control.number_of_controls, 0.0);
+    joint_control.write_controls(&mujoco, &mut control.data);
+}
+
+impl MuJoCoJointControl {
+    /// Write the controls of the commanded joints' actuators into `ctrl`, which holds a value
+    /// per actuator
+    pub(crate) fn write_controls(&self, simulation: &impl Backend, ctrl: &mut [f64]) {
+        let model = wrappers::model(simulation);
+        let data = wrappers::data(simulation);
+        let qpos = unsafe { wrappers::slice(data.qpos, model.nq as usize) };
+        let qvel = unsafe { wrappers::slice(data.qvel, model.nv as usize) };
+
+        for (joint, mode) in self.modes.iter() {
+            let Some(joint_actuator) = self.actuators.get(joint) else {
+                continue;
+            };
+            let q = qpos[joint_actuator.qpos_adr];
+            let v = qvel[joint_actuator.dof_adr];
+
+            let torque = match *mode {
+                MuJoCoControlMode::Position { target, kp, kd } => kp * (target - q) - kd * v,
+                MuJoCoControlMode::Velocity { target, kd } => kd * (target - v),
+                MuJoCoControlMode::Torque(torque) => torque,
+            };
+            ctrl[joint_actuator.actuator] =
+                ctrl_for_torque(simulation, joint_actuator.actuator, torque);
+        }
+    }
+}
+
+/// Joint-space feedback `simulate_physics` runs before every step, so position and velocity
+/// commands track the state of that step instead of the one at the start of the frame
+#[derive(SystemParam)]
+pub struct JointFeedback<'w> {
+    joint_control: Res<'w, MuJoCoJointControl>,
+    pd_controller: Res<'w, MuJoCoPdController>,
+}
+
+impl JointFeedback<'_> {
+    /// `target` with the controls of commanded joints evaluated at the current state, or
+    /// `None` when no joint is commanded. PD targets win over joint commands, like the
+    /// order `apply_joint_control` and `apply_pd_controller` run in
+    pub(crate) fn controls(&self, simulation: &impl Backend, target: &[f64]) -> Option<Vec<f64>> {
+        if self.joint_control.modes.is_empty() && self.pd_controller.target_qpos.is_empty() {
+            return None;
+        }
+        let mut ctrl = target.to_vec();
+        ctrl.resize(ctrl.len().max(wrappers::model(simulation).nu as usize), 0.0);
+        self.joint_control.write_controls(simulation, &mut ctrl);
+        self.pd_controller
+            .write_controls(simulation, &self.joint_control, &mut ctrl);
+        Some(ctrl)
+    }
+}
@@ -0,0 +1,120 @@
+use bevy::prelude::*;
+
+use crate::{wrappers, MuJoCoResources, MuJoCoSimulation};
+
+/// Actuator addressed by id or by name
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ActuatorRef {
+    Id(usize),
+    Name(String),
+}
+
+impl From<usize> for ActuatorRef {
+    fn from(id: usize) -> Self {
+        ActuatorRef::Id(id)
+    }
+}
+
+impl From<&str> for ActuatorRef {
+    fn from(name: &str) -> Self {
+        ActuatorRef::Name(name.to_string())
+    }
+}
+
+impl From<String> for ActuatorRef {
+    fn from(name: String) -> Self {
+        ActuatorRef::Name(name)
+    }
+}
+
+impl ActuatorRef {
+    /// Actuator id in the compiled model
+    pub(crate) fn resolve(&self, model: &crate::sys::mjModel) -> Option<usize> {
+        match self {
+            ActuatorRef::Id(id) => (*id < model.nu as usize).then_some(*id),
+            ActuatorRef::Name(name) => {
+                wrappers::name_to_id(model, model.name_actuatoradr, model.nu, name)
+            }
+        }
+    }
+}
+
+/// Set the control of one actuator, an alternative to writing `MuJoCoResources::control`.
+/// Applied right before stepping, when several events target the same actuator in a frame
+/// the last one wins. The value is kept until it is changed again
+#[derive(Event, Debug, Clone)]
+pub struct MuJoCoControlEvent {
+    pub actuator: ActuatorRef,
+    pub value: f64,
+}
+
+impl MuJoCoControlEvent {
+    pub fn new(actuator: impl Into<ActuatorRef>, value: f64) -> Self {
+        MuJoCoControlEvent {
+            actuator: actuator.into(),
+            value,
+        }
+    }
+}
+
+/// Several controls in one event, applied in order before the `MuJoCoControlEvent`s of the frame
+#[derive(Event, Debug, Clone, Default)]
+pub struct MuJoCoControlBatch {
+    pub controls: Vec<(ActuatorRef, f64)>,
+}
+
+impl MuJoCoControlBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(mut self, actuator: impl Into<ActuatorRef>, value: f64) -> Self {
+        self.controls.push((actuator.into(), value));
+        self
+    }
+}
+
+/// Write control events into `MuJoCoResources::control`. Unknown actuators and non-finite
+/// values are dropped, values outside `ctrlrange` of limited actuators are clamped
+pub fn apply_control_events(
+    mujoco: Res<MuJoCoSimulation>,
+    mut mujoco_resources: ResMut<MuJoCoResources>,
+    mut events: EventReader<MuJoCoControlEvent>,
+    mut batches: EventReader<MuJoCoControlBatch>,
+) {
+    if events.is_empty() && batches.is_empty() {
+        return;
+    }
+
+    let mujoco = mujoco.lock().unwrap();
+    let model = wrappers::model(&mujoco);
+    let nu = model.nu as usize;
+    let ctrllimited = unsafe { wrappers::slice(model.actuator_ctrllimited, nu) };
+    let ctrlrange = unsafe { wrappers::slice(model.actuator_ctrlrange, 2 * nu) };
+
+    let control = &mut mujoco_resources.control;
+    control.data.resize(control.number_of_controls, 0.0);
+
+    let controls = batches
+        .read()
+        .flat_map(|batch| batch.controls.iter().map(|(a, v)| (a, *v)))
+        .chain(events.read().map(|event| (&event.actuator, event.value)));
+    for (actuator, value) in controls {
+        let Some(id) = actuator.resolve(model) else {
+            warn!("control for unknown actuator {actuator:?}");
+            continue;
+        };
+        if !value.is_finite() {
+            warn!("non-finite control {value} for actuator {actuator:?}");
+            continue;
+        }
+
+        let (low, high) = (ctrlrange[2 * id], ctrlrange[2 * id + 1]);
+        control.data[id] = if ctrllimited[id] != 0 && !(low..=high).contains(&value) {
+            warn!("control {value} for actuator {actuator:?} clamped to [{low}, {high}]");
+            value.clamp(low, high)
+        } else {
+            value
+        };
+    }
+}
@@ -0,0 +1,69 @@
+use bevy::prelude::*;
+
+use crate::{wrappers, Backend};
+
+/// Limits applied to the controls before every step
+#[derive(Resource, Debug, Clone)]
+pub struct MuJoCoControlLimits {
+    /// Clamp controls of actuators with `ctrllimited` to their `ctrlrange`
+    pub clamp: bool,
+    /// Largest change of any control per second of simulated time, unlimited when `None`
+    pub slew_rate: Option<f64>,
+    /// Slew rates per actuator id, overriding `slew_rate`
+    pub actuator_slew_rates: Vec<Option<f64>>,
+    clipped: Vec<bool>,
+}
+
+impl MuJoCoControlLimits {
+    pub(crate) fn from_simulation(simulation: &impl Backend) -> Self {
+        let nu = wrappers::model(simulation).nu as usize;
+        MuJoCoControlLimits {
+            clamp: true,
+            slew_rate: None,
+            actuator_slew_rates: vec![None; nu],
+            clipped: vec![false; nu],
+        }
+    }
+
+    /// Write `target` controls into `mjData.ctrl` for a step of `dt` seconds.
+    /// Logs when a control starts being clamped
+    pub(crate) fn apply(&mut self, simulation: &impl Backend, target: &[f64], dt: f64) {
+        let model = wrappers::model(simulation);
+        let nu = model.nu as usize;
+        let ctrllimited = unsafe { wrappers::slice(model.actuator_ctrllimited, nu) };
+        let ctrlrange = unsafe { wrappers::slice(model.actuator_ctrlrange, 2 * nu) };
+        let ctrl = unsafe { wrappers::slice_mut(wrappers::data(simulation).ctrl, nu) };
+        self.clipped.resize(nu, false);
+
+        for (id, (ctrl, target)) in ctrl.iter_mut().zip(target).enumerate() {
+            let mut value = *target;
+
+            let (low, high) = (ctrlrange[2 * id], ctrlrange[2 * id + 1]);
+            let clipped = self.clamp && ctrllimited[id] != 0 && !(low..=high).contains(&value);
+            if clipped {
+                value = value.clamp(low, high);
+                if !self.clipped[id] {
+                    let name = unsafe { wrappers::slice(model.name_actuatoradr, nu) }[id];
+                    warn!(
+                        "control {target} of actuator {} clamped to [{low}, {high}]",
+                        wrappers::name(model, name)
+                    );
+                }
+            }
+            self.clipped[id] = clipped;
+
+            let slew_rate = self
+                .actuator_slew_rates
+                .get(id)
+                .copied()
+                .flatten()
+                .or(self.slew_rate);
+            if let Some(slew_rate) = slew_rate {
+                let max_change = slew_rate * dt;
+                value = value.clamp(*ctrl - max_change, *ctrl + max_change);
+            }
+
+            *ctrl = value;
+        }
+    }
+}
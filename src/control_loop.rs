@@ -0,0 +1,126 @@
+//! Control at a fixed rate below the physics rate: a control tick happens every
+//! `control_decimation` physics steps, e.g. a 50 Hz policy on a 1 kHz simulation ticks every
+//! 20 steps, independent of how many steps a frame takes
+
+use bevy::prelude::*;
+
+use crate::{wrappers, Backend};
+
+/// Simulation state a control callback sees at a tick, in MuJoCo coordinates
+pub struct ControlInput<'a> {
+    pub tick: u64,
+    pub time: f64,
+    pub qpos: &'a [f64],
+    pub qvel: &'a [f64],
+    pub act: &'a [f64],
+    /// Forces the actuators applied in the last step, in actuation and in joint space
+    pub actuator_force: &'a [f64],
+    pub qfrc_actuator: &'a [f64],
+    pub sensor_data: &'a [f64],
+}
+
+type ControlCallback = Box<dyn FnMut(&ControlInput, &mut [f64]) + Send + Sync>;
+
+/// Rate of control ticks and the callback run at each of them. With a callback the controls it
+/// writes are held until the next tick and used instead of `MuJoCoResources::control`
+#[derive(Resource)]
+pub struct MuJoCoControlLoop {
+    /// Physics steps per control tick, 0 and 1 tick at every step
+    pub control_decimation: usize,
+    callback: Option<ControlCallback>,
+    ctrl: Vec<f64>,
+    /// Steps since the last tick, carried over across frames
+    phase: usize,
+    ticks: u64,
+}
+
+impl Default for MuJoCoControlLoop {
+    fn default() -> Self {
+        MuJoCoControlLoop::new(1)
+    }
+}
+
+impl MuJoCoControlLoop {
+    pub fn new(control_decimation: usize) -> Self {
+        MuJoCoControlLoop {
+            control_decimation,
+            callback: None,
+            ctrl: vec![],
+            phase: 0,
+            ticks: 0,
+        }
+    }
+
+    /// Run `callback` at every tick, between physics steps. It gets the state and the `nu`
+    /// controls of the last tick to overwrite
+    pub fn with_callback(
+        mut self,
+        callback: impl FnMut(&ControlInput, &mut [f64]) + Send + Sync + 'static,
+    ) -> Self {
+        self.set_callback(callback);
+        self
+    }
+
+    pub fn set_callback(
+        &mut self,
+        callback: impl FnMut(&ControlInput, &mut [f64]) + Send + Sync + 'static,
+    ) {
+        self.callback = Some(Box::new(callback));
+    }
+
+    /// Go back to `MuJoCoResources::control`
+    pub fn clear_callback(&mut self) {
+        self.callback = None;
+        self.ctrl.clear();
+    }
+
+    /// Number of ticks so far
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Advance by one physics step, returning the tick number if a tick is due before it
+    pub(crate) fn tick(&mut self) -> Option<u64> {
+        let due = self.phase == 0;
+        self.phase = (self.phase + 1) % self.control_decimation.max(1);
+        if !due {
+            return None;
+        }
+        self.ticks += 1;
+        Some(self.ticks - 1)
+    }
+
+    /// Run the callback on the current state
+    pub(crate) fn run(&mut self, simulation: &impl Backend, tick: u64) {
+        let Some(callback) = self.callback.as_mut() else {
+            return;
+        };
+        let model = wrappers::model(simulation);
+        let data = wrappers::data(simulation);
+        self.ctrl.resize(model.nu as usize, 0.0);
+        let input = ControlInput {
+            tick,
+            time: data.time,
+            qpos: unsafe { wrappers::slice(data.qpos, model.nq as usize) },
+            qvel: unsafe { wrappers::slice(data.qvel, model.nv as usize) },
+            act: unsafe { wrappers::slice(data.act, model.na as usize) },
+            actuator_force: unsafe { wrappers::slice(data.actuator_force, model.nu as usize) },
+            qfrc_actuator: unsafe { wrappers::slice(data.qfrc_actuator, model.nv as usize) },
+            sensor_data: unsafe { wrappers::slice(data.sensordata, model.nsensordata as usize) },
+        };
+        callback(&input, &mut self.ctrl);
+    }
+
+    /// Controls held from the last tick, `None` without a callback
+    pub(crate) fn control(&self) -> Option<&[f64]> {
+        self.callback.as_ref().map(|_| self.ctrl.as_slice())
+    }
+}
+
+/// Sent for every control tick of a frame once the frame's steps are done
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ControlTick {
+    pub tick: u64,
+    /// Simulation time at the tick
+    pub time: f64,
+}
@@ -0,0 +1,116 @@
+use bevy::{
+    pbr::wireframe::Wireframe,
+    prelude::*,
+    render::{mesh::Indices, render_asset::RenderAssetUsages, render_resource::PrimitiveTopology},
+};
+
+use crate::{wrappers, Backend, MuJoCoMesh, MuJoCoPluginSettings, MuJoCoSimulation};
+
+/// How to display the convex hulls MuJoCo uses to collide mesh geoms
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConvexHullDisplay {
+    #[default]
+    Hidden,
+    /// Semi-transparent hull drawn over the visual mesh
+    Transparent,
+    /// Hull edges only, requires bevy's `WireframePlugin`
+    Wireframe,
+}
+
+/// Convex hull of the mesh geom with the given id, spawned as a child of its `MuJoCoMesh` entity
+#[derive(Component)]
+pub struct MuJoCoConvexHull {
+    pub geom_id: i32,
+}
+
+/// Build the convex hull MuJoCo computed for a mesh geom from `mesh_graph`
+pub(crate) fn convex_hull_mesh(simulation: &impl Backend, geom_id: i32) -> Option<Mesh> {
+    let model = wrappers::model(simulation);
+    let mesh_id = *unsafe { wrappers::slice(model.geom_dataid, model.ngeom as usize) }
+        .get(geom_id as usize)?;
+    if mesh_id < 0 {
+        return None;
+    }
+    let mesh_id = mesh_id as usize;
+    let nmesh = model.nmesh as usize;
+
+    let graph_adr = unsafe { wrappers::slice(model.mesh_graphadr, nmesh) }[mesh_id];
+    if graph_adr < 0 {
+        return None;
+    }
+    let vert_adr = unsafe { wrappers::slice(model.mesh_vertadr, nmesh) }[mesh_id] as usize;
+    let vert_num = unsafe { wrappers::slice(model.mesh_vertnum, nmesh) }[mesh_id] as usize;
+    let vertices = &unsafe { wrappers::slice(model.mesh_vert, 3 * model.nmeshvert as usize) }
+        [3 * vert_adr..3 * (vert_adr + vert_num)];
+
+    // graph layout: numvert, numface, vert_edgeadr[numvert], vert_globalid[numvert],
+    // edge_localid[numvert + 3 * numface], face_globalid[3 * numface]
+    let graph = &unsafe { wrappers::slice(model.mesh_graph, model.nmeshgraph as usize) }
+        [graph_adr as usize..];
+    let (numvert, numface) = (graph[0] as usize, graph[1] as usize);
+    let face_adr = 2 + numvert + numvert + (numvert + 3 * numface);
+    let faces = &graph[face_adr..face_adr + 3 * numface];
+
+    // Flat shaded hull: every face gets its own vertices
+    let positions: Vec<[f32; 3]> = faces
+        .iter()
+        .map(|&v| {
+            let v = v as usize;
+            [vertices[3 * v], vertices[3 * v + 1], vertices[3 * v + 2]]
+        })
+        .collect();
+
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+    );
+    mesh.insert_indices(Indices::U32((0..positions.len() as u32).collect()));
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.compute_flat_normals();
+    Some(mesh)
+}
+
+/// Attach convex hull entities to newly spawned mesh geoms
+pub fn spawn_convex_hulls(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mujoco: Res<MuJoCoSimulation>,
+    settings: Res<MuJoCoPluginSettings>,
+    mesh_geoms: Query<(Entity, &MuJoCoMesh), Added<MuJoCoMesh>>,
+) {
+    if settings.convex_hulls == ConvexHullDisplay::Hidden || mesh_geoms.is_empty() {
+        return;
+    }
+
+    let mujoco = mujoco.lock().unwrap();
+    let material = materials.add(StandardMaterial {
+        base_color: Color::srgba(1.0, 0.5, 0.0, 0.35),
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        ..default()
+    });
+
+    for (entity, mesh_geom) in mesh_geoms.iter() {
+        let Some(hull) = convex_hull_mesh(&mujoco, mesh_geom.id) else {
+            continue;
+        };
+
+        commands.entity(entity).with_children(|children| {
+            let mut cmd = children.spawn((
+                MuJoCoConvexHull {
+                    geom_id: mesh_geom.id,
+                },
+                Name::new(format!("MuJoCo::convex_hull_{}", mesh_geom.id)),
+                Mesh3d(meshes.add(hull)),
+                Transform::IDENTITY,
+            ));
+
+            if settings.convex_hulls == ConvexHullDisplay::Wireframe {
+                cmd.insert(Wireframe);
+            } else {
+                cmd.insert(MeshMaterial3d(material.clone()));
+            }
+        });
+    }
+}
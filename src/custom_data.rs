@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{wrappers, Backend};
+
+/// Element of a `<custom><tuple>`: an object of the model and a parameter
+#[derive(Debug, Clone, PartialEq)]
+pub struct TupleElement {
+    /// Object type (`mjtObj`)
+    pub object_type: i32,
+    pub object_id: usize,
+    /// Name of the object, empty for unnamed objects
+    pub object_name: String,
+    pub parameter: f64,
+}
+
+/// `<custom>` fields of the model by name: `<numeric>` arrays, `<text>` strings and
+/// `<tuple>` object lists, often used for gains and policy metadata
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MuJoCoCustomData {
+    pub numeric: HashMap<String, Vec<f64>>,
+    pub text: HashMap<String, String>,
+    pub tuple: HashMap<String, Vec<TupleElement>>,
+}
+
+impl MuJoCoCustomData {
+    pub(crate) fn from_simulation(simulation: &impl Backend) -> Self {
+        let model = wrappers::model(simulation);
+        let (nnumeric, ntext, ntuple) = (
+            model.nnumeric as usize,
+            model.ntext as usize,
+            model.ntuple as usize,
+        );
+
+        let numeric_adr = unsafe { wrappers::slice(model.numeric_adr, nnumeric) };
+        let numeric_size = unsafe { wrappers::slice(model.numeric_size, nnumeric) };
+        let numeric_data =
+            unsafe { wrappers::slice(model.numeric_data, model.nnumericdata as usize) };
+        let numeric = wrappers::names(model, model.name_numericadr, model.nnumeric)
+            .into_iter()
+            .enumerate()
+            .map(|(id, name)| {
+                let adr = numeric_adr[id] as usize;
+                (
+                    name,
+                    numeric_data[adr..adr + numeric_size[id] as usize].to_vec(),
+                )
+            })
+            .collect();
+
+        let text_adr = unsafe { wrappers::slice(model.text_adr, ntext) };
+        let text_size = unsafe { wrappers::slice(model.text_size, ntext) };
+        let text_data = unsafe { wrappers::slice(model.text_data, model.ntextdata as usize) };
+        let text = wrappers::names(model, model.name_textadr, model.ntext)
+            .into_iter()
+            .enumerate()
+            .map(|(id, name)| {
+                let adr = text_adr[id] as usize;
+                // the size includes the terminating null
+                let bytes: Vec<u8> = text_data[adr..adr + text_size[id] as usize]
+                    .iter()
+                    .take_while(|c| **c != 0)
+                    .map(|c| *c as u8)
+                    .collect();
+                (name, String::from_utf8_lossy(&bytes).into_owned())
+            })
+            .collect();
+
+        let tuple_adr = unsafe { wrappers::slice(model.tuple_adr, ntuple) };
+        let tuple_size = unsafe { wrappers::slice(model.tuple_size, ntuple) };
+        let ntupledata = model.ntupledata as usize;
+        let tuple_objtype = unsafe { wrappers::slice(model.tuple_objtype, ntupledata) };
+        let tuple_objid = unsafe { wrappers::slice(model.tuple_objid, ntupledata) };
+        let tuple_objprm = unsafe { wrappers::slice(model.tuple_objprm, ntupledata) };
+        let tuple = wrappers::names(model, model.name_tupleadr, model.ntuple)
+            .into_iter()
+            .enumerate()
+            .map(|(id, name)| {
+                let adr = tuple_adr[id] as usize;
+                let elements = (adr..adr + tuple_size[id] as usize)
+                    .map(|i| TupleElement {
+                        object_type: tuple_objtype[i],
+                        object_id: tuple_objid[i] as usize,
+                        object_name: wrappers::object_name(model, tuple_objtype[i], tuple_objid[i])
+                            .unwrap_or_default(),
+                        parameter: tuple_objprm[i],
+                    })
+                    .collect();
+                (name, elements)
+            })
+            .collect();
+
+        MuJoCoCustomData {
+            numeric,
+            text,
+            tuple,
+        }
+    }
+
+    /// Numeric field with a single value
+    pub fn scalar(&self, name: &str) -> Option<f64> {
+        self.numeric.get(name)?.first().copied()
+    }
+}
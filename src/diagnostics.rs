@@ -0,0 +1,95 @@
+use bevy::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticSeverity {
+    Warning,
+    Error,
+}
+
+/// A message reported by the MuJoCo compiler while loading the model
+#[derive(Debug, Clone)]
+pub struct MuJoCoDiagnostic {
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+    /// MJCF file the message refers to, the model file unless MuJoCo names another one
+    pub file: String,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+}
+
+impl MuJoCoDiagnostic {
+    /// Extract file/line/column information from a MuJoCo compiler message
+    pub(crate) fn parse(severity: DiagnosticSeverity, message: &str, model_path: &str) -> Self {
+        let message = message.trim().to_string();
+        let file = quoted_after(&message, "file '")
+            .or_else(|| word_after(&message, "File: "))
+            .unwrap_or_else(|| model_path.to_string());
+
+        MuJoCoDiagnostic {
+            severity,
+            line: number_after(&message, "line "),
+            column: number_after(&message, "column "),
+            file,
+            message,
+        }
+    }
+
+    /// Emit the diagnostic to the bevy log as `file:line:column: message`
+    pub(crate) fn log(&self) {
+        let mut location = self.file.clone();
+        if let Some(line) = self.line {
+            location += &format!(":{line}");
+        }
+        if let Some(column) = self.column {
+            location += &format!(":{column}");
+        }
+
+        match self.severity {
+            DiagnosticSeverity::Warning => warn!("{location}: {}", self.message),
+            DiagnosticSeverity::Error => error!("{location}: {}", self.message),
+        }
+    }
+}
+
+/// Compiler warnings and errors of the last model load
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MuJoCoDiagnostics {
+    pub diagnostics: Vec<MuJoCoDiagnostic>,
+}
+
+impl MuJoCoDiagnostics {
+    pub fn errors(&self) -> impl Iterator<Item = &MuJoCoDiagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == DiagnosticSeverity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &MuJoCoDiagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == DiagnosticSeverity::Warning)
+    }
+}
+
+fn number_after(message: &str, prefix: &str) -> Option<u32> {
+    let start = message.find(prefix)? + prefix.len();
+    let digits: String = message[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok()
+}
+
+fn quoted_after(message: &str, prefix: &str) -> Option<String> {
+    let start = message.find(prefix)? + prefix.len();
+    let end = message[start..].find('\'')?;
+    Some(message[start..start + end].to_string())
+}
+
+fn word_after(message: &str, prefix: &str) -> Option<String> {
+    let start = message.find(prefix)? + prefix.len();
+    message[start..]
+        .split_whitespace()
+        .next()
+        .map(|word| word.to_string())
+}
@@ -0,0 +1,131 @@
+use bevy::prelude::*;
+
+use crate::{
+    wrappers, Backend, MuJoCoSimulation, MuJoCoWarningKind, ResetOptions, SimulationHandle,
+};
+
+/// Why the simulation was considered diverged
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DivergenceReason {
+    NonFiniteQpos,
+    NonFiniteQacc,
+    /// Largest `|qacc|` above `MuJoCoDivergencePolicy::max_qacc`
+    QaccLimit {
+        max: f64,
+    },
+    /// MuJoCo found a bad qpos, qvel or qacc and reset the data itself
+    BadState,
+}
+
+/// What to do once the simulation diverged
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DivergenceReset {
+    /// Only report the divergence
+    #[default]
+    None,
+    /// Reset to the initial state of the model (`mj_resetData`)
+    Initial,
+    /// Reset to a keyframe (`mj_resetDataKeyframe`)
+    Keyframe(usize),
+}
+
+/// Divergence checks run after every step
+#[derive(Resource, Debug, Clone)]
+pub struct MuJoCoDivergencePolicy {
+    pub max_qacc: f64,
+    pub reset: DivergenceReset,
+}
+
+impl Default for MuJoCoDivergencePolicy {
+    fn default() -> Self {
+        MuJoCoDivergencePolicy {
+            max_qacc: 1e8,
+            reset: DivergenceReset::None,
+        }
+    }
+}
+
+/// Sent when the simulation diverged. No more steps are taken in that frame
+#[derive(Event, Debug, Clone, Copy)]
+pub struct MuJoCoDivergence {
+    /// Simulation time of the step that diverged
+    pub time: f64,
+    pub reason: DivergenceReason,
+    /// The simulation was reset by `MuJoCoDivergencePolicy::reset`
+    pub reset: bool,
+}
+
+/// Counters of the warnings MuJoCo reports when it resets a diverged simulation
+pub(crate) fn bad_state_warnings(simulation: &impl Backend) -> [i32; 3] {
+    let warning = &wrappers::data(simulation).warning;
+    [
+        MuJoCoWarningKind::BadQpos,
+        MuJoCoWarningKind::BadQvel,
+        MuJoCoWarningKind::BadQacc,
+    ]
+    .map(|kind| warning[kind.index()].number)
+}
+
+/// Check the state after a step, `warnings` are the `bad_state_warnings` before stepping
+pub(crate) fn check_divergence(
+    simulation: &impl Backend,
+    policy: &MuJoCoDivergencePolicy,
+    warnings: [i32; 3],
+) -> Option<DivergenceReason> {
+    let model = wrappers::model(simulation);
+    let data = wrappers::data(simulation);
+
+    if bad_state_warnings(simulation)
+        .iter()
+        .zip(warnings)
+        .any(|(now, before)| *now > before)
+    {
+        return Some(DivergenceReason::BadState);
+    }
+    if unsafe { wrappers::slice(data.qpos, model.nq as usize) }
+        .iter()
+        .any(|q| !q.is_finite())
+    {
+        return Some(DivergenceReason::NonFiniteQpos);
+    }
+
+    let qacc = unsafe { wrappers::slice(data.qacc, model.nv as usize) };
+    if qacc.iter().any(|a| !a.is_finite()) {
+        return Some(DivergenceReason::NonFiniteQacc);
+    }
+    let max = qacc.iter().fold(0.0, |max: f64, a| max.max(a.abs()));
+    (max > policy.max_qacc).then_some(DivergenceReason::QaccLimit { max })
+}
+
+/// Reset the simulation to the initial state or a keyframe, randomized by `options`, and
+/// recompute derived quantities
+pub(crate) fn reset_simulation(
+    simulation: &SimulationHandle,
+    keyframe: Option<usize>,
+    options: &ResetOptions,
+) {
+    let (model, data) = (simulation.model_ptr(), simulation.data_ptr());
+    let nkey = wrappers::model(simulation).nkey as usize;
+    unsafe {
+        match keyframe {
+            Some(key) if key < nkey => {
+                crate::sys::no_render::mj_resetDataKeyframe(model, data, key as i32)
+            }
+            _ => crate::sys::no_render::mj_resetData(model, data),
+        }
+        crate::sys::no_render::mj_forward(model, data);
+    }
+    options.apply(simulation);
+}
+
+impl MuJoCoSimulation {
+    /// Reset to the initial state of the model, or to a keyframe when given
+    pub fn reset(&self, keyframe: Option<usize>) {
+        reset_simulation(&self.lock().unwrap(), keyframe, &ResetOptions::default());
+    }
+
+    /// Reset like `reset`, then randomize the state with `options`
+    pub fn reset_with(&self, keyframe: Option<usize>, options: &ResetOptions) {
+        reset_simulation(&self.lock().unwrap(), keyframe, options);
+    }
+}
@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{wrappers, Backend, MuJoCoSimulation};
+
+/// `mjtEnableBit::mjENBL_ENERGY`
+pub(crate) const ENABLE_ENERGY: i32 = 1 << 1;
+
+/// Energy and momentum of the whole model, momentum in the MuJoCo world frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct MuJoCoEnergy {
+    /// Zero unless `MuJoCoOptions::energy` is enabled
+    pub kinetic: f64,
+    /// Zero unless `MuJoCoOptions::energy` is enabled
+    pub potential: f64,
+    pub linear_momentum: [f64; 3],
+    /// About the center of mass of the model
+    pub angular_momentum: [f64; 3],
+}
+
+/// Read `mjData.energy` and compute the momentum of the world subtree with `mj_subtreeVel`
+pub(crate) fn energy(simulation: &impl Backend) -> MuJoCoEnergy {
+    unsafe { crate::sys::no_render::mj_subtreeVel(simulation.model_ptr(), simulation.data_ptr()) };
+    let model = wrappers::model(simulation);
+    let data = wrappers::data(simulation);
+    let nbody = model.nbody as usize;
+    let mass = unsafe { wrappers::slice(model.body_subtreemass, nbody) }
+        .first()
+        .copied()
+        .unwrap_or_default();
+    let linvel = unsafe { wrappers::slice(data.subtree_linvel, 3 * nbody) };
+    let angmom = unsafe { wrappers::slice(data.subtree_angmom, 3 * nbody) };
+    if linvel.len() < 3 || angmom.len() < 3 {
+        return MuJoCoEnergy::default();
+    }
+
+    MuJoCoEnergy {
+        // mjData.energy is (potential, kinetic)
+        kinetic: data.energy[1],
+        potential: data.energy[0],
+        linear_momentum: [mass * linvel[0], mass * linvel[1], mass * linvel[2]],
+        angular_momentum: [angmom[0], angmom[1], angmom[2]],
+    }
+}
+
+impl MuJoCoSimulation {
+    /// Energy and total momentum of the current state
+    pub fn energy(&self) -> MuJoCoEnergy {
+        energy(&self.lock().unwrap())
+    }
+}
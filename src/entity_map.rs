@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+/// Lookup from MuJoCo object ids and names to the spawned bevy entities.
+/// Joints map to the entity of the body they belong to. Unnamed objects are only found by id
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MuJoCoEntityMap {
+    pub bodies: HashMap<i32, Entity>,
+    pub geoms: HashMap<i32, Entity>,
+    pub joints: HashMap<i32, Entity>,
+    pub(crate) body_names: HashMap<String, i32>,
+    pub(crate) geom_names: HashMap<String, i32>,
+    pub(crate) joint_names: HashMap<String, i32>,
+    /// `MuJoCo::world` entity, when the plugin spawned it instead of using a `MuJoCoSceneRoot`
+    pub(crate) world: Option<Entity>,
+}
+
+impl MuJoCoEntityMap {
+    pub fn body(&self, id: i32) -> Option<Entity> {
+        self.bodies.get(&id).copied()
+    }
+
+    pub fn geom(&self, id: i32) -> Option<Entity> {
+        self.geoms.get(&id).copied()
+    }
+
+    pub fn joint(&self, id: i32) -> Option<Entity> {
+        self.joints.get(&id).copied()
+    }
+
+    pub fn body_by_name(&self, name: &str) -> Option<Entity> {
+        self.body(*self.body_names.get(name)?)
+    }
+
+    pub fn geom_by_name(&self, name: &str) -> Option<Entity> {
+        self.geom(*self.geom_names.get(name)?)
+    }
+
+    pub fn joint_by_name(&self, name: &str) -> Option<Entity> {
+        self.joint(*self.joint_names.get(name)?)
+    }
+
+    /// MuJoCo body id of a spawned entity
+    pub fn body_id(&self, entity: Entity) -> Option<i32> {
+        self.bodies
+            .iter()
+            .find(|(_, e)| **e == entity)
+            .map(|(id, _)| *id)
+    }
+
+    pub(crate) fn insert_body(&mut self, id: i32, name: &str, entity: Entity) {
+        self.bodies.insert(id, entity);
+        if !name.is_empty() {
+            self.body_names.insert(name.to_string(), id);
+        }
+    }
+
+    pub(crate) fn insert_geom(&mut self, id: i32, name: &str, entity: Entity) {
+        self.geoms.insert(id, entity);
+        if !name.is_empty() {
+            self.geom_names.insert(name.to_string(), id);
+        }
+    }
+
+    /// Map joints to the entities of their bodies, `joints` are `(name, body id)` by joint id
+    pub(crate) fn insert_joints(&mut self, joints: &[(String, i32)]) {
+        for (id, (name, body_id)) in joints.iter().enumerate() {
+            if let Some(entity) = self.body(*body_id) {
+                self.joints.insert(id as i32, entity);
+                if !name.is_empty() {
+                    self.joint_names.insert(name.clone(), id as i32);
+                }
+            }
+        }
+    }
+}
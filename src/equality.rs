@@ -0,0 +1,151 @@
+use bevy::prelude::*;
+use nalgebra::{Quaternion, UnitQuaternion, Vector3};
+
+use crate::{wrappers, Backend, MuJoCoSimulation};
+
+/// Kind of equality constraint (`mjtEq`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EqualityType {
+    Connect,
+    Weld,
+    Joint,
+    Tendon,
+    Other,
+}
+
+impl EqualityType {
+    fn from_raw(value: i32) -> Self {
+        match value {
+            0 => EqualityType::Connect,
+            1 => EqualityType::Weld,
+            2 => EqualityType::Joint,
+            3 => EqualityType::Tendon,
+            _ => EqualityType::Other,
+        }
+    }
+}
+
+/// An equality constraint defined in the model
+#[derive(Debug, Clone)]
+pub struct EqualityConstraint {
+    pub id: usize,
+    pub name: String,
+    pub eq_type: EqualityType,
+    /// Ids of the constrained objects: bodies for connect/weld, joints or tendons otherwise
+    pub obj1_id: i32,
+    pub obj2_id: i32,
+    pub active: bool,
+}
+
+/// Equality constraints of the loaded model. Toggle `active` to enable or disable
+/// a constraint; changes are written to the simulation before the next step
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MuJoCoEqualityConstraints {
+    pub constraints: Vec<EqualityConstraint>,
+}
+
+impl MuJoCoEqualityConstraints {
+    pub(crate) fn from_simulation(simulation: &impl Backend) -> Self {
+        let model = wrappers::model(simulation);
+        let neq = model.neq as usize;
+        let names = wrappers::names(model, model.name_eqadr, model.neq);
+        let eq_type = unsafe { wrappers::slice(model.eq_type, neq) };
+        let obj1_id = unsafe { wrappers::slice(model.eq_obj1id, neq) };
+        let obj2_id = unsafe { wrappers::slice(model.eq_obj2id, neq) };
+        let active = unsafe { wrappers::eq_active(simulation) };
+
+        let constraints = (0..neq)
+            .map(|id| EqualityConstraint {
+                id,
+                name: names[id].clone(),
+                eq_type: EqualityType::from_raw(eq_type[id]),
+                obj1_id: obj1_id[id],
+                obj2_id: obj2_id[id],
+                active: active[id] != 0,
+            })
+            .collect();
+
+        MuJoCoEqualityConstraints { constraints }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&EqualityConstraint> {
+        self.constraints.iter().find(|c| c.name == name)
+    }
+
+    /// Enable or disable a constraint by name, returns `false` if there's no such constraint
+    pub fn set_active(&mut self, name: &str, active: bool) -> bool {
+        match self.constraints.iter_mut().find(|c| c.name == name) {
+            Some(constraint) => {
+                constraint.active = active;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Find a weld constraint between two bodies, in either order
+    pub fn find_weld(&self, body_a: i32, body_b: i32) -> Option<&EqualityConstraint> {
+        self.constraints.iter().find(|c| {
+            c.eq_type == EqualityType::Weld
+                && ((c.obj1_id == body_a && c.obj2_id == body_b)
+                    || (c.obj1_id == body_b && c.obj2_id == body_a))
+        })
+    }
+
+    pub(crate) fn write(&self, simulation: &impl Backend) {
+        let eq_active = unsafe { wrappers::eq_active(simulation) };
+        for constraint in &self.constraints {
+            eq_active[constraint.id] = constraint.active as u8;
+        }
+    }
+}
+
+/// Write changed activation flags of `MuJoCoEqualityConstraints` into the simulation
+pub fn apply_equality_constraints(
+    mujoco: Res<MuJoCoSimulation>,
+    equality_constraints: Res<MuJoCoEqualityConstraints>,
+) {
+    if !equality_constraints.is_changed() {
+        return;
+    }
+
+    let mujoco = mujoco.lock().unwrap();
+    equality_constraints.write(&mujoco);
+}
+
+/// Set `relpose` of a weld constraint to the current relative pose of its bodies,
+/// so activating it holds the bodies where they are instead of snapping them together
+pub(crate) fn weld_current_pose(simulation: &impl Backend, constraint: &EqualityConstraint) {
+    let model = wrappers::model(simulation);
+    let data = wrappers::data(simulation);
+    let nbody = model.nbody as usize;
+    let xpos = unsafe { wrappers::slice(data.xpos, 3 * nbody) };
+    let xquat = unsafe { wrappers::slice(data.xquat, 4 * nbody) };
+
+    let pose = |body_id: i32| {
+        let i = body_id as usize;
+        let position = Vector3::new(xpos[3 * i], xpos[3 * i + 1], xpos[3 * i + 2]);
+        let rotation = UnitQuaternion::from_quaternion(Quaternion::new(
+            xquat[4 * i],
+            xquat[4 * i + 1],
+            xquat[4 * i + 2],
+            xquat[4 * i + 3],
+        ));
+        (position, rotation)
+    };
+    let (position1, rotation1) = pose(constraint.obj1_id);
+    let (position2, rotation2) = pose(constraint.obj2_id);
+
+    // pose of body2 in the frame of body1
+    let relative_position = rotation1.inverse_transform_vector(&(position2 - position1));
+    let relative_rotation = rotation1.inverse() * rotation2;
+
+    // weld eq_data layout: anchor[3], relpose[7] (pos, quat), torquescale
+    let eq_data = unsafe { wrappers::slice_mut(model.eq_data, 11 * model.neq as usize) };
+    let relpose = &mut eq_data[11 * constraint.id + 3..11 * constraint.id + 10];
+    relpose[..3].copy_from_slice(relative_position.as_slice());
+    relpose[3] = relative_rotation.w;
+    relpose[4] = relative_rotation.i;
+    relpose[5] = relative_rotation.j;
+    relpose[6] = relative_rotation.k;
+}
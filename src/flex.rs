@@ -0,0 +1,172 @@
+//! Flex objects (`<flexcomp>` cloth and soft bodies, MuJoCo 3 only): one dynamic mesh per flex
+//! whose vertices are rewritten from `mjData::flexvert_xpos` after every step
+
+use bevy::{
+    prelude::*,
+    render::{mesh::Indices, render_asset::RenderAssetUsages, render_resource::PrimitiveTopology},
+};
+
+use crate::{
+    adapters::*, simulate_physics, wrappers, MuJoCoPluginSettings, MuJoCoResources,
+    MuJoCoSceneRoot, MuJoCoSimulation, SimulationHandle, SpawnMode,
+};
+
+/// Entity rendering a flex object, spawned under the scene root in `SpawnMode::Full`.
+/// Its transform is the identity, the mesh vertices are in the scene frame
+#[derive(Component)]
+pub struct MuJoCoFlex {
+    pub id: usize,
+}
+
+pub(crate) struct FlexPlugin;
+
+impl Plugin for FlexPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                spawn_flexes.run_if(resource_added::<MuJoCoResources>),
+                update_flexes.after(simulate_physics),
+            )
+                .chain()
+                .run_if(resource_exists::<MuJoCoResources>),
+        );
+        app.add_systems(
+            Update,
+            despawn_flexes.run_if(resource_removed::<MuJoCoResources>),
+        );
+    }
+}
+
+/// Triangles of a flex: the elements of 2d flexes, the faces of 3d (tetrahedral) flexes.
+/// 1d flexes are lines and have no surface to draw
+fn flex_triangles(model: &crate::sys::mjModel, flex: usize) -> Vec<u32> {
+    let nflex = model.nflex as usize;
+    let dim = unsafe { wrappers::slice(model.flex_dim, nflex) }[flex] as usize;
+    let elemnum = unsafe { wrappers::slice(model.flex_elemnum, nflex) }[flex] as usize;
+    let dataadr = unsafe { wrappers::slice(model.flex_elemdataadr, nflex) }[flex] as usize;
+    let elem = unsafe { wrappers::slice(model.flex_elem, model.nflexelemdata as usize) };
+    let elem = &elem[dataadr..dataadr + (dim + 1) * elemnum];
+
+    match dim {
+        2 => elem.iter().map(|v| *v as u32).collect(),
+        3 => elem
+            .chunks_exact(4)
+            .flat_map(|t| {
+                let t = [t[0] as u32, t[1] as u32, t[2] as u32, t[3] as u32];
+                [
+                    t[0], t[2], t[1], t[0], t[1], t[3], t[1], t[2], t[3], t[0], t[3], t[2],
+                ]
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Vertex positions of a flex in the bevy scene frame
+fn flex_positions(simulation: &SimulationHandle, flex: usize) -> Vec<[f32; 3]> {
+    let model = wrappers::model(simulation);
+    let nflex = model.nflex as usize;
+    let vertadr = unsafe { wrappers::slice(model.flex_vertadr, nflex) }[flex] as usize;
+    let vertnum = unsafe { wrappers::slice(model.flex_vertnum, nflex) }[flex] as usize;
+    let xpos = unsafe {
+        wrappers::slice(
+            wrappers::data(simulation).flexvert_xpos,
+            3 * model.nflexvert as usize,
+        )
+    };
+
+    xpos[3 * vertadr..3 * (vertadr + vertnum)]
+        .chunks_exact(3)
+        .map(|p| position_mujoco_2_bevy(simulation.up_axis, [p[0], p[1], p[2]]).to_array())
+        .collect()
+}
+
+fn flex_mesh(simulation: &SimulationHandle, flex: usize) -> Mesh {
+    let model = wrappers::model(simulation);
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+    );
+    mesh.insert_indices(Indices::U32(flex_triangles(model, flex)));
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, flex_positions(simulation, flex));
+    mesh.compute_smooth_normals();
+    mesh
+}
+
+fn spawn_flexes(
+    mut commands: Commands,
+    mujoco: Res<MuJoCoSimulation>,
+    settings: Res<MuJoCoPluginSettings>,
+    scene_roots: Query<Entity, With<MuJoCoSceneRoot>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if settings.spawn_mode != SpawnMode::Full {
+        return;
+    }
+    let Ok(scene_root) = scene_roots.get_single() else {
+        return;
+    };
+
+    let mujoco = mujoco.lock().unwrap();
+    let model = wrappers::model(&mujoco);
+    let nflex = model.nflex as usize;
+    let names = wrappers::names(model, model.name_flexadr, model.nflex);
+    let flex_rgba = unsafe { wrappers::slice(model.flex_rgba, 4 * nflex) };
+
+    for flex in 0..nflex {
+        let mesh = flex_mesh(&mujoco, flex);
+        let aabb = mesh.compute_aabb();
+        let rgba = &flex_rgba[4 * flex..4 * flex + 4];
+        // cloth is seen from both sides
+        let material = StandardMaterial {
+            base_color: Color::srgba(rgba[0], rgba[1], rgba[2], rgba[3]),
+            double_sided: true,
+            cull_mode: None,
+            ..default()
+        };
+
+        let mut entity = commands.spawn((
+            MuJoCoFlex { id: flex },
+            Name::new(format!("MuJoCo::flex_{}", names[flex])),
+            Mesh3d(meshes.add(mesh)),
+            MeshMaterial3d(materials.add(material)),
+            SpatialBundle::default(),
+        ));
+        if let Some(aabb) = aabb {
+            entity.insert(aabb);
+        }
+        entity.set_parent(scene_root);
+    }
+}
+
+fn update_flexes(
+    mut commands: Commands,
+    mujoco: Res<MuJoCoSimulation>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    flexes: Query<(Entity, &MuJoCoFlex, &Mesh3d)>,
+) {
+    if flexes.is_empty() {
+        return;
+    }
+
+    let mujoco = mujoco.lock().unwrap();
+    for (entity, flex, mesh) in flexes.iter() {
+        let Some(mesh) = meshes.get_mut(&mesh.0) else {
+            continue;
+        };
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, flex_positions(&mujoco, flex.id));
+        mesh.compute_smooth_normals();
+        // bevy doesn't recompute bounds of a changed mesh
+        if let Some(aabb) = mesh.compute_aabb() {
+            commands.entity(entity).insert(aabb);
+        }
+    }
+}
+
+fn despawn_flexes(mut commands: Commands, flexes: Query<Entity, With<MuJoCoFlex>>) {
+    for entity in flexes.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
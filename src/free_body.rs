@@ -0,0 +1,305 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{
+    adapters::*, parameters::set_const, wrappers, Backend, GeomType, JointType, MuJoCoEntityMap,
+    MuJoCoSimulation, SimulationHandle,
+};
+
+/// Bodies whose name starts with this prefix, have a free joint and a geom are used as a
+/// pool for `MuJoCoCommands::spawn_free_body`, e.g.
+/// `<body name="bevy_free_body_0"><freejoint/><geom type="sphere" size="0.05"/></body>`
+pub const FREE_BODY_POOL_PREFIX: &str = "bevy_free_body_";
+
+/// Where pooled bodies wait in MuJoCo world coordinates, with collisions disabled
+const PARKING_POSITION: [f64; 3] = [0.0, 0.0, -1000.0];
+
+/// Shape of a body spawned at runtime, sizes in meters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FreeBodyShape {
+    Sphere {
+        radius: f64,
+    },
+    Box {
+        half_extents: [f64; 3],
+    },
+    /// Capsule along the body Z axis in MuJoCo (Y in bevy)
+    Capsule {
+        radius: f64,
+        half_length: f64,
+    },
+}
+
+impl FreeBodyShape {
+    pub(crate) fn geom_type(&self) -> GeomType {
+        match self {
+            FreeBodyShape::Sphere { .. } => GeomType::SPHERE,
+            FreeBodyShape::Box { .. } => GeomType::BOX,
+            FreeBodyShape::Capsule { .. } => GeomType::CAPSULE,
+        }
+    }
+
+    pub(crate) fn geom_size(&self) -> [f64; 3] {
+        match *self {
+            FreeBodyShape::Sphere { radius } => [radius, 0.0, 0.0],
+            FreeBodyShape::Box { half_extents } => half_extents,
+            FreeBodyShape::Capsule {
+                radius,
+                half_length,
+            } => [radius, half_length, 0.0],
+        }
+    }
+
+    /// Radius of the bounding sphere used by the broad phase
+    pub(crate) fn bounding_radius(&self) -> f64 {
+        match *self {
+            FreeBodyShape::Sphere { radius } => radius,
+            FreeBodyShape::Box {
+                half_extents: [x, y, z],
+            } => (x * x + y * y + z * z).sqrt(),
+            FreeBodyShape::Capsule {
+                radius,
+                half_length,
+            } => radius + half_length,
+        }
+    }
+
+    /// Diagonal inertia of the solid shape with uniform density
+    fn inertia(&self, mass: f64) -> [f64; 3] {
+        match *self {
+            FreeBodyShape::Sphere { radius } => [0.4 * mass * radius * radius; 3],
+            FreeBodyShape::Box {
+                half_extents: [x, y, z],
+            } => [
+                mass * (y * y + z * z) / 3.0,
+                mass * (x * x + z * z) / 3.0,
+                mass * (x * x + y * y) / 3.0,
+            ],
+            // approximated by a cylinder of the full length
+            FreeBodyShape::Capsule {
+                radius,
+                half_length,
+            } => {
+                let length = 2.0 * (half_length + radius);
+                let lateral = mass * (3.0 * radius * radius + length * length) / 12.0;
+                [lateral, lateral, 0.5 * mass * radius * radius]
+            }
+        }
+    }
+
+    /// Mesh in the geom frame of non-mesh geoms, which is rotated so bevy Y is MuJoCo Z
+    fn mesh(&self) -> Mesh {
+        match *self {
+            FreeBodyShape::Sphere { radius } => Mesh::from(Sphere::new(radius as f32)),
+            FreeBodyShape::Box {
+                half_extents: [x, y, z],
+            } => Mesh::from(Cuboid::new(2.0 * x as f32, 2.0 * z as f32, 2.0 * y as f32)),
+            FreeBodyShape::Capsule {
+                radius,
+                half_length,
+            } => Mesh::from(Capsule3d::new(radius as f32, 2.0 * half_length as f32)),
+        }
+    }
+}
+
+/// A pre-allocated free body
+#[derive(Debug, Clone)]
+pub(crate) struct PooledBody {
+    pub(crate) body: usize,
+    pub(crate) geom: usize,
+    pub(crate) qpos_adr: usize,
+    pub(crate) dof_adr: usize,
+    contype: i32,
+    conaffinity: i32,
+}
+
+/// Pool of free bodies declared in the MJCF, see `FREE_BODY_POOL_PREFIX`
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MuJoCoFreeBodyPool {
+    pub(crate) parked: Vec<PooledBody>,
+    /// Bodies in the simulation, oldest first
+    pub(crate) spawned: VecDeque<PooledBody>,
+}
+
+impl MuJoCoFreeBodyPool {
+    /// Find pool bodies and park them
+    pub(crate) fn from_simulation(simulation: &impl Backend) -> Self {
+        let model = wrappers::model(simulation);
+        let (nbody, njnt) = (model.nbody as usize, model.njnt as usize);
+        let body_names = wrappers::names(model, model.name_bodyadr, model.nbody);
+        let body_jntadr = unsafe { wrappers::slice(model.body_jntadr, nbody) };
+        let body_geomadr = unsafe { wrappers::slice(model.body_geomadr, nbody) };
+        let jnt_type = unsafe { wrappers::slice(model.jnt_type, njnt) };
+        let jnt_qposadr = unsafe { wrappers::slice(model.jnt_qposadr, njnt) };
+        let jnt_dofadr = unsafe { wrappers::slice(model.jnt_dofadr, njnt) };
+        let geom_contype = unsafe { wrappers::slice(model.geom_contype, model.ngeom as usize) };
+        let geom_conaffinity =
+            unsafe { wrappers::slice(model.geom_conaffinity, model.ngeom as usize) };
+
+        let parked: Vec<PooledBody> = (0..nbody)
+            .filter(|&body| body_names[body].starts_with(FREE_BODY_POOL_PREFIX))
+            .filter_map(|body| {
+                let (joint, geom) = (body_jntadr[body], body_geomadr[body]);
+                if joint < 0
+                    || geom < 0
+                    || JointType::from_raw(jnt_type[joint as usize]) != JointType::FREE
+                {
+                    warn!(
+                        "{} needs a free joint and a geom to be used as a free body",
+                        body_names[body]
+                    );
+                    return None;
+                }
+                let geom = geom as usize;
+                Some(PooledBody {
+                    body,
+                    geom,
+                    qpos_adr: jnt_qposadr[joint as usize] as usize,
+                    dof_adr: jnt_dofadr[joint as usize] as usize,
+                    contype: geom_contype[geom],
+                    conaffinity: geom_conaffinity[geom],
+                })
+            })
+            .collect();
+
+        let model = wrappers::model(simulation);
+        let ngeom = model.ngeom as usize;
+        for pooled in parked.iter() {
+            unsafe { wrappers::slice_mut(model.geom_contype, ngeom)[pooled.geom] = 0 };
+            unsafe { wrappers::slice_mut(model.geom_conaffinity, ngeom)[pooled.geom] = 0 };
+        }
+
+        let pool = MuJoCoFreeBodyPool {
+            parked,
+            spawned: VecDeque::new(),
+        };
+        pool.park(simulation);
+        pool
+    }
+
+    /// Number of bodies that can be spawned before the oldest one is recycled
+    pub fn available(&self) -> usize {
+        self.parked.len()
+    }
+
+    /// Keep parked bodies still at the parking position
+    pub(crate) fn park(&self, simulation: &impl Backend) {
+        let model = wrappers::model(simulation);
+        let data = wrappers::data(simulation);
+        let qpos = unsafe { wrappers::slice_mut(data.qpos, model.nq as usize) };
+        let qvel = unsafe { wrappers::slice_mut(data.qvel, model.nv as usize) };
+
+        for (i, pooled) in self.parked.iter().enumerate() {
+            // spread bodies apart so parked ones never overlap
+            let x = PARKING_POSITION[0] + i as f64;
+            qpos[pooled.qpos_adr..pooled.qpos_adr + 7].copy_from_slice(&[
+                x,
+                PARKING_POSITION[1],
+                PARKING_POSITION[2],
+                1.0,
+                0.0,
+                0.0,
+                0.0,
+            ]);
+            qvel[pooled.dof_adr..pooled.dof_adr + 6].fill(0.0);
+        }
+    }
+
+    /// Take a parked body, or recycle the oldest spawned one, and place it in the simulation
+    pub(crate) fn spawn(
+        &mut self,
+        simulation: &SimulationHandle,
+        shape: FreeBodyShape,
+        mass: f64,
+        pose: Transform,
+    ) -> Option<PooledBody> {
+        let pooled = match self.parked.pop() {
+            Some(pooled) => pooled,
+            None => self.spawned.pop_front()?,
+        };
+
+        let model = wrappers::model(simulation);
+        let (ngeom, nbody) = (model.ngeom as usize, model.nbody as usize);
+        unsafe {
+            wrappers::slice_mut(model.geom_type, ngeom)[pooled.geom] = shape.geom_type().to_raw()
+        };
+        unsafe {
+            wrappers::slice_mut(model.geom_size, 3 * ngeom)[3 * pooled.geom..3 * pooled.geom + 3]
+                .copy_from_slice(&shape.geom_size())
+        };
+        unsafe {
+            wrappers::slice_mut(model.geom_rbound, ngeom)[pooled.geom] = shape.bounding_radius()
+        };
+        unsafe { wrappers::slice_mut(model.geom_contype, ngeom)[pooled.geom] = pooled.contype };
+        unsafe {
+            wrappers::slice_mut(model.geom_conaffinity, ngeom)[pooled.geom] = pooled.conaffinity
+        };
+        unsafe { wrappers::slice_mut(model.body_mass, nbody)[pooled.body] = mass };
+        unsafe {
+            wrappers::slice_mut(model.body_inertia, 3 * nbody)[3 * pooled.body..3 * pooled.body + 3]
+                .copy_from_slice(&shape.inertia(mass))
+        };
+        set_const(simulation);
+
+        // pose is given in the bevy scene frame
+        let (position, quat) = pose_bevy_2_mujoco(simulation.up_axis, &pose);
+        let model = wrappers::model(simulation);
+        let data = wrappers::data(simulation);
+        let qpos = unsafe { wrappers::slice_mut(data.qpos, model.nq as usize) };
+        qpos[pooled.qpos_adr..pooled.qpos_adr + 3].copy_from_slice(&position);
+        qpos[pooled.qpos_adr + 3..pooled.qpos_adr + 7].copy_from_slice(&quat);
+        unsafe {
+            wrappers::slice_mut(data.qvel, model.nv as usize)[pooled.dof_adr..pooled.dof_adr + 6]
+                .fill(0.0)
+        };
+        unsafe {
+            crate::sys::no_render::mj_forward(simulation.model_ptr(), simulation.data_ptr());
+        }
+
+        self.spawned.push_back(pooled.clone());
+        Some(pooled)
+    }
+}
+
+/// Swap the mesh of a spawned pool body and show it
+pub(crate) fn show_free_body(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    entity_map: &MuJoCoEntityMap,
+    pooled: &PooledBody,
+    shape: FreeBodyShape,
+) {
+    if let Some(geom) = entity_map.geom(pooled.geom as i32) {
+        commands
+            .entity(geom)
+            .insert(Mesh3d(meshes.add(shape.mesh())));
+    }
+    if let Some(body) = entity_map.body(pooled.body as i32) {
+        commands.entity(body).insert(Visibility::Inherited);
+    }
+}
+
+/// Keep parked pool bodies in place and hidden
+pub fn park_free_bodies(
+    mujoco: Res<MuJoCoSimulation>,
+    pool: Res<MuJoCoFreeBodyPool>,
+    entity_map: Res<MuJoCoEntityMap>,
+    mut visibilities: Query<&mut Visibility>,
+) {
+    if pool.parked.is_empty() {
+        return;
+    }
+
+    let mujoco = mujoco.lock().unwrap();
+    pool.park(&mujoco);
+
+    for pooled in pool.parked.iter() {
+        let Some(entity) = entity_map.body(pooled.body as i32) else {
+            continue;
+        };
+        if let Ok(mut visibility) = visibilities.get_mut(entity) {
+            visibility.set_if_neq(Visibility::Hidden);
+        }
+    }
+}
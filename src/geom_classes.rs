@@ -0,0 +1,146 @@
+//! Default classes of geoms, which the compiled model doesn't keep: the MJCF document is parsed
+//! again and each geom gets the class it was authored with, explicit or inherited through
+//! `childclass`, so renderers can tell e.g. `visual` from `collision` geoms
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{MjcfElement, MuJoCoGeom};
+
+/// Default class of a rendered geom, `main` when none applies
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct MuJoCoGeomClass(pub String);
+
+/// Default classes of the model's geoms, by geom id, and the `<geom>` attributes each class
+/// resolves to. Available for models loaded from an MJCF file
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MuJoCoGeomClasses {
+    pub classes: Vec<String>,
+    /// Attributes of `<default><geom>` per class, merged with those of its parent classes
+    pub defaults: HashMap<String, Vec<(String, String)>>,
+}
+
+impl MuJoCoGeomClasses {
+    /// Classes of an MJCF document with includes expanded. `None` if the geoms of the document
+    /// don't match the `ngeom` geoms of the compiled model
+    pub(crate) fn from_mjcf(mjcf: &MjcfElement, ngeom: usize) -> Option<Self> {
+        let mut defaults = HashMap::new();
+        for default in mjcf.children.iter().filter(|child| child.tag == "default") {
+            resolve_defaults(default, &[], &mut defaults);
+        }
+
+        // geom ids follow bodies in depth-first order, world geoms first
+        let mut bodies = vec![vec![]];
+        for worldbody in mjcf
+            .children
+            .iter()
+            .filter(|child| child.tag == "worldbody")
+        {
+            collect_geoms(worldbody, "main", 0, &mut bodies);
+        }
+        let classes: Vec<String> = bodies.into_iter().flatten().collect();
+        if classes.len() != ngeom {
+            warn!(
+                "MJCF document has {} geoms, the model {ngeom}: geom classes unavailable",
+                classes.len()
+            );
+            return None;
+        }
+
+        Some(MuJoCoGeomClasses { classes, defaults })
+    }
+
+    pub fn class(&self, geom: usize) -> Option<&str> {
+        self.classes.get(geom).map(String::as_str)
+    }
+
+    /// Ids of the geoms of a class
+    pub fn geoms(&self, class: &str) -> impl Iterator<Item = usize> + '_ {
+        let class = class.to_string();
+        self.classes
+            .iter()
+            .enumerate()
+            .filter(move |(_, geom_class)| **geom_class == class)
+            .map(|(id, _)| id)
+    }
+
+    /// Attribute of a geom's default class, e.g. `"group"` or `"contype"`
+    pub fn default_attribute(&self, geom: usize, key: &str) -> Option<&str> {
+        self.defaults
+            .get(self.class(geom)?)?
+            .iter()
+            .find(|(attribute, _)| attribute == key)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+fn resolve_defaults(
+    default: &MjcfElement,
+    inherited: &[(String, String)],
+    defaults: &mut HashMap<String, Vec<(String, String)>>,
+) {
+    let class = default.attribute("class").unwrap_or("main");
+    let mut attributes = inherited.to_vec();
+    if let Some(geom) = default.child("geom") {
+        for (key, value) in geom.attributes.iter() {
+            match attributes
+                .iter_mut()
+                .find(|(attribute, _)| attribute == key)
+            {
+                Some((_, inherited)) => *inherited = value.clone(),
+                None => attributes.push((key.clone(), value.clone())),
+            }
+        }
+    }
+    for child in default
+        .children
+        .iter()
+        .filter(|child| child.tag == "default")
+    {
+        resolve_defaults(child, &attributes, defaults);
+    }
+    defaults.insert(class.to_string(), attributes);
+}
+
+fn collect_geoms(
+    element: &MjcfElement,
+    childclass: &str,
+    body: usize,
+    bodies: &mut Vec<Vec<String>>,
+) {
+    for child in element.children.iter() {
+        match child.tag.as_str() {
+            "geom" => {
+                let class = child.attribute("class").unwrap_or(childclass);
+                bodies[body].push(class.to_string());
+            }
+            "body" | "frame" => {
+                let childclass = child.attribute("childclass").unwrap_or(childclass);
+                let body = if child.tag == "body" {
+                    bodies.push(vec![]);
+                    bodies.len() - 1
+                } else {
+                    body
+                };
+                collect_geoms(child, childclass, body, bodies);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Tag newly spawned geom entities with their class
+pub(crate) fn attach_geom_classes(
+    mut commands: Commands,
+    geom_classes: Res<MuJoCoGeomClasses>,
+    geoms: Query<(Entity, &MuJoCoGeom), Added<MuJoCoGeom>>,
+) {
+    for (entity, geom) in geoms.iter() {
+        if let Some(class) = geom_classes.class(geom.id as usize) {
+            commands
+                .entity(entity)
+                .insert(MuJoCoGeomClass(class.to_string()));
+        }
+    }
+}
@@ -0,0 +1,465 @@
+//! Export of the spawned scene to glTF 2.0: the entity hierarchy under `MuJoCoSceneRoot` with
+//! current transforms, triangle meshes and `StandardMaterial` factors, optionally with recorded
+//! body animation. Textures aren't exported
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::Path;
+
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::*,
+    render::mesh::{Indices, PrimitiveTopology, VertexAttributeValues},
+};
+
+use crate::{MuJoCoAnimationRecorder, MuJoCoSceneRoot};
+
+/// Write snapshots of the spawned scene to files, e.g. to render them in Blender
+#[derive(SystemParam)]
+pub struct MuJoCoSceneExporter<'w, 's> {
+    meshes: Res<'w, Assets<Mesh>>,
+    materials: Res<'w, Assets<StandardMaterial>>,
+    roots: Query<'w, 's, Entity, With<MuJoCoSceneRoot>>,
+    nodes: Query<
+        'w,
+        's,
+        (
+            &'static Transform,
+            Option<&'static Name>,
+            Option<&'static Children>,
+            Option<&'static Visibility>,
+            Option<&'static Mesh3d>,
+            Option<&'static MeshMaterial3d<StandardMaterial>>,
+        ),
+    >,
+}
+
+impl MuJoCoSceneExporter<'_, '_> {
+    /// Write the scene with its current pose. A `.glb` path gets a single binary file, any other
+    /// path a `.gltf` JSON file with the buffer in a `.bin` file next to it
+    pub fn export_gltf(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        self.write(path.as_ref(), None)
+    }
+
+    /// Write the scene with the recorded body poses as an animation, one translation and one
+    /// rotation channel per body, keyed at the recorded simulation times
+    pub fn export_gltf_animation(
+        &self,
+        path: impl AsRef<Path>,
+        recorder: &MuJoCoAnimationRecorder,
+    ) -> std::io::Result<()> {
+        self.write(path.as_ref(), Some(recorder))
+    }
+
+    fn write(
+        &self,
+        path: &Path,
+        animation: Option<&MuJoCoAnimationRecorder>,
+    ) -> std::io::Result<()> {
+        let mut gltf = GltfWriter::default();
+        let roots: Vec<usize> = self
+            .roots
+            .iter()
+            .filter_map(|root| self.add_node(&mut gltf, root))
+            .collect();
+        if roots.is_empty() {
+            return Err(std::io::Error::other("no MuJoCo scene spawned"));
+        }
+        if let Some(recorder) = animation {
+            gltf.animation(recorder);
+        }
+
+        if path.extension().is_some_and(|extension| extension == "glb") {
+            let json = gltf.json(&roots, None);
+            return std::fs::write(path, glb(json, &gltf.buffer));
+        }
+        let bin_path = path.with_extension("bin");
+        let bin_name = bin_path.file_name().unwrap_or_default().to_string_lossy();
+        std::fs::write(&bin_path, &gltf.buffer)?;
+        std::fs::write(path, gltf.json(&roots, Some(&bin_name)))
+    }
+
+    fn add_node(&self, gltf: &mut GltfWriter, entity: Entity) -> Option<usize> {
+        let (transform, name, children, visibility, mesh, material) =
+            self.nodes.get(entity).ok()?;
+        if visibility == Some(&Visibility::Hidden) {
+            return None;
+        }
+
+        // reserve the index so the node comes before its children
+        let index = gltf.nodes.len();
+        gltf.nodes.push(String::new());
+        let children: Vec<usize> = children
+            .into_iter()
+            .flat_map(|children| children.iter())
+            .filter_map(|child| self.add_node(gltf, *child))
+            .collect();
+        let mesh = mesh.and_then(|mesh| {
+            let material = material.map(|material| material.id());
+            gltf.mesh(&self.meshes, &self.materials, mesh.id(), material)
+        });
+
+        let (t, r, s) = (transform.translation, transform.rotation, transform.scale);
+        let mut node = format!(
+            "{{\"translation\":[{},{},{}],\"rotation\":[{},{},{},{}],\"scale\":[{},{},{}]",
+            t.x, t.y, t.z, r.x, r.y, r.z, r.w, s.x, s.y, s.z
+        );
+        if let Some(name) = name {
+            let _ = write!(node, ",\"name\":{}", json_string(name.as_str()));
+        }
+        if let Some(mesh) = mesh {
+            let _ = write!(node, ",\"mesh\":{mesh}");
+        }
+        if !children.is_empty() {
+            let _ = write!(node, ",\"children\":{}", json_indices(&children));
+        }
+        node.push('}');
+        gltf.nodes[index] = node;
+        gltf.entity_nodes.insert(entity, index);
+        Some(index)
+    }
+}
+
+#[derive(Default)]
+struct GltfWriter {
+    nodes: Vec<String>,
+    meshes: Vec<String>,
+    materials: Vec<String>,
+    accessors: Vec<String>,
+    buffer_views: Vec<String>,
+    buffer: Vec<u8>,
+    animations: Vec<String>,
+    entity_nodes: HashMap<Entity, usize>,
+    /// glTF mesh of a bevy mesh and material, `None` for meshes that can't be exported
+    mesh_ids: HashMap<(AssetId<Mesh>, Option<AssetId<StandardMaterial>>), Option<usize>>,
+    material_ids: HashMap<AssetId<StandardMaterial>, usize>,
+}
+
+// glTF component types and buffer view targets
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+impl GltfWriter {
+    fn mesh(
+        &mut self,
+        meshes: &Assets<Mesh>,
+        materials: &Assets<StandardMaterial>,
+        mesh: AssetId<Mesh>,
+        material: Option<AssetId<StandardMaterial>>,
+    ) -> Option<usize> {
+        if let Some(index) = self.mesh_ids.get(&(mesh, material)) {
+            return *index;
+        }
+        let index = meshes
+            .get(mesh)
+            .and_then(|mesh| self.primitive(mesh))
+            .map(|primitive| {
+                let material = material.and_then(|material| self.material(materials, material));
+                let material =
+                    material.map_or(String::new(), |index| format!(",\"material\":{index}"));
+                self.meshes
+                    .push(format!("{{\"primitives\":[{{{primitive}{material}}}]}}"));
+                self.meshes.len() - 1
+            });
+        self.mesh_ids.insert((mesh, material), index);
+        index
+    }
+
+    /// Attributes and indices of a triangle mesh
+    fn primitive(&mut self, mesh: &Mesh) -> Option<String> {
+        if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+            return None;
+        }
+        let Some(VertexAttributeValues::Float32x3(positions)) =
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            return None;
+        };
+
+        let (min, max) =
+            positions
+                .iter()
+                .fold(([f32::MAX; 3], [f32::MIN; 3]), |(min, max), position| {
+                    (
+                        [0, 1, 2].map(|axis| min[axis].min(position[axis])),
+                        [0, 1, 2].map(|axis| max[axis].max(position[axis])),
+                    )
+                });
+        let bounds = format!(
+            ",\"min\":[{},{},{}],\"max\":[{},{},{}]",
+            min[0], min[1], min[2], max[0], max[1], max[2]
+        );
+        let position = self.accessor(
+            bytes(positions.iter().flatten()),
+            ARRAY_BUFFER,
+            FLOAT,
+            positions.len(),
+            "VEC3",
+            &bounds,
+        );
+        let mut attributes = format!("\"POSITION\":{position}");
+        if let Some(VertexAttributeValues::Float32x3(normals)) =
+            mesh.attribute(Mesh::ATTRIBUTE_NORMAL)
+        {
+            let normal = self.accessor(
+                bytes(normals.iter().flatten()),
+                ARRAY_BUFFER,
+                FLOAT,
+                normals.len(),
+                "VEC3",
+                "",
+            );
+            let _ = write!(attributes, ",\"NORMAL\":{normal}");
+        }
+        if let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+            let uv = self.accessor(
+                bytes(uvs.iter().flatten()),
+                ARRAY_BUFFER,
+                FLOAT,
+                uvs.len(),
+                "VEC2",
+                "",
+            );
+            let _ = write!(attributes, ",\"TEXCOORD_0\":{uv}");
+        }
+
+        let mut primitive = format!("\"attributes\":{{{attributes}}}");
+        if let Some(indices) = mesh.indices() {
+            let indices: Vec<u32> = match indices {
+                Indices::U16(indices) => indices.iter().map(|index| *index as u32).collect(),
+                Indices::U32(indices) => indices.clone(),
+            };
+            let indices = self.accessor(
+                indices
+                    .iter()
+                    .flat_map(|index| index.to_le_bytes())
+                    .collect(),
+                ELEMENT_ARRAY_BUFFER,
+                UNSIGNED_INT,
+                indices.len(),
+                "SCALAR",
+                "",
+            );
+            let _ = write!(primitive, ",\"indices\":{indices}");
+        }
+        Some(primitive)
+    }
+
+    fn material(
+        &mut self,
+        materials: &Assets<StandardMaterial>,
+        material: AssetId<StandardMaterial>,
+    ) -> Option<usize> {
+        if let Some(index) = self.material_ids.get(&material) {
+            return Some(*index);
+        }
+        let material_data = materials.get(material)?;
+        let color = material_data.base_color.to_linear();
+        let mut json = format!(
+            "{{\"pbrMetallicRoughness\":{{\"baseColorFactor\":[{},{},{},{}],\"metallicFactor\":{},\"roughnessFactor\":{}}}",
+            color.red,
+            color.green,
+            color.blue,
+            color.alpha,
+            material_data.metallic,
+            material_data.perceptual_roughness
+        );
+        if color.alpha < 1.0 {
+            json.push_str(",\"alphaMode\":\"BLEND\"");
+        }
+        if material_data.double_sided {
+            json.push_str(",\"doubleSided\":true");
+        }
+        json.push('}');
+        self.materials.push(json);
+        let index = self.materials.len() - 1;
+        self.material_ids.insert(material, index);
+        Some(index)
+    }
+
+    /// Channels of the recorded tracks whose entities were exported
+    fn animation(&mut self, recorder: &MuJoCoAnimationRecorder) {
+        let Some(start) = recorder.times.first() else {
+            return;
+        };
+        let times: Vec<f32> = recorder.times.iter().map(|t| (t - start) as f32).collect();
+        let bounds = format!(
+            ",\"min\":[{}],\"max\":[{}]",
+            times[0],
+            times[times.len() - 1]
+        );
+        let input = self.accessor(
+            bytes(times.iter()),
+            0,
+            FLOAT,
+            times.len(),
+            "SCALAR",
+            &bounds,
+        );
+
+        let (mut samplers, mut channels) = (vec![], vec![]);
+        for track in recorder.tracks.iter() {
+            let Some(node) = self.entity_nodes.get(&track.entity).copied() else {
+                continue;
+            };
+            let translations = track.transforms.iter().map(|t| t.translation.to_array());
+            let rotations = track.transforms.iter().map(|t| t.rotation.to_array());
+            let translation = self.accessor(
+                bytes(translations.flatten().collect::<Vec<_>>().iter()),
+                0,
+                FLOAT,
+                track.transforms.len(),
+                "VEC3",
+                "",
+            );
+            let rotation = self.accessor(
+                bytes(rotations.flatten().collect::<Vec<_>>().iter()),
+                0,
+                FLOAT,
+                track.transforms.len(),
+                "VEC4",
+                "",
+            );
+            for (path, output) in [("translation", translation), ("rotation", rotation)] {
+                samplers.push(format!(
+                    "{{\"input\":{input},\"output\":{output},\"interpolation\":\"LINEAR\"}}"
+                ));
+                channels.push(format!(
+                    "{{\"sampler\":{},\"target\":{{\"node\":{node},\"path\":\"{path}\"}}}}",
+                    samplers.len() - 1
+                ));
+            }
+        }
+        if !channels.is_empty() {
+            self.animations.push(format!(
+                "{{\"name\":\"simulation\",\"samplers\":[{}],\"channels\":[{}]}}",
+                samplers.join(","),
+                channels.join(",")
+            ));
+        }
+    }
+
+    /// Append data as a buffer view with one accessor, returning the accessor index
+    fn accessor(
+        &mut self,
+        data: Vec<u8>,
+        target: u32,
+        component_type: u32,
+        count: usize,
+        kind: &str,
+        bounds: &str,
+    ) -> usize {
+        // accessor offsets have to be aligned to the component size
+        while self.buffer.len() % 4 != 0 {
+            self.buffer.push(0);
+        }
+        // animation data has no target
+        let target = match target {
+            0 => String::new(),
+            target => format!(",\"target\":{target}"),
+        };
+        self.buffer_views.push(format!(
+            "{{\"buffer\":0,\"byteOffset\":{},\"byteLength\":{}{target}}}",
+            self.buffer.len(),
+            data.len()
+        ));
+        self.buffer.extend_from_slice(&data);
+        self.accessors.push(format!(
+            "{{\"bufferView\":{},\"componentType\":{component_type},\"count\":{count},\"type\":\"{kind}\"{bounds}}}",
+            self.buffer_views.len() - 1
+        ));
+        self.accessors.len() - 1
+    }
+
+    /// The glTF JSON document, `uri` is the external buffer file, `None` for GLB
+    fn json(&self, roots: &[usize], uri: Option<&str>) -> String {
+        let mut json = format!(
+            "{{\"asset\":{{\"version\":\"2.0\",\"generator\":\"bevy_mujoco\"}},\"scene\":0,\"scenes\":[{{\"nodes\":{}}}]",
+            json_indices(roots)
+        );
+        // glTF doesn't allow empty arrays
+        for (key, items) in [
+            ("nodes", &self.nodes),
+            ("meshes", &self.meshes),
+            ("materials", &self.materials),
+            ("accessors", &self.accessors),
+            ("bufferViews", &self.buffer_views),
+            ("animations", &self.animations),
+        ] {
+            if !items.is_empty() {
+                let _ = write!(json, ",\"{key}\":[{}]", items.join(","));
+            }
+        }
+        if !self.buffer.is_empty() {
+            let uri = uri.map_or(String::new(), |uri| {
+                format!(",\"uri\":{}", json_string(uri))
+            });
+            let _ = write!(
+                json,
+                ",\"buffers\":[{{\"byteLength\":{}{uri}}}]",
+                self.buffer.len()
+            );
+        }
+        json.push('}');
+        json
+    }
+}
+
+fn bytes<'a>(values: impl Iterator<Item = &'a f32>) -> Vec<u8> {
+    values.flat_map(|value| value.to_le_bytes()).collect()
+}
+
+fn json_indices(indices: &[usize]) -> String {
+    let indices: Vec<String> = indices.iter().map(usize::to_string).collect();
+    format!("[{}]", indices.join(","))
+}
+
+fn json_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// Binary glTF container with a JSON and a BIN chunk
+fn glb(json: String, buffer: &[u8]) -> Vec<u8> {
+    let mut json = json.into_bytes();
+    // chunks are 4-byte aligned, JSON padded with spaces and BIN with zeros
+    while json.len() % 4 != 0 {
+        json.push(b' ');
+    }
+    let mut bin = buffer.to_vec();
+    while bin.len() % 4 != 0 {
+        bin.push(0);
+    }
+
+    let mut length = 12 + 8 + json.len();
+    if !bin.is_empty() {
+        length += 8 + bin.len();
+    }
+    let mut glb = Vec::with_capacity(length);
+    glb.extend_from_slice(b"glTF");
+    glb.extend_from_slice(&2u32.to_le_bytes());
+    glb.extend_from_slice(&(length as u32).to_le_bytes());
+    glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"JSON");
+    glb.extend_from_slice(&json);
+    if !bin.is_empty() {
+        glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"BIN\0");
+        glb.extend_from_slice(&bin);
+    }
+    glb
+}
@@ -0,0 +1,371 @@
+//! Inverse kinematics: a damped least-squares solver on site jacobians and `MuJoCoIkTarget`
+//! entities, draggable gizmos whose pose the actuated joints follow through PD targets
+
+use bevy::{math::primitives::InfinitePlane3d, prelude::*, window::PrimaryWindow};
+use nalgebra::{DMatrix, DVector, Matrix3, Rotation3, UnitQuaternion, Vector3};
+
+use crate::{
+    adapters::*,
+    apply_pd_controller,
+    sys::{mjModel, no_render},
+    wrappers, Backend, JointType, MuJoCoJointControl, MuJoCoModelInfo, MuJoCoPdController,
+    MuJoCoResources, MuJoCoSceneRoot, MuJoCoSimulation, MuJoCoStateBuffer,
+};
+
+/// Parameters of the damped least-squares solver
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IkOptions {
+    /// Damping λ of `dq = Jᵀ (J Jᵀ + λ² I)⁻¹ e`, trades accuracy for stability near singularities
+    pub damping: f64,
+    pub iterations: usize,
+    /// Stop once the position error (and orientation error in radians) is below this norm
+    pub tolerance: f64,
+}
+
+impl Default for IkOptions {
+    fn default() -> Self {
+        IkOptions {
+            damping: 0.05,
+            iterations: 20,
+            tolerance: 1e-4,
+        }
+    }
+}
+
+/// Solve for joint positions that bring `site` to `position` (and `orientation` when given),
+/// moving only `dofs` and starting from `qpos`. Works on a copy of `mjData`, the simulation
+/// is left untouched. Limited joints are kept in their range
+pub(crate) fn solve_ik(
+    model: &mjModel,
+    site: usize,
+    position: Vector3<f64>,
+    orientation: Option<UnitQuaternion<f64>>,
+    dofs: &[usize],
+    qpos: &[f64],
+    options: &IkOptions,
+) -> Vec<f64> {
+    let (nq, nv, njnt, nsite) = (
+        model.nq as usize,
+        model.nv as usize,
+        model.njnt as usize,
+        model.nsite as usize,
+    );
+    let rows = if orientation.is_some() { 6 } else { 3 };
+
+    let data = unsafe { no_render::mj_makeData(model) };
+    let data_ref = unsafe { &mut *data };
+    let data_qpos = unsafe { wrappers::slice_mut(data_ref.qpos, nq) };
+    data_qpos.copy_from_slice(&qpos[..nq]);
+
+    let (mut jacp, mut jacr) = (vec![0.0; 3 * nv], vec![0.0; 3 * nv]);
+    for _ in 0..options.iterations {
+        unsafe {
+            no_render::mj_kinematics(model, data);
+            no_render::mj_comPos(model, data);
+        }
+
+        let xpos =
+            &unsafe { wrappers::slice(data_ref.site_xpos, 3 * nsite) }[3 * site..3 * site + 3];
+        let mut error = DVector::zeros(rows);
+        error
+            .fixed_rows_mut::<3>(0)
+            .copy_from(&(position - Vector3::from_column_slice(xpos)));
+        if let Some(orientation) = orientation {
+            let xmat =
+                &unsafe { wrappers::slice(data_ref.site_xmat, 9 * nsite) }[9 * site..9 * site + 9];
+            let current = Rotation3::from_matrix_unchecked(Matrix3::from_row_slice(xmat));
+            let rotation_error = orientation.to_rotation_matrix() * current.inverse();
+            error
+                .fixed_rows_mut::<3>(3)
+                .copy_from(&rotation_error.scaled_axis());
+        }
+        if error.norm() < options.tolerance {
+            break;
+        }
+
+        unsafe {
+            no_render::mj_jacSite(
+                model,
+                data,
+                jacp.as_mut_ptr(),
+                jacr.as_mut_ptr(),
+                site as i32,
+            );
+        }
+        let jacobian = DMatrix::from_fn(rows, dofs.len(), |row, column| {
+            let jac = if row < 3 { &jacp } else { &jacr };
+            jac[(row % 3) * nv + dofs[column]]
+        });
+        let damped = &jacobian * jacobian.transpose()
+            + DMatrix::identity(rows, rows) * options.damping.powi(2);
+        let Some(solution) = damped.cholesky().map(|c| c.solve(&error)) else {
+            break;
+        };
+        let step = jacobian.transpose() * solution;
+
+        let mut qvel = vec![0.0; nv];
+        for (dof, dq) in dofs.iter().zip(step.iter()) {
+            qvel[*dof] = *dq;
+        }
+        unsafe { no_render::mj_integratePos(model, data_ref.qpos, qvel.as_ptr(), 1.0) };
+
+        let jnt_type = unsafe { wrappers::slice(model.jnt_type, njnt) };
+        let jnt_limited = unsafe { wrappers::slice(model.jnt_limited, njnt) };
+        let jnt_range = unsafe { wrappers::slice(model.jnt_range, 2 * njnt) };
+        let jnt_qposadr = unsafe { wrappers::slice(model.jnt_qposadr, njnt) };
+        let data_qpos = unsafe { wrappers::slice_mut(data_ref.qpos, nq) };
+        for joint in (0..njnt)
+            .filter(|&j| jnt_limited[j] != 0 && JointType::from_raw(jnt_type[j]).is_scalar())
+        {
+            let q = &mut data_qpos[jnt_qposadr[joint] as usize];
+            *q = q.clamp(jnt_range[2 * joint], jnt_range[2 * joint + 1]);
+        }
+    }
+
+    let solution = unsafe { wrappers::slice(data_ref.qpos, nq) }.to_vec();
+    unsafe { no_render::mj_deleteData(data) };
+    solution
+}
+
+impl MuJoCoSimulation {
+    /// Joint positions that bring a site, by name, to a position (and orientation quaternion
+    /// w, x, y, z when given) in MuJoCo coordinates, starting from the current qpos.
+    /// Only hinge and slide joints move
+    pub fn solve_ik(
+        &self,
+        site: &str,
+        position: [f64; 3],
+        orientation: Option<[f64; 4]>,
+        options: &IkOptions,
+    ) -> Option<Vec<f64>> {
+        let simulation = self.lock().unwrap();
+        let model = wrappers::model(&simulation);
+        let site = wrappers::name_to_id(model, model.name_siteadr, model.nsite, site)?;
+        let njnt = model.njnt as usize;
+        let jnt_type = unsafe { wrappers::slice(model.jnt_type, njnt) };
+        let jnt_dofadr = unsafe { wrappers::slice(model.jnt_dofadr, njnt) };
+        let dofs: Vec<usize> = (0..njnt)
+            .filter(|&j| JointType::from_raw(jnt_type[j]).is_scalar())
+            .map(|j| jnt_dofadr[j] as usize)
+            .collect();
+        let qpos = unsafe { wrappers::slice(wrappers::data(&simulation).qpos, model.nq as usize) }
+            .to_vec();
+
+        Some(solve_ik(
+            model,
+            site,
+            Vector3::from(position),
+            orientation.map(|q| {
+                UnitQuaternion::from_quaternion(nalgebra::Quaternion::new(q[0], q[1], q[2], q[3]))
+            }),
+            &dofs,
+            &qpos,
+            options,
+        ))
+    }
+}
+
+/// Copy of the compiled model `solve_ik_targets` solves on, so solving every frame doesn't hold
+/// the simulation lock. Kinematics only depend on parts of the model the plugin doesn't change
+struct IkModel(*mut mjModel);
+
+// MuJoCo doesn't keep thread-local state for a model, and the solver only reads it
+unsafe impl Send for IkModel {}
+unsafe impl Sync for IkModel {}
+
+impl IkModel {
+    fn copy(simulation: &impl Backend) -> Option<Self> {
+        let model =
+            unsafe { no_render::mj_copyModel(std::ptr::null_mut(), simulation.model_ptr()) };
+        (!model.is_null()).then_some(IkModel(model))
+    }
+}
+
+impl Drop for IkModel {
+    fn drop(&mut self) {
+        unsafe { no_render::mj_deleteModel(self.0) };
+    }
+}
+
+/// IK target for a site. The entity's pose, in the scene frame, is solved for every frame and
+/// the solution written to `MuJoCoPdController::target_qpos` of the actuated joints, so set PD
+/// gains for them. Spawn it with a `SpatialBundle` and drag the gizmo with the left mouse button
+/// to move the target
+#[derive(Component, Debug, Clone)]
+pub struct MuJoCoIkTarget {
+    pub site: String,
+    /// Match the site orientation as well as its position
+    pub orientation: bool,
+    pub options: IkOptions,
+}
+
+impl MuJoCoIkTarget {
+    pub fn new(site: &str) -> Self {
+        MuJoCoIkTarget {
+            site: site.to_string(),
+            orientation: false,
+            options: IkOptions::default(),
+        }
+    }
+
+    pub fn with_orientation(mut self) -> Self {
+        self.orientation = true;
+        self
+    }
+}
+
+/// Solve `MuJoCoIkTarget`s and draw them as draggable gizmos
+pub struct MuJoCoIkPlugin;
+
+impl Plugin for MuJoCoIkPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (drag_ik_targets, solve_ik_targets, draw_ik_targets)
+                .chain()
+                .before(apply_pd_controller)
+                .run_if(resource_exists::<MuJoCoResources>),
+        );
+    }
+}
+
+/// Gizmo radius in scene units and pick radius in pixels
+const TARGET_RADIUS: f32 = 0.03;
+const PICK_RADIUS: f32 = 20.0;
+
+fn drag_ik_targets(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut targets: Query<(Entity, &mut Transform, &GlobalTransform), With<MuJoCoIkTarget>>,
+    parents: Query<&Parent>,
+    global_transforms: Query<&GlobalTransform>,
+    mut dragged: Local<Option<Entity>>,
+) {
+    if !mouse.pressed(MouseButton::Left) {
+        *dragged = None;
+        return;
+    }
+    let Some(cursor) = windows.get_single().ok().and_then(|w| w.cursor_position()) else {
+        return;
+    };
+    let Some((camera, camera_transform)) = cameras.iter().find(|(camera, _)| camera.is_active)
+    else {
+        return;
+    };
+
+    if mouse.just_pressed(MouseButton::Left) {
+        *dragged = targets
+            .iter()
+            .filter_map(|(entity, _, transform)| {
+                let screen = camera
+                    .world_to_viewport(camera_transform, transform.translation())
+                    .ok()?;
+                Some((entity, screen.distance(cursor)))
+            })
+            .filter(|(_, distance)| *distance < PICK_RADIUS)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(entity, _)| entity);
+    }
+
+    let Some(entity) = *dragged else {
+        return;
+    };
+    let Ok((_, mut transform, global_transform)) = targets.get_mut(entity) else {
+        return;
+    };
+    let Ok(ray) = camera.viewport_to_world(camera_transform, cursor) else {
+        return;
+    };
+    // move in the plane facing the camera through the target
+    let plane = InfinitePlane3d::new(camera_transform.forward());
+    let Some(distance) = ray.intersect_plane(global_transform.translation(), plane) else {
+        return;
+    };
+    let point = ray.get_point(distance);
+    transform.translation = match parents
+        .get(entity)
+        .ok()
+        .and_then(|parent| global_transforms.get(parent.get()).ok())
+    {
+        Some(parent) => parent.affine().inverse().transform_point3(point),
+        None => point,
+    };
+}
+
+fn solve_ik_targets(
+    mujoco: Res<MuJoCoSimulation>,
+    model_info: Res<MuJoCoModelInfo>,
+    state_buffer: Res<MuJoCoStateBuffer>,
+    targets: Query<(&MuJoCoIkTarget, &GlobalTransform)>,
+    scene_roots: Query<&GlobalTransform, With<MuJoCoSceneRoot>>,
+    joint_control: Res<MuJoCoJointControl>,
+    mut pd_controller: ResMut<MuJoCoPdController>,
+    mut ik_model: Local<Option<IkModel>>,
+) {
+    if targets.is_empty() {
+        return;
+    }
+
+    // copied once for every loaded model, the only time the simulation is locked
+    if ik_model.is_none() || mujoco.is_changed() {
+        *ik_model = IkModel::copy(&mujoco.lock().unwrap());
+    }
+    let Some(ik_model) = ik_model.as_ref() else {
+        return;
+    };
+    let model = unsafe { &*ik_model.0 };
+    // solve from the state of the latest snapshot, nothing was published yet when it's empty
+    let mut qpos = state_buffer.front().qpos.clone();
+    if qpos.len() != model_info.nq {
+        return;
+    }
+    let scene_transform = scene_roots.get_single().copied().unwrap_or_default();
+    let dofs: Vec<usize> = joint_control
+        .actuators
+        .values()
+        .map(|actuator| actuator.dof_adr)
+        .collect();
+
+    // targets are solved in turn, each starting from the previous solution
+    for (target, transform) in targets.iter() {
+        let Some(site) = model_info.site_id(&target.site) else {
+            warn_once!("IK target site {} not found in the model", target.site);
+            continue;
+        };
+        // pose is given in the bevy scene frame
+        let pose = transform.reparented_to(&scene_transform);
+        let position = position_bevy_2_mujoco(model_info.up_axis, pose.translation);
+        let orientation = target.orientation.then(|| {
+            let basis = model_info.up_axis.basis();
+            let rotation = basis.inverse() * pose.rotation * basis;
+            UnitQuaternion::from_quaternion(nalgebra::Quaternion::new(
+                rotation.w as f64,
+                rotation.x as f64,
+                rotation.y as f64,
+                rotation.z as f64,
+            ))
+        });
+        qpos = solve_ik(
+            model,
+            site,
+            Vector3::from(position),
+            orientation,
+            &dofs,
+            &qpos,
+            &target.options,
+        );
+    }
+
+    for (joint, actuator) in joint_control.actuators.iter() {
+        pd_controller.set_target(joint, qpos[actuator.qpos_adr]);
+    }
+}
+
+fn draw_ik_targets(targets: Query<&GlobalTransform, With<MuJoCoIkTarget>>, mut gizmos: Gizmos) {
+    for transform in targets.iter() {
+        let isometry = Isometry3d::new(transform.translation(), transform.rotation());
+        gizmos.sphere(isometry, TARGET_RADIUS, Color::srgb(1.0, 0.8, 0.0));
+        gizmos.axes(*transform, 4.0 * TARGET_RADIUS);
+    }
+}
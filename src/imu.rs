@@ -0,0 +1,123 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{adapters::*, noise::gaussian, wrappers, MuJoCoSimulation};
+
+/// `mjtObj::mjOBJ_SITE`
+const OBJ_SITE: i32 = 6;
+
+/// `mjtSensor` values, `mjSENS_CAMPROJECTION` was inserted before the joint sensors in MuJoCo 3.x
+const SENS_ACCELEROMETER: i32 = 1;
+const SENS_GYRO: i32 = 3;
+#[cfg(not(mujoco3))]
+const SENS_FRAMEQUAT: i32 = 24;
+#[cfg(mujoco3)]
+const SENS_FRAMEQUAT: i32 = crate::sys::mjSENS_FRAMEQUAT as i32;
+
+/// Readings of the framequat / gyro / accelerometer sensors attached to a site,
+/// expressed in the bevy scene frame
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Imu {
+    pub orientation: Quat,
+    pub angular_velocity: Vec3,
+    pub linear_acceleration: Vec3,
+}
+
+/// Standard deviations of the Gaussian noise added to `Imu` readings
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ImuNoise {
+    /// Radians, applied as a random rotation around each axis
+    pub orientation: f32,
+    pub angular_velocity: f32,
+    pub linear_acceleration: f32,
+}
+
+fn gaussian_vec3(rng: &mut impl Rng, std: f32) -> Vec3 {
+    Vec3::new(
+        gaussian(rng, std as f64) as f32,
+        gaussian(rng, std as f64) as f32,
+        gaussian(rng, std as f64) as f32,
+    )
+}
+
+impl MuJoCoSimulation {
+    /// IMU readings from the sensors attached to a site. Gyro and accelerometer values are
+    /// rotated from the site frame to the scene frame. Orientation falls back to the site
+    /// frame when the model has no framequat sensor on the site. Returns `None` when the site
+    /// doesn't exist or has none of the three sensors
+    pub fn imu(&self, site: &str) -> Option<Imu> {
+        let simulation = self.lock().unwrap();
+        let model = wrappers::model(&simulation);
+        let data = wrappers::data(&simulation);
+        let site_id = wrappers::name_to_id(model, model.name_siteadr, model.nsite, site)?;
+
+        let nsensor = model.nsensor as usize;
+        let sensor_type = unsafe { wrappers::slice(model.sensor_type, nsensor) };
+        let sensor_objtype = unsafe { wrappers::slice(model.sensor_objtype, nsensor) };
+        let sensor_objid = unsafe { wrappers::slice(model.sensor_objid, nsensor) };
+        let sensor_adr = unsafe { wrappers::slice(model.sensor_adr, nsensor) };
+        let sensordata = unsafe { wrappers::slice(data.sensordata, model.nsensordata as usize) };
+
+        let reading = |kind: i32, dim: usize| {
+            (0..nsensor)
+                .find(|&i| {
+                    sensor_type[i] == kind
+                        && sensor_objtype[i] == OBJ_SITE
+                        && sensor_objid[i] as usize == site_id
+                })
+                .map(|i| &sensordata[sensor_adr[i] as usize..sensor_adr[i] as usize + dim])
+        };
+        let framequat = reading(SENS_FRAMEQUAT, 4);
+        let gyro = reading(SENS_GYRO, 3);
+        let accelerometer = reading(SENS_ACCELEROMETER, 3);
+        if framequat.is_none() && gyro.is_none() && accelerometer.is_none() {
+            return None;
+        }
+
+        // site frame in MuJoCo world coordinates, xmat is row-major
+        let xmat = &unsafe { wrappers::slice(data.site_xmat, 9 * model.nsite as usize) }
+            [9 * site_id..9 * site_id + 9];
+        let site_rotation = Quat::from_mat3(&Mat3::from_cols_array(&[
+            xmat[0] as f32,
+            xmat[3] as f32,
+            xmat[6] as f32,
+            xmat[1] as f32,
+            xmat[4] as f32,
+            xmat[7] as f32,
+            xmat[2] as f32,
+            xmat[5] as f32,
+            xmat[8] as f32,
+        ]));
+        let to_scene = |local: Option<&[f64]>| {
+            local.map_or(Vec3::ZERO, |v| {
+                let world = site_rotation * Vec3::new(v[0] as f32, v[1] as f32, v[2] as f32);
+                position_mujoco_2_bevy(
+                    simulation.up_axis,
+                    [world.x as f64, world.y as f64, world.z as f64],
+                )
+            })
+        };
+
+        let orientation = framequat.map_or(site_rotation, |q| {
+            Quat::from_xyzw(q[1] as f32, q[2] as f32, q[3] as f32, q[0] as f32).normalize()
+        });
+
+        Some(Imu {
+            orientation: simulation.up_axis.basis() * orientation,
+            angular_velocity: to_scene(gyro),
+            linear_acceleration: to_scene(accelerometer),
+        })
+    }
+
+    /// IMU readings with Gaussian noise added
+    pub fn imu_with_noise(&self, site: &str, noise: &ImuNoise, rng: &mut impl Rng) -> Option<Imu> {
+        let imu = self.imu(site)?;
+        let rotation_noise = gaussian_vec3(rng, noise.orientation);
+        Some(Imu {
+            orientation: (Quat::from_scaled_axis(rotation_noise) * imu.orientation).normalize(),
+            angular_velocity: imu.angular_velocity + gaussian_vec3(rng, noise.angular_velocity),
+            linear_acceleration: imu.linear_acceleration
+                + gaussian_vec3(rng, noise.linear_acceleration),
+        })
+    }
+}
@@ -0,0 +1,175 @@
+//! TCP bridge for driving the simulation from another process (e.g. a Python policy).
+//!
+//! The server speaks newline-delimited JSON. After every frame each client receives
+//!
+//! ```json
+//! {"type": "state", "time": 1.25, "state": {"sensor_data": [], "qpos": [], "qvel": [], "cfrc_ext": [], "act": [], "actuator_force": [], "qfrc_actuator": [], "energy": {...}}}
+//! ```
+//!
+//! and clients send controls, applied before the next step (the last message of a frame wins)
+//!
+//! ```json
+//! {"type": "control", "data": [0.0, 0.1, 0.2]}
+//! ```
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpListener};
+use std::sync::mpsc::{channel, sync_channel, Receiver, Sender, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    apply_joint_control, simulate_physics, MuJoCoResources, MuJoCoState, MuJoCoStateBuffer,
+    MuJoCoSystemSet,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IpcMessage {
+    State { time: f64, state: MuJoCoState },
+    Control { data: Vec<f64> },
+}
+
+/// Serve simulation state and accept controls over TCP at `address`
+pub struct MuJoCoIpcPlugin {
+    pub address: String,
+}
+
+impl Default for MuJoCoIpcPlugin {
+    fn default() -> Self {
+        MuJoCoIpcPlugin {
+            address: "127.0.0.1:7878".to_string(),
+        }
+    }
+}
+
+/// States a client may fall behind by before it is dropped
+const CLIENT_QUEUE: usize = 64;
+
+#[derive(Resource)]
+struct IpcServer {
+    /// Queues of the clients' writer threads
+    clients: Arc<Mutex<Vec<SyncSender<Arc<str>>>>>,
+    controls: Mutex<Receiver<Vec<f64>>>,
+}
+
+impl Plugin for MuJoCoIpcPlugin {
+    fn build(&self, app: &mut App) {
+        let listener = match TcpListener::bind(&self.address) {
+            Ok(listener) => listener,
+            Err(err) => {
+                error!("MuJoCo IPC server can't listen on {}: {err}", self.address);
+                return;
+            }
+        };
+        info!("MuJoCo IPC server listening on {}", self.address);
+
+        let clients = Arc::new(Mutex::new(vec![]));
+        let (sender, receiver) = channel();
+        {
+            let clients = clients.clone();
+            thread::spawn(move || accept_clients(listener, clients, sender));
+        }
+
+        app.insert_resource(IpcServer {
+            clients,
+            controls: Mutex::new(receiver),
+        });
+        app.add_systems(
+            Update,
+            (
+                // replaces all controls, joint commands and control events apply on top
+                receive_controls
+                    .in_set(MuJoCoSystemSet::Control)
+                    .before(apply_joint_control),
+                send_state.after(simulate_physics),
+            )
+                .run_if(resource_exists::<MuJoCoResources>),
+        );
+    }
+}
+
+fn accept_clients(
+    listener: TcpListener,
+    clients: Arc<Mutex<Vec<SyncSender<Arc<str>>>>>,
+    controls: Sender<Vec<f64>>,
+) {
+    for mut stream in listener.incoming().flatten() {
+        let _ = stream.set_nodelay(true);
+        let Ok(reader) = stream.try_clone() else {
+            continue;
+        };
+
+        // states are written on the client's own thread, a slow client never stalls the app
+        let (sender, states) = sync_channel::<Arc<str>>(CLIENT_QUEUE);
+        clients.lock().unwrap().push(sender);
+        thread::spawn(move || {
+            for line in states {
+                if stream.write_all(line.as_bytes()).is_err() {
+                    break;
+                }
+            }
+            // also ends the reader thread
+            let _ = stream.shutdown(Shutdown::Both);
+        });
+
+        let controls = controls.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(reader).lines() {
+                let Ok(line) = line else {
+                    return;
+                };
+                match serde_json::from_str::<IpcMessage>(&line) {
+                    Ok(IpcMessage::Control { data }) => {
+                        if controls.send(data).is_err() {
+                            return;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(err) => warn!("invalid MuJoCo IPC message: {err}"),
+                }
+            }
+        });
+    }
+}
+
+fn receive_controls(server: Res<IpcServer>, mut mujoco_resources: ResMut<MuJoCoResources>) {
+    let controls = server.controls.lock().unwrap();
+    if let Some(data) = controls.try_iter().last() {
+        mujoco_resources.control.data = data;
+    }
+}
+
+fn send_state(
+    server: Res<IpcServer>,
+    state_buffer: Res<MuJoCoStateBuffer>,
+    mujoco_resources: Res<MuJoCoResources>,
+) {
+    let mut clients = server.clients.lock().unwrap();
+    if clients.is_empty() {
+        return;
+    }
+
+    let message = IpcMessage::State {
+        time: state_buffer.front().time,
+        state: mujoco_resources.state.clone(),
+    };
+    let Ok(mut line) = serde_json::to_string(&message) else {
+        return;
+    };
+    line.push('\n');
+    let line: Arc<str> = line.into();
+
+    // drop clients that disconnected or fell too far behind
+    clients.retain(|client| match client.try_send(line.clone()) {
+        Ok(()) => true,
+        Err(TrySendError::Full(_)) => {
+            warn!("dropping MuJoCo IPC client that can't keep up");
+            false
+        }
+        Err(TrySendError::Disconnected(_)) => false,
+    });
+}
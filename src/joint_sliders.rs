@@ -0,0 +1,108 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+
+use crate::{
+    apply_pd_controller, wrappers, JointType, MuJoCoModelInfo, MuJoCoPdController,
+    MuJoCoPluginSettings, MuJoCoResources, MuJoCoSimulation,
+};
+
+/// An egui window with a slider per hinge and slide joint, like the joint panel of MuJoCo's
+/// `simulate`: sliders set joint positions while paused and PD targets while running
+pub struct MuJoCoJointSlidersPlugin;
+
+impl Plugin for MuJoCoJointSlidersPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+        app.add_systems(
+            Update,
+            joint_sliders
+                .before(apply_pd_controller)
+                .run_if(resource_exists::<MuJoCoResources>),
+        );
+    }
+}
+
+struct JointSlider {
+    name: String,
+    qpos_adr: usize,
+    range: std::ops::RangeInclusive<f64>,
+}
+
+/// Sliders of the loaded model's hinge and slide joints, ranges from `jnt_range` when limited
+fn build_sliders(mujoco: &MuJoCoSimulation, model_info: &MuJoCoModelInfo) -> Vec<JointSlider> {
+    let simulation = mujoco.lock().unwrap();
+    let model = wrappers::model(&simulation);
+    let jnt_limited = unsafe { wrappers::slice(model.jnt_limited, model_info.njnt) };
+    let jnt_range = unsafe { wrappers::slice(model.jnt_range, 2 * model_info.njnt) };
+
+    (0..model_info.njnt)
+        .filter(|&joint| JointType::from_raw(model_info.jnt_type[joint]).is_scalar())
+        .map(|joint| {
+            let jnt_type = JointType::from_raw(model_info.jnt_type[joint]);
+            let range = match (jnt_limited[joint] != 0, jnt_type) {
+                (true, _) => jnt_range[2 * joint]..=jnt_range[2 * joint + 1],
+                (false, JointType::HINGE) => -std::f64::consts::PI..=std::f64::consts::PI,
+                (false, _) => -1.0..=1.0,
+            };
+            JointSlider {
+                name: model_info.joint_names[joint].clone(),
+                qpos_adr: model_info.jnt_qposadr[joint],
+                range,
+            }
+        })
+        .collect()
+}
+
+fn joint_sliders(
+    mut contexts: EguiContexts,
+    mut sliders: Local<Vec<JointSlider>>,
+    mujoco: Res<MuJoCoSimulation>,
+    model_info: Res<MuJoCoModelInfo>,
+    settings: Res<MuJoCoPluginSettings>,
+    mujoco_resources: Res<MuJoCoResources>,
+    mut pd_controller: ResMut<MuJoCoPdController>,
+) {
+    if model_info.is_added() || sliders.is_empty() {
+        *sliders = build_sliders(&mujoco, &model_info);
+    }
+
+    let qpos = &mujoco_resources.state.qpos;
+    let mut changed = vec![];
+    egui::Window::new("Joints").show(contexts.ctx_mut(), |ui| {
+        for (index, slider) in sliders.iter().enumerate() {
+            let current = qpos.get(slider.qpos_adr).copied().unwrap_or_default();
+            let mut value = match settings.pause_simulation {
+                true => current,
+                false => pd_controller
+                    .target_qpos
+                    .get(&slider.name)
+                    .copied()
+                    .unwrap_or(current),
+            };
+            let response =
+                ui.add(egui::Slider::new(&mut value, slider.range.clone()).text(&slider.name));
+            if response.changed() {
+                changed.push((index, value));
+            }
+        }
+    });
+    if changed.is_empty() {
+        return;
+    }
+
+    if settings.pause_simulation {
+        let mut qpos = qpos.clone();
+        for (index, value) in changed {
+            if let Some(q) = qpos.get_mut(sliders[index].qpos_adr) {
+                *q = value;
+            }
+        }
+        mujoco.set_qpos(&qpos);
+    } else {
+        for (index, value) in changed {
+            pd_controller.set_target(&sliders[index].name, value);
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use bevy::prelude::*;
+
+use crate::{apply_mujoco_commands, MuJoCoCommands, MuJoCoPluginSettings, MuJoCoResources};
+
+/// Default simulation keys: space pauses and resumes, right arrow steps once while paused
+pub struct MuJoCoKeybindingsPlugin;
+
+impl Plugin for MuJoCoKeybindingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            simulation_keys
+                .before(apply_mujoco_commands)
+                .run_if(resource_exists::<MuJoCoResources>),
+        );
+    }
+}
+
+fn simulation_keys(
+    keys: Res<ButtonInput<KeyCode>>,
+    settings: Res<MuJoCoPluginSettings>,
+    mut mujoco_commands: MuJoCoCommands,
+) {
+    if keys.just_pressed(KeyCode::Space) {
+        if settings.pause_simulation {
+            mujoco_commands.resume();
+        } else {
+            mujoco_commands.pause();
+        }
+    }
+    if keys.just_pressed(KeyCode::ArrowRight) {
+        mujoco_commands.step_once();
+    }
+}
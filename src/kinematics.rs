@@ -0,0 +1,177 @@
+use bevy::prelude::*;
+use nalgebra::Matrix3xX;
+
+use crate::{
+    adapters::pose_bevy_2_mujoco, wrappers, Backend, JointType, MuJoCoSimulation, SimulationHandle,
+};
+
+/// Move a body to a pose in the bevy scene frame: the qpos of its free joint is written and
+/// its velocity zeroed, or the pose of a mocap body is set. Kinematics are recomputed with
+/// `mj_forward`. Returns false for bodies that are neither free nor mocap
+pub(crate) fn teleport_body(simulation: &SimulationHandle, body: usize, pose: &Transform) -> bool {
+    let model = wrappers::model(simulation);
+    let data = wrappers::data(simulation);
+    let (nbody, njnt) = (model.nbody as usize, model.njnt as usize);
+    let (position, quat) = pose_bevy_2_mujoco(simulation.up_axis, pose);
+
+    let jntadr = unsafe { wrappers::slice(model.body_jntadr, nbody) }[body];
+    let mocapid = unsafe { wrappers::slice(model.body_mocapid, nbody) }[body];
+    let jnt_type = unsafe { wrappers::slice(model.jnt_type, njnt) };
+    if jntadr >= 0 && JointType::from_raw(jnt_type[jntadr as usize]) == JointType::FREE {
+        let qpos_adr =
+            unsafe { wrappers::slice(model.jnt_qposadr, njnt) }[jntadr as usize] as usize;
+        let dof_adr = unsafe { wrappers::slice(model.jnt_dofadr, njnt) }[jntadr as usize] as usize;
+        let qpos = unsafe { wrappers::slice_mut(data.qpos, model.nq as usize) };
+        qpos[qpos_adr..qpos_adr + 3].copy_from_slice(&position);
+        qpos[qpos_adr + 3..qpos_adr + 7].copy_from_slice(&quat);
+        unsafe {
+            wrappers::slice_mut(data.qvel, model.nv as usize)[dof_adr..dof_adr + 6].fill(0.0)
+        };
+    } else if mocapid >= 0 {
+        let mocap = mocapid as usize;
+        let nmocap = model.nmocap as usize;
+        unsafe {
+            wrappers::slice_mut(data.mocap_pos, 3 * nmocap)[3 * mocap..3 * mocap + 3]
+                .copy_from_slice(&position)
+        };
+        unsafe {
+            wrappers::slice_mut(data.mocap_quat, 4 * nmocap)[4 * mocap..4 * mocap + 4]
+                .copy_from_slice(&quat)
+        };
+    } else {
+        return false;
+    }
+
+    unsafe {
+        crate::sys::no_render::mj_forward(simulation.model_ptr(), simulation.data_ptr());
+    }
+    simulation.teleported.set(true);
+    true
+}
+
+impl MuJoCoSimulation {
+    /// Translational and rotational jacobians (3 x nv) of a site, by site name
+    pub fn jacobian_site(&self, site: &str) -> Option<(Matrix3xX<f64>, Matrix3xX<f64>)> {
+        let simulation = self.lock().unwrap();
+        let model = wrappers::model(&simulation);
+        let site_id = wrappers::name_to_id(model, model.name_siteadr, model.nsite, site)?;
+        let nv = model.nv as usize;
+
+        let (mut jacp, mut jacr) = (vec![0.0; 3 * nv], vec![0.0; 3 * nv]);
+        unsafe {
+            crate::sys::no_render::mj_jacSite(
+                simulation.model_ptr(),
+                simulation.data_ptr(),
+                jacp.as_mut_ptr(),
+                jacr.as_mut_ptr(),
+                site_id as i32,
+            );
+        }
+        Some((
+            Matrix3xX::from_row_slice(&jacp),
+            Matrix3xX::from_row_slice(&jacr),
+        ))
+    }
+
+    /// Translational and rotational jacobians (3 x nv) of a body frame, by body name
+    pub fn jacobian_body(&self, body: &str) -> Option<(Matrix3xX<f64>, Matrix3xX<f64>)> {
+        let simulation = self.lock().unwrap();
+        let model = wrappers::model(&simulation);
+        let body_id = wrappers::body_id(model, body)?;
+        let nv = model.nv as usize;
+
+        let (mut jacp, mut jacr) = (vec![0.0; 3 * nv], vec![0.0; 3 * nv]);
+        unsafe {
+            crate::sys::no_render::mj_jacBody(
+                simulation.model_ptr(),
+                simulation.data_ptr(),
+                jacp.as_mut_ptr(),
+                jacr.as_mut_ptr(),
+                body_id as i32,
+            );
+        }
+        Some((
+            Matrix3xX::from_row_slice(&jacp),
+            Matrix3xX::from_row_slice(&jacr),
+        ))
+    }
+
+    /// Generalized forces needed to produce `qacc` at the current qpos/qvel (`mj_inverse`).
+    /// With zero `qacc` and velocities this is the gravity compensation torque
+    pub fn inverse_dynamics(&self, qacc: &[f64]) -> Vec<f64> {
+        let simulation = self.lock().unwrap();
+        let nv = wrappers::model(&simulation).nv as usize;
+        let data = wrappers::data(&simulation);
+        let data_qacc = unsafe { wrappers::slice_mut(data.qacc, nv) };
+
+        // keep the simulation's qacc untouched
+        let saved_qacc = data_qacc.to_vec();
+        for (dst, src) in data_qacc.iter_mut().zip(qacc) {
+            *dst = *src;
+        }
+        unsafe {
+            crate::sys::no_render::mj_inverse(simulation.model_ptr(), simulation.data_ptr());
+        }
+        let qfrc_inverse = unsafe { wrappers::slice(data.qfrc_inverse, nv) }.to_vec();
+        unsafe { wrappers::slice_mut(data.qacc, nv).copy_from_slice(&saved_qacc) };
+
+        qfrc_inverse
+    }
+
+    /// Recompute kinematics and derived quantities without advancing time (`mj_forward`)
+    pub fn forward(&self) {
+        let simulation = self.lock().unwrap();
+        unsafe {
+            crate::sys::no_render::mj_forward(simulation.model_ptr(), simulation.data_ptr());
+        }
+    }
+
+    /// Set joint positions and update body and site poses right away, without stepping.
+    /// Velocities are left untouched
+    pub fn set_qpos(&self, qpos: &[f64]) {
+        {
+            let simulation = self.lock().unwrap();
+            let nq = wrappers::model(&simulation).nq as usize;
+            let data = wrappers::data(&simulation);
+            for (dst, src) in unsafe { wrappers::slice_mut(data.qpos, nq) }
+                .iter_mut()
+                .zip(qpos)
+            {
+                *dst = *src;
+            }
+        }
+        self.forward();
+    }
+
+    /// World position and orientation quaternion (w, x, y, z) of a body in MuJoCo coordinates
+    pub fn body_pose(&self, body_id: usize) -> Option<([f64; 3], [f64; 4])> {
+        let simulation = self.lock().unwrap();
+        let nbody = wrappers::model(&simulation).nbody as usize;
+        let data = wrappers::data(&simulation);
+        let xpos =
+            unsafe { wrappers::slice(data.xpos, 3 * nbody) }.get(3 * body_id..3 * body_id + 3)?;
+        let xquat =
+            unsafe { wrappers::slice(data.xquat, 4 * nbody) }.get(4 * body_id..4 * body_id + 4)?;
+        Some((
+            [xpos[0], xpos[1], xpos[2]],
+            [xquat[0], xquat[1], xquat[2], xquat[3]],
+        ))
+    }
+
+    /// World position and row-major orientation matrix of a site in MuJoCo coordinates, by site name
+    pub fn site_pose(&self, site: &str) -> Option<([f64; 3], [f64; 9])> {
+        let simulation = self.lock().unwrap();
+        let model = wrappers::model(&simulation);
+        let site_id = wrappers::name_to_id(model, model.name_siteadr, model.nsite, site)?;
+        let nsite = model.nsite as usize;
+        let data = wrappers::data(&simulation);
+        let xpos =
+            &unsafe { wrappers::slice(data.site_xpos, 3 * nsite) }[3 * site_id..3 * site_id + 3];
+        let xmat =
+            &unsafe { wrappers::slice(data.site_xmat, 9 * nsite) }[9 * site_id..9 * site_id + 9];
+
+        let mut mat = [0.0; 9];
+        mat.copy_from_slice(xmat);
+        Some(([xpos[0], xpos[1], xpos[2]], mat))
+    }
+}
@@ -1,35 +1,520 @@
+#[cfg(native)]
+mod actuator_groups;
+#[cfg(native)]
 mod adapters;
+#[cfg(native)]
+mod alignment;
+#[cfg(native)]
+mod animation_export;
+#[cfg(native)]
+mod asset_cache;
+#[cfg(native)]
+mod assets;
+#[cfg(native)]
+mod backend;
+#[cfg(native)]
+mod batch;
+#[cfg(native)]
+mod body_dynamics;
+#[cfg(native)]
+mod capture;
+#[cfg(native)]
+mod collision_filter;
+#[cfg(native)]
+mod collision_proxy;
+#[cfg(native)]
+mod commands;
+#[cfg(native)]
+mod constraint_forces;
+#[cfg(native)]
+mod control;
+#[cfg(native)]
+mod control_events;
+#[cfg(native)]
+mod control_limits;
+#[cfg(native)]
+mod control_loop;
+#[cfg(native)]
+mod convex_hull;
+#[cfg(native)]
+mod custom_data;
+#[cfg(native)]
+mod diagnostics;
+#[cfg(native)]
+mod divergence;
+#[cfg(native)]
+mod energy;
+#[cfg(native)]
+mod entity_map;
+#[cfg(native)]
+mod equality;
+#[cfg(all(native, mujoco3))]
+mod flex;
+#[cfg(native)]
+mod free_body;
+#[cfg(native)]
+mod geom_classes;
+#[cfg(native)]
+mod gltf_export;
+#[cfg(native)]
+mod ik;
+#[cfg(native)]
+mod imu;
+#[cfg(all(native, feature = "ipc"))]
+mod ipc;
+#[cfg(all(native, feature = "egui"))]
+mod joint_sliders;
+#[cfg(all(native, feature = "keybindings"))]
+mod keybindings;
+#[cfg(native)]
+mod kinematics;
+#[cfg(native)]
+mod lidar;
+#[cfg(native)]
+mod mjcf;
+#[cfg(native)]
+mod mjcf_builder;
+#[cfg(native)]
+mod model_elements;
+#[cfg(native)]
+mod model_info;
+#[cfg(native)]
+mod mujoco_plugins;
+#[cfg(native)]
 mod mujoco_shape;
+#[cfg(native)]
+mod noise;
+#[cfg(native)]
+mod observation;
+#[cfg(native)]
+mod options;
+#[cfg(native)]
+mod parameters;
+#[cfg(native)]
+mod pd_controller;
+#[cfg(native)]
+mod physics_diagnostics;
+#[cfg(native)]
+mod physics_threads;
+#[cfg(all(native, feature = "picking"))]
+mod picking;
+#[cfg(all(native, feature = "rapier_interop"))]
+mod rapier_interop;
+#[cfg(native)]
+mod raycast;
+#[cfg(native)]
+mod render_mode;
+mod replay;
+#[cfg(all(native, feature = "rerun"))]
+mod rerun_logger;
+#[cfg(native)]
+mod reset;
+#[cfg(native)]
+mod rigid_body;
+#[cfg(all(native, feature = "ros2"))]
+mod ros2;
+#[cfg(native)]
+mod scene;
+#[cfg(native)]
+mod scratch;
+#[cfg(native)]
+mod state_buffer;
+#[cfg(native)]
+mod state_f32;
+#[cfg(native)]
+mod step_control;
+#[cfg(native)]
+mod sys;
+#[cfg(native)]
+mod task;
+#[cfg(all(native, feature = "telemetry"))]
+mod telemetry;
+#[cfg(native)]
+mod teleop;
+#[cfg(native)]
+mod terrain;
+#[cfg(native)]
+mod time;
+#[cfg(native)]
+mod unsupported;
+#[cfg(native)]
+mod visibility;
+#[cfg(native)]
+mod visual;
+#[cfg(native)]
+mod warnings;
+#[cfg(native)]
+mod wrappers;
 
-use bevy::{ecs::system::EntityCommands, prelude::*, render::mesh::Mesh};
-use serde::Serialize;
+#[cfg(native)]
+use bevy::{
+    ecs::system::{EntityCommands, SystemParam},
+    pbr::{NotShadowCaster, NotShadowReceiver},
+    prelude::*,
+    render::mesh::Mesh,
+};
+#[cfg(native)]
+use serde::{Deserialize, Serialize};
 
-use std::cell::RefCell;
-use std::rc::Rc;
+#[cfg(native)]
+use std::cell::{Cell, RefCell};
+#[cfg(native)]
+use std::mem::ManuallyDrop;
+#[cfg(native)]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(native)]
 use std::sync::{Arc, Mutex};
 
-use mujoco_rust::{self, Body, Geom, GeomType};
-
+#[cfg(native)]
+pub use crate::actuator_groups::*;
+#[cfg(native)]
 use crate::adapters::*;
+#[cfg(native)]
+pub use crate::adapters::{pose_bevy_2_mujoco, pose_mujoco_2_bevy};
+#[cfg(native)]
+pub use crate::alignment::*;
+#[cfg(native)]
+pub use crate::animation_export::*;
+#[cfg(native)]
+pub use crate::asset_cache::*;
+#[cfg(native)]
+pub use crate::assets::*;
+#[cfg(native)]
+pub use crate::backend::*;
+#[cfg(native)]
+pub use crate::batch::*;
+#[cfg(native)]
+pub use crate::body_dynamics::*;
+#[cfg(native)]
+pub use crate::capture::*;
+#[cfg(native)]
+pub use crate::collision_filter::*;
+#[cfg(native)]
+pub use crate::collision_proxy::*;
+#[cfg(native)]
+pub use crate::commands::*;
+#[cfg(native)]
+pub use crate::constraint_forces::*;
+#[cfg(native)]
+pub use crate::control::*;
+#[cfg(native)]
+pub use crate::control_events::*;
+#[cfg(native)]
+pub use crate::control_limits::*;
+#[cfg(native)]
+pub use crate::control_loop::*;
+#[cfg(native)]
+pub use crate::convex_hull::*;
+#[cfg(native)]
+pub use crate::custom_data::*;
+#[cfg(native)]
+pub use crate::diagnostics::*;
+#[cfg(native)]
+pub use crate::divergence::*;
+#[cfg(native)]
+pub use crate::energy::*;
+#[cfg(native)]
+pub use crate::entity_map::*;
+#[cfg(native)]
+pub use crate::equality::*;
+#[cfg(all(native, mujoco3))]
+pub use crate::flex::*;
+#[cfg(native)]
+pub use crate::free_body::*;
+#[cfg(native)]
+pub use crate::geom_classes::*;
+#[cfg(native)]
+pub use crate::gltf_export::*;
+#[cfg(native)]
+pub use crate::ik::*;
+#[cfg(native)]
+pub use crate::imu::*;
+#[cfg(all(native, feature = "ipc"))]
+pub use crate::ipc::*;
+#[cfg(all(native, feature = "egui"))]
+pub use crate::joint_sliders::*;
+#[cfg(all(native, feature = "keybindings"))]
+pub use crate::keybindings::*;
+#[cfg(native)]
+pub use crate::lidar::*;
+#[cfg(native)]
+pub use crate::mjcf::*;
+#[cfg(native)]
+pub use crate::mjcf_builder::*;
+#[cfg(native)]
+pub use crate::model_elements::*;
+#[cfg(native)]
+pub use crate::model_info::*;
+#[cfg(native)]
+pub use crate::mujoco_plugins::*;
+#[cfg(native)]
+pub use crate::noise::*;
+#[cfg(native)]
+pub use crate::observation::*;
+#[cfg(native)]
+pub use crate::options::*;
+#[cfg(native)]
+pub use crate::parameters::*;
+#[cfg(native)]
+pub use crate::pd_controller::*;
+#[cfg(native)]
+pub use crate::physics_diagnostics::*;
+#[cfg(all(native, feature = "picking"))]
+pub use crate::picking::*;
+#[cfg(all(native, feature = "rapier_interop"))]
+pub use crate::rapier_interop::*;
+#[cfg(native)]
+pub use crate::raycast::*;
+#[cfg(native)]
+pub use crate::render_mode::*;
+pub use crate::replay::*;
+#[cfg(all(native, feature = "rerun"))]
+pub use crate::rerun_logger::*;
+#[cfg(native)]
+pub use crate::reset::*;
+#[cfg(native)]
+pub use crate::rigid_body::*;
+#[cfg(all(native, feature = "ros2"))]
+pub use crate::ros2::*;
+#[cfg(native)]
+pub use crate::scene::*;
+#[cfg(native)]
+pub use crate::scratch::*;
+#[cfg(native)]
+pub use crate::state_buffer::*;
+#[cfg(native)]
+pub use crate::state_f32::*;
+#[cfg(native)]
+pub use crate::step_control::*;
+#[cfg(native)]
+pub use crate::task::*;
+#[cfg(all(native, feature = "telemetry"))]
+pub use crate::telemetry::*;
+#[cfg(native)]
+pub use crate::teleop::*;
+#[cfg(native)]
+pub use crate::terrain::*;
+#[cfg(native)]
+pub use crate::time::*;
+#[cfg(native)]
+pub use crate::unsupported::*;
+#[cfg(native)]
+pub use crate::visibility::*;
+#[cfg(native)]
+pub use crate::visual::*;
+#[cfg(native)]
+pub use crate::warnings::*;
+
+// MuJoCo doesn't build for the web, web builds only play back recordings
+#[cfg(all(target_arch = "wasm32", not(feature = "wasm")))]
+compile_error!("wasm32 builds need the `wasm` feature, which drops the MuJoCo dependency");
+#[cfg(all(
+    feature = "wasm",
+    any(feature = "mujoco-rust", feature = "raw-backend")
+))]
+compile_error!("the `wasm` feature can't be combined with a MuJoCo backend feature");
 
+#[cfg(native)]
 #[derive(Component)]
 pub struct MuJoCoBody {
     pub id: i32,
     pub root_body: bool,
+    /// Parent body id, cached at spawn time for transform sync
+    pub(crate) parent_id: usize,
 }
 
+/// Marks body and geom entities of fixtures that never move: the world body and bodies welded
+/// to it (no joints up to the root, not mocap bodies), and their geoms. They are placed when
+/// spawned and left out of transform sync, so their transforms are never written again
+#[cfg(native)]
+#[derive(Component)]
+pub struct MuJoCoStatic;
+
+#[cfg(native)]
 #[derive(Component)]
 pub struct MuJoCoMesh {
     pub id: i32,
 }
 
-#[derive(Resource, Default)]
+/// Entity of a rendered MuJoCo geom, a child of its `MuJoCoBody` entity
+#[cfg(native)]
+#[derive(Component)]
+pub struct MuJoCoGeom {
+    pub id: i32,
+}
+
+/// Marks an entity under which the MuJoCo body hierarchy is spawned instead of
+/// a `MuJoCo::world` entity. Spawn it during `Startup` at the latest.
+/// The `MuJoCo::world` entity carries it as well, so the scene frame is always
+/// the transform of the `MuJoCoSceneRoot` entity
+#[cfg(native)]
+#[derive(Component, Default)]
+pub struct MuJoCoSceneRoot;
+
+#[cfg(native)]
+#[derive(Resource)]
 pub struct MuJoCoPluginSettings {
     pub model_xml_path: String,
+    /// Directory relative model paths are resolved against before falling back to
+    /// the current directory, the executable directory and the crate directory
+    pub assets_path: Option<std::path::PathBuf>,
     pub pause_simulation: bool,
-    pub target_fps: f64,
+    /// Advance the paused simulation by one step in the next `simulate_physics`, which clears it
+    pub step_once: bool,
+    /// Simulated seconds per real second: 0.1 is slow motion, 4.0 is fast-forward
+    pub time_scale: f64,
+    pub step_mode: StepMode,
+    /// Per-frame limits of `StepMode::Realtime` stepping
+    pub step_budget: StepBudget,
+    /// Which axis is up in the bevy scene, MuJoCo's Z axis is turned onto it
+    pub up_axis: UpAxis,
+    /// Transform of the root `MuJoCo::world` entity: offset, rotation and uniform scale of the scene.
+    /// Not used when the scene is spawned under a `MuJoCoSceneRoot`
+    pub world_transform: Transform,
+    /// Display the convex hulls used to collide mesh geoms
+    pub convex_hulls: ConvexHullDisplay,
+    pub spawn_mode: SpawnMode,
+    /// Scene composed of several models, loaded instead of `model_xml_path` when set
+    pub scene: Option<SceneBuilder>,
+    /// Side length of planes with size 0, which MuJoCo treats as infinite
+    pub plane_size: f32,
+    pub shadows: ShadowSettings,
+    /// Directories of MuJoCo engine plugin libraries to register before the model compiles,
+    /// in addition to `default_plugin_directories` (`mujoco3` feature)
+    pub plugin_directories: Vec<std::path::PathBuf>,
+    /// Worker threads MuJoCo steps with, constraint islands are solved in parallel
+    /// (`mujoco3` feature, MuJoCo 3.1+). 1 steps on the simulation system's thread
+    pub physics_threads: usize,
+    /// Update geom entities from `geom_xpos` / `geom_xmat` every frame instead of keeping them
+    /// where they were spawned relative to their bodies, for geoms that move on their own
+    /// (engine plugins, flex) or when geom and body poses must match exactly
+    pub track_geoms: bool,
+}
+
+/// Which geoms cast and receive shadows, indexed by MuJoCo geom group (0-5)
+#[cfg(native)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShadowSettings {
+    pub cast: [bool; 6],
+    pub receive: [bool; 6],
+    /// Planes usually are the ground and only receive shadows
+    pub planes_cast: bool,
+}
+
+#[cfg(native)]
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        ShadowSettings {
+            cast: [true; 6],
+            receive: [true; 6],
+            planes_cast: false,
+        }
+    }
+}
+
+#[cfg(native)]
+impl ShadowSettings {
+    /// Insert `NotShadowCaster` / `NotShadowReceiver` for a geom of the given group
+    pub(crate) fn apply(&self, entity: &mut EntityCommands, group: i32, plane: bool) {
+        let group = usize::try_from(group).ok();
+        let cast = group.and_then(|group| self.cast.get(group)).copied();
+        let receive = group.and_then(|group| self.receive.get(group)).copied();
+        if !cast.unwrap_or(true) || (plane && !self.planes_cast) {
+            entity.insert(NotShadowCaster);
+        }
+        if !receive.unwrap_or(true) {
+            entity.insert(NotShadowReceiver);
+        }
+    }
 }
 
+/// What the plugin spawns for the model
+#[cfg(native)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpawnMode {
+    /// Body and geom entities with meshes and materials
+    #[default]
+    Full,
+    /// Body and geom entities with transforms only, for custom rendering
+    TransformOnly,
+}
+
+#[cfg(native)]
+impl Default for MuJoCoPluginSettings {
+    fn default() -> Self {
+        Self {
+            model_xml_path: String::new(),
+            assets_path: None,
+            pause_simulation: false,
+            step_once: false,
+            time_scale: 1.0,
+            step_mode: StepMode::default(),
+            step_budget: StepBudget::default(),
+            up_axis: UpAxis::default(),
+            world_transform: Transform::IDENTITY,
+            convex_hulls: ConvexHullDisplay::default(),
+            spawn_mode: SpawnMode::default(),
+            scene: None,
+            plane_size: 100.0,
+            shadows: ShadowSettings::default(),
+            plugin_directories: vec![],
+            physics_threads: 1,
+            track_geoms: false,
+        }
+    }
+}
+
+/// Up axis of the bevy scene. MuJoCo models are Z-up; the change of basis is applied to root
+/// bodies and to every pose converted between MuJoCo and bevy. A simulation keeps the axis it
+/// was set up with, see `MuJoCoSimulation::up_axis`
+#[cfg(native)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpAxis {
+    /// bevy's convention: MuJoCo (x, y, z) is bevy (x, z, -y)
+    #[default]
+    Y,
+    /// MuJoCo coordinates unchanged, for Z-up bevy worlds
+    Z,
+}
+
+#[cfg(native)]
+impl UpAxis {
+    /// Rotation from the MuJoCo world frame to the bevy scene frame
+    pub fn basis(self) -> Quat {
+        match self {
+            UpAxis::Y => Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2),
+            UpAxis::Z => Quat::IDENTITY,
+        }
+    }
+}
+
+/// How the plugin advances the simulation
+#[cfg(native)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepMode {
+    /// Step every frame to keep up with real time scaled by `time_scale`
+    #[default]
+    Realtime,
+    /// Never step automatically, only on `MuJoCoStepRequest` events
+    Manual,
+}
+
+/// Ask the plugin to advance the simulation by `n_steps` calls to `mj_step` (`StepMode::Manual` only)
+#[cfg(native)]
+#[derive(Event, Debug, Clone, Copy)]
+pub struct MuJoCoStepRequest {
+    pub n_steps: usize,
+}
+
+/// Sent once a `MuJoCoStepRequest` has been completed
+#[cfg(native)]
+#[derive(Event, Debug, Clone, Copy)]
+pub struct MuJoCoStepped {
+    /// Simulation time after the requested steps
+    pub time: f64,
+}
+
+#[cfg(native)]
 #[derive(Resource, Default)]
 pub struct MuJoCoResources {
     pub geoms: Vec<Geom>,
@@ -39,163 +524,825 @@ pub struct MuJoCoResources {
     pub control: MuJoCoControl,
 }
 
-#[derive(Default, Debug, Serialize, Clone)]
+#[cfg(native)]
+#[derive(Default, Debug, Serialize, Deserialize, Clone)]
 pub struct MuJoCoState {
     pub sensor_data: Vec<f64>,
     pub qpos: Vec<f64>,
     pub qvel: Vec<f64>,
     pub cfrc_ext: Vec<[f64; 6]>,
+    /// Activations of actuators with internal dynamics (muscles, filters), `na` values
+    pub act: Vec<f64>,
+    /// Actuator forces in actuation space, `nu` values
+    pub actuator_force: Vec<f64>,
+    /// Generalized forces of all actuators in joint space, `nv` values: the torque or force
+    /// each joint receives from its motors, see `MuJoCoModelInfo::dof_range`
+    pub qfrc_actuator: Vec<f64>,
+    pub energy: MuJoCoEnergy,
 }
 
+#[cfg(native)]
 #[derive(Default, Debug)]
 pub struct MuJoCoControl {
     pub data: Vec<f64>,
     pub number_of_controls: usize,
+    /// Optional `(simulation time, ctrl)` points, sorted by time. When not empty the control
+    /// applied before every `mj_step` is interpolated linearly from these points instead of
+    /// holding `data` for the whole frame. Points in the past are dropped as time advances
+    pub trajectory: Vec<(f64, Vec<f64>)>,
 }
 
+#[cfg(native)]
+impl MuJoCoControl {
+    /// Control interpolated from `trajectory` at a given simulation time, held constant
+    /// before the first and after the last point
+    pub fn interpolate(&self, time: f64) -> Option<Vec<f64>> {
+        let first = self.trajectory.first()?;
+        let last = self.trajectory.last()?;
+        if time <= first.0 {
+            return Some(first.1.clone());
+        }
+        if time >= last.0 {
+            return Some(last.1.clone());
+        }
+
+        let next = self.trajectory.iter().position(|(t, _)| *t > time)?;
+        let ((t0, c0), (t1, c1)) = (&self.trajectory[next - 1], &self.trajectory[next]);
+        let alpha = (time - t0) / (t1 - t0);
+        Some(
+            c0.iter()
+                .zip(c1.iter())
+                .map(|(a, b)| a + (b - a) * alpha)
+                .collect(),
+        )
+    }
+
+    /// Drop trajectory points that are no longer needed for interpolation at `time`
+    fn prune_trajectory(&mut self, time: f64) {
+        let past = self.trajectory.iter().filter(|(t, _)| *t <= time).count();
+        if past > 1 {
+            self.trajectory.drain(..past - 1);
+        }
+    }
+}
+
+#[cfg(native)]
 pub struct MuJoCoPlugin;
 
+#[cfg(native)]
 impl Plugin for MuJoCoPlugin {
     fn build(&self, app: &mut App) {
-        let mj_plugin_settings = app.world().get_resource::<MuJoCoPluginSettings>().unwrap();
+        app.init_resource::<MuJoCoPluginSettings>();
+        app.init_resource::<MuJoCoSensorNoise>();
+        app.init_resource::<MuJoCoAssetCache>();
+        app.init_resource::<MuJoCoDivergencePolicy>();
+        app.init_resource::<ResetOptions>();
+        app.init_resource::<MuJoCoControlLoop>();
+        app.init_resource::<MuJoCoObservation>();
+        app.init_resource::<MuJoCoVisibility>();
+        app.add_event::<MuJoCoLoadError>();
+        app.add_event::<MuJoCoCommand>();
+        app.add_event::<MuJoCoUnsupportedFeatures>();
+        app.add_event::<MuJoCoStepRequest>();
+        app.add_event::<MuJoCoStepped>();
+        app.add_event::<SimulationLagging>();
+        app.add_event::<ControlTick>();
+        app.add_event::<MuJoCoLidarScan>();
+        app.add_event::<MuJoCoDivergence>();
+        app.add_event::<MuJoCoWarning>();
+        app.add_event::<MuJoCoControlEvent>();
+        app.add_event::<MuJoCoControlBatch>();
+        app.add_systems(Startup, load_mujoco);
+        app.add_systems(
+            PreUpdate,
+            setup_mujoco.run_if(resource_added::<MuJoCoSimulation>),
+        );
+        app.configure_sets(
+            Update,
+            (
+                MuJoCoSystemSet::Control,
+                MuJoCoSystemSet::Step,
+                MuJoCoSystemSet::Sync,
+            )
+                .chain(),
+        );
+        app.add_systems(
+            Update,
+            (
+                (
+                    apply_mujoco_commands,
+                    park_free_bodies,
+                    apply_mujoco_options,
+                    apply_physical_parameters,
+                    apply_collision_filter,
+                    apply_equality_constraints,
+                    apply_joint_control,
+                    apply_pd_controller,
+                    apply_actuator_groups,
+                    apply_control_events,
+                )
+                    .chain()
+                    .in_set(MuJoCoSystemSet::Control),
+                simulate_physics.in_set(MuJoCoSystemSet::Step),
+                sync_transforms.in_set(MuJoCoSystemSet::Sync),
+                sync_geom_transforms
+                    .after(sync_transforms)
+                    .in_set(MuJoCoSystemSet::Sync)
+                    .run_if(|settings: Res<MuJoCoPluginSettings>| settings.track_geoms),
+            )
+                .run_if(resource_exists::<MuJoCoResources>),
+        );
+        app.add_systems(
+            Update,
+            (
+                attach_visuals,
+                apply_visibility.after(attach_visuals),
+                attach_geom_classes.run_if(resource_exists::<MuJoCoGeomClasses>),
+                spawn_convex_hulls,
+                scan_lidars.after(simulate_physics),
+                report_warnings.after(simulate_physics),
+                observe
+                    .after(simulate_physics)
+                    .run_if(resource_exists::<ObservationSpec>),
+                record_replay
+                    .after(MuJoCoSystemSet::Sync)
+                    .run_if(resource_exists::<MuJoCoReplayRecorder>),
+                record_animation
+                    .after(MuJoCoSystemSet::Sync)
+                    .run_if(resource_exists::<MuJoCoAnimationRecorder>),
+                capture_frames
+                    .after(MuJoCoSystemSet::Sync)
+                    .run_if(resource_exists::<MuJoCoCapture>),
+            )
+                .run_if(resource_exists::<MuJoCoResources>),
+        );
+        #[cfg(mujoco3)]
+        app.add_plugins(FlexPlugin);
+    }
+}
+
+/// Stages of a simulation frame in `Update`, in order. Order your systems against them,
+/// e.g. write controls `.before(MuJoCoSystemSet::Control)` and read entity transforms
+/// `.after(MuJoCoSystemSet::Sync)`
+#[cfg(native)]
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MuJoCoSystemSet {
+    /// Commands, options, parameters and controls are written into the simulation
+    Control,
+    /// `simulate_physics` steps the simulation and publishes the new state
+    Step,
+    /// Body transforms follow the new state
+    Sync,
+}
+
+/// Sent when the model from `MuJoCoPluginSettings::model_xml_path` can't be loaded.
+/// The app keeps running without a simulation
+#[cfg(native)]
+#[derive(Event, Debug, Clone)]
+pub struct MuJoCoLoadError {
+    pub path: String,
+    pub message: String,
+}
+
+#[cfg(native)]
+impl std::fmt::Display for MuJoCoLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "failed to load MuJoCo model {}: {}",
+            self.path, self.message
+        )
+    }
+}
+
+#[cfg(native)]
+impl std::error::Error for MuJoCoLoadError {}
 
-        let model =
-            mujoco_rust::Model::from_xml(mj_plugin_settings.model_xml_path.as_str()).unwrap();
+/// Number of `SimulationHandle`s alive
+#[cfg(native)]
+static LIVE_SIMULATIONS: AtomicUsize = AtomicUsize::new(0);
 
-        let simulation = MuJoCoSimulation::new(model);
+/// Owns a compiled model and its data, loaded with the `PluginBackend`, and frees both when
+/// dropped
+#[cfg(native)]
+pub struct SimulationHandle {
+    simulation: ManuallyDrop<PluginBackend>,
+    /// Dropped after the data it is bound to
+    pub(crate) thread_pool: Option<physics_threads::ThreadPool>,
+    /// Up axis of the scene the simulation is shown in
+    pub(crate) up_axis: UpAxis,
+    /// Set when a body is teleported, so a paused simulation publishes the new pose even if
+    /// qpos didn't change, as for mocap bodies
+    pub(crate) teleported: Cell<bool>,
+}
+
+#[cfg(native)]
+impl std::ops::Deref for SimulationHandle {
+    type Target = PluginBackend;
 
-        app.insert_resource(simulation);
-        app.add_systems(Update, simulate_physics);
-        app.add_systems(Startup, setup_mujoco);
+    fn deref(&self) -> &Self::Target {
+        &self.simulation
     }
 }
 
+#[cfg(native)]
+impl Drop for SimulationHandle {
+    fn drop(&mut self) {
+        step_control::remove_step_controller(self.simulation.data_ptr());
+        free_plugin_backend(&mut self.simulation);
+        LIVE_SIMULATIONS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Shared handle to the simulation. The model and data are freed when the last clone of the
+/// inner `Arc` is dropped, e.g. after `MuJoCoCommands::unload`
+#[cfg(native)]
 #[derive(Deref, DerefMut, Resource)]
-pub struct MuJoCoSimulation(Arc<Mutex<mujoco_rust::Simulation>>);
+pub struct MuJoCoSimulation(Arc<Mutex<SimulationHandle>>);
 
+#[cfg(native)]
 impl MuJoCoSimulation {
-    pub fn new(model: mujoco_rust::Model) -> Self {
-        let simulation = mujoco_rust::Simulation::new(model);
-        MuJoCoSimulation(Arc::new(Mutex::new(simulation)))
+    pub fn new(simulation: PluginBackend) -> Self {
+        LIVE_SIMULATIONS.fetch_add(1, Ordering::SeqCst);
+        MuJoCoSimulation(Arc::new(Mutex::new(SimulationHandle {
+            simulation: ManuallyDrop::new(simulation),
+            thread_pool: None,
+            up_axis: UpAxis::default(),
+            teleported: Cell::new(false),
+        })))
+    }
+
+    /// Up axis of the scene poses of this simulation are converted to and from,
+    /// `MuJoCoPluginSettings::up_axis` when it was set up
+    pub fn up_axis(&self) -> UpAxis {
+        self.lock().unwrap().up_axis
+    }
+
+    /// Number of simulations that haven't been freed yet, for leak checks
+    pub fn live_count() -> usize {
+        LIVE_SIMULATIONS.load(Ordering::SeqCst)
+    }
+
+    /// Load and compile an MJCF/URDF model from disk. Plugin libraries in
+    /// `default_plugin_directories` are registered first
+    pub fn load(path: &str) -> Result<Self, MuJoCoLoadError> {
+        let error = |message: String| MuJoCoLoadError {
+            path: path.to_string(),
+            message,
+        };
+
+        if !std::path::Path::new(path).is_file() {
+            return Err(error("file not found".to_string()));
+        }
+        wrappers::check_library_version().map_err(error)?;
+
+        load_plugin_libraries(&default_plugin_directories());
+        let simulation = load_plugin_backend(path).map_err(|message| {
+            if message.contains("plugin") {
+                error(format!(
+                    "{message} (register plugin libraries with \
+                     MuJoCoPluginSettings::plugin_directories or MUJOCO_PLUGIN_DIR)"
+                ))
+            } else {
+                error(message)
+            }
+        })?;
+        Ok(MuJoCoSimulation::new(simulation))
     }
 }
 
-pub fn simulate_physics(
-    mujoco: ResMut<MuJoCoSimulation>,
-    settings: ResMut<MuJoCoPluginSettings>,
-    mut bodies_query: Query<(Entity, &mut Transform, &MuJoCoBody)>,
-    mut mujoco_resources: ResMut<MuJoCoResources>,
+/// Load the model configured in `MuJoCoPluginSettings`, reporting failures as `MuJoCoLoadError`
+/// and compiler messages as `MuJoCoDiagnostics`. Nothing is loaded when a `MuJoCoSimulation`
+/// was inserted by the app, e.g. one built with `MjcfBuilder`
+#[cfg(native)]
+fn load_mujoco(
+    mut commands: Commands,
+    settings: Res<MuJoCoPluginSettings>,
+    simulation: Option<Res<MuJoCoSimulation>>,
+    mut load_errors: EventWriter<MuJoCoLoadError>,
 ) {
-    if settings.pause_simulation {
+    if simulation.is_some() {
+        commands.init_resource::<MuJoCoDiagnostics>();
         return;
     }
 
-    let mujoco = mujoco.lock().unwrap();
+    let path = match &settings.scene {
+        Some(scene) => {
+            let path = scene_path();
+            // placements are converted to MuJoCo coordinates while composing
+            let scene = scene.clone().up_axis(settings.up_axis);
+            if let Err(error) = scene.write(settings.assets_path.as_deref(), &path) {
+                let diagnostic = MuJoCoDiagnostic::parse(
+                    DiagnosticSeverity::Error,
+                    &error.to_string(),
+                    &path.to_string_lossy(),
+                );
+                diagnostic.log();
+                load_errors.send(MuJoCoLoadError {
+                    path: path.to_string_lossy().into_owned(),
+                    message: error.to_string(),
+                });
+                commands.insert_resource(MuJoCoDiagnostics {
+                    diagnostics: vec![diagnostic],
+                });
+                return;
+            }
+            path
+        }
+        None => resolve_asset_path(settings.assets_path.as_deref(), &settings.model_xml_path),
+    };
+    let path = path.to_string_lossy();
+    let path = path.as_ref();
+    load_plugin_libraries(&settings.plugin_directories);
+    let (result, warnings) = wrappers::capture_warnings(|| MuJoCoSimulation::load(path));
 
-    // Set control data
-    mujoco.control(&mujoco_resources.control.data);
+    let mut diagnostics: Vec<MuJoCoDiagnostic> = warnings
+        .iter()
+        .map(|warning| MuJoCoDiagnostic::parse(DiagnosticSeverity::Warning, warning, path))
+        .collect();
 
-    // Target 60 fps in simulation
-    let sim_start = mujoco.state.time();
-    while mujoco.state.time() - sim_start < 1.0 / settings.target_fps {
-        mujoco.step();
+    match result {
+        Ok(simulation) => {
+            // default classes and other authoring data are only kept in the MJCF document
+            if path.ends_with(".xml") {
+                match MjcfElement::load(std::path::Path::new(path)) {
+                    Ok(root) => {
+                        let ngeom = wrappers::model(&simulation.lock().unwrap()).ngeom as usize;
+                        if let Some(geom_classes) = MuJoCoGeomClasses::from_mjcf(&root, ngeom) {
+                            commands.insert_resource(geom_classes);
+                        }
+                        commands.insert_resource(MuJoCoMjcf {
+                            path: path.into(),
+                            root,
+                        });
+                    }
+                    Err(error) => warn!("MJCF document unavailable: {error}"),
+                }
+            }
+            commands.insert_resource(simulation);
+        }
+        Err(load_error) => {
+            diagnostics.push(MuJoCoDiagnostic::parse(
+                DiagnosticSeverity::Error,
+                &load_error.message,
+                path,
+            ));
+            load_errors.send(load_error);
+        }
     }
 
-    let cfrc_ext = mujoco.cfrc_ext();
-    let cfrc_ext: Vec<[f64; 6]> = cfrc_ext
-        .iter()
-        .map(|e| [e[0], e[1], e[2], e[3], e[4], e[5]])
-        .collect();
+    for diagnostic in &diagnostics {
+        diagnostic.log();
+    }
+    commands.insert_resource(MuJoCoDiagnostics { diagnostics });
+}
 
-    // Read Sensor data
-    mujoco_resources.state = MuJoCoState {
-        sensor_data: mujoco.sensordata(),
-        qpos: mujoco.qpos(),
-        qvel: mujoco.qvel(),
-        cfrc_ext,
+/// Events `simulate_physics` sends about the frame's stepping
+#[cfg(native)]
+#[derive(SystemParam)]
+pub struct SimulationEvents<'w> {
+    stepped: EventWriter<'w, MuJoCoStepped>,
+    lagging: EventWriter<'w, SimulationLagging>,
+    divergence: EventWriter<'w, MuJoCoDivergence>,
+    control_ticks: EventWriter<'w, ControlTick>,
+}
+
+#[cfg(native)]
+#[allow(clippy::too_many_arguments)]
+pub fn simulate_physics(
+    mujoco: ResMut<MuJoCoSimulation>,
+    mut settings: ResMut<MuJoCoPluginSettings>,
+    time: Res<Time>,
+    mut time_budget: Local<f64>,
+    state_buffer: Res<MuJoCoStateBuffer>,
+    mut scratch: ResMut<MuJoCoStateScratch>,
+    state_f32: Option<ResMut<MuJoCoStateF32>>,
+    mut mujoco_resources: ResMut<MuJoCoResources>,
+    mut step_requests: EventReader<MuJoCoStepRequest>,
+    sensor_noise: Res<MuJoCoSensorNoise>,
+    mut sim_time: ResMut<MuJoCoTime>,
+    divergence_policy: Res<MuJoCoDivergencePolicy>,
+    reset_options: Res<ResetOptions>,
+    mut control_limits: ResMut<MuJoCoControlLimits>,
+    mut control_loop: ResMut<MuJoCoControlLoop>,
+    (feedback, mut events): (JointFeedback, SimulationEvents),
+) {
+    let mujoco = mujoco.lock().unwrap();
+
+    let control = &mujoco_resources.control;
+    let timestep = wrappers::model(&mujoco).opt.timestep;
+    let steps = std::cell::Cell::new(0);
+    let divergence = std::cell::Cell::new(None);
+    let warnings = bad_state_warnings(&mujoco);
+    let mut control_ticks = vec![];
+    let mut step = || {
+        if let Some(tick) = control_loop.tick() {
+            control_loop.run(&mujoco, tick);
+            control_ticks.push(ControlTick {
+                tick,
+                time: mujoco.time(),
+            });
+        }
+        // Set control data, clamped and rate limited. A control loop callback holds its own,
+        // joint commands and PD targets are evaluated at this step's state
+        let interpolated = control.interpolate(mujoco.time());
+        let feedback_control = match control_loop.control() {
+            Some(_) => None,
+            None => feedback.controls(&mujoco, interpolated.as_deref().unwrap_or(&control.data)),
+        };
+        let target = control_loop
+            .control()
+            .or(feedback_control.as_deref())
+            .unwrap_or(interpolated.as_deref().unwrap_or(&control.data));
+        control_limits.apply(&mujoco, target, timestep);
+        let step_time = mujoco.time();
+        mujoco.step();
+        steps.set(steps.get() + 1);
+        if let Some(reason) = check_divergence(&mujoco, &divergence_policy, warnings) {
+            divergence.set(Some((step_time, reason)));
+        }
     };
+    let time_before = mujoco.time();
+    let stepping_started = std::time::Instant::now();
+
+    // cleared without change detection, systems watching the settings only see user changes
+    if settings.step_once {
+        settings.bypass_change_detection().step_once = false;
+        if settings.pause_simulation {
+            step();
+        }
+    }
 
-    let positions = mujoco.xpos();
-    let rotations = mujoco.xquat();
+    // While paused the state is still synced, so poses set with `MuJoCoSimulation::set_qpos` show up
+    match settings.step_mode {
+        StepMode::Realtime if settings.pause_simulation => {}
+        StepMode::Realtime => {
+            // Advance simulated time by the scaled frame time, carrying over
+            // the overshoot of the last step to the next frame
+            *time_budget += time.delta_secs_f64() * settings.time_scale;
+            let max_steps = settings.step_budget.realtime_steps(
+                *time_budget,
+                timestep,
+                sim_time.average_step_time,
+            );
+            while *time_budget > 0.0 && divergence.get().is_none() && steps.get() < max_steps {
+                let step_start = mujoco.time();
+                step();
+                *time_budget -= mujoco.time() - step_start;
+            }
+            if divergence.get().is_none() {
+                if let Some(lag) = settings.step_budget.carry_over(&mut *time_budget) {
+                    events.lagging.send(SimulationLagging {
+                        lag,
+                        steps: steps.get(),
+                    });
+                }
+            }
+        }
+        StepMode::Manual => {
+            for request in step_requests.read() {
+                for _ in 0..request.n_steps {
+                    if divergence.get().is_some() {
+                        break;
+                    }
+                    step();
+                }
+                events.stepped.send(MuJoCoStepped {
+                    time: mujoco.time(),
+                });
+            }
+        }
+    }
 
-    for (_, mut transform, body) in bodies_query.iter_mut() {
-        let body_id = body.id as usize;
-        let mj_body = mujoco_resources.bodies[body_id].clone();
-        let parent_body_id = mj_body.parent_id as usize;
+    events.control_ticks.send_batch(control_ticks);
 
-        let geom = mj_body.render_geom(&mujoco_resources.geoms);
-        if geom.is_none() {
-            continue;
+    if let Some((time, reason)) = divergence.get() {
+        let reset = match divergence_policy.reset {
+            DivergenceReset::None => false,
+            DivergenceReset::Initial => {
+                reset_simulation(&mujoco, None, &reset_options);
+                true
+            }
+            DivergenceReset::Keyframe(key) => {
+                reset_simulation(&mujoco, Some(key), &reset_options);
+                true
+            }
+        };
+        if reset {
+            *time_budget = 0.0;
         }
-        let geom = geom.unwrap();
+        warn!("MuJoCo simulation diverged at t={time}: {reason:?}");
+        events.divergence.send(MuJoCoDivergence {
+            time,
+            reason,
+            reset,
+        });
+    }
 
-        let (body_pos, parent_body_pos) = (positions[body_id], positions[parent_body_id]);
-        let (body_rot, parent_prot) = (rotations[body_id], rotations[parent_body_id]);
+    sim_time.record(
+        mujoco.time(),
+        mujoco.time() - time_before,
+        steps.get(),
+        stepping_started.elapsed(),
+    );
 
-        let (body_translation, parent_body_translation) = (
-            vec3_mujoco_2_bevy(body_pos),
-            vec3_mujoco_2_bevy(parent_body_pos),
-        );
+    mujoco_resources.control.prune_trajectory(mujoco.time());
+
+    // Read state into preallocated buffers, `clone_from` reuses the state's allocations
+    scratch.extract(&mujoco);
+    // Nothing moved while paused unless qpos was set or a body teleported, don't publish so
+    // transforms aren't rewritten
+    let teleported = mujoco.teleported.take();
+    if settings.pause_simulation
+        && !teleported
+        && !mujoco_resources.state.qpos.is_empty()
+        && scratch.qpos == mujoco_resources.state.qpos
+    {
+        return;
+    }
+    sensor_noise.apply(&mujoco, &mut scratch.sensor_data);
+    let state = &mut mujoco_resources.state;
+    state.sensor_data.clone_from(&scratch.sensor_data);
+    state.qpos.clone_from(&scratch.qpos);
+    state.qvel.clone_from(&scratch.qvel);
+    state.cfrc_ext.clone_from(&scratch.cfrc_ext);
+    state.act.clone_from(&scratch.act);
+    state.actuator_force.clone_from(&scratch.actuator_force);
+    state.qfrc_actuator.clone_from(&scratch.qfrc_actuator);
+    state.energy = energy::energy(&mujoco);
 
-        let (body_rot, parent_body_rot) = (
-            quat_mujoco_2_bevy(body_rot),
-            quat_mujoco_2_bevy(parent_prot),
+    let model = wrappers::model(&mujoco);
+    let data = wrappers::data(&mujoco);
+    let (nbody, ngeom) = (model.nbody as usize, model.ngeom as usize);
+    let track_geoms = settings.track_geoms;
+    let state = &mujoco_resources.state;
+    state_buffer.publish(|snapshot| {
+        snapshot.time = data.time;
+        snapshot.qpos.clone_from(&state.qpos);
+        snapshot.qvel.clone_from(&state.qvel);
+        snapshot.sensor_data.clone_from(&state.sensor_data);
+        snapshot.cfrc_ext.clone_from(&state.cfrc_ext);
+        snapshot.act.clone_from(&state.act);
+        snapshot.actuator_force.clone_from(&state.actuator_force);
+        snapshot.qfrc_actuator.clone_from(&state.qfrc_actuator);
+        snapshot.xpos.clear();
+        snapshot.xpos.extend(
+            unsafe { wrappers::slice(data.xpos, 3 * nbody) }
+                .chunks_exact(3)
+                .map(|p| [p[0], p[1], p[2]]),
         );
+        snapshot.xmat.clear();
+        snapshot.xmat.extend(
+            unsafe { wrappers::slice(data.xmat, 9 * nbody) }
+                .chunks_exact(9)
+                .map(|m| std::array::from_fn(|i| m[i])),
+        );
+        snapshot.xquat.clear();
+        snapshot.xquat.extend(
+            unsafe { wrappers::slice(data.xquat, 4 * nbody) }
+                .chunks_exact(4)
+                .map(|q| [q[0], q[1], q[2], q[3]]),
+        );
+        let contacts = unsafe { wrappers::slice(data.contact, data.ncon as usize) };
+        snapshot.contact_geoms.clear();
+        snapshot
+            .contact_geoms
+            .extend(contacts.iter().map(wrappers::contact_geoms));
+        snapshot.contact_pos.clear();
+        snapshot
+            .contact_pos
+            .extend(contacts.iter().map(|contact| contact.pos));
+        snapshot.geom_xpos.clear();
+        snapshot.geom_xmat.clear();
+        if track_geoms {
+            snapshot.geom_xpos.extend(
+                unsafe { wrappers::slice(data.geom_xpos, 3 * ngeom) }
+                    .chunks_exact(3)
+                    .map(|p| [p[0], p[1], p[2]]),
+            );
+            snapshot.geom_xmat.extend(
+                unsafe { wrappers::slice(data.geom_xmat, 9 * ngeom) }
+                    .chunks_exact(9)
+                    .map(|m| std::array::from_fn(|i| m[i])),
+            );
+        }
+    });
 
-        // Converting from MuJoCo to Bevy coordinate system
-        let parent_rotation_inverse = parent_body_rot.inverse();
-        transform.translation =
-            parent_rotation_inverse.mul_vec3(body_translation - parent_body_translation);
+    if let Some(mut state_f32) = state_f32 {
+        let xpos = unsafe { wrappers::slice(data.xpos, 3 * nbody) };
+        let xmat = unsafe { wrappers::slice(data.xmat, 9 * nbody) };
+        state_f32.fill(mujoco.up_axis, xpos, xmat);
+    }
+}
 
-        transform.rotation = parent_rotation_inverse * body_rot;
+/// Pose changes below this (meters, quaternion components) don't update transforms
+#[cfg(native)]
+const TRANSFORM_EPSILON: f32 = 1e-6;
 
-        if body.root_body {
-            let correction = Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2);
-            transform.translation = correction.mul_vec3(transform.translation);
-            transform.rotation = correction * transform.rotation;
-        }
+/// Update body transforms from the latest `MuJoCoStateBuffer` snapshot, or from
+/// `MuJoCoStateF32` when the app enabled it
+#[cfg(native)]
+pub fn sync_transforms(
+    state_buffer: Res<MuJoCoStateBuffer>,
+    model_info: Res<MuJoCoModelInfo>,
+    state_f32: Option<Res<MuJoCoStateF32>>,
+    mut synced_version: Local<u64>,
+    mut bodies_query: Query<(&mut Transform, Ref<MuJoCoBody>), Without<MuJoCoStatic>>,
+) {
+    let snapshot = state_buffer.front();
+    if snapshot.xpos.is_empty() {
+        return;
+    }
+    let spawned = bodies_query.iter().any(|(_, body)| body.is_added());
+    if snapshot.version == *synced_version && !spawned {
+        return;
+    }
+    *synced_version = snapshot.version;
 
-        if geom.geom_type != GeomType::MESH {
-            transform.translation -= geom_correction(&geom);
-        }
+    // Poses relative to the parent don't depend on the common frame both poses are in,
+    // so the f32 mirror (bevy scene frame) and the snapshot (MuJoCo frame) give the same result
+    let pose = |id: usize| match &state_f32 {
+        Some(state_f32) => (state_f32.positions[id], state_f32.rotations[id]),
+        None => (
+            array_mujoco_2_bevy(snapshot.xpos[id]),
+            rotation_from_xmat(&snapshot.xmat[id]),
+        ),
+    };
+
+    // bodies only read their own and their parent's pose, so they can be synced in parallel
+    bodies_query
+        .par_iter_mut()
+        .for_each(|(mut transform, body)| {
+            let (body_translation, body_rot) = pose(body.id as usize);
+            let (parent_body_translation, parent_body_rot) = pose(body.parent_id);
+
+            // Converting from MuJoCo to Bevy coordinate system
+            let parent_rotation_inverse = parent_body_rot.inverse();
+            let mut translation =
+                parent_rotation_inverse.mul_vec3(body_translation - parent_body_translation);
+
+            let mut rotation = parent_rotation_inverse * body_rot;
+
+            if body.root_body {
+                let correction = model_info.up_axis.basis();
+                translation = correction.mul_vec3(translation);
+                rotation = correction * rotation;
+            }
+
+            // writing through `Mut` marks the transform changed and triggers propagation
+            if !transform
+                .translation
+                .abs_diff_eq(translation, TRANSFORM_EPSILON)
+                || !transform.rotation.abs_diff_eq(rotation, TRANSFORM_EPSILON)
+            {
+                transform.translation = translation;
+                transform.rotation = rotation;
+            }
+        });
+}
+
+/// Update geom transforms relative to their bodies from the snapshot's `geom_xpos` /
+/// `geom_xmat`, when `MuJoCoPluginSettings::track_geoms` is set
+#[cfg(native)]
+pub fn sync_geom_transforms(
+    state_buffer: Res<MuJoCoStateBuffer>,
+    mut synced_version: Local<u64>,
+    bodies: Query<&MuJoCoBody>,
+    mut geoms_query: Query<
+        (&mut Transform, Ref<MuJoCoGeom>, &Parent, Has<MuJoCoMesh>),
+        Without<MuJoCoStatic>,
+    >,
+) {
+    let snapshot = state_buffer.front();
+    if snapshot.geom_xpos.is_empty() {
+        return;
+    }
+    let spawned = geoms_query.iter().any(|(_, geom, ..)| geom.is_added());
+    if snapshot.version == *synced_version && !spawned {
+        return;
     }
+    *synced_version = snapshot.version;
+
+    geoms_query
+        .par_iter_mut()
+        .for_each(|(mut transform, geom, parent, mesh)| {
+            let (Ok(body), Some(geom_xpos)) = (
+                bodies.get(parent.get()),
+                snapshot.geom_xpos.get(geom.id as usize),
+            ) else {
+                return;
+            };
+            let body_id = body.id as usize;
+            let body_rotation_inverse = rotation_from_xmat(&snapshot.xmat[body_id]).inverse();
+            let offset = array_mujoco_2_bevy(std::array::from_fn(|i| {
+                geom_xpos[i] - snapshot.xpos[body_id][i]
+            }));
+
+            let translation = body_rotation_inverse.mul_vec3(offset);
+            let mut rotation =
+                body_rotation_inverse * rotation_from_xmat(&snapshot.geom_xmat[geom.id as usize]);
+            // primitives are built along bevy's Y axis, see `geom_transforms`
+            if !mesh {
+                rotation *= Quat::from_rotation_x(std::f32::consts::FRAC_PI_2);
+            }
+
+            if !transform
+                .translation
+                .abs_diff_eq(translation, TRANSFORM_EPSILON)
+                || !transform.rotation.abs_diff_eq(rotation, TRANSFORM_EPSILON)
+            {
+                transform.translation = translation;
+                transform.rotation = rotation;
+            }
+        });
 }
 
+#[cfg(native)]
 fn setup_mujoco(
     mut commands: Commands,
-    meshes: ResMut<Assets<Mesh>>,
-    materials: ResMut<Assets<StandardMaterial>>,
     mujoco: ResMut<MuJoCoSimulation>,
+    settings: Res<MuJoCoPluginSettings>,
+    scene_roots: Query<Entity, With<MuJoCoSceneRoot>>,
+    mut unsupported_features_events: EventWriter<MuJoCoUnsupportedFeatures>,
 ) {
+    mujoco.lock().unwrap().up_axis = settings.up_axis;
+    if !mujoco.set_physics_threads(settings.physics_threads) {
+        warn!(
+            "can't step with {} threads, stepping with {}",
+            settings.physics_threads,
+            mujoco.physics_threads()
+        );
+    }
+    // world frames of bodies and geoms, geom transforms are spawned from them
+    mujoco.forward();
     let mujoco = mujoco.lock().unwrap();
-    let bodies = mujoco.model.bodies();
-    let geoms = mujoco.model.geoms();
+    let bodies = model_elements::bodies(&mujoco);
+    let geoms = model_elements::geoms(&mujoco);
+
+    let unsupported_features = MuJoCoUnsupportedFeatures::scan(&mujoco, &geoms);
+    unsupported_features.warn();
+    unsupported_features_events.send(unsupported_features.clone());
+    commands.insert_resource(unsupported_features);
+    commands.insert_resource(MuJoCoOptions::from_simulation(&mujoco));
+    commands.insert_resource(MuJoCoPhysicalParameters::from_simulation(&mujoco));
+    commands.insert_resource(MuJoCoCollisionFilter::from_simulation(&mujoco));
+    commands.insert_resource(MuJoCoFreeBodyPool::from_simulation(&mujoco));
+    commands.insert_resource(MuJoCoStateBuffer::default());
+    commands.insert_resource(MuJoCoStateScratch::from_simulation(&mujoco));
+    commands.insert_resource(MuJoCoEqualityConstraints::from_simulation(&mujoco));
+    commands.insert_resource(MuJoCoJointControl::from_simulation(&mujoco));
+    commands.insert_resource(MuJoCoControlLimits::from_simulation(&mujoco));
+    commands.insert_resource(MuJoCoActuatorGroups::from_simulation(&mujoco));
+    commands.insert_resource(MuJoCoCustomData::from_simulation(&mujoco));
+    commands.insert_resource(MuJoCoModelInfo::from_simulation(&mujoco));
+    commands.init_resource::<MuJoCoPdController>();
+    commands.insert_resource(MuJoCoTime {
+        elapsed: mujoco.time(),
+        ..default()
+    });
 
     commands.insert_resource(MuJoCoResources {
         geoms: geoms.clone(),
         bodies: bodies.clone(),
         control: MuJoCoControl {
-            number_of_controls: mujoco.model.nu(),
+            number_of_controls: wrappers::model(&mujoco).nu as usize,
             ..default()
         },
         ..default()
     });
 
+    let model = wrappers::model(&mujoco);
+    let joints: Vec<(String, i32)> = wrappers::names(model, model.name_jntadr, model.njnt)
+        .into_iter()
+        .zip(
+            unsafe { wrappers::slice(model.jnt_bodyid, model.njnt as usize) }
+                .iter()
+                .copied(),
+        )
+        .collect();
+    let entity_map = RefCell::new(MuJoCoEntityMap::default());
+
     // This is a closure that can call itself recursively
     struct SpawnEntities<'s> {
         f: &'s dyn Fn(&SpawnEntities, BodyTree, &mut ChildBuilder, usize),
+        /// Whether bodies are static fixtures, by body id
+        static_bodies: &'s [bool],
+        /// Body transforms relative to their parents, by body id
+        body_transforms: &'s [Transform],
+        /// Geom transforms relative to their bodies, by geom id
+        geom_transforms: &'s [Transform],
+        up_axis: UpAxis,
     }
 
     impl SpawnEntities<'_> {
-        /// Spawn a bevy entity for MuJoCo body
-        #[allow(clippy::too_many_arguments)]
+        /// Spawn a bevy entity for MuJoCo body and an entity for its render geom.
+        /// Meshes and materials are attached later by `attach_visuals`
         fn spawn_body(
             &self,
             child_builder: &mut ChildBuilder,
             body: &Body,
             geoms: &[Geom],
-            meshes: &Rc<RefCell<ResMut<Assets<Mesh>>>>,
-            materials: &Rc<RefCell<ResMut<Assets<StandardMaterial>>>>,
+            entity_map: &RefCell<MuJoCoEntityMap>,
             add_children: impl FnOnce(&mut ChildBuilder),
             depth: usize,
         ) {
@@ -204,58 +1351,75 @@ fn setup_mujoco(
                 return;
             }
             let geom = &geom.unwrap();
-            let mesh = geom_mesh(geom);
-            let mut body_transform = body_transform(body);
-            let geom_transform = geom_transform(geom);
+            let mut body_transform = self.body_transforms[body.id as usize];
+            let geom_transform = self.geom_transforms[geom.id as usize];
 
-            let mut binding: EntityCommands;
-            {
-                let mut materials = materials.borrow_mut();
-                let mut meshes = meshes.borrow_mut();
+            if depth == 0 {
+                let correction = self.up_axis.basis();
+                body_transform.translation = correction.mul_vec3(body_transform.translation);
+                body_transform.rotation = correction * body_transform.rotation;
+            }
 
-                if depth == 0 {
-                    let correction = Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2);
-                    body_transform.translation = correction.mul_vec3(body_transform.translation);
-                    body_transform.rotation = correction * body_transform.rotation;
-                }
+            let mut binding: EntityCommands = child_builder.spawn((
+                MuJoCoBody {
+                    id: body.id,
+                    root_body: depth == 0,
+                    parent_id: body.parent_id as usize,
+                },
+                Name::new(format!("MuJoCo::body_{}", body.name)),
+                SpatialBundle {
+                    transform: body_transform,
+                    ..default()
+                },
+            ));
 
-                binding = child_builder.spawn((
-                    MuJoCoBody {
-                        id: body.id,
-                        root_body: depth == 0,
-                    },
-                    Name::new(format!("MuJoCo::body_{}", body.name)),
+            let is_static = self.static_bodies[body.id as usize];
+            if is_static {
+                binding.insert(MuJoCoStatic);
+            }
+
+            let mut entity_map = entity_map.borrow_mut();
+            entity_map.insert_body(body.id, &body.name, binding.id());
+
+            binding.with_children(|children| {
+                let mut cmd = children.spawn((
+                    MuJoCoGeom { id: geom.id },
+                    Name::new(format!("MuJoCo::mesh_{}", body.name)),
                     SpatialBundle {
-                        transform: body_transform,
+                        transform: geom_transform,
                         ..default()
                     },
                 ));
 
-                binding.with_children(|children| {
-                    let mut cmd = children.spawn(PbrBundle {
-                        mesh: Mesh3d(meshes.add(mesh)),
-                        material: MeshMaterial3d(materials.add(geom_material(geom))),
-                        transform: geom_transform,
-                        ..default()
-                    });
-
-                    cmd.insert(Name::new(format!("MuJoCo::mesh_{}", body.name)));
-                    if geom.geom_type == GeomType::MESH {
-                        cmd.insert(MuJoCoMesh { id: geom.id });
-                    }
-                });
-            }
+                if geom.geom_type == GeomType::MESH {
+                    cmd.insert(MuJoCoMesh { id: geom.id });
+                }
+                if is_static {
+                    cmd.insert(MuJoCoStatic);
+                }
+                entity_map.insert_geom(geom.id, &geom.name, cmd.id());
+            });
+            drop(entity_map);
 
             binding.with_children(add_children);
         }
     }
 
-    let meshes = Rc::new(RefCell::new(meshes));
-    let materials = Rc::new(RefCell::new(materials));
-    let commands = Rc::new(RefCell::new(commands));
-
     // closure implementation
+    let nbody = model.nbody as usize;
+    // welded to the world: no joints between the body and the world body
+    let body_weldid = unsafe { wrappers::slice(model.body_weldid, nbody) };
+    let body_mocapid = unsafe { wrappers::slice(model.body_mocapid, nbody) };
+    let static_bodies: Vec<bool> = (0..nbody)
+        .map(|id| body_weldid[id] == 0 && body_mocapid[id] < 0)
+        .collect();
+    let body_transforms = body_transforms(&mujoco);
+    let geom_transforms = geom_transforms(&mujoco);
     let spawn_entities = SpawnEntities {
+        static_bodies: &static_bodies,
+        body_transforms: &body_transforms,
+        geom_transforms: &geom_transforms,
+        up_axis: mujoco.up_axis,
         // A function that spawn body into the current position in a tree
         f: &|func, body, child_builder, depth| {
             let root_leaf = body.data();
@@ -275,22 +1439,78 @@ fn setup_mujoco(
                 child_builder,
                 root_leaf,
                 &geoms,
-                &meshes,
-                &materials,
+                &entity_map,
                 add_children,
                 depth,
             );
         },
     };
 
-    let mut commands = commands.borrow_mut();
     let body_tree = body_tree(&bodies);
     // each mujoco body is defined as a tree
-    commands
-        .spawn((Name::new("MuJoCo::world"), SpatialBundle::default()))
-        .with_children(|child_builder| {
-            for body in body_tree {
-                (spawn_entities.f)(&spawn_entities, body, child_builder, 0);
-            }
-        });
+    let spawned_world = scene_roots.get_single().is_err();
+    let mut scene_root = match scene_roots.get_single() {
+        Ok(scene_root) => commands.entity(scene_root),
+        Err(_) => commands.spawn((
+            MuJoCoSceneRoot,
+            Name::new("MuJoCo::world"),
+            SpatialBundle {
+                transform: settings.world_transform,
+                ..default()
+            },
+        )),
+    };
+    scene_root.with_children(|child_builder| {
+        for body in body_tree {
+            (spawn_entities.f)(&spawn_entities, body, child_builder, 0);
+        }
+    });
+    let scene_root = scene_root.id();
+
+    let mut entity_map = entity_map.take();
+    entity_map.insert_joints(&joints);
+    entity_map.world = spawned_world.then_some(scene_root);
+    commands.insert_resource(entity_map);
+}
+
+/// Attach meshes and materials to newly spawned geom entities (`SpawnMode::Full` only)
+#[cfg(native)]
+#[allow(clippy::too_many_arguments)]
+pub fn attach_visuals(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut asset_cache: ResMut<MuJoCoAssetCache>,
+    settings: Res<MuJoCoPluginSettings>,
+    mujoco: Res<MuJoCoSimulation>,
+    mujoco_resources: Res<MuJoCoResources>,
+    geoms_query: Query<(Entity, &MuJoCoGeom), Added<MuJoCoGeom>>,
+) {
+    if settings.spawn_mode != SpawnMode::Full || geoms_query.is_empty() {
+        return;
+    }
+
+    let mujoco = mujoco.lock().unwrap();
+    let model = wrappers::model(&mujoco);
+    let geom_group = unsafe { wrappers::slice(model.geom_group, model.ngeom as usize) };
+    for (entity, mj_geom) in geoms_query.iter() {
+        let geom = &mujoco_resources.geoms[mj_geom.id as usize];
+        // unsupported geoms are reported on load and spawned without a mesh
+        let Some(mesh) = asset_cache.geom_mesh(&mujoco, geom, settings.plane_size, &mut meshes)
+        else {
+            continue;
+        };
+        let material = asset_cache.geom_material(geom, &mut materials);
+
+        let mut entity = commands.entity(entity);
+        if let Some(aabb) = mesh_aabb(&meshes, &mesh) {
+            entity.insert(aabb);
+        }
+        entity.insert((Mesh3d(mesh), MeshMaterial3d(material)));
+        settings.shadows.apply(
+            &mut entity,
+            geom_group[mj_geom.id as usize],
+            geom.geom_type == GeomType::PLANE,
+        );
+    }
 }
@@ -0,0 +1,107 @@
+use bevy::prelude::*;
+
+use crate::{adapters::*, raycast::cast_ray, wrappers, MuJoCoSceneRoot, MuJoCoSimulation};
+
+/// Planar lidar attached to a MuJoCo site. Rays are fanned out in the site's XY plane,
+/// centered on the site X axis
+#[derive(Component, Debug, Clone)]
+pub struct MuJoCoLidar {
+    pub site: String,
+    /// Horizontal field of view in radians
+    pub fov: f32,
+    pub ray_count: usize,
+    pub max_range: f32,
+    /// Draw beams with gizmos
+    pub draw_beams: bool,
+}
+
+impl Default for MuJoCoLidar {
+    fn default() -> Self {
+        MuJoCoLidar {
+            site: String::new(),
+            fov: std::f32::consts::PI,
+            ray_count: 180,
+            max_range: 10.0,
+            draw_beams: false,
+        }
+    }
+}
+
+/// Ranges measured by a `MuJoCoLidar` this frame, `max_range` where nothing was hit
+#[derive(Event, Debug, Clone)]
+pub struct MuJoCoLidarScan {
+    pub entity: Entity,
+    pub ranges: Vec<f32>,
+}
+
+/// Cast the rays of every `MuJoCoLidar` and send the results as `MuJoCoLidarScan` events
+pub fn scan_lidars(
+    mujoco: Res<MuJoCoSimulation>,
+    lidars: Query<(Entity, &MuJoCoLidar)>,
+    scene_roots: Query<&GlobalTransform, With<MuJoCoSceneRoot>>,
+    mut scans: EventWriter<MuJoCoLidarScan>,
+    mut gizmos: Gizmos,
+) {
+    if lidars.is_empty() {
+        return;
+    }
+
+    let mujoco = mujoco.lock().unwrap();
+    let model = wrappers::model(&mujoco);
+    let data = wrappers::data(&mujoco);
+    let nsite = model.nsite as usize;
+    let site_xpos = unsafe { wrappers::slice(data.site_xpos, 3 * nsite) };
+    let site_xmat = unsafe { wrappers::slice(data.site_xmat, 9 * nsite) };
+    let site_bodyid = unsafe { wrappers::slice(model.site_bodyid, nsite) };
+    let scene_transform = scene_roots.get_single().copied().unwrap_or_default();
+
+    for (entity, lidar) in lidars.iter() {
+        let Some(site) = wrappers::name_to_id(model, model.name_siteadr, model.nsite, &lidar.site)
+        else {
+            warn_once!("lidar site {} not found in the model", lidar.site);
+            continue;
+        };
+        let origin = [
+            site_xpos[3 * site],
+            site_xpos[3 * site + 1],
+            site_xpos[3 * site + 2],
+        ];
+        let xmat = &site_xmat[9 * site..9 * site + 9];
+
+        let ranges: Vec<f32> = (0..lidar.ray_count)
+            .map(|i| {
+                let t = if lidar.ray_count > 1 {
+                    i as f32 / (lidar.ray_count - 1) as f32
+                } else {
+                    0.5
+                };
+                let angle = (t - 0.5) * lidar.fov;
+                let (local_x, local_y) = (angle.cos() as f64, angle.sin() as f64);
+                // rotate from site frame to world frame, xmat is row-major
+                let direction = [
+                    xmat[0] * local_x + xmat[1] * local_y,
+                    xmat[3] * local_x + xmat[4] * local_y,
+                    xmat[6] * local_x + xmat[7] * local_y,
+                ];
+
+                let range = cast_ray(&mujoco, origin, direction, Some(site_bodyid[site] as usize))
+                    .map_or(lidar.max_range, |(_, distance)| {
+                        (distance as f32).min(lidar.max_range)
+                    });
+
+                if lidar.draw_beams {
+                    let start = position_mujoco_2_bevy(mujoco.up_axis, origin);
+                    let end = start + position_mujoco_2_bevy(mujoco.up_axis, direction) * range;
+                    gizmos.line(
+                        scene_transform.transform_point(start),
+                        scene_transform.transform_point(end),
+                        Color::srgb(1.0, 0.2, 0.2),
+                    );
+                }
+                range
+            })
+            .collect();
+
+        scans.send(MuJoCoLidarScan { entity, ranges });
+    }
+}
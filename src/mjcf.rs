@@ -0,0 +1,257 @@
+//! Owned MJCF document tree, parsed with `roxmltree` and serialized back to XML
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::{Deref, Resource};
+
+/// Error reading or parsing an MJCF file
+#[derive(Debug)]
+pub enum MjcfError {
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    Parse {
+        path: Option<PathBuf>,
+        message: String,
+    },
+}
+
+impl std::fmt::Display for MjcfError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MjcfError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            MjcfError::Parse {
+                path: Some(path),
+                message,
+            } => write!(f, "{}: {}", path.display(), message),
+            MjcfError::Parse {
+                path: None,
+                message,
+            } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for MjcfError {}
+
+/// An MJCF element with its attributes in document order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MjcfElement {
+    pub tag: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<MjcfElement>,
+    /// Text of the comments directly inside the element
+    pub comments: Vec<String>,
+}
+
+impl MjcfElement {
+    pub fn new(tag: &str) -> Self {
+        MjcfElement {
+            tag: tag.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_attribute(mut self, key: &str, value: impl ToString) -> Self {
+        self.set_attribute(key, value);
+        self
+    }
+
+    pub fn with_child(mut self, child: MjcfElement) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn set_attribute(&mut self, key: &str, value: impl ToString) {
+        let value = value.to_string();
+        match self.attributes.iter_mut().find(|(k, _)| k == key) {
+            Some((_, v)) => *v = value,
+            None => self.attributes.push((key.to_string(), value)),
+        }
+    }
+
+    pub fn remove_attribute(&mut self, key: &str) -> Option<String> {
+        let index = self.attributes.iter().position(|(k, _)| k == key)?;
+        Some(self.attributes.remove(index).1)
+    }
+
+    /// First child with a tag
+    pub fn child(&self, tag: &str) -> Option<&MjcfElement> {
+        self.children.iter().find(|child| child.tag == tag)
+    }
+
+    /// First child with a tag, created if missing
+    pub fn child_mut(&mut self, tag: &str) -> &mut MjcfElement {
+        let index = match self.children.iter().position(|child| child.tag == tag) {
+            Some(index) => index,
+            None => {
+                self.children.push(MjcfElement::new(tag));
+                self.children.len() - 1
+            }
+        };
+        &mut self.children[index]
+    }
+
+    /// Numbers of a whitespace separated attribute, `None` if missing or malformed
+    pub fn numbers(&self, key: &str) -> Option<Vec<f64>> {
+        self.attribute(key)?
+            .split_whitespace()
+            .map(|value| value.parse().ok())
+            .collect()
+    }
+
+    /// Descendants with a tag, in document order
+    pub fn descendants(&self, tag: &str) -> Vec<&MjcfElement> {
+        let mut found = vec![];
+        for child in self.children.iter() {
+            if child.tag == tag {
+                found.push(child);
+            }
+            found.extend(child.descendants(tag));
+        }
+        found
+    }
+
+    /// First descendant with a tag and a `name` attribute
+    pub fn find(&self, tag: &str, name: &str) -> Option<&MjcfElement> {
+        self.descendants(tag)
+            .into_iter()
+            .find(|element| element.attribute("name") == Some(name))
+    }
+
+    /// Visit this element and all its descendants, parents first
+    pub fn visit_mut(&mut self, f: &mut impl FnMut(&mut MjcfElement)) {
+        f(self);
+        for child in self.children.iter_mut() {
+            child.visit_mut(f);
+        }
+    }
+
+    /// Parse an MJCF document. `<include>` elements are kept as they are
+    pub fn parse(xml: &str) -> Result<Self, MjcfError> {
+        let document = roxmltree::Document::parse(xml).map_err(|error| MjcfError::Parse {
+            path: None,
+            message: error.to_string(),
+        })?;
+        Ok(Self::from_node(document.root_element()))
+    }
+
+    /// Read an MJCF file, replacing `<include file="..."/>` elements with the children of
+    /// the included file's root. Include paths are relative to the directory of `path`,
+    /// like in MuJoCo
+    pub fn load(path: &Path) -> Result<Self, MjcfError> {
+        let directory = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        let mut root = Self::read(path)?;
+        root.expand_includes(&directory)?;
+        Ok(root)
+    }
+
+    fn read(path: &Path) -> Result<Self, MjcfError> {
+        let xml = std::fs::read_to_string(path).map_err(|error| MjcfError::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        Self::parse(&xml).map_err(|error| match error {
+            MjcfError::Parse { message, .. } => MjcfError::Parse {
+                path: Some(path.to_path_buf()),
+                message,
+            },
+            error => error,
+        })
+    }
+
+    fn expand_includes(&mut self, directory: &Path) -> Result<(), MjcfError> {
+        let mut children = Vec::with_capacity(self.children.len());
+        for mut child in std::mem::take(&mut self.children) {
+            match (child.tag.as_str(), child.attribute("file")) {
+                ("include", Some(file)) => {
+                    let mut included = Self::read(&directory.join(file))?;
+                    included.expand_includes(directory)?;
+                    children.extend(included.children);
+                }
+                _ => {
+                    child.expand_includes(directory)?;
+                    children.push(child);
+                }
+            }
+        }
+        self.children = children;
+        Ok(())
+    }
+
+    fn from_node(node: roxmltree::Node) -> Self {
+        MjcfElement {
+            tag: node.tag_name().name().to_string(),
+            attributes: node
+                .attributes()
+                .map(|attribute| (attribute.name().to_string(), attribute.value().to_string()))
+                .collect(),
+            children: node
+                .children()
+                .filter(|child| child.is_element())
+                .map(Self::from_node)
+                .collect(),
+            comments: node
+                .children()
+                .filter(|child| child.is_comment())
+                .filter_map(|child| child.text())
+                .map(|text| text.trim().to_string())
+                .collect(),
+        }
+    }
+
+    /// Serialize to an indented XML document
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::new();
+        self.write_xml(&mut xml, 0);
+        xml
+    }
+
+    fn write_xml(&self, xml: &mut String, depth: usize) {
+        let indent = "  ".repeat(depth);
+        let _ = write!(xml, "{indent}<{}", self.tag);
+        for (key, value) in self.attributes.iter() {
+            let _ = write!(xml, " {key}=\"{}\"", escape(value));
+        }
+        if self.children.is_empty() && self.comments.is_empty() {
+            xml.push_str("/>\n");
+            return;
+        }
+
+        xml.push_str(">\n");
+        for comment in self.comments.iter() {
+            let _ = writeln!(xml, "{indent}  <!-- {comment} -->");
+        }
+        for child in self.children.iter() {
+            child.write_xml(xml, depth + 1);
+        }
+        let _ = writeln!(xml, "{indent}</{}>", self.tag);
+    }
+}
+
+/// MJCF document of the loaded model with includes expanded, for authoring-only data the
+/// compiled model drops: custom attributes, class names, comments. Available for models
+/// loaded from an MJCF file
+#[derive(Resource, Deref, Debug, Clone)]
+pub struct MuJoCoMjcf {
+    pub path: PathBuf,
+    #[deref]
+    pub root: MjcfElement,
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,217 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{MjcfElement, MjcfError, MuJoCoLoadError, MuJoCoSimulation};
+
+/// Builds MJCF documents in code, e.g. for tests and procedural scenes:
+///
+/// ```ignore
+/// let simulation = MjcfBuilder::new()
+///     .worldbody()
+///     .add_geom("floor", "plane", &[5.0, 5.0, 0.1])
+///     .add_body("ball", [0.0, 0.0, 1.0])
+///     .add_freejoint()
+///     .add_geom("ball", "sphere", &[0.1])
+///     .end()
+///     .load()?;
+/// ```
+///
+/// Elements are added to the current element. `worldbody`, `asset` and `actuator` select a
+/// section, `add_body` enters the new body until `end` is called.
+/// Positions are in MuJoCo coordinates (Z up)
+#[derive(Debug, Clone)]
+pub struct MjcfBuilder {
+    root: MjcfElement,
+    /// Child indices from the root to the current element
+    cursor: Vec<usize>,
+}
+
+impl Default for MjcfBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MjcfBuilder {
+    pub fn new() -> Self {
+        MjcfBuilder {
+            root: MjcfElement::new("mujoco").with_attribute("model", "bevy_mujoco"),
+            cursor: Vec::new(),
+        }
+    }
+
+    pub fn model(mut self, name: &str) -> Self {
+        self.root.set_attribute("model", name);
+        self
+    }
+
+    /// Set an attribute of `<option>`, e.g. `.option("timestep", 0.002)`
+    pub fn option(mut self, key: &str, value: impl ToString) -> Self {
+        self.root.child_mut("option").set_attribute(key, value);
+        self
+    }
+
+    pub fn worldbody(self) -> Self {
+        self.section("worldbody")
+    }
+
+    pub fn asset(self) -> Self {
+        self.section("asset")
+    }
+
+    pub fn actuator(self) -> Self {
+        self.section("actuator")
+    }
+
+    /// Select a top-level section, created if missing
+    pub fn section(mut self, tag: &str) -> Self {
+        self.select(tag);
+        self
+    }
+
+    /// Add a body and make it the current element
+    pub fn add_body(mut self, name: &str, pos: [f64; 3]) -> Self {
+        let body = MjcfElement::new("body")
+            .with_attribute("name", name)
+            .with_attribute("pos", format_vector(&pos));
+        let index = self.push(body);
+        self.cursor.push(index);
+        self
+    }
+
+    /// Leave the current body
+    pub fn end(mut self) -> Self {
+        if self.cursor.len() > 1 {
+            self.cursor.pop();
+        }
+        self
+    }
+
+    /// Add a geom of an MJCF type (`"sphere"`, `"box"`, `"capsule"`, `"plane"`, ...)
+    pub fn add_geom(self, name: &str, geom_type: &str, size: &[f64]) -> Self {
+        self.add(
+            MjcfElement::new("geom")
+                .with_attribute("name", name)
+                .with_attribute("type", geom_type)
+                .with_attribute("size", format_vector(size)),
+        )
+    }
+
+    /// Add a joint of an MJCF type (`"hinge"`, `"slide"`, `"ball"`)
+    pub fn add_joint(self, name: &str, joint_type: &str, axis: [f64; 3]) -> Self {
+        self.add(
+            MjcfElement::new("joint")
+                .with_attribute("name", name)
+                .with_attribute("type", joint_type)
+                .with_attribute("axis", format_vector(&axis)),
+        )
+    }
+
+    pub fn add_freejoint(self) -> Self {
+        self.add(MjcfElement::new("freejoint"))
+    }
+
+    pub fn add_site(self, name: &str, pos: [f64; 3]) -> Self {
+        self.add(
+            MjcfElement::new("site")
+                .with_attribute("name", name)
+                .with_attribute("pos", format_vector(&pos)),
+        )
+    }
+
+    /// Add a motor driving a joint, to be called in the `actuator` section
+    pub fn add_motor(self, name: &str, joint: &str, gear: f64) -> Self {
+        self.add(
+            MjcfElement::new("motor")
+                .with_attribute("name", name)
+                .with_attribute("joint", joint)
+                .with_attribute("gear", gear),
+        )
+    }
+
+    /// Add any element to the current element
+    pub fn add(mut self, element: MjcfElement) -> Self {
+        self.push(element);
+        self
+    }
+
+    /// Set an attribute of the last added element, e.g. `.attribute("rgba", "1 0 0 1")`
+    pub fn attribute(mut self, key: &str, value: impl ToString) -> Self {
+        if let Some(element) = self.current().children.last_mut() {
+            element.set_attribute(key, value);
+        }
+        self
+    }
+
+    pub fn build(self) -> MjcfElement {
+        self.root
+    }
+
+    pub fn to_xml(&self) -> String {
+        self.root.to_xml()
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), MjcfError> {
+        std::fs::write(path, self.to_xml()).map_err(|error| MjcfError::Io {
+            path: path.to_path_buf(),
+            error,
+        })
+    }
+
+    /// Compile the document. Insert the result with `App::insert_resource` to use it
+    /// instead of `MuJoCoPluginSettings::model_xml_path`
+    pub fn load(&self) -> Result<MuJoCoSimulation, MuJoCoLoadError> {
+        let path = temporary_path();
+        self.write(&path).map_err(|error| MuJoCoLoadError {
+            path: path.to_string_lossy().into_owned(),
+            message: error.to_string(),
+        })?;
+        let simulation = MuJoCoSimulation::load(&path.to_string_lossy());
+        let _ = std::fs::remove_file(&path);
+        simulation
+    }
+
+    fn select(&mut self, tag: &str) {
+        self.root.child_mut(tag);
+        let index = self
+            .root
+            .children
+            .iter()
+            .position(|child| child.tag == tag)
+            .unwrap();
+        self.cursor = vec![index];
+    }
+
+    /// Current element, `worldbody` when no section was selected
+    fn current(&mut self) -> &mut MjcfElement {
+        if self.cursor.is_empty() {
+            self.select("worldbody");
+        }
+        let mut element = &mut self.root;
+        for &index in self.cursor.iter() {
+            element = &mut element.children[index];
+        }
+        element
+    }
+
+    fn push(&mut self, element: MjcfElement) -> usize {
+        let current = self.current();
+        current.children.push(element);
+        current.children.len() - 1
+    }
+}
+
+fn format_vector(values: &[f64]) -> String {
+    values
+        .iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Unique path for compiling a document, MuJoCo only loads models from files
+fn temporary_path() -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!("bevy_mujoco_{}_{}.xml", std::process::id(), count))
+}
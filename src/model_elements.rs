@@ -0,0 +1,283 @@
+//! Bodies and geoms of a compiled model, read through a `Backend` so the plugin spawns scenes
+//! the same way whichever bindings loaded the model, and the MuJoCo enums of model fields
+
+use crate::{wrappers, Backend};
+
+/// Geom type (`mjtGeom`)
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(i32)]
+pub enum GeomType {
+    PLANE = 0,
+    HFIELD = 1,
+    SPHERE = 2,
+    CAPSULE = 3,
+    ELLIPSOID = 4,
+    CYLINDER = 5,
+    BOX = 6,
+    MESH = 7,
+    /// Types added by later MuJoCo versions, e.g. SDF geoms
+    UNKNOWN = -1,
+}
+
+impl GeomType {
+    pub fn from_raw(geom_type: i32) -> Self {
+        match geom_type {
+            0 => GeomType::PLANE,
+            1 => GeomType::HFIELD,
+            2 => GeomType::SPHERE,
+            3 => GeomType::CAPSULE,
+            4 => GeomType::ELLIPSOID,
+            5 => GeomType::CYLINDER,
+            6 => GeomType::BOX,
+            7 => GeomType::MESH,
+            _ => GeomType::UNKNOWN,
+        }
+    }
+
+    /// The `mjtGeom` value, written into `mjModel.geom_type`
+    pub fn to_raw(self) -> i32 {
+        self as i32
+    }
+}
+
+/// Joint type (`mjtJoint`)
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(i32)]
+pub enum JointType {
+    FREE = 0,
+    BALL = 1,
+    SLIDE = 2,
+    HINGE = 3,
+    UNKNOWN = -1,
+}
+
+impl JointType {
+    pub fn from_raw(jnt_type: i32) -> Self {
+        match jnt_type {
+            0 => JointType::FREE,
+            1 => JointType::BALL,
+            2 => JointType::SLIDE,
+            3 => JointType::HINGE,
+            _ => JointType::UNKNOWN,
+        }
+    }
+
+    /// Slide and hinge joints, with a single `qpos` value
+    pub fn is_scalar(self) -> bool {
+        matches!(self, JointType::SLIDE | JointType::HINGE)
+    }
+
+    /// Number of `qpos` values
+    pub fn nq(self) -> usize {
+        match self {
+            JointType::FREE => 7,
+            JointType::BALL => 4,
+            _ => 1,
+        }
+    }
+
+    /// Number of dofs
+    pub fn nv(self) -> usize {
+        match self {
+            JointType::FREE => 6,
+            JointType::BALL => 3,
+            _ => 1,
+        }
+    }
+}
+
+/// Actuator transmission type (`mjtTrn`)
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(i32)]
+pub enum TransmissionType {
+    JOINT = 0,
+    JOINTINPARENT = 1,
+    SLIDERCRANK = 2,
+    TENDON = 3,
+    SITE = 4,
+    BODY = 5,
+    UNKNOWN = -1,
+}
+
+impl TransmissionType {
+    pub fn from_raw(trn_type: i32) -> Self {
+        match trn_type {
+            0 => TransmissionType::JOINT,
+            1 => TransmissionType::JOINTINPARENT,
+            2 => TransmissionType::SLIDERCRANK,
+            3 => TransmissionType::TENDON,
+            4 => TransmissionType::SITE,
+            5 => TransmissionType::BODY,
+            _ => TransmissionType::UNKNOWN,
+        }
+    }
+}
+
+/// Actuator bias type (`mjtBias`)
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(i32)]
+pub enum BiasType {
+    NONE = 0,
+    AFFINE = 1,
+    MUSCLE = 2,
+    USER = 3,
+    UNKNOWN = -1,
+}
+
+impl BiasType {
+    pub fn from_raw(bias_type: i32) -> Self {
+        match bias_type {
+            0 => BiasType::NONE,
+            1 => BiasType::AFFINE,
+            2 => BiasType::MUSCLE,
+            3 => BiasType::USER,
+            _ => BiasType::UNKNOWN,
+        }
+    }
+}
+
+/// Vertices and triangles of a mesh asset, in the mesh frame
+#[derive(Debug, Clone, Default)]
+pub struct GeomMesh {
+    pub vertices: Vec<[f32; 3]>,
+    /// Per vertex normals, empty when the model doesn't store them per vertex
+    pub normals: Vec<[f32; 3]>,
+    pub indices: Vec<u32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Geom {
+    pub id: i32,
+    pub name: String,
+    pub body_id: i32,
+    pub geom_type: GeomType,
+    /// Type specific sizes (`geom_size`), e.g. radius and half length for capsules
+    pub size: [f64; 3],
+    /// rgba of the geom, or of its material when the geom's own is left at the default
+    pub color: [f32; 4],
+    /// Mesh of mesh geoms
+    pub mesh: Option<GeomMesh>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Body {
+    pub id: i32,
+    pub name: String,
+    /// 0 for root bodies and the world body itself
+    pub parent_id: i32,
+    /// First geom of the body, -1 for bodies without geoms
+    pub geom_adr: i32,
+}
+
+impl Body {
+    /// The geom the body is drawn with, its first geom
+    pub fn render_geom<'a>(&self, geoms: &'a [Geom]) -> Option<&'a Geom> {
+        geoms.get(usize::try_from(self.geom_adr).ok()?)
+    }
+}
+
+/// All bodies of the model, by id, the world body first
+pub(crate) fn bodies(simulation: &impl Backend) -> Vec<Body> {
+    let model = wrappers::model(simulation);
+    let nbody = model.nbody as usize;
+    let names = wrappers::names(model, model.name_bodyadr, model.nbody);
+    let body_parentid = unsafe { wrappers::slice(model.body_parentid, nbody) };
+    let body_geomadr = unsafe { wrappers::slice(model.body_geomadr, nbody) };
+
+    names
+        .into_iter()
+        .enumerate()
+        .map(|(body, name)| Body {
+            id: body as i32,
+            name,
+            parent_id: body_parentid[body],
+            geom_adr: body_geomadr[body],
+        })
+        .collect()
+}
+
+/// All geoms of the model, by id
+pub(crate) fn geoms(simulation: &impl Backend) -> Vec<Geom> {
+    let model = wrappers::model(simulation);
+    let (ngeom, nmat) = (model.ngeom as usize, model.nmat as usize);
+    let names = wrappers::names(model, model.name_geomadr, model.ngeom);
+    let geom_type = unsafe { wrappers::slice(model.geom_type, ngeom) };
+    let geom_bodyid = unsafe { wrappers::slice(model.geom_bodyid, ngeom) };
+    let geom_dataid = unsafe { wrappers::slice(model.geom_dataid, ngeom) };
+    let geom_matid = unsafe { wrappers::slice(model.geom_matid, ngeom) };
+    let geom_size = unsafe { wrappers::slice(model.geom_size, 3 * ngeom) };
+    let geom_rgba = unsafe { wrappers::slice(model.geom_rgba, 4 * ngeom) };
+    let mat_rgba = unsafe { wrappers::slice(model.mat_rgba, 4 * nmat) };
+
+    names
+        .into_iter()
+        .enumerate()
+        .map(|(geom, name)| {
+            let geom_type = GeomType::from_raw(geom_type[geom]);
+            let mut color: [f32; 4] = std::array::from_fn(|i| geom_rgba[4 * geom + i]);
+            // like MuJoCo's renderer, materials color geoms that keep the default rgba
+            if let Ok(material) = usize::try_from(geom_matid[geom]) {
+                if color == [0.5, 0.5, 0.5, 1.0] {
+                    color = std::array::from_fn(|i| mat_rgba[4 * material + i]);
+                }
+            }
+            let mesh = match (geom_type, usize::try_from(geom_dataid[geom])) {
+                (GeomType::MESH, Ok(mesh)) => Some(mesh_data(simulation, mesh)),
+                _ => None,
+            };
+            Geom {
+                id: geom as i32,
+                name,
+                body_id: geom_bodyid[geom],
+                geom_type,
+                size: std::array::from_fn(|i| geom_size[3 * geom + i]),
+                color,
+                mesh,
+            }
+        })
+        .collect()
+}
+
+/// Vertices, normals and faces of a mesh asset
+fn mesh_data(simulation: &impl Backend, mesh: usize) -> GeomMesh {
+    let model = wrappers::model(simulation);
+    let nmesh = model.nmesh as usize;
+    let mesh_vertadr = unsafe { wrappers::slice(model.mesh_vertadr, nmesh) };
+    let mesh_vertnum = unsafe { wrappers::slice(model.mesh_vertnum, nmesh) };
+    let mesh_faceadr = unsafe { wrappers::slice(model.mesh_faceadr, nmesh) };
+    let mesh_facenum = unsafe { wrappers::slice(model.mesh_facenum, nmesh) };
+    let mesh_vert = unsafe { wrappers::slice(model.mesh_vert, 3 * model.nmeshvert as usize) };
+    let mesh_face = unsafe { wrappers::slice(model.mesh_face, 3 * model.nmeshface as usize) };
+
+    let (vertadr, vertnum) = (mesh_vertadr[mesh] as usize, mesh_vertnum[mesh] as usize);
+    let (faceadr, facenum) = (mesh_faceadr[mesh] as usize, mesh_facenum[mesh] as usize);
+    let vertices = mesh_vert[3 * vertadr..3 * (vertadr + vertnum)]
+        .chunks_exact(3)
+        .map(|vertex| [vertex[0], vertex[1], vertex[2]])
+        .collect();
+    // MuJoCo 3.x indexes normals per face corner, they are computed from the faces instead
+    #[cfg(not(mujoco3))]
+    let normals = {
+        let mesh_normal =
+            unsafe { wrappers::slice(model.mesh_normal, 3 * model.nmeshvert as usize) };
+        mesh_normal[3 * vertadr..3 * (vertadr + vertnum)]
+            .chunks_exact(3)
+            .map(|normal| [normal[0], normal[1], normal[2]])
+            .collect()
+    };
+    #[cfg(mujoco3)]
+    let normals = vec![];
+
+    GeomMesh {
+        vertices,
+        normals,
+        indices: mesh_face[3 * faceadr..3 * (faceadr + facenum)]
+            .iter()
+            .map(|index| *index as u32)
+            .collect(),
+    }
+}
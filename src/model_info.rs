@@ -0,0 +1,154 @@
+use bevy::prelude::*;
+
+use crate::{wrappers, JointType, SimulationHandle, TransmissionType, UpAxis};
+
+/// Sizes, names and index arrays of the compiled model, copied once at load so controllers
+/// can build index maps without reading `mjModel` pointers
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MuJoCoModelInfo {
+    pub nq: usize,
+    pub nv: usize,
+    pub nu: usize,
+    pub na: usize,
+    pub nbody: usize,
+    pub njnt: usize,
+    pub ngeom: usize,
+    pub nsite: usize,
+    pub nsensor: usize,
+    pub nmocap: usize,
+    pub timestep: f64,
+
+    pub body_names: Vec<String>,
+    pub joint_names: Vec<String>,
+    pub actuator_names: Vec<String>,
+    pub sensor_names: Vec<String>,
+    pub site_names: Vec<String>,
+
+    /// Joint type (`mjtJoint`): 0 free, 1 ball, 2 slide, 3 hinge
+    pub jnt_type: Vec<i32>,
+    pub jnt_qposadr: Vec<usize>,
+    pub jnt_dofadr: Vec<usize>,
+    pub jnt_bodyid: Vec<usize>,
+    pub dof_jntid: Vec<usize>,
+    pub dof_bodyid: Vec<usize>,
+    /// First joint of a body, -1 for bodies without joints
+    pub body_jntadr: Vec<i32>,
+    pub body_jntnum: Vec<usize>,
+    pub body_parentid: Vec<usize>,
+    /// Mocap id of every body, -1 for bodies that aren't mocap bodies
+    pub body_mocapid: Vec<i32>,
+    pub geom_bodyid: Vec<usize>,
+    /// Transmission type (`mjtTrn`) of every actuator, 0 for joints
+    pub actuator_trntype: Vec<i32>,
+    /// Transmission target ids of every actuator, e.g. the joint id for joint transmissions
+    pub actuator_trnid: Vec<[i32; 2]>,
+    pub sensor_adr: Vec<usize>,
+    pub sensor_dim: Vec<usize>,
+
+    /// Up axis of the scene the model is shown in, see `MuJoCoSimulation::up_axis`
+    pub up_axis: UpAxis,
+}
+
+fn to_usize(values: &[i32]) -> Vec<usize> {
+    values.iter().map(|value| *value as usize).collect()
+}
+
+impl MuJoCoModelInfo {
+    pub(crate) fn from_simulation(simulation: &SimulationHandle) -> Self {
+        let model = wrappers::model(simulation);
+        let (nbody, njnt, ngeom, nv, nu, nsensor) = (
+            model.nbody as usize,
+            model.njnt as usize,
+            model.ngeom as usize,
+            model.nv as usize,
+            model.nu as usize,
+            model.nsensor as usize,
+        );
+
+        MuJoCoModelInfo {
+            nq: model.nq as usize,
+            nv,
+            nu,
+            na: model.na as usize,
+            nbody,
+            njnt,
+            ngeom,
+            nsite: model.nsite as usize,
+            nsensor,
+            nmocap: model.nmocap as usize,
+            timestep: model.opt.timestep,
+
+            body_names: wrappers::names(model, model.name_bodyadr, model.nbody),
+            joint_names: wrappers::names(model, model.name_jntadr, model.njnt),
+            actuator_names: wrappers::names(model, model.name_actuatoradr, model.nu),
+            sensor_names: wrappers::names(model, model.name_sensoradr, model.nsensor),
+            site_names: wrappers::names(model, model.name_siteadr, model.nsite),
+
+            jnt_type: unsafe { wrappers::slice(model.jnt_type, njnt) }.to_vec(),
+            jnt_qposadr: to_usize(unsafe { wrappers::slice(model.jnt_qposadr, njnt) }),
+            jnt_dofadr: to_usize(unsafe { wrappers::slice(model.jnt_dofadr, njnt) }),
+            jnt_bodyid: to_usize(unsafe { wrappers::slice(model.jnt_bodyid, njnt) }),
+            dof_jntid: to_usize(unsafe { wrappers::slice(model.dof_jntid, nv) }),
+            dof_bodyid: to_usize(unsafe { wrappers::slice(model.dof_bodyid, nv) }),
+            body_jntadr: unsafe { wrappers::slice(model.body_jntadr, nbody) }.to_vec(),
+            body_jntnum: to_usize(unsafe { wrappers::slice(model.body_jntnum, nbody) }),
+            body_parentid: to_usize(unsafe { wrappers::slice(model.body_parentid, nbody) }),
+            body_mocapid: unsafe { wrappers::slice(model.body_mocapid, nbody) }.to_vec(),
+            geom_bodyid: to_usize(unsafe { wrappers::slice(model.geom_bodyid, ngeom) }),
+            actuator_trntype: unsafe { wrappers::slice(model.actuator_trntype, nu) }.to_vec(),
+            actuator_trnid: unsafe { wrappers::slice(model.actuator_trnid, 2 * nu) }
+                .chunks_exact(2)
+                .map(|ids| [ids[0], ids[1]])
+                .collect(),
+            sensor_adr: to_usize(unsafe { wrappers::slice(model.sensor_adr, nsensor) }),
+            sensor_dim: to_usize(unsafe { wrappers::slice(model.sensor_dim, nsensor) }),
+
+            up_axis: simulation.up_axis,
+        }
+    }
+
+    pub fn body_id(&self, name: &str) -> Option<usize> {
+        self.body_names.iter().position(|body| body == name)
+    }
+
+    pub fn joint_id(&self, name: &str) -> Option<usize> {
+        self.joint_names.iter().position(|joint| joint == name)
+    }
+
+    pub fn actuator_id(&self, name: &str) -> Option<usize> {
+        self.actuator_names
+            .iter()
+            .position(|actuator| actuator == name)
+    }
+
+    pub fn sensor_id(&self, name: &str) -> Option<usize> {
+        self.sensor_names.iter().position(|sensor| sensor == name)
+    }
+
+    pub fn site_id(&self, name: &str) -> Option<usize> {
+        self.site_names.iter().position(|site| site == name)
+    }
+
+    /// Range of a joint's values in `qpos`
+    pub fn qpos_range(&self, joint: usize) -> std::ops::Range<usize> {
+        let adr = self.jnt_qposadr[joint];
+        adr..adr + JointType::from_raw(self.jnt_type[joint]).nq()
+    }
+
+    /// Range of a joint's values in `qvel`
+    pub fn dof_range(&self, joint: usize) -> std::ops::Range<usize> {
+        let adr = self.jnt_dofadr[joint];
+        adr..adr + JointType::from_raw(self.jnt_type[joint]).nv()
+    }
+
+    /// Range of a sensor's values in `sensor_data`
+    pub fn sensor_range(&self, sensor: usize) -> std::ops::Range<usize> {
+        self.sensor_adr[sensor]..self.sensor_adr[sensor] + self.sensor_dim[sensor]
+    }
+
+    /// Joint driven by an actuator with a joint transmission
+    pub fn actuator_joint(&self, actuator: usize) -> Option<usize> {
+        (TransmissionType::from_raw(self.actuator_trntype[actuator]) == TransmissionType::JOINT)
+            .then(|| self.actuator_trnid[actuator][0] as usize)
+    }
+}
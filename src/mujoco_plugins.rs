@@ -0,0 +1,97 @@
+//! Engine plugins of MuJoCo 3 (elasticity, actuator, sensor and SDF plugins). MuJoCo ships them
+//! as shared libraries that have to be registered before a model using them compiles
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use bevy::prelude::*;
+
+/// Directories already scanned, plugins are registered once per process
+static LOADED_DIRECTORIES: Mutex<Option<HashSet<PathBuf>>> = Mutex::new(None);
+
+/// Directories searched for plugin libraries besides `MuJoCoPluginSettings::plugin_directories`:
+/// `MUJOCO_PLUGIN_DIR` and the `mujoco_plugin` directory of the MuJoCo install at `MUJOCO_DIR`
+pub fn default_plugin_directories() -> Vec<PathBuf> {
+    let mut directories = vec![];
+    if let Some(directory) = std::env::var_os("MUJOCO_PLUGIN_DIR") {
+        directories.push(PathBuf::from(directory));
+    }
+    let root = std::env::var_os("MUJOCO_DIR").or_else(|| std::env::var_os("MUJOCO_PREFIX"));
+    if let Some(root) = root.map(PathBuf::from) {
+        directories.extend(
+            ["bin", "lib"]
+                .iter()
+                .map(|subdirectory| root.join(subdirectory).join("mujoco_plugin")),
+        );
+    }
+    directories.retain(|directory| directory.is_dir());
+    directories
+}
+
+/// Register the plugin libraries found in each directory. Directories scanned before are
+/// skipped. Returns the number of plugins registered in total
+pub fn load_plugin_libraries<P: AsRef<Path>>(directories: &[P]) -> usize {
+    let mut loaded = LOADED_DIRECTORIES.lock().unwrap();
+    let loaded = loaded.get_or_insert_with(HashSet::new);
+    for directory in directories.iter().map(AsRef::as_ref) {
+        if !directory.is_dir() {
+            warn!("plugin directory {} not found", directory.display());
+            continue;
+        }
+        if loaded.insert(directory.to_path_buf()) {
+            ffi::load_all(directory);
+        }
+    }
+    ffi::plugin_count()
+}
+
+#[cfg(mujoco3)]
+mod ffi {
+    use std::ffi::{c_char, c_int, CStr, CString};
+    use std::path::Path;
+
+    use bevy::prelude::*;
+
+    type LoadCallback = unsafe extern "C" fn(filename: *const c_char, first: c_int, count: c_int);
+
+    extern "C" {
+        fn mj_loadAllPluginLibraries(directory: *const c_char, callback: Option<LoadCallback>);
+        fn mjp_pluginCount() -> c_int;
+    }
+
+    unsafe extern "C" fn log_library(filename: *const c_char, _first: c_int, count: c_int) {
+        let filename = CStr::from_ptr(filename).to_string_lossy();
+        info!("loaded {count} MuJoCo plugin(s) from {filename}");
+    }
+
+    pub(super) fn load_all(directory: &Path) {
+        let Ok(directory) = CString::new(directory.to_string_lossy().as_bytes()) else {
+            return;
+        };
+        unsafe { mj_loadAllPluginLibraries(directory.as_ptr(), Some(log_library)) };
+    }
+
+    pub(super) fn plugin_count() -> usize {
+        unsafe { mjp_pluginCount() as usize }
+    }
+}
+
+/// MuJoCo before 3.0 has no engine plugins
+#[cfg(not(mujoco3))]
+mod ffi {
+    use std::path::Path;
+
+    use bevy::prelude::*;
+
+    pub(super) fn load_all(directory: &Path) {
+        warn_once!(
+            "plugin directory {} ignored, engine plugins need the mujoco3 feature",
+            directory.display()
+        );
+    }
+
+    pub(super) fn plugin_count() -> usize {
+        0
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{wrappers, Backend};
+
+/// Corruption applied to every value of a sensor: `quantize(value + bias + N(0, std))`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SensorNoise {
+    /// Standard deviation of the Gaussian noise
+    pub std: f64,
+    /// Constant offset
+    pub bias: f64,
+    /// Resolution readings are rounded to, 0 disables quantization
+    pub quantization: f64,
+}
+
+impl SensorNoise {
+    pub fn corrupt(&self, value: f64, rng: &mut impl Rng) -> f64 {
+        let value = value + self.bias + gaussian(rng, self.std);
+        if self.quantization > 0.0 {
+            (value / self.quantization).round() * self.quantization
+        } else {
+            value
+        }
+    }
+}
+
+/// Noise applied to `MuJoCoState::sensor_data` every frame, keyed by sensor name.
+/// Sensors without an entry are published unchanged
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MuJoCoSensorNoise {
+    pub sensors: HashMap<String, SensorNoise>,
+}
+
+impl MuJoCoSensorNoise {
+    pub fn set(&mut self, sensor: &str, noise: SensorNoise) {
+        self.sensors.insert(sensor.to_string(), noise);
+    }
+
+    /// Corrupt the slices of `sensor_data` that belong to configured sensors
+    pub(crate) fn apply(&self, simulation: &impl Backend, sensor_data: &mut [f64]) {
+        if self.sensors.is_empty() {
+            return;
+        }
+
+        let model = wrappers::model(simulation);
+        let nsensor = model.nsensor as usize;
+        let sensor_adr = unsafe { wrappers::slice(model.sensor_adr, nsensor) };
+        let sensor_dim = unsafe { wrappers::slice(model.sensor_dim, nsensor) };
+        let mut rng = rand::thread_rng();
+
+        for (sensor, noise) in self.sensors.iter() {
+            let Some(id) = wrappers::name_to_id(model, model.name_sensoradr, model.nsensor, sensor)
+            else {
+                warn_once!("noisy sensor {} not found in the model", sensor);
+                continue;
+            };
+            let adr = sensor_adr[id] as usize;
+            let Some(values) = sensor_data.get_mut(adr..adr + sensor_dim[id] as usize) else {
+                continue;
+            };
+            for value in values {
+                *value = noise.corrupt(*value, &mut rng);
+            }
+        }
+    }
+}
+
+/// Sample from a zero-mean normal distribution (Box-Muller)
+pub(crate) fn gaussian(rng: &mut impl Rng, std: f64) -> f64 {
+    if std == 0.0 {
+        return 0.0;
+    }
+    let u1: f64 = rng.gen_range(f64::EPSILON..1.0);
+    let u2: f64 = rng.gen();
+    std * (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
@@ -0,0 +1,259 @@
+//! Observation vectors for learning: the terms of an `ObservationSpec` concatenated into a flat
+//! `Vec<f32>` after every simulation step, optionally normalized by running statistics
+
+use std::ops::{Bound, RangeBounds};
+
+use bevy::prelude::*;
+
+use crate::{MuJoCoModelInfo, MuJoCoResources, MuJoCoStateBuffer, MuJoCoStateSnapshot};
+
+type Slice = (Bound<usize>, Bound<usize>);
+
+/// One part of an observation
+#[derive(Debug, Clone, PartialEq)]
+pub enum ObservationTerm {
+    /// Slice of `MuJoCoState::qpos`
+    Qpos(Slice),
+    /// Slice of `MuJoCoState::qvel`
+    Qvel(Slice),
+    /// Slice of `MuJoCoState::act`
+    Act(Slice),
+    /// Slice of `MuJoCoState::actuator_force`
+    ActuatorForce(Slice),
+    /// Slice of `MuJoCoState::qfrc_actuator`
+    QfrcActuator(Slice),
+    /// Slice of `MuJoCoState::sensor_data`, with noise applied
+    SensorData(Slice),
+    /// All values of a sensor by name
+    Sensor(String),
+    /// World position and orientation quaternion (w, x, y, z) of a body by name, 7 values
+    /// in MuJoCo's frame
+    BodyPose(String),
+    /// 1.0 when any geom of a body by name touches something, 0.0 otherwise
+    BodyContact(String),
+}
+
+impl ObservationTerm {
+    /// Append the values of this term to `values`
+    fn observe(
+        &self,
+        model_info: &MuJoCoModelInfo,
+        snapshot: &MuJoCoStateSnapshot,
+        resources: &MuJoCoResources,
+        values: &mut Vec<f32>,
+    ) {
+        let state = &resources.state;
+
+        match self {
+            ObservationTerm::Qpos(slice) => extend(values, &state.qpos, *slice),
+            ObservationTerm::Qvel(slice) => extend(values, &state.qvel, *slice),
+            ObservationTerm::Act(slice) => extend(values, &state.act, *slice),
+            ObservationTerm::ActuatorForce(slice) => extend(values, &state.actuator_force, *slice),
+            ObservationTerm::QfrcActuator(slice) => extend(values, &state.qfrc_actuator, *slice),
+            ObservationTerm::SensorData(slice) => extend(values, &state.sensor_data, *slice),
+            ObservationTerm::Sensor(name) => {
+                let Some(id) = model_info.sensor_id(name) else {
+                    warn_once!("observed sensor {} not found in the model", name);
+                    return;
+                };
+                extend(
+                    values,
+                    &state.sensor_data,
+                    slice(model_info.sensor_range(id)),
+                );
+            }
+            ObservationTerm::BodyPose(name) => {
+                let Some(body) = model_info.body_id(name) else {
+                    warn_once!("observed body {} not found in the model", name);
+                    return;
+                };
+                let (Some(xpos), Some(xquat)) = (snapshot.xpos.get(body), snapshot.xquat.get(body))
+                else {
+                    return;
+                };
+                values.extend(xpos.iter().chain(xquat).map(|v| *v as f32));
+            }
+            ObservationTerm::BodyContact(name) => {
+                let Some(body) = model_info.body_id(name) else {
+                    warn_once!("observed body {} not found in the model", name);
+                    return;
+                };
+                let touching = snapshot.contact_geoms.iter().any(|geoms| {
+                    geoms
+                        .iter()
+                        .any(|geom| *geom >= 0 && model_info.geom_bodyid[*geom as usize] == body)
+                });
+                values.push(if touching { 1.0 } else { 0.0 });
+            }
+        }
+    }
+}
+
+fn extend(values: &mut Vec<f32>, source: &[f64], slice: Slice) {
+    match source.get(slice) {
+        Some(source) => values.extend(source.iter().map(|v| *v as f32)),
+        None => warn_once!(
+            "observed slice {:?} out of bounds of {} values",
+            slice,
+            source.len()
+        ),
+    }
+}
+
+fn slice(range: impl RangeBounds<usize>) -> Slice {
+    (range.start_bound().cloned(), range.end_bound().cloned())
+}
+
+/// Terms of the observation, in order. Insert it as a resource to have `MuJoCoObservation`
+/// published after every step, e.g.
+/// `ObservationSpec::default().qpos(7..).qvel(6..).body_contact("FR_calf").normalized()`
+#[derive(Resource, Debug, Clone, Default)]
+pub struct ObservationSpec {
+    pub terms: Vec<ObservationTerm>,
+    /// Normalize values by their running mean and standard deviation
+    pub normalize: bool,
+    /// Bound of normalized values
+    pub clip: f32,
+}
+
+impl ObservationSpec {
+    pub fn qpos(mut self, range: impl RangeBounds<usize>) -> Self {
+        self.terms.push(ObservationTerm::Qpos(slice(range)));
+        self
+    }
+
+    pub fn qvel(mut self, range: impl RangeBounds<usize>) -> Self {
+        self.terms.push(ObservationTerm::Qvel(slice(range)));
+        self
+    }
+
+    pub fn act(mut self, range: impl RangeBounds<usize>) -> Self {
+        self.terms.push(ObservationTerm::Act(slice(range)));
+        self
+    }
+
+    pub fn actuator_force(mut self, range: impl RangeBounds<usize>) -> Self {
+        self.terms
+            .push(ObservationTerm::ActuatorForce(slice(range)));
+        self
+    }
+
+    pub fn qfrc_actuator(mut self, range: impl RangeBounds<usize>) -> Self {
+        self.terms.push(ObservationTerm::QfrcActuator(slice(range)));
+        self
+    }
+
+    pub fn sensor_data(mut self, range: impl RangeBounds<usize>) -> Self {
+        self.terms.push(ObservationTerm::SensorData(slice(range)));
+        self
+    }
+
+    pub fn sensor(mut self, name: &str) -> Self {
+        self.terms.push(ObservationTerm::Sensor(name.to_string()));
+        self
+    }
+
+    pub fn body_pose(mut self, name: &str) -> Self {
+        self.terms.push(ObservationTerm::BodyPose(name.to_string()));
+        self
+    }
+
+    pub fn body_contact(mut self, name: &str) -> Self {
+        self.terms
+            .push(ObservationTerm::BodyContact(name.to_string()));
+        self
+    }
+
+    /// Normalize values by running statistics, clipped to ±5 standard deviations
+    pub fn normalized(mut self) -> Self {
+        self.normalize = true;
+        self.clip = 5.0;
+        self
+    }
+}
+
+/// Running mean and variance of every observation value (Welford's algorithm)
+#[derive(Debug, Clone, Default)]
+pub struct ObservationStatistics {
+    pub count: u64,
+    pub mean: Vec<f64>,
+    m2: Vec<f64>,
+}
+
+impl ObservationStatistics {
+    pub fn update(&mut self, values: &[f32]) {
+        if self.mean.len() != values.len() {
+            *self = ObservationStatistics {
+                count: 0,
+                mean: vec![0.0; values.len()],
+                m2: vec![0.0; values.len()],
+            };
+        }
+        self.count += 1;
+        let count = self.count as f64;
+        for ((mean, m2), value) in self.mean.iter_mut().zip(&mut self.m2).zip(values) {
+            let value = *value as f64;
+            let delta = value - *mean;
+            *mean += delta / count;
+            *m2 += delta * (value - *mean);
+        }
+    }
+
+    pub fn std(&self) -> Vec<f64> {
+        self.m2
+            .iter()
+            .map(|m2| (m2 / self.count.max(1) as f64).sqrt())
+            .collect()
+    }
+
+    pub fn normalize(&self, values: &[f32], clip: f32) -> Vec<f32> {
+        values
+            .iter()
+            .zip(self.mean.iter().zip(self.std()))
+            .map(|(value, (mean, std))| {
+                let value = ((*value as f64 - mean) / (std + 1e-8)) as f32;
+                if clip > 0.0 {
+                    value.clamp(-clip, clip)
+                } else {
+                    value
+                }
+            })
+            .collect()
+    }
+}
+
+/// Observation built from `ObservationSpec` after the latest step
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MuJoCoObservation {
+    /// Observation fed to the policy, normalized when the spec asks for it
+    pub values: Vec<f32>,
+    /// Observation before normalization
+    pub raw: Vec<f32>,
+    pub statistics: ObservationStatistics,
+    /// Keep `statistics` fixed, e.g. when evaluating a trained policy
+    pub freeze_statistics: bool,
+}
+
+pub(crate) fn observe(
+    spec: Res<ObservationSpec>,
+    model_info: Res<MuJoCoModelInfo>,
+    state_buffer: Res<MuJoCoStateBuffer>,
+    mujoco_resources: Res<MuJoCoResources>,
+    mut observation: ResMut<MuJoCoObservation>,
+) {
+    let snapshot = state_buffer.front();
+    let mut raw = Vec::new();
+    for term in spec.terms.iter() {
+        term.observe(&model_info, &snapshot, &mujoco_resources, &mut raw);
+    }
+
+    if spec.normalize {
+        if !observation.freeze_statistics {
+            observation.statistics.update(&raw);
+        }
+        observation.values = observation.statistics.normalize(&raw, spec.clip);
+    } else {
+        observation.values = raw.clone();
+    }
+    observation.raw = raw;
+}
@@ -0,0 +1,101 @@
+use bevy::prelude::*;
+
+use crate::{energy::ENABLE_ENERGY, wrappers, Backend, MuJoCoSimulation};
+
+/// Numerical integrator used by MuJoCo (`mjtIntegrator`)
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum Integrator {
+    /// `mjINT_EULER`
+    #[default]
+    Euler = 0,
+    /// `mjINT_RK4`
+    Rk4 = 1,
+    /// `mjINT_IMPLICIT`
+    Implicit = 2,
+    /// `mjINT_IMPLICITFAST`
+    ImplicitFast = 3,
+}
+
+impl Integrator {
+    const ALL: [Integrator; 4] = [
+        Integrator::Euler,
+        Integrator::Rk4,
+        Integrator::Implicit,
+        Integrator::ImplicitFast,
+    ];
+
+    /// Unknown values fall back to the default
+    fn from_raw(value: i32) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|integrator| integrator.to_raw() == value)
+            .unwrap_or_default()
+    }
+
+    fn to_raw(self) -> i32 {
+        self as i32
+    }
+}
+
+/// Physics options of the loaded model (`mjOption`).
+/// Vectors are in MuJoCo world coordinates (Z up). Modifying this resource writes
+/// the values back into the model before the next step
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct MuJoCoOptions {
+    pub timestep: f64,
+    pub gravity: [f64; 3],
+    pub wind: [f64; 3],
+    pub density: f64,
+    pub viscosity: f64,
+    pub integrator: Integrator,
+    pub iterations: i32,
+    /// Compute kinetic and potential energy every step (`mjENBL_ENERGY`), reported in
+    /// `MuJoCoState::energy`
+    pub energy: bool,
+}
+
+impl MuJoCoOptions {
+    /// Read options from a compiled model
+    pub(crate) fn from_simulation(simulation: &impl Backend) -> Self {
+        let opt = &wrappers::model(simulation).opt;
+        MuJoCoOptions {
+            timestep: opt.timestep,
+            gravity: opt.gravity,
+            wind: opt.wind,
+            density: opt.density,
+            viscosity: opt.viscosity,
+            integrator: Integrator::from_raw(opt.integrator),
+            iterations: opt.iterations,
+            energy: opt.enableflags & ENABLE_ENERGY != 0,
+        }
+    }
+
+    /// Write options into a compiled model
+    pub(crate) fn write(&self, simulation: &impl Backend) {
+        // the caller holds the simulation locked, no other reference to the model is in use
+        let opt = unsafe { &mut wrappers::model_mut(simulation).opt };
+        opt.timestep = self.timestep;
+        opt.gravity = self.gravity;
+        opt.wind = self.wind;
+        opt.density = self.density;
+        opt.viscosity = self.viscosity;
+        opt.integrator = self.integrator.to_raw();
+        opt.iterations = self.iterations;
+        if self.energy {
+            opt.enableflags |= ENABLE_ENERGY;
+        } else {
+            opt.enableflags &= !ENABLE_ENERGY;
+        }
+    }
+}
+
+/// Write changed `MuJoCoOptions` back into the model
+pub fn apply_mujoco_options(mujoco: Res<MuJoCoSimulation>, options: Res<MuJoCoOptions>) {
+    if !options.is_changed() {
+        return;
+    }
+
+    let mujoco = mujoco.lock().unwrap();
+    options.write(&mujoco);
+}
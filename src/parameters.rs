@@ -0,0 +1,176 @@
+use bevy::prelude::*;
+
+use crate::{wrappers, Backend, MuJoCoSimulation};
+
+/// Physical parameters of the loaded model that can be tuned while the simulation runs.
+/// Values are indexed by object id, use the setters to change them by name.
+/// Modifying this resource writes the values back into the model before the next step
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct MuJoCoPhysicalParameters {
+    /// Sliding, torsional and rolling friction per geom
+    pub geom_friction: Vec<[f64; 3]>,
+    /// Damping per degree of freedom
+    pub dof_damping: Vec<f64>,
+    /// Mass per body
+    pub body_mass: Vec<f64>,
+    /// Spring stiffness per joint
+    pub jnt_stiffness: Vec<f64>,
+    geom_names: Vec<String>,
+    body_names: Vec<String>,
+    joint_names: Vec<String>,
+    /// Range of dofs of every joint
+    joint_dofs: Vec<std::ops::Range<usize>>,
+}
+
+impl MuJoCoPhysicalParameters {
+    /// Read parameters from a compiled model
+    pub(crate) fn from_simulation(simulation: &impl Backend) -> Self {
+        let model = wrappers::model(simulation);
+        let (ngeom, nbody, njnt, nv) = (
+            model.ngeom as usize,
+            model.nbody as usize,
+            model.njnt as usize,
+            model.nv as usize,
+        );
+
+        let jnt_dofadr = unsafe { wrappers::slice(model.jnt_dofadr, njnt) };
+        let joint_dofs = (0..njnt)
+            .map(|joint| {
+                let start = jnt_dofadr[joint] as usize;
+                let end = jnt_dofadr.get(joint + 1).map_or(nv, |adr| *adr as usize);
+                start..end
+            })
+            .collect();
+
+        MuJoCoPhysicalParameters {
+            geom_friction: unsafe { wrappers::slice(model.geom_friction, 3 * ngeom) }
+                .chunks_exact(3)
+                .map(|f| [f[0], f[1], f[2]])
+                .collect(),
+            dof_damping: unsafe { wrappers::slice(model.dof_damping, nv) }.to_vec(),
+            body_mass: unsafe { wrappers::slice(model.body_mass, nbody) }.to_vec(),
+            jnt_stiffness: unsafe { wrappers::slice(model.jnt_stiffness, njnt) }.to_vec(),
+            geom_names: wrappers::names(model, model.name_geomadr, model.ngeom),
+            body_names: wrappers::names(model, model.name_bodyadr, model.nbody),
+            joint_names: wrappers::names(model, model.name_jntadr, model.njnt),
+            joint_dofs,
+        }
+    }
+
+    pub fn set_geom_friction(&mut self, geom: &str, friction: [f64; 3]) -> bool {
+        let Some(id) = self.geom_names.iter().position(|name| name == geom) else {
+            return false;
+        };
+        self.geom_friction[id] = friction;
+        true
+    }
+
+    /// Set the damping of every degree of freedom of a joint
+    pub fn set_joint_damping(&mut self, joint: &str, damping: f64) -> bool {
+        let Some(id) = self.joint_names.iter().position(|name| name == joint) else {
+            return false;
+        };
+        for dof in self.joint_dofs[id].clone() {
+            self.dof_damping[dof] = damping;
+        }
+        true
+    }
+
+    pub fn set_body_mass(&mut self, body: &str, mass: f64) -> bool {
+        let Some(id) = self.body_names.iter().position(|name| name == body) else {
+            return false;
+        };
+        self.body_mass[id] = mass;
+        true
+    }
+
+    pub fn set_joint_stiffness(&mut self, joint: &str, stiffness: f64) -> bool {
+        let Some(id) = self.joint_names.iter().position(|name| name == joint) else {
+            return false;
+        };
+        self.jnt_stiffness[id] = stiffness;
+        true
+    }
+
+    /// Write parameters into a compiled model. Derived constants depending on body
+    /// masses are recomputed with `mj_setConst` when a mass changed. Nothing is written when a
+    /// field doesn't have a value for every object of the model
+    pub(crate) fn write(&self, simulation: &impl Backend) -> Result<(), String> {
+        let model = wrappers::model(simulation);
+        let (ngeom, nbody, njnt, nv) = (
+            model.ngeom as usize,
+            model.nbody as usize,
+            model.njnt as usize,
+            model.nv as usize,
+        );
+        for (field, len, expected) in [
+            ("geom_friction", self.geom_friction.len(), ngeom),
+            ("dof_damping", self.dof_damping.len(), nv),
+            ("body_mass", self.body_mass.len(), nbody),
+            ("jnt_stiffness", self.jnt_stiffness.len(), njnt),
+        ] {
+            if len != expected {
+                return Err(format!(
+                    "{field} has {len} values, the model has {expected}"
+                ));
+            }
+        }
+
+        for (dst, src) in unsafe { wrappers::slice_mut(model.geom_friction, 3 * ngeom) }
+            .chunks_exact_mut(3)
+            .zip(self.geom_friction.iter())
+        {
+            dst.copy_from_slice(src);
+        }
+        unsafe { wrappers::slice_mut(model.dof_damping, nv).copy_from_slice(&self.dof_damping) };
+        unsafe {
+            wrappers::slice_mut(model.jnt_stiffness, njnt).copy_from_slice(&self.jnt_stiffness)
+        };
+
+        let body_mass = unsafe { wrappers::slice_mut(model.body_mass, nbody) };
+        if body_mass != self.body_mass.as_slice() {
+            body_mass.copy_from_slice(&self.body_mass);
+            set_const(simulation);
+        }
+        Ok(())
+    }
+}
+
+/// Recompute derived model constants with `mj_setConst`. It runs kinematics at `qpos0`
+/// in the simulation's data, so the state is saved and restored around the call
+pub(crate) fn set_const(simulation: &impl Backend) {
+    let model = wrappers::model(simulation);
+    let data = wrappers::data(simulation);
+    let (nq, nv) = (model.nq as usize, model.nv as usize);
+    let qpos = unsafe { wrappers::slice(data.qpos, nq) }.to_vec();
+    let qvel = unsafe { wrappers::slice(data.qvel, nv) }.to_vec();
+    let time = data.time;
+
+    unsafe {
+        crate::sys::no_render::mj_setConst(simulation.model_ptr(), simulation.data_ptr());
+    }
+
+    // the caller holds the simulation locked, no other reference to the data is in use
+    let data = unsafe { wrappers::data_mut(simulation) };
+    unsafe { wrappers::slice_mut(data.qpos, nq).copy_from_slice(&qpos) };
+    unsafe { wrappers::slice_mut(data.qvel, nv).copy_from_slice(&qvel) };
+    data.time = time;
+    unsafe {
+        crate::sys::no_render::mj_forward(simulation.model_ptr(), simulation.data_ptr());
+    }
+}
+
+/// Write changed `MuJoCoPhysicalParameters` back into the model
+pub fn apply_physical_parameters(
+    mujoco: Res<MuJoCoSimulation>,
+    parameters: Res<MuJoCoPhysicalParameters>,
+) {
+    if !parameters.is_changed() {
+        return;
+    }
+
+    let mujoco = mujoco.lock().unwrap();
+    if let Err(error) = parameters.write(&mujoco) {
+        warn!("physical parameters not applied: {error}");
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    ctrl_for_torque, wrappers, Backend, MuJoCoJointControl, MuJoCoResources, MuJoCoSimulation,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PdGains {
+    pub kp: f64,
+    pub kd: f64,
+}
+
+/// Joint-space PD controller. Every joint with an entry in `target_qpos` is driven
+/// towards its target with torque `kp * (target - q) - kd * qvel`, using the joint's
+/// gains from `gains` or `default_gains`. The torque is evaluated before every step
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MuJoCoPdController {
+    pub default_gains: PdGains,
+    pub gains: HashMap<String, PdGains>,
+    pub target_qpos: HashMap<String, f64>,
+}
+
+impl MuJoCoPdController {
+    pub fn new(kp: f64, kd: f64) -> Self {
+        MuJoCoPdController {
+            default_gains: PdGains { kp, kd },
+            ..default()
+        }
+    }
+
+    pub fn set_gains(&mut self, joint: &str, kp: f64, kd: f64) {
+        self.gains.insert(joint.to_string(), PdGains { kp, kd });
+    }
+
+    pub fn set_target(&mut self, joint: &str, target_qpos: f64) {
+        self.target_qpos.insert(joint.to_string(), target_qpos);
+    }
+
+    pub fn gains(&self, joint: &str) -> PdGains {
+        self.gains.get(joint).copied().unwrap_or(self.default_gains)
+    }
+}
+
+/// Compute PD torques from the qpos/qvel at the start of the frame and write them as actuator
+/// controls. `simulate_physics` evaluates them again before every step
+pub fn apply_pd_controller(
+    mujoco: Res<MuJoCoSimulation>,
+    pd_controller: Res<MuJoCoPdController>,
+    joint_control: Res<MuJoCoJointControl>,
+    mut mujoco_resources: ResMut<MuJoCoResources>,
+) {
+    if pd_controller.target_qpos.is_empty() {
+        return;
+    }
+
+    let mujoco = mujoco.lock().unwrap();
+    let control = &mut mujoco_resources.control;
+    control.data.resize(control.number_of_controls, 0.0);
+    pd_controller.write_controls(&mujoco, &joint_control, &mut control.data);
+}
+
+impl MuJoCoPdController {
+    /// Write the PD controls of the joints with a target into `ctrl`, which holds a value per
+    /// actuator
+    pub(crate) fn write_controls(
+        &self,
+        simulation: &impl Backend,
+        joint_control: &MuJoCoJointControl,
+        ctrl: &mut [f64],
+    ) {
+        let model = wrappers::model(simulation);
+        let data = wrappers::data(simulation);
+        let qpos = unsafe { wrappers::slice(data.qpos, model.nq as usize) };
+        let qvel = unsafe { wrappers::slice(data.qvel, model.nv as usize) };
+
+        for (joint, target) in self.target_qpos.iter() {
+            let Some(joint_actuator) = joint_control.actuators.get(joint) else {
+                continue;
+            };
+            let gains = self.gains(joint);
+            let torque = gains.kp * (target - qpos[joint_actuator.qpos_adr])
+                - gains.kd * qvel[joint_actuator.dof_adr];
+            ctrl[joint_actuator.actuator] =
+                ctrl_for_torque(simulation, joint_actuator.actuator, torque);
+        }
+    }
+}
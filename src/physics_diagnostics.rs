@@ -0,0 +1,120 @@
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+};
+
+use crate::{simulate_physics, wrappers, MuJoCoResources, MuJoCoSimulation, MuJoCoTime};
+
+// `mjtConstraint` values of `efc_type`
+const CNSTR_EQUALITY: i32 = 0;
+const CNSTR_FRICTION_DOF: i32 = 1;
+const CNSTR_FRICTION_TENDON: i32 = 2;
+
+/// Publish physics health to bevy's `DiagnosticsStore`, so diagnostic overlays and
+/// `LogDiagnosticsPlugin` can show it next to the frame time
+#[derive(Default)]
+pub struct MuJoCoDiagnosticsPlugin;
+
+impl MuJoCoDiagnosticsPlugin {
+    /// Simulation time in seconds
+    pub const SIM_TIME: DiagnosticPath = DiagnosticPath::const_new("mujoco/sim_time");
+    /// Simulated time advanced per wall-clock time of the frame
+    pub const REALTIME_FACTOR: DiagnosticPath = DiagnosticPath::const_new("mujoco/realtime_factor");
+    pub const STEPS_PER_FRAME: DiagnosticPath = DiagnosticPath::const_new("mujoco/steps_per_frame");
+    /// Wall time of one `mj_step` in milliseconds
+    pub const STEP_TIME: DiagnosticPath = DiagnosticPath::const_new("mujoco/step_time");
+    /// Number of contacts
+    pub const NCON: DiagnosticPath = DiagnosticPath::const_new("mujoco/ncon");
+    /// Number of scalar constraints
+    pub const NEFC: DiagnosticPath = DiagnosticPath::const_new("mujoco/nefc");
+    pub const SOLVER_ITERATIONS: DiagnosticPath =
+        DiagnosticPath::const_new("mujoco/solver_iterations");
+    /// Largest equality residual or penetration depth over all constraints
+    pub const CONSTRAINT_VIOLATION: DiagnosticPath =
+        DiagnosticPath::const_new("mujoco/constraint_violation");
+}
+
+impl Plugin for MuJoCoDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(Self::SIM_TIME).with_suffix("s"))
+            .register_diagnostic(Diagnostic::new(Self::REALTIME_FACTOR).with_suffix("x"))
+            .register_diagnostic(Diagnostic::new(Self::STEPS_PER_FRAME))
+            .register_diagnostic(Diagnostic::new(Self::STEP_TIME).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(Self::NCON))
+            .register_diagnostic(Diagnostic::new(Self::NEFC))
+            .register_diagnostic(Diagnostic::new(Self::SOLVER_ITERATIONS))
+            .register_diagnostic(Diagnostic::new(Self::CONSTRAINT_VIOLATION));
+        app.add_systems(
+            Update,
+            measure_physics
+                .after(simulate_physics)
+                .run_if(resource_exists::<MuJoCoResources>),
+        );
+    }
+}
+
+/// Solver iterations of the last step
+#[cfg(mujoco3)]
+pub(crate) fn solver_iterations(data: &crate::sys::mjData) -> i32 {
+    // one counter per constraint island, islands are solved one after another
+    data.solver_niter.iter().sum()
+}
+
+/// Solver iterations of the last step
+#[cfg(not(mujoco3))]
+pub(crate) fn solver_iterations(data: &crate::sys::mjData) -> i32 {
+    data.solver_iter
+}
+
+/// Largest equality residual or penetration over the active constraints
+pub(crate) fn constraint_violation(data: &crate::sys::mjData) -> f64 {
+    let nefc = data.nefc as usize;
+    let efc_type = unsafe { wrappers::slice(data.efc_type, nefc) };
+    let efc_pos = unsafe { wrappers::slice(data.efc_pos, nefc) };
+
+    efc_type
+        .iter()
+        .zip(efc_pos)
+        .map(|(efc_type, pos)| match *efc_type {
+            // residual
+            CNSTR_EQUALITY => pos.abs(),
+            // no position
+            CNSTR_FRICTION_DOF | CNSTR_FRICTION_TENDON => 0.0,
+            // limits and contacts: distance, negative when violated
+            _ => (-pos).max(0.0),
+        })
+        .fold(0.0, f64::max)
+}
+
+fn measure_physics(
+    mut diagnostics: Diagnostics,
+    mujoco: Res<MuJoCoSimulation>,
+    sim_time: Res<MuJoCoTime>,
+    time: Res<Time>,
+) {
+    let mujoco = mujoco.lock().unwrap();
+    let data = wrappers::data(&mujoco);
+
+    diagnostics.add_measurement(&MuJoCoDiagnosticsPlugin::SIM_TIME, || sim_time.elapsed);
+    diagnostics.add_measurement(&MuJoCoDiagnosticsPlugin::STEPS_PER_FRAME, || {
+        sim_time.steps as f64
+    });
+    if time.delta_secs_f64() > 0.0 {
+        diagnostics.add_measurement(&MuJoCoDiagnosticsPlugin::REALTIME_FACTOR, || {
+            sim_time.delta / time.delta_secs_f64()
+        });
+    }
+    if let Some(step_time) = sim_time.mean_step_time() {
+        diagnostics.add_measurement(&MuJoCoDiagnosticsPlugin::STEP_TIME, || {
+            step_time.as_secs_f64() * 1000.0
+        });
+    }
+    diagnostics.add_measurement(&MuJoCoDiagnosticsPlugin::NCON, || data.ncon as f64);
+    diagnostics.add_measurement(&MuJoCoDiagnosticsPlugin::NEFC, || data.nefc as f64);
+    diagnostics.add_measurement(&MuJoCoDiagnosticsPlugin::SOLVER_ITERATIONS, || {
+        solver_iterations(data) as f64
+    });
+    diagnostics.add_measurement(&MuJoCoDiagnosticsPlugin::CONSTRAINT_VIOLATION, || {
+        constraint_violation(data)
+    });
+}
@@ -0,0 +1,76 @@
+//! Multi-threaded stepping with a MuJoCo thread pool (MuJoCo 3.1+). The pool is bound to the
+//! simulation's `mjData` once and lives as long as the data
+
+#[cfg(mujoco31)]
+use crate::sys::{
+    mjENBL_ISLAND, mjThreadPool,
+    no_render::{mju_bindThreadPool, mju_threadPoolCreate, mju_threadPoolDestroy},
+};
+use crate::{Backend, MuJoCoSimulation};
+
+/// `mjThreadPool` bound to a simulation, destroyed after the data it was bound to
+#[cfg_attr(not(mujoco31), allow(dead_code))]
+pub(crate) struct ThreadPool {
+    #[cfg(mujoco31)]
+    pool: *mut mjThreadPool,
+    pub(crate) threads: usize,
+}
+
+// the pool is only used by MuJoCo from inside `mj_step`, which runs under the simulation lock
+unsafe impl Send for ThreadPool {}
+unsafe impl Sync for ThreadPool {}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        #[cfg(mujoco31)]
+        unsafe {
+            mju_threadPoolDestroy(self.pool)
+        };
+    }
+}
+
+/// Create a pool with `threads` workers and bind it to the simulation's data. Constraint
+/// islands are solved independently (`mjENBL_ISLAND`), which is what the pool parallelizes
+#[cfg(mujoco31)]
+fn bind(simulation: &impl Backend, threads: usize) -> Option<ThreadPool> {
+    let pool = unsafe { mju_threadPoolCreate(threads) };
+    if pool.is_null() {
+        return None;
+    }
+    unsafe { mju_bindThreadPool(simulation.data_ptr(), pool.cast()) };
+    // the caller holds the simulation locked, no other reference to the model is in use
+    unsafe { crate::wrappers::model_mut(simulation).opt.enableflags |= mjENBL_ISLAND as i32 };
+    Some(ThreadPool { pool, threads })
+}
+
+#[cfg(not(mujoco31))]
+fn bind(_simulation: &impl Backend, _threads: usize) -> Option<ThreadPool> {
+    bevy::log::warn_once!(
+        "physics threads need the mujoco3 feature and MuJoCo 3.1+, stepping single-threaded"
+    );
+    None
+}
+
+impl MuJoCoSimulation {
+    /// Step with `threads` worker threads. A pool can be bound to a simulation once, later
+    /// calls with a different count return `false`. 0 and 1 step on the calling thread
+    pub fn set_physics_threads(&self, threads: usize) -> bool {
+        let mut simulation = self.lock().unwrap();
+        match &simulation.thread_pool {
+            Some(pool) => return pool.threads == threads,
+            None if threads <= 1 => return true,
+            None => {}
+        }
+        simulation.thread_pool = bind(&simulation, threads);
+        simulation.thread_pool.is_some()
+    }
+
+    /// Worker threads used for stepping, 1 without a thread pool
+    pub fn physics_threads(&self) -> usize {
+        let simulation = self.lock().unwrap();
+        simulation
+            .thread_pool
+            .as_ref()
+            .map_or(1, |pool| pool.threads)
+    }
+}
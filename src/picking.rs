@@ -0,0 +1,197 @@
+//! Body selection with bevy's mesh picking: clicking a geom selects the body owning it,
+//! highlights the body's geoms and keeps `SelectedBody` up to date for inspector panels
+
+use bevy::{
+    picking::{
+        events::{Click, Pointer},
+        mesh_picking::MeshPickingPlugin,
+        pointer::PointerButton,
+    },
+    prelude::*,
+};
+
+use crate::{
+    MuJoCoBody, MuJoCoGeom, MuJoCoModelInfo, MuJoCoResources, MuJoCoSimulation, MuJoCoSystemSet,
+    SpatialVector,
+};
+
+/// Emissive color added to the materials of the selected body's geoms
+const HIGHLIGHT: LinearRgba = LinearRgba::rgb(0.6, 0.4, 0.0);
+
+/// Select bodies by clicking their geoms with the primary mouse button, clicking the background
+/// clears the selection
+pub struct MuJoCoPickingPlugin;
+
+impl Plugin for MuJoCoPickingPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<MeshPickingPlugin>() {
+            app.add_plugins(MeshPickingPlugin);
+        }
+        app.init_resource::<SelectedBody>();
+        app.add_observer(select_body);
+        app.add_systems(
+            Update,
+            (update_selected_body, highlight_selected_body)
+                .chain()
+                .after(MuJoCoSystemSet::Sync)
+                .run_if(resource_exists::<MuJoCoResources>),
+        );
+    }
+}
+
+/// The selected body, `None` when nothing is selected
+#[derive(Resource, Debug, Clone, Default)]
+pub struct SelectedBody(pub Option<BodyProperties>);
+
+/// Properties of a body, refreshed every frame while it's selected. Poses and velocities are in
+/// MuJoCo coordinates
+#[derive(Debug, Clone, Default)]
+pub struct BodyProperties {
+    pub entity: Option<Entity>,
+    pub id: usize,
+    pub name: String,
+    pub mass: f64,
+    /// Names of the joints attaching the body to its parent
+    pub joints: Vec<String>,
+    /// `qpos` and `qvel` entries of these joints
+    pub qpos: Vec<f64>,
+    pub qvel: Vec<f64>,
+    pub position: [f64; 3],
+    /// Orientation quaternion (w, x, y, z)
+    pub quaternion: [f64; 4],
+    pub velocity: SpatialVector,
+}
+
+impl SelectedBody {
+    /// Entity of the selected body
+    pub fn entity(&self) -> Option<Entity> {
+        self.0.as_ref().and_then(|body| body.entity)
+    }
+}
+
+/// Geom entity with its material swapped for a highlighted copy
+#[derive(Component)]
+struct Highlighted {
+    material: Handle<StandardMaterial>,
+}
+
+fn select_body(
+    mut trigger: Trigger<Pointer<Click>>,
+    mut selected: ResMut<SelectedBody>,
+    geoms: Query<&Parent, With<MuJoCoGeom>>,
+    bodies: Query<&MuJoCoBody>,
+    windows: Query<(), With<Window>>,
+) {
+    if trigger.event().event.button != PointerButton::Primary {
+        return;
+    }
+
+    let target = trigger.entity();
+    if windows.contains(target) {
+        selected.0 = None;
+        return;
+    }
+    let Ok(parent) = geoms.get(target) else {
+        return;
+    };
+    let Ok(body) = bodies.get(parent.get()) else {
+        return;
+    };
+    // the click bubbles up to the body and scene entities, handled here already
+    trigger.propagate(false);
+    selected.0 = Some(BodyProperties {
+        entity: Some(parent.get()),
+        id: body.id as usize,
+        ..default()
+    });
+}
+
+/// Fill in the properties of the selected body, clear the selection once its entity is gone
+fn update_selected_body(
+    mut selected: ResMut<SelectedBody>,
+    mujoco: Res<MuJoCoSimulation>,
+    model_info: Res<MuJoCoModelInfo>,
+    mujoco_resources: Res<MuJoCoResources>,
+    bodies: Query<(), With<MuJoCoBody>>,
+) {
+    let Some(entity) = selected.entity() else {
+        return;
+    };
+    if !bodies.contains(entity) {
+        selected.0 = None;
+        return;
+    }
+
+    let selected = selected.0.as_mut().unwrap();
+    let id = selected.id;
+    let joints: Vec<usize> = match model_info.body_jntadr[id] {
+        adr if adr < 0 => vec![],
+        adr => (adr as usize..adr as usize + model_info.body_jntnum[id]).collect(),
+    };
+    let state = &mujoco_resources.state;
+
+    selected.name.clone_from(&model_info.body_names[id]);
+    selected.mass = mujoco.body_mass(id).unwrap_or_default();
+    selected.joints = joints
+        .iter()
+        .map(|&joint| model_info.joint_names[joint].clone())
+        .collect();
+    selected.qpos = joints
+        .iter()
+        .flat_map(|&joint| state.qpos[model_info.qpos_range(joint)].iter().copied())
+        .collect();
+    selected.qvel = joints
+        .iter()
+        .flat_map(|&joint| state.qvel[model_info.dof_range(joint)].iter().copied())
+        .collect();
+    if let Some((position, quaternion)) = mujoco.body_pose(id) {
+        selected.position = position;
+        selected.quaternion = quaternion;
+    }
+    selected.velocity = mujoco.body_velocity(id).unwrap_or_default();
+}
+
+/// Swap the materials of the selected body's geoms for emissive copies, restore them when the
+/// selection changes
+fn highlight_selected_body(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    selected: Res<SelectedBody>,
+    mut highlighted: Local<Option<Entity>>,
+    children: Query<&Children>,
+    mut geoms: Query<
+        (&mut MeshMaterial3d<StandardMaterial>, Option<&Highlighted>),
+        With<MuJoCoGeom>,
+    >,
+) {
+    if *highlighted == selected.entity() {
+        return;
+    }
+
+    if let Some(body) = highlighted.take() {
+        for &child in children.get(body).into_iter().flatten() {
+            if let Ok((mut material, Some(original))) = geoms.get_mut(child) {
+                material.0 = original.material.clone();
+                commands.entity(child).remove::<Highlighted>();
+            }
+        }
+    }
+
+    let Some(body) = selected.entity() else {
+        return;
+    };
+    for &child in children.get(body).into_iter().flatten() {
+        let Ok((mut material, None)) = geoms.get_mut(child) else {
+            continue;
+        };
+        let Some(mut copy) = materials.get(&material.0).cloned() else {
+            continue;
+        };
+        copy.emissive = copy.emissive + HIGHLIGHT;
+        let original = std::mem::replace(&mut material.0, materials.add(copy));
+        commands
+            .entity(child)
+            .insert(Highlighted { material: original });
+    }
+    *highlighted = Some(body);
+}
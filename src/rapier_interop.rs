@@ -0,0 +1,126 @@
+//! Bridge to bevy_rapier for hybrid scenes: MuJoCo robots next to rapier-simulated props.
+//! MuJoCo bodies drive rapier kinematic bodies, and rapier bodies drive MuJoCo mocap bodies,
+//! both through the same pose conversions the plugin renders with
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::RigidBody;
+
+use crate::{
+    adapters::{pose_bevy_2_mujoco, pose_mujoco_2_bevy},
+    wrappers, MuJoCoModelInfo, MuJoCoResources, MuJoCoSceneRoot, MuJoCoSimulation,
+    MuJoCoStateBuffer, MuJoCoSystemSet,
+};
+
+/// Make this rapier body follow a MuJoCo body, e.g. so a robot's gripper pushes rapier props.
+/// The entity is a kinematic position-based rigid body; give it a `Collider` matching the
+/// body's geoms. Its `Transform` is set to the body's world pose, so spawn it without a parent
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+#[require(RigidBody(|| RigidBody::KinematicPositionBased))]
+pub struct MuJoCoToRapier {
+    pub body: String,
+}
+
+impl MuJoCoToRapier {
+    pub fn new(body: &str) -> Self {
+        MuJoCoToRapier {
+            body: body.to_string(),
+        }
+    }
+}
+
+/// Make a MuJoCo mocap body follow this rapier body, e.g. so a rapier-simulated crate collides
+/// with a MuJoCo robot. Mocap bodies are kinematic in MuJoCo: they push simulated bodies but
+/// aren't pushed back, the rapier body stays in charge of its own motion
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct RapierToMuJoCo {
+    /// Body declared with `mocap="true"` in the MJCF
+    pub body: String,
+}
+
+impl RapierToMuJoCo {
+    pub fn new(body: &str) -> Self {
+        RapierToMuJoCo {
+            body: body.to_string(),
+        }
+    }
+}
+
+/// Add `MuJoCoToRapier` and `RapierToMuJoCo` syncing. Add bevy_rapier's `RapierPhysicsPlugin`
+/// separately; rapier steps in `PostUpdate`, after both directions are synced in `Update`
+pub struct MuJoCoRapierPlugin;
+
+impl Plugin for MuJoCoRapierPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                rapier_to_mujoco.in_set(MuJoCoSystemSet::Control),
+                mujoco_to_rapier.after(MuJoCoSystemSet::Sync),
+            )
+                .run_if(resource_exists::<MuJoCoResources>),
+        );
+    }
+}
+
+/// Set kinematic rapier bodies to the world poses of their MuJoCo bodies after this frame's steps
+fn mujoco_to_rapier(
+    state_buffer: Res<MuJoCoStateBuffer>,
+    model_info: Res<MuJoCoModelInfo>,
+    scene_roots: Query<&GlobalTransform, With<MuJoCoSceneRoot>>,
+    mut followers: Query<(&MuJoCoToRapier, &mut Transform)>,
+) {
+    let snapshot = state_buffer.front();
+    if snapshot.xpos.is_empty() {
+        return;
+    }
+    let scene_transform = scene_roots.get_single().copied().unwrap_or_default();
+
+    for (follower, mut transform) in followers.iter_mut() {
+        let Some(body) = model_info.body_id(&follower.body) else {
+            warn_once!("MuJoCo body {} not found for rapier", follower.body);
+            continue;
+        };
+        let pose = pose_mujoco_2_bevy(
+            model_info.up_axis,
+            &snapshot.xpos[body],
+            &snapshot.xmat[body],
+        );
+        let pose = scene_transform.mul_transform(pose).compute_transform();
+        transform.translation = pose.translation;
+        transform.rotation = pose.rotation;
+    }
+}
+
+/// Move mocap bodies to the poses rapier gave their entities, relative to the scene root.
+/// Poses are the entities' `GlobalTransform`, propagated at the end of the previous frame
+fn rapier_to_mujoco(
+    mujoco: Res<MuJoCoSimulation>,
+    scene_roots: Query<&GlobalTransform, With<MuJoCoSceneRoot>>,
+    leaders: Query<(&RapierToMuJoCo, &GlobalTransform)>,
+) {
+    if leaders.is_empty() {
+        return;
+    }
+
+    let mujoco = mujoco.lock().unwrap();
+    let model = wrappers::model(&mujoco);
+    let (nbody, nmocap) = (model.nbody as usize, model.nmocap as usize);
+    let body_mocapid = unsafe { wrappers::slice(model.body_mocapid, nbody) };
+    let data = wrappers::data(&mujoco);
+    let mocap_pos = unsafe { wrappers::slice_mut(data.mocap_pos, 3 * nmocap) };
+    let mocap_quat = unsafe { wrappers::slice_mut(data.mocap_quat, 4 * nmocap) };
+    let scene_transform = scene_roots.get_single().copied().unwrap_or_default();
+
+    for (leader, transform) in leaders.iter() {
+        let Some(mocap) = wrappers::body_id(model, &leader.body)
+            .and_then(|body| usize::try_from(body_mocapid[body]).ok())
+        else {
+            warn_once!("{} isn't a MuJoCo mocap body", leader.body);
+            continue;
+        };
+        let (position, quat) =
+            pose_bevy_2_mujoco(mujoco.up_axis, &transform.reparented_to(&scene_transform));
+        mocap_pos[3 * mocap..3 * mocap + 3].copy_from_slice(&position);
+        mocap_quat[4 * mocap..4 * mocap + 4].copy_from_slice(&quat);
+    }
+}
@@ -0,0 +1,67 @@
+use bevy::prelude::*;
+
+use crate::{adapters::*, Backend, MuJoCoSimulation};
+
+/// Result of a ray cast into the MuJoCo world
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    pub geom_id: i32,
+    /// Distance from the ray origin to the hit point
+    pub distance: f32,
+    /// Hit point in the bevy scene frame
+    pub point: Vec3,
+}
+
+/// Cast a ray in MuJoCo coordinates, ignoring geoms of `exclude_body`
+pub(crate) fn cast_ray(
+    simulation: &impl Backend,
+    origin: [f64; 3],
+    direction: [f64; 3],
+    exclude_body: Option<usize>,
+) -> Option<(i32, f64)> {
+    let mut geom_id = -1;
+    let distance = unsafe {
+        crate::sys::no_render::mj_ray(
+            simulation.model_ptr(),
+            simulation.data_ptr(),
+            origin.as_ptr(),
+            direction.as_ptr(),
+            std::ptr::null(),
+            1,
+            exclude_body.map_or(-1, |body| body as i32),
+            &mut geom_id,
+        )
+    };
+
+    (geom_id >= 0 && distance >= 0.0).then_some((geom_id, distance))
+}
+
+impl MuJoCoSimulation {
+    /// Cast a ray against all geoms. `origin` and `direction` are in the bevy scene frame,
+    /// i.e. relative to the `MuJoCo::world` entity (or `MuJoCoSceneRoot`)
+    pub fn raycast(&self, origin: Vec3, direction: Vec3) -> Option<RayHit> {
+        self.raycast_excluding(origin, direction, None)
+    }
+
+    /// Cast a ray against all geoms except those of `exclude_body`
+    pub fn raycast_excluding(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        exclude_body: Option<usize>,
+    ) -> Option<RayHit> {
+        let direction = direction.try_normalize()?;
+        let simulation = self.lock().unwrap();
+        let (geom_id, distance) = cast_ray(
+            &simulation,
+            position_bevy_2_mujoco(simulation.up_axis, origin),
+            position_bevy_2_mujoco(simulation.up_axis, direction),
+            exclude_body,
+        )?;
+        Some(RayHit {
+            geom_id,
+            distance: distance as f32,
+            point: origin + direction * distance as f32,
+        })
+    }
+}
@@ -0,0 +1,118 @@
+//! Debug render modes switching every MuJoCo geom to wireframe or semi-transparent rendering,
+//! e.g. to check collision geoms against visual meshes
+
+use bevy::{
+    pbr::wireframe::{Wireframe, WireframePlugin},
+    prelude::*,
+};
+
+use crate::{MuJoCoGeom, MuJoCoResources};
+
+/// Opacity of geoms in `MuJoCoRenderMode::Ghost`
+const GHOST_ALPHA: f32 = 0.3;
+
+/// Add the `MuJoCoRenderMode` resource and render geoms accordingly. Wireframes need the
+/// `POLYGON_MODE_LINE` wgpu feature, see bevy's `WireframePlugin`
+pub struct MuJoCoRenderModePlugin;
+
+impl Plugin for MuJoCoRenderModePlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<WireframePlugin>() {
+            app.add_plugins(WireframePlugin);
+        }
+        app.init_resource::<MuJoCoRenderMode>();
+        app.add_systems(
+            Update,
+            apply_render_mode.run_if(resource_exists::<MuJoCoResources>),
+        );
+    }
+}
+
+/// How geoms are drawn, changeable at runtime. The materials a mode replaces are kept and
+/// restored when switching back to `Normal`
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MuJoCoRenderMode {
+    #[default]
+    Normal,
+    /// Edges only
+    Wireframe,
+    /// Semi-transparent materials, overlapping geoms show through each other
+    Ghost,
+}
+
+impl MuJoCoRenderMode {
+    /// Normal, wireframe, ghost, normal, ...
+    pub fn next(self) -> Self {
+        match self {
+            MuJoCoRenderMode::Normal => MuJoCoRenderMode::Wireframe,
+            MuJoCoRenderMode::Wireframe => MuJoCoRenderMode::Ghost,
+            MuJoCoRenderMode::Ghost => MuJoCoRenderMode::Normal,
+        }
+    }
+}
+
+/// Geom entity rendered in a debug mode, with the material to restore
+#[derive(Component)]
+struct RenderModeMaterial {
+    mode: MuJoCoRenderMode,
+    material: Handle<StandardMaterial>,
+}
+
+#[allow(clippy::type_complexity)]
+fn apply_render_mode(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mode: Res<MuJoCoRenderMode>,
+    added: Query<(), (With<MuJoCoGeom>, Added<MeshMaterial3d<StandardMaterial>>)>,
+    mut geoms: Query<
+        (
+            Entity,
+            &mut MeshMaterial3d<StandardMaterial>,
+            Option<&RenderModeMaterial>,
+        ),
+        With<MuJoCoGeom>,
+    >,
+) {
+    if !mode.is_changed() && added.is_empty() {
+        return;
+    }
+
+    let mode = *mode;
+    for (entity, mut material, current) in geoms.iter_mut() {
+        if current.map_or(MuJoCoRenderMode::Normal, |current| current.mode) == mode {
+            continue;
+        }
+        let original = match current {
+            Some(current) => current.material.clone(),
+            None => material.0.clone(),
+        };
+
+        let mut entity = commands.entity(entity);
+        let alpha = match mode {
+            MuJoCoRenderMode::Normal => {
+                material.0 = original;
+                entity.remove::<(RenderModeMaterial, Wireframe)>();
+                continue;
+            }
+            // the wireframe is drawn by its own pipeline, the surface is fully transparent
+            MuJoCoRenderMode::Wireframe => {
+                entity.insert(Wireframe);
+                0.0
+            }
+            MuJoCoRenderMode::Ghost => {
+                entity.remove::<Wireframe>();
+                GHOST_ALPHA
+            }
+        };
+        let Some(mut copy) = materials.get(&original).cloned() else {
+            continue;
+        };
+        copy.base_color = copy.base_color.with_alpha(alpha);
+        copy.alpha_mode = AlphaMode::Blend;
+        material.0 = materials.add(copy);
+        entity.insert(RenderModeMaterial {
+            mode,
+            material: original,
+        });
+    }
+}
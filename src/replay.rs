@@ -0,0 +1,344 @@
+//! Playback of recorded simulations without MuJoCo, e.g. in web builds with the `wasm`
+//! feature. Natively, `MuJoCoReplayRecorder` records the transforms of body entities into a
+//! `.mjreplay` file, and `MuJoCoSceneExporter::export_gltf` writes the scene's meshes once.
+//! `MuJoCoReplayPlugin` loads both through the asset server and moves the body nodes of the
+//! glTF scene
+
+use std::fmt::Write;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    gltf::GltfAssetLabel,
+    prelude::*,
+};
+
+#[cfg(native)]
+use crate::{MuJoCoBody, MuJoCoStateBuffer};
+
+/// Body transforms of one recorded frame, relative to their parent entities
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReplayFrame {
+    pub time: f64,
+    /// Translation and rotation of every body, scale is left as spawned
+    pub poses: Vec<Transform>,
+}
+
+/// A recorded simulation: the bodies of the scene and their transforms over time
+#[derive(Asset, TypePath, Debug, Clone, Default, PartialEq)]
+pub struct MuJoCoReplay {
+    /// Names of the body entities, `MuJoCo::body_<name>`
+    pub body_names: Vec<String>,
+    pub frames: Vec<ReplayFrame>,
+}
+
+impl MuJoCoReplay {
+    /// Seconds between the first and the last frame
+    pub fn duration(&self) -> f64 {
+        match (self.frames.first(), self.frames.last()) {
+            (Some(first), Some(last)) => last.time - first.time,
+            _ => 0.0,
+        }
+    }
+
+    /// Transforms of all bodies at a time relative to the first frame, interpolated between
+    /// frames
+    pub fn sample(&self, time: f64) -> Vec<Transform> {
+        let Some(start) = self.frames.first().map(|frame| frame.time) else {
+            return vec![];
+        };
+        let last = self.frames.len() - 1;
+        let next = self
+            .frames
+            .iter()
+            .position(|frame| frame.time - start > time);
+        let (a, b, s) = match next {
+            None => (last, last, 0.0),
+            Some(0) => (0, 0, 0.0),
+            Some(next) => {
+                let (t0, t1) = (
+                    self.frames[next - 1].time - start,
+                    self.frames[next].time - start,
+                );
+                (next - 1, next, ((time - t0) / (t1 - t0)) as f32)
+            }
+        };
+
+        self.frames[a]
+            .poses
+            .iter()
+            .zip(&self.frames[b].poses)
+            .map(|(a, b)| Transform {
+                translation: a.translation.lerp(b.translation, s),
+                rotation: a.rotation.slerp(b.rotation, s),
+                ..default()
+            })
+            .collect()
+    }
+
+    /// The `.mjreplay` text: a header, a `body <name>` line per body and a
+    /// `frame <time> <translation> <rotation xyzw> ...` line per frame
+    pub fn to_text(&self) -> String {
+        let mut text = String::from("mjreplay 1\n");
+        for name in self.body_names.iter() {
+            let _ = writeln!(text, "body {name}");
+        }
+        for frame in self.frames.iter() {
+            let _ = write!(text, "frame {}", frame.time);
+            for pose in frame.poses.iter() {
+                let values = pose.translation.to_array().into_iter();
+                for value in values.chain(pose.rotation.to_array()) {
+                    let _ = write!(text, " {value}");
+                }
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Read `.mjreplay` text written by `to_text`
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().enumerate();
+        if lines.next().map(|(_, line)| line.trim()) != Some("mjreplay 1") {
+            return Err("not a version 1 mjreplay file".to_string());
+        }
+
+        let mut replay = MuJoCoReplay::default();
+        for (number, line) in lines {
+            let error = |message: &str| format!("line {}: {message}", number + 1);
+            let (kind, rest) = line.split_once(' ').unwrap_or((line, ""));
+            match kind {
+                "body" => replay.body_names.push(rest.to_string()),
+                "frame" => {
+                    let (time, poses) = rest.split_once(' ').unwrap_or((rest, ""));
+                    let values = poses
+                        .split_whitespace()
+                        .map(str::parse)
+                        .collect::<Result<Vec<f32>, _>>()
+                        .map_err(|_| error("bad number"))?;
+                    if values.len() != 7 * replay.body_names.len() {
+                        return Err(error("frame doesn't have a pose for every body"));
+                    }
+                    replay.frames.push(ReplayFrame {
+                        time: time.parse().map_err(|_| error("bad time"))?,
+                        poses: values
+                            .chunks_exact(7)
+                            .map(|pose| Transform {
+                                translation: Vec3::from_slice(&pose[..3]),
+                                rotation: Quat::from_slice(&pose[3..]),
+                                ..default()
+                            })
+                            .collect(),
+                    });
+                }
+                "" => {}
+                _ => return Err(error("unknown line")),
+            }
+        }
+        Ok(replay)
+    }
+}
+
+/// Body transforms recorded every frame the simulation steps while `recording` is set,
+/// exported for `MuJoCoReplayPlugin`. Insert the resource to record
+#[cfg(native)]
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MuJoCoReplayRecorder {
+    pub recording: bool,
+    pub replay: MuJoCoReplay,
+    /// Version of the last recorded state snapshot
+    version: u64,
+}
+
+#[cfg(native)]
+impl MuJoCoReplayRecorder {
+    /// A recorder that starts recording right away
+    pub fn new() -> Self {
+        MuJoCoReplayRecorder {
+            recording: true,
+            ..default()
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.replay = MuJoCoReplay::default();
+    }
+
+    /// Write the recording as a `.mjreplay` file
+    pub fn export(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.replay.to_text())
+    }
+}
+
+/// Append the body transforms synced from a new state snapshot to `MuJoCoReplayRecorder`
+#[cfg(native)]
+pub(crate) fn record_replay(
+    mut recorder: ResMut<MuJoCoReplayRecorder>,
+    state_buffer: Res<MuJoCoStateBuffer>,
+    bodies: Query<(&MuJoCoBody, &Name, &Transform)>,
+) {
+    let (version, time) = {
+        let snapshot = state_buffer.front();
+        (snapshot.version, snapshot.time)
+    };
+    if !recorder.recording || version == recorder.version || bodies.is_empty() {
+        return;
+    }
+    recorder.version = version;
+
+    let mut bodies: Vec<_> = bodies.iter().collect();
+    bodies.sort_by_key(|(body, _, _)| body.id);
+    let replay = &mut recorder.replay;
+    if replay.body_names.is_empty() {
+        replay.body_names = bodies
+            .iter()
+            .map(|(_, name, _)| name.as_str().to_string())
+            .collect();
+    }
+    replay.frames.push(ReplayFrame {
+        time,
+        poses: bodies
+            .iter()
+            .map(|(_, _, transform)| Transform {
+                translation: transform.translation,
+                rotation: transform.rotation,
+                ..default()
+            })
+            .collect(),
+    });
+}
+
+#[derive(Default)]
+struct MuJoCoReplayLoader;
+
+impl AssetLoader for MuJoCoReplayLoader {
+    type Asset = MuJoCoReplay;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<MuJoCoReplay, Self::Error> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes).await?;
+        let text = String::from_utf8(bytes).map_err(std::io::Error::other)?;
+        MuJoCoReplay::parse(&text).map_err(std::io::Error::other)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["mjreplay"]
+    }
+}
+
+/// Spawn a scene exported with `MuJoCoSceneExporter::export_gltf` and play a recorded
+/// `.mjreplay` on it. Both are asset paths, relative to the `assets` folder, which web builds
+/// fetch from the server next to the page
+pub struct MuJoCoReplayPlugin {
+    pub scene_path: String,
+    pub replay_path: String,
+}
+
+impl Plugin for MuJoCoReplayPlugin {
+    fn build(&self, app: &mut App) {
+        let (scene_path, replay_path) = (self.scene_path.clone(), self.replay_path.clone());
+        app.init_asset::<MuJoCoReplay>()
+            .init_asset_loader::<MuJoCoReplayLoader>()
+            .add_systems(
+                Startup,
+                move |mut commands: Commands, asset_server: Res<AssetServer>| {
+                    commands.spawn((
+                        SceneRoot(
+                            asset_server
+                                .load(GltfAssetLabel::Scene(0).from_asset(scene_path.clone())),
+                        ),
+                        Name::new("MuJoCo::replay"),
+                    ));
+                    commands.insert_resource(MuJoCoReplayPlayer::new(
+                        asset_server.load(replay_path.clone()),
+                    ));
+                },
+            )
+            .add_systems(Update, play_replay);
+    }
+}
+
+/// Playback state of `MuJoCoReplayPlugin`
+#[derive(Resource, Debug, Clone)]
+pub struct MuJoCoReplayPlayer {
+    pub replay: Handle<MuJoCoReplay>,
+    /// Seconds since the first frame
+    pub time: f64,
+    pub speed: f64,
+    pub paused: bool,
+    /// Start over after the last frame, otherwise hold it
+    pub looping: bool,
+    /// Body entities of the scene in replay order, found once the scene is spawned
+    bodies: Vec<Option<Entity>>,
+}
+
+impl MuJoCoReplayPlayer {
+    pub fn new(replay: Handle<MuJoCoReplay>) -> Self {
+        MuJoCoReplayPlayer {
+            replay,
+            time: 0.0,
+            speed: 1.0,
+            paused: false,
+            looping: true,
+            bodies: vec![],
+        }
+    }
+}
+
+/// Advance the replay and set the transforms of the body entities
+fn play_replay(
+    time: Res<Time>,
+    replays: Res<Assets<MuJoCoReplay>>,
+    player: Option<ResMut<MuJoCoReplayPlayer>>,
+    names: Query<(Entity, &Name)>,
+    mut transforms: Query<&mut Transform>,
+) {
+    let Some(mut player) = player else {
+        return;
+    };
+    let Some(replay) = replays.get(&player.replay) else {
+        return;
+    };
+
+    if player.bodies.is_empty() {
+        let bodies: Vec<Option<Entity>> = replay
+            .body_names
+            .iter()
+            .map(|body| {
+                names
+                    .iter()
+                    .find_map(|(entity, name)| (name.as_str() == body).then_some(entity))
+            })
+            .collect();
+        // the scene isn't spawned yet
+        if bodies.iter().all(Option::is_none) {
+            return;
+        }
+        player.bodies = bodies;
+    }
+
+    if !player.paused {
+        player.time += time.delta_secs_f64() * player.speed;
+        let duration = replay.duration();
+        player.time = if player.looping && duration > 0.0 {
+            player.time.rem_euclid(duration)
+        } else {
+            player.time.clamp(0.0, duration)
+        };
+    }
+
+    let poses = replay.sample(player.time);
+    for (entity, pose) in player.bodies.iter().zip(poses) {
+        let Some(Ok(mut transform)) = entity.map(|entity| transforms.get_mut(entity)) else {
+            continue;
+        };
+        transform.translation = pose.translation;
+        transform.rotation = pose.rotation;
+    }
+}
@@ -0,0 +1,162 @@
+//! Logs the simulation to a rerun viewer: body poses, geom meshes, joint positions and contacts,
+//! all on the `sim_time` timeline in the MuJoCo world frame (Z up)
+
+use bevy::{prelude::*, render::mesh::VertexAttributeValues};
+
+use crate::{
+    adapters::*, simulate_physics, JointType, MuJoCoModelInfo, MuJoCoPluginSettings,
+    MuJoCoResources, MuJoCoSimulation, MuJoCoStateBuffer,
+};
+
+/// Log the simulation to a spawned rerun viewer
+pub struct MuJoCoRerunPlugin {
+    pub application_id: String,
+}
+
+impl Default for MuJoCoRerunPlugin {
+    fn default() -> Self {
+        MuJoCoRerunPlugin {
+            application_id: "bevy_mujoco".to_string(),
+        }
+    }
+}
+
+#[derive(Resource, Deref)]
+pub struct MuJoCoRerunRecording(pub rerun::RecordingStream);
+
+impl Plugin for MuJoCoRerunPlugin {
+    fn build(&self, app: &mut App) {
+        let recording =
+            match rerun::RecordingStreamBuilder::new(self.application_id.as_str()).spawn() {
+                Ok(recording) => recording,
+                Err(err) => {
+                    error!("can't start rerun recording: {err}");
+                    return;
+                }
+            };
+
+        app.insert_resource(MuJoCoRerunRecording(recording));
+        app.add_systems(
+            Update,
+            log_to_rerun
+                .after(simulate_physics)
+                .run_if(resource_exists::<MuJoCoResources>),
+        );
+    }
+}
+
+/// Log geom meshes once, relative to their body frames
+fn log_meshes(
+    recording: &rerun::RecordingStream,
+    mujoco_resources: &MuJoCoResources,
+    geom_transforms: &[Transform],
+    plane_size: f32,
+) {
+    let _ = recording.log_static("world", &rerun::ViewCoordinates::RIGHT_HAND_Z_UP);
+
+    for body in mujoco_resources.bodies.iter() {
+        let Some(geom) = body.render_geom(&mujoco_resources.geoms) else {
+            continue;
+        };
+        let Some(mesh) = geom_mesh(&geom, plane_size) else {
+            continue;
+        };
+        let Some(VertexAttributeValues::Float32x3(positions)) =
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            continue;
+        };
+        let Some(indices) = mesh.indices() else {
+            continue;
+        };
+        let triangles: Vec<[u32; 3]> = indices
+            .iter()
+            .map(|i| i as u32)
+            .collect::<Vec<u32>>()
+            .chunks_exact(3)
+            .map(|t| [t[0], t[1], t[2]])
+            .collect();
+
+        let path = format!("world/{}/{}", body.name, geom.name);
+        let transform = geom_transforms[geom.id as usize];
+        let _ = recording.log_static(
+            path.as_str(),
+            &rerun::Transform3D::from_translation_rotation(
+                transform.translation.to_array(),
+                rerun::Quaternion::from_xyzw(transform.rotation.to_array()),
+            ),
+        );
+        let _ = recording.log_static(
+            path.as_str(),
+            &rerun::Mesh3D::new(positions.clone())
+                .with_triangle_indices(triangles)
+                .with_albedo_factor(rerun::Rgba32::from_linear_unmultiplied_rgba_f32(
+                    geom.color[0],
+                    geom.color[1],
+                    geom.color[2],
+                    geom.color[3],
+                )),
+        );
+    }
+}
+
+fn log_to_rerun(
+    recording: Res<MuJoCoRerunRecording>,
+    mujoco: Res<MuJoCoSimulation>,
+    model_info: Res<MuJoCoModelInfo>,
+    state_buffer: Res<MuJoCoStateBuffer>,
+    mujoco_resources: Res<MuJoCoResources>,
+    settings: Res<MuJoCoPluginSettings>,
+    mut meshes_logged: Local<bool>,
+) {
+    if !*meshes_logged {
+        let geom_transforms = geom_transforms(&mujoco.lock().unwrap());
+        log_meshes(
+            &recording,
+            &mujoco_resources,
+            &geom_transforms,
+            settings.plane_size,
+        );
+        *meshes_logged = true;
+    }
+
+    let snapshot = state_buffer.front();
+    // nothing published yet
+    if snapshot.version == 0 {
+        return;
+    }
+    recording.set_time_seconds("sim_time", snapshot.time);
+
+    // body poses in world frame
+    for body in mujoco_resources.bodies.iter().filter(|body| body.id > 0) {
+        let i = body.id as usize;
+        let _ = recording.log(
+            format!("world/{}", body.name),
+            &rerun::Transform3D::from_translation_rotation(
+                snapshot.xpos[i].map(|x| x as f32),
+                rerun::Quaternion::from_wxyz(snapshot.xquat[i].map(|x| x as f32)),
+            ),
+        );
+    }
+
+    // scalar joints (hinge and slide)
+    for joint in
+        (0..model_info.njnt).filter(|&j| JointType::from_raw(model_info.jnt_type[j]).is_scalar())
+    {
+        let _ = recording.log(
+            format!("joints/{}", model_info.joint_names[joint]),
+            &rerun::Scalar::new(snapshot.qpos[model_info.jnt_qposadr[joint]]),
+        );
+    }
+
+    // contact points
+    let points: Vec<[f32; 3]> = snapshot
+        .contact_pos
+        .iter()
+        .map(|pos| pos.map(|x| x as f32))
+        .collect();
+    let _ = recording.log(
+        "world/contacts",
+        &rerun::Points3D::new(points).with_radii([0.01]),
+    );
+}
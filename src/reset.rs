@@ -0,0 +1,111 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    kinematics::teleport_body, noise::gaussian, wrappers, Backend, JointType, SimulationHandle,
+    UpAxis,
+};
+
+/// Random offset added to state values on reset
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ResetNoise {
+    #[default]
+    None,
+    /// Uniform in `[-half_width, half_width]`
+    Uniform(f64),
+    /// Zero-mean normal with a standard deviation
+    Gaussian(f64),
+}
+
+impl ResetNoise {
+    fn sample(&self, rng: &mut impl Rng) -> f64 {
+        match *self {
+            ResetNoise::None => 0.0,
+            ResetNoise::Uniform(half_width) if half_width > 0.0 => {
+                rng.gen_range(-half_width..=half_width)
+            }
+            ResetNoise::Uniform(_) => 0.0,
+            ResetNoise::Gaussian(std) => gaussian(rng, std),
+        }
+    }
+}
+
+/// Axis-aligned box in the bevy scene frame that a body is placed in, uniformly
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Region {
+    pub min: Vec3,
+    pub max: Vec3,
+    /// Rotate the body by a random angle around the vertical axis
+    pub random_yaw: bool,
+}
+
+impl Region {
+    fn sample(&self, up_axis: UpAxis, rng: &mut impl Rng) -> Transform {
+        let mut lerp = || rng.gen_range(0.0..=1.0);
+        let translation = self.min + (self.max - self.min) * Vec3::new(lerp(), lerp(), lerp());
+        let rotation = if self.random_yaw {
+            let vertical = up_axis.basis() * Vec3::Z;
+            Quat::from_axis_angle(vertical, rng.gen_range(0.0..std::f32::consts::TAU))
+        } else {
+            Quat::IDENTITY
+        };
+        Transform::from_translation(translation).with_rotation(rotation)
+    }
+}
+
+/// Initial-state randomization applied after every reset: the plugin's divergence and task
+/// resets and `MuJoCoSimulation::reset_with`
+#[derive(Resource, Debug, Clone, Default)]
+pub struct ResetOptions {
+    /// Added to the position of every hinge and slide joint, kept inside joint limits
+    pub qpos_noise: ResetNoise,
+    /// Added to every dof velocity
+    pub qvel_noise: ResetNoise,
+    /// Free or mocap bodies by name, placed in a region
+    pub free_body_placement: Vec<(String, Region)>,
+}
+
+impl ResetOptions {
+    pub(crate) fn apply(&self, simulation: &SimulationHandle) {
+        if self.qpos_noise == ResetNoise::None
+            && self.qvel_noise == ResetNoise::None
+            && self.free_body_placement.is_empty()
+        {
+            return;
+        }
+
+        let model = wrappers::model(simulation);
+        let data = wrappers::data(simulation);
+        let njnt = model.njnt as usize;
+        let mut rng = rand::thread_rng();
+
+        let jnt_type = unsafe { wrappers::slice(model.jnt_type, njnt) };
+        let jnt_limited = unsafe { wrappers::slice(model.jnt_limited, njnt) };
+        let jnt_range = unsafe { wrappers::slice(model.jnt_range, 2 * njnt) };
+        let jnt_qposadr = unsafe { wrappers::slice(model.jnt_qposadr, njnt) };
+        let qpos = unsafe { wrappers::slice_mut(data.qpos, model.nq as usize) };
+        for joint in (0..njnt).filter(|&j| JointType::from_raw(jnt_type[j]).is_scalar()) {
+            let q = &mut qpos[jnt_qposadr[joint] as usize];
+            *q += self.qpos_noise.sample(&mut rng);
+            if jnt_limited[joint] != 0 {
+                *q = q.clamp(jnt_range[2 * joint], jnt_range[2 * joint + 1]);
+            }
+        }
+        for qvel in unsafe { wrappers::slice_mut(data.qvel, model.nv as usize) } {
+            *qvel += self.qvel_noise.sample(&mut rng);
+        }
+
+        for (body, region) in self.free_body_placement.iter() {
+            let placed = wrappers::body_id(model, body).is_some_and(|id| {
+                teleport_body(simulation, id, &region.sample(simulation.up_axis, &mut rng))
+            });
+            if !placed {
+                warn_once!("can't place body {body} on reset, it isn't a free or mocap body");
+            }
+        }
+
+        unsafe {
+            crate::sys::no_render::mj_forward(simulation.model_ptr(), simulation.data_ptr());
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use crate::{body_dynamics::SpatialVector, wrappers, Backend, JointType, MuJoCoSimulation};
+
+/// A body moving freely in the world (its first joint is a free joint), e.g. a box in a stack
+/// or a domino, in MuJoCo coordinates. Its entity follows the same pose in the bevy scene
+#[derive(Debug, Clone, PartialEq)]
+pub struct FreeBody {
+    pub id: usize,
+    pub name: String,
+    pub position: [f64; 3],
+    /// w, x, y, z
+    pub quaternion: [f64; 4],
+    /// Velocity of the body frame origin in the world frame
+    pub velocity: SpatialVector,
+}
+
+/// Change the velocity of `body` as an impulse `linear` (N s) at `point` and `angular` (N m s)
+/// would, all in MuJoCo world coordinates. Bodies welded to the world don't move
+pub(crate) fn apply_impulse(
+    simulation: &impl Backend,
+    body: usize,
+    linear: [f64; 3],
+    angular: [f64; 3],
+    point: [f64; 3],
+) -> bool {
+    let model = wrappers::model(simulation);
+    if body == 0 || body >= model.nbody as usize {
+        return false;
+    }
+    let nv = model.nv as usize;
+
+    // dqvel = M^-1 J^T impulse, with the inertia factorized at the current state
+    let mut qfrc = vec![0.0; nv];
+    let mut dqvel = vec![0.0; nv];
+    unsafe {
+        crate::sys::no_render::mj_forward(simulation.model_ptr(), simulation.data_ptr());
+        crate::sys::no_render::mj_applyFT(
+            simulation.model_ptr(),
+            simulation.data_ptr(),
+            linear.as_ptr(),
+            angular.as_ptr(),
+            point.as_ptr(),
+            body as i32,
+            qfrc.as_mut_ptr(),
+        );
+        crate::sys::no_render::mj_solveM(
+            simulation.model_ptr(),
+            simulation.data_ptr(),
+            dqvel.as_mut_ptr(),
+            qfrc.as_ptr(),
+            1,
+        );
+    }
+
+    let data = wrappers::data(simulation);
+    for (qvel, dqvel) in unsafe { wrappers::slice_mut(data.qvel, nv) }
+        .iter_mut()
+        .zip(&dqvel)
+    {
+        *qvel += dqvel;
+    }
+    true
+}
+
+impl MuJoCoSimulation {
+    /// Bodies whose first joint is a free joint, with their poses and velocities. Bodies of
+    /// the free body pool (see `FREE_BODY_POOL_PREFIX`) are included, parked ones too
+    pub fn free_bodies(&self) -> Vec<FreeBody> {
+        let simulation = self.lock().unwrap();
+        let model = wrappers::model(&simulation);
+        let (nbody, njnt) = (model.nbody as usize, model.njnt as usize);
+        let body_names = wrappers::names(model, model.name_bodyadr, model.nbody);
+        let body_jntadr = unsafe { wrappers::slice(model.body_jntadr, nbody) };
+        let jnt_type = unsafe { wrappers::slice(model.jnt_type, njnt) };
+        let jnt_dofadr = unsafe { wrappers::slice(model.jnt_dofadr, njnt) };
+        let data = wrappers::data(&simulation);
+        let xpos = unsafe { wrappers::slice(data.xpos, 3 * nbody) };
+        let xquat = unsafe { wrappers::slice(data.xquat, 4 * nbody) };
+        let xmat = unsafe { wrappers::slice(data.xmat, 9 * nbody) };
+        let qvel = unsafe { wrappers::slice(data.qvel, model.nv as usize) };
+
+        (0..nbody)
+            .filter_map(|body| {
+                let joint = usize::try_from(body_jntadr[body]).ok()?;
+                if JointType::from_raw(jnt_type[joint]) != JointType::FREE {
+                    return None;
+                }
+                let dof = jnt_dofadr[joint] as usize;
+                // free joint velocities: linear in the world frame, angular in the body frame
+                let local = &qvel[dof + 3..dof + 6];
+                let xmat = &xmat[9 * body..9 * body + 9];
+                let angular = std::array::from_fn(|row| {
+                    (0..3).map(|col| xmat[3 * row + col] * local[col]).sum()
+                });
+                Some(FreeBody {
+                    id: body,
+                    name: body_names[body].clone(),
+                    position: [xpos[3 * body], xpos[3 * body + 1], xpos[3 * body + 2]],
+                    quaternion: [
+                        xquat[4 * body],
+                        xquat[4 * body + 1],
+                        xquat[4 * body + 2],
+                        xquat[4 * body + 3],
+                    ],
+                    velocity: SpatialVector {
+                        angular,
+                        linear: [qvel[dof], qvel[dof + 1], qvel[dof + 2]],
+                    },
+                })
+            })
+            .collect()
+    }
+
+    /// Apply a linear impulse (N s) at `point` to a body, in MuJoCo world coordinates. The
+    /// velocity changes right away, like a hit. Returns false for unknown bodies
+    pub fn apply_impulse(&self, body_id: usize, impulse: [f64; 3], point: [f64; 3]) -> bool {
+        let simulation = self.lock().unwrap();
+        apply_impulse(&simulation, body_id, impulse, [0.0; 3], point)
+    }
+
+    /// Apply an angular impulse (N m s) to a body, in MuJoCo world coordinates, e.g. to
+    /// spin it. Returns false for unknown bodies
+    pub fn apply_angular_impulse(&self, body_id: usize, impulse: [f64; 3]) -> bool {
+        let simulation = self.lock().unwrap();
+        let nbody = wrappers::model(&simulation).nbody as usize;
+        let Some(xipos) = unsafe { wrappers::slice(wrappers::data(&simulation).xipos, 3 * nbody) }
+            .get(3 * body_id..3 * body_id + 3)
+        else {
+            return false;
+        };
+        let point = [xipos[0], xipos[1], xipos[2]];
+        apply_impulse(&simulation, body_id, [0.0; 3], impulse, point)
+    }
+}
@@ -0,0 +1,192 @@
+//! ROS 2 bridge: publishes `sensor_msgs/JointState` on `/joint_states` and body poses on `/tf`,
+//! and applies `sensor_msgs/JointState` messages received on `/joint_commands`
+//! (positions become `MuJoCoPdController` targets, efforts become `MuJoCoJointControl` torques)
+
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use futures::{executor::LocalPool, task::LocalSpawnExt, StreamExt};
+use r2r::{
+    builtin_interfaces::msg::Time as RosTime,
+    geometry_msgs::msg::{Quaternion, Transform as RosTransform, TransformStamped, Vector3},
+    sensor_msgs::msg::JointState,
+    std_msgs::msg::Header,
+    tf2_msgs::msg::TFMessage,
+    Publisher, QosProfile,
+};
+
+use crate::{
+    apply_joint_control, simulate_physics, JointType, MuJoCoControlMode, MuJoCoJointControl,
+    MuJoCoModelInfo, MuJoCoPdController, MuJoCoResources, MuJoCoStateBuffer, MuJoCoSystemSet,
+};
+
+/// Bridge the simulation to ROS 2 under the given node name
+pub struct MuJoCoRos2Plugin {
+    pub node_name: String,
+    pub namespace: String,
+}
+
+impl Default for MuJoCoRos2Plugin {
+    fn default() -> Self {
+        MuJoCoRos2Plugin {
+            node_name: "bevy_mujoco".to_string(),
+            namespace: String::new(),
+        }
+    }
+}
+
+#[derive(Resource)]
+struct Ros2Bridge {
+    joint_states: Publisher<JointState>,
+    tf: Publisher<TFMessage>,
+    commands: Mutex<Receiver<JointState>>,
+}
+
+impl Plugin for MuJoCoRos2Plugin {
+    fn build(&self, app: &mut App) {
+        let bridge = match start_node(&self.node_name, &self.namespace) {
+            Ok(bridge) => bridge,
+            Err(err) => {
+                error!("can't start ROS 2 node {}: {err}", self.node_name);
+                return;
+            }
+        };
+
+        app.insert_resource(bridge);
+        app.add_systems(
+            Update,
+            (
+                // commands become PD targets and joint torques of this frame
+                receive_joint_commands
+                    .in_set(MuJoCoSystemSet::Control)
+                    .before(apply_joint_control),
+                publish_joint_states.after(simulate_physics),
+            )
+                .run_if(resource_exists::<MuJoCoResources>),
+        );
+    }
+}
+
+fn start_node(node_name: &str, namespace: &str) -> r2r::Result<Ros2Bridge> {
+    let context = r2r::Context::create()?;
+    let mut node = r2r::Node::create(context, node_name, namespace)?;
+
+    let joint_states =
+        node.create_publisher::<JointState>("/joint_states", QosProfile::default())?;
+    let tf = node.create_publisher::<TFMessage>("/tf", QosProfile::default())?;
+    let mut subscription =
+        node.subscribe::<JointState>("/joint_commands", QosProfile::default())?;
+
+    let (sender, receiver) = channel();
+    thread::spawn(move || {
+        let mut pool = LocalPool::new();
+        let spawned = pool.spawner().spawn_local(async move {
+            while let Some(command) = subscription.next().await {
+                if sender.send(command).is_err() {
+                    return;
+                }
+            }
+        });
+        if spawned.is_err() {
+            return;
+        }
+
+        loop {
+            node.spin_once(Duration::from_millis(10));
+            pool.run_until_stalled();
+        }
+    });
+
+    Ok(Ros2Bridge {
+        joint_states,
+        tf,
+        commands: Mutex::new(receiver),
+    })
+}
+
+fn ros_time(time: f64) -> RosTime {
+    RosTime {
+        sec: time.floor() as i32,
+        nanosec: (time.fract() * 1e9) as u32,
+    }
+}
+
+fn receive_joint_commands(
+    bridge: Res<Ros2Bridge>,
+    mut pd_controller: ResMut<MuJoCoPdController>,
+    mut joint_control: ResMut<MuJoCoJointControl>,
+) {
+    let commands = bridge.commands.lock().unwrap();
+    for command in commands.try_iter() {
+        for (i, joint) in command.name.iter().enumerate() {
+            if let Some(position) = command.position.get(i) {
+                pd_controller.set_target(joint, *position);
+            } else if let Some(effort) = command.effort.get(i) {
+                joint_control.set(joint, MuJoCoControlMode::Torque(*effort));
+            }
+        }
+    }
+}
+
+fn publish_joint_states(
+    bridge: Res<Ros2Bridge>,
+    model_info: Res<MuJoCoModelInfo>,
+    state_buffer: Res<MuJoCoStateBuffer>,
+) {
+    let snapshot = state_buffer.front();
+    // nothing published yet
+    if snapshot.version == 0 {
+        return;
+    }
+    let stamp = ros_time(snapshot.time);
+
+    let mut joint_state = JointState {
+        header: Header {
+            stamp: stamp.clone(),
+            frame_id: String::new(),
+        },
+        ..Default::default()
+    };
+    // hinge and slide joints have a single scalar position
+    for joint in
+        (0..model_info.njnt).filter(|&j| JointType::from_raw(model_info.jnt_type[j]).is_scalar())
+    {
+        let (q, v) = (model_info.jnt_qposadr[joint], model_info.jnt_dofadr[joint]);
+        joint_state.name.push(model_info.joint_names[joint].clone());
+        joint_state.position.push(snapshot.qpos[q]);
+        joint_state.velocity.push(snapshot.qvel[v]);
+        joint_state.effort.push(snapshot.qfrc_actuator[v]);
+    }
+    if let Err(err) = bridge.joint_states.publish(&joint_state) {
+        warn!("can't publish joint states: {err}");
+    }
+
+    // body poses in the MuJoCo world frame, which is Z up like ROS
+    let transforms = (1..model_info.nbody)
+        .map(|body| {
+            let ([x, y, z], [qw, qx, qy, qz]) = (snapshot.xpos[body], snapshot.xquat[body]);
+            TransformStamped {
+                header: Header {
+                    stamp: stamp.clone(),
+                    frame_id: "world".to_string(),
+                },
+                child_frame_id: model_info.body_names[body].clone(),
+                transform: RosTransform {
+                    translation: Vector3 { x, y, z },
+                    rotation: Quaternion {
+                        w: qw,
+                        x: qx,
+                        y: qy,
+                        z: qz,
+                    },
+                },
+            }
+        })
+        .collect();
+    if let Err(err) = bridge.tf.publish(&TFMessage { transforms }) {
+        warn!("can't publish tf: {err}");
+    }
+}
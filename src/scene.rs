@@ -0,0 +1,461 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use bevy::math::{DQuat, DVec3};
+use bevy::prelude::*;
+
+use crate::{adapters::*, resolve_asset_path, MjcfElement, MjcfError, UpAxis};
+
+/// Attributes holding the name of a model object, prefixed when composing scenes
+const NAME_ATTRIBUTES: &[&str] = &[
+    "name",
+    "class",
+    "childclass",
+    "body",
+    "body1",
+    "body2",
+    "joint",
+    "joint1",
+    "joint2",
+    "jointinparent",
+    "geom",
+    "geom1",
+    "geom2",
+    "site",
+    "sidesite",
+    "refsite",
+    "cranksite",
+    "slidersite",
+    "tendon",
+    "actuator",
+    "objname",
+    "refname",
+    "target",
+    "mesh",
+    "material",
+    "texture",
+    "hfield",
+    "skin",
+];
+
+/// Attributes of assets pointing to files
+const FILE_ATTRIBUTES: &[&str] = &[
+    "file",
+    "fileright",
+    "fileleft",
+    "fileup",
+    "filedown",
+    "filefront",
+    "fileback",
+];
+
+/// Assets MuJoCo names after their file when they have no `name`
+const FILE_NAMED_ASSETS: &[&str] = &["mesh", "texture", "hfield", "skin"];
+
+/// Sections copied from every model. Singleton sections (`compiler`, `option`, `size`,
+/// `visual`, `statistic`) are taken from the first model that has them
+const MERGED_SECTIONS: &[&str] = &[
+    "asset",
+    "worldbody",
+    "actuator",
+    "sensor",
+    "equality",
+    "contact",
+    "tendon",
+    "custom",
+];
+
+/// Elements that accept a `class` attribute outside of `worldbody`
+const CLASSED_ELEMENTS: &[&str] = &[
+    "mesh",
+    "material",
+    "motor",
+    "position",
+    "velocity",
+    "general",
+    "cylinder",
+    "muscle",
+    "damper",
+    "adhesion",
+    "intvelocity",
+    "weld",
+    "connect",
+    "joint",
+    "spatial",
+    "fixed",
+    "pair",
+];
+
+/// Top-level `worldbody` elements that accept a `class` (`childclass` for bodies)
+const WORLDBODY_CLASSED_ELEMENTS: &[&str] = &["body", "geom", "site", "camera", "light"];
+
+#[derive(Debug, Clone)]
+struct SceneModel {
+    path: String,
+    prefix: String,
+    pose: Transform,
+}
+
+/// Composes several MJCF models (robot, terrain, props) into a single scene.
+/// Names of every model are prefixed to keep them unique and the top-level elements of
+/// its `worldbody` are moved to the model's pose. Set it as `MuJoCoPluginSettings::scene`
+/// to load the merged scene through the plugin.
+///
+/// Keyframes are dropped since their sizes no longer match the merged model
+#[derive(Debug, Clone, Default)]
+pub struct SceneBuilder {
+    models: Vec<SceneModel>,
+    up_axis: UpAxis,
+}
+
+impl SceneBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a model file with a name prefix and a pose in the bevy scene frame
+    pub fn add(mut self, path: &str, prefix: &str, pose: Transform) -> Self {
+        self.models.push(SceneModel {
+            path: path.to_string(),
+            prefix: prefix.to_string(),
+            pose,
+        });
+        self
+    }
+
+    /// Up axis of the bevy scene the poses are given in. The plugin sets it to
+    /// `MuJoCoPluginSettings::up_axis`
+    pub fn up_axis(mut self, up_axis: UpAxis) -> Self {
+        self.up_axis = up_axis;
+        self
+    }
+
+    /// Merge the models into a single MJCF document. Relative model paths are resolved
+    /// like `MuJoCoPluginSettings::model_xml_path`
+    pub fn build(&self, assets_path: Option<&Path>) -> Result<MjcfElement, MjcfError> {
+        let mut scene = MjcfElement::new("mujoco").with_attribute("model", "scene");
+        let mut main_default = MjcfElement::new("default");
+
+        for model in self.models.iter() {
+            let path = resolve_asset_path(assets_path, &model.path);
+            let mut root = MjcfElement::load(&path)?;
+            let directory = path.parent().unwrap_or(Path::new("")).to_path_buf();
+
+            name_file_assets(&mut root);
+            absolutize_files(&mut root, &directory);
+            for child in root.children.iter_mut() {
+                prefix_names(child, &model.prefix);
+            }
+
+            let angles = AngleConvention::of(root.child("compiler"));
+            let main_class = format!("{}main", model.prefix);
+            let mut has_defaults = false;
+
+            for mut section in root.children {
+                match section.tag.as_str() {
+                    "compiler" => {
+                        for key in ["meshdir", "texturedir", "assetdir"] {
+                            section.remove_attribute(key);
+                        }
+                        match scene.child("compiler") {
+                            Some(compiler)
+                                if compiler.attribute("angle") != section.attribute("angle") =>
+                            {
+                                warn!("{} uses a different compiler angle unit than the rest of the scene", model.path);
+                            }
+                            Some(_) => {}
+                            None => scene.children.push(section),
+                        }
+                    }
+                    "option" | "size" | "visual" | "statistic" => {
+                        if scene.child(&section.tag).is_none() {
+                            scene.children.push(section);
+                        }
+                    }
+                    "default" => {
+                        has_defaults = true;
+                        section.set_attribute("class", &main_class);
+                        main_default.children.push(section);
+                    }
+                    "keyframe" => {
+                        warn!("keyframes of {} are dropped from the scene", model.path);
+                    }
+                    tag if MERGED_SECTIONS.contains(&tag) => {
+                        if tag == "worldbody" {
+                            place(&mut section, model.pose, self.up_axis, &angles).map_err(
+                                |message| MjcfError::Parse {
+                                    path: Some(path.clone()),
+                                    message,
+                                },
+                            )?;
+                        }
+                        if has_defaults {
+                            apply_main_class(&mut section, &main_class);
+                        }
+                        scene.child_mut(tag).children.extend(section.children);
+                    }
+                    tag => {
+                        warn!("<{tag}> of {} is not supported in scenes", model.path);
+                    }
+                }
+            }
+        }
+
+        if !main_default.children.is_empty() {
+            // defaults have to come before the elements using them
+            scene.children.insert(0, main_default);
+        }
+        Ok(scene)
+    }
+
+    /// Merge the models and write the scene to a file
+    pub fn write(&self, assets_path: Option<&Path>, path: &Path) -> Result<(), MjcfError> {
+        let xml = self.build(assets_path)?.to_xml();
+        std::fs::write(path, xml).map_err(|error| MjcfError::Io {
+            path: path.to_path_buf(),
+            error,
+        })
+    }
+}
+
+/// Scenes written by this process, to give each one its own file
+static SCENE_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// Where the plugin writes a merged scene before loading it, unique per process and load so
+/// apps and tests running at the same time don't overwrite each other's scenes
+pub(crate) fn scene_path() -> PathBuf {
+    let scene = SCENE_FILES.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!(
+        "bevy_mujoco_scene_{}_{scene}.xml",
+        std::process::id()
+    ))
+}
+
+fn prefix_names(element: &mut MjcfElement, prefix: &str) {
+    element.visit_mut(&mut |element| {
+        for (key, value) in element.attributes.iter_mut() {
+            if NAME_ATTRIBUTES.contains(&key.as_str()) && !value.is_empty() {
+                *value = format!("{prefix}{value}");
+            }
+        }
+    });
+}
+
+/// Name assets without a `name` after their file, as MuJoCo's compiler does, so that the
+/// prefixed references to them still match
+fn name_file_assets(root: &mut MjcfElement) {
+    for section in root
+        .children
+        .iter_mut()
+        .filter(|section| section.tag == "asset")
+    {
+        for asset in section.children.iter_mut() {
+            if !FILE_NAMED_ASSETS.contains(&asset.tag.as_str())
+                || asset.attribute("name").is_some_and(|name| !name.is_empty())
+            {
+                continue;
+            }
+            let stem = asset
+                .attribute("file")
+                .and_then(|file| Path::new(file).file_stem())
+                .map(|stem| stem.to_string_lossy().into_owned());
+            if let Some(stem) = stem {
+                asset.set_attribute("name", stem);
+            }
+        }
+    }
+}
+
+/// Make asset file paths absolute, honoring `compiler` asset directories
+fn absolutize_files(root: &mut MjcfElement, directory: &Path) {
+    let compiler = root.child("compiler");
+    let directory_of = |key: &str| {
+        let assetdir = compiler.and_then(|compiler| compiler.attribute("assetdir"));
+        let dir = compiler
+            .and_then(|compiler| compiler.attribute(key))
+            .or(assetdir)
+            .unwrap_or("");
+        directory.join(dir)
+    };
+    let (meshdir, texturedir) = (directory_of("meshdir"), directory_of("texturedir"));
+
+    root.visit_mut(&mut |element| {
+        let base = match element.tag.as_str() {
+            "mesh" | "skin" | "hfield" => &meshdir,
+            "texture" => &texturedir,
+            _ => return,
+        };
+        for (key, value) in element.attributes.iter_mut() {
+            if FILE_ATTRIBUTES.contains(&key.as_str()) {
+                *value = base.join(&*value).to_string_lossy().into_owned();
+            }
+        }
+    });
+}
+
+/// Make elements without a class use the model's own main default class
+fn apply_main_class(section: &mut MjcfElement, main_class: &str) {
+    let worldbody = section.tag == "worldbody";
+    for element in section.children.iter_mut() {
+        let key = if worldbody && element.tag == "body" {
+            "childclass"
+        } else {
+            "class"
+        };
+        let classes = if worldbody {
+            WORLDBODY_CLASSED_ELEMENTS
+        } else {
+            CLASSED_ELEMENTS
+        };
+        if classes.contains(&element.tag.as_str()) && element.attribute(key).is_none() {
+            element.set_attribute(key, main_class);
+        }
+    }
+}
+
+/// How a model's `compiler` interprets `euler` and `axisangle` orientations
+struct AngleConvention {
+    /// Angles are in degrees, MuJoCo's default
+    degrees: bool,
+    /// `eulerseq`, lowercase axes rotate with the frame, uppercase axes are fixed
+    euler_sequence: Vec<char>,
+}
+
+impl AngleConvention {
+    fn of(compiler: Option<&MjcfElement>) -> Self {
+        let attribute = |key| compiler.and_then(|compiler| compiler.attribute(key));
+        AngleConvention {
+            degrees: attribute("angle") != Some("radian"),
+            euler_sequence: attribute("eulerseq").unwrap_or("xyz").chars().collect(),
+        }
+    }
+
+    fn radians(&self, angle: f64) -> f64 {
+        if self.degrees {
+            angle.to_radians()
+        } else {
+            angle
+        }
+    }
+}
+
+/// Orientation of an element given with `quat`, `axisangle`, `euler`, `xyaxes` or `zaxis`,
+/// converted to a quaternion the way MuJoCo's compiler does. `None` for malformed values
+fn orientation(element: &MjcfElement, angles: &AngleConvention) -> Option<DQuat> {
+    let values = |key, len| element.numbers(key).filter(|v: &Vec<f64>| v.len() == len);
+
+    if let Some(v) = values("axisangle", 4) {
+        let axis = DVec3::new(v[0], v[1], v[2]).try_normalize()?;
+        return Some(DQuat::from_axis_angle(axis, angles.radians(v[3])));
+    }
+    if let Some(v) = values("euler", 3) {
+        if angles.euler_sequence.len() != 3 {
+            return None;
+        }
+        let mut quat = DQuat::IDENTITY;
+        for (key, angle) in angles.euler_sequence.iter().zip(v) {
+            let axis = match key.to_ascii_lowercase() {
+                'x' => DVec3::X,
+                'y' => DVec3::Y,
+                'z' => DVec3::Z,
+                _ => return None,
+            };
+            let rotation = DQuat::from_axis_angle(axis, angles.radians(angle));
+            // lowercase axes rotate with the frame, uppercase axes are fixed
+            quat = if key.is_ascii_lowercase() {
+                quat * rotation
+            } else {
+                rotation * quat
+            };
+        }
+        return Some(quat);
+    }
+    if let Some(v) = values("xyaxes", 6) {
+        let x = DVec3::new(v[0], v[1], v[2]).try_normalize()?;
+        let y = DVec3::new(v[3], v[4], v[5]);
+        let y = (y - x * x.dot(y)).try_normalize()?;
+        return Some(DQuat::from_mat3(&bevy::math::DMat3::from_cols(
+            x,
+            y,
+            x.cross(y),
+        )));
+    }
+    if let Some(v) = values("zaxis", 3) {
+        let z = DVec3::new(v[0], v[1], v[2]).try_normalize()?;
+        return Some(DQuat::from_rotation_arc(DVec3::Z, z));
+    }
+    let Some(v) = values("quat", 4) else {
+        return Some(DQuat::IDENTITY);
+    };
+    let quat = DQuat::from_xyzw(v[1], v[2], v[3], v[0]);
+    (quat.length_squared() > 0.0).then(|| quat.normalize())
+}
+
+/// Attributes an orientation can be given with, replaced by `quat` when an element is placed
+const ORIENTATION_ATTRIBUTES: &[&str] = &["quat", "axisangle", "euler", "xyaxes", "zaxis"];
+
+/// Move the top-level elements of a `worldbody` to a pose in the bevy scene frame.
+/// Orientations are rewritten as `quat`, malformed ones are reported as errors
+fn place(
+    worldbody: &mut MjcfElement,
+    pose: Transform,
+    up_axis: UpAxis,
+    angles: &AngleConvention,
+) -> Result<(), String> {
+    let translation = DVec3::from_array(position_bevy_2_mujoco(up_axis, pose.translation));
+    let basis = up_axis.basis();
+    let rotation = (basis.inverse() * pose.rotation * basis).as_dquat();
+    if translation == DVec3::ZERO && rotation == DQuat::IDENTITY {
+        return Ok(());
+    }
+
+    let transform = |point: DVec3| translation + rotation * point;
+    for element in worldbody.children.iter_mut() {
+        if let Some(fromto) = element.numbers("fromto").filter(|v| v.len() == 6) {
+            let from = transform(DVec3::new(fromto[0], fromto[1], fromto[2]));
+            let to = transform(DVec3::new(fromto[3], fromto[4], fromto[5]));
+            element.set_attribute(
+                "fromto",
+                format_numbers(&[from.to_array(), to.to_array()].concat()),
+            );
+            continue;
+        }
+
+        let pos = element
+            .numbers("pos")
+            .filter(|v| v.len() == 3)
+            .map_or(DVec3::ZERO, |v| DVec3::new(v[0], v[1], v[2]));
+        element.set_attribute("pos", format_numbers(&transform(pos).to_array()));
+
+        if element.tag == "light" {
+            let dir = element
+                .numbers("dir")
+                .filter(|v| v.len() == 3)
+                .map_or(DVec3::NEG_Z, |v| DVec3::new(v[0], v[1], v[2]));
+            element.set_attribute("dir", format_numbers(&(rotation * dir).to_array()));
+            continue;
+        }
+
+        let Some(quat) = orientation(element, angles) else {
+            return Err(format!(
+                "malformed orientation of <{}> {}",
+                element.tag,
+                element.attribute("name").unwrap_or_default()
+            ));
+        };
+        let quat = rotation * quat;
+        for key in ORIENTATION_ATTRIBUTES {
+            element.remove_attribute(key);
+        }
+        element.set_attribute("quat", format_numbers(&[quat.w, quat.x, quat.y, quat.z]));
+    }
+    Ok(())
+}
+
+fn format_numbers(values: &[f64]) -> String {
+    values
+        .iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
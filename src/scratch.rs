@@ -0,0 +1,151 @@
+use bevy::prelude::*;
+
+use crate::{wrappers, Backend, MuJoCoSimulation};
+
+/// Copy as much of `src` as fits into `out`, returning the number of values copied
+fn copy_into<T: Copy>(src: &[T], out: &mut [T]) -> usize {
+    let len = src.len().min(out.len());
+    out[..len].copy_from_slice(&src[..len]);
+    len
+}
+
+pub(crate) fn copy_qpos_into(simulation: &impl Backend, out: &mut [f64]) -> usize {
+    let model = wrappers::model(simulation);
+    copy_into(
+        unsafe { wrappers::slice(wrappers::data(simulation).qpos, model.nq as usize) },
+        out,
+    )
+}
+
+pub(crate) fn copy_qvel_into(simulation: &impl Backend, out: &mut [f64]) -> usize {
+    let model = wrappers::model(simulation);
+    copy_into(
+        unsafe { wrappers::slice(wrappers::data(simulation).qvel, model.nv as usize) },
+        out,
+    )
+}
+
+pub(crate) fn copy_sensordata_into(simulation: &impl Backend, out: &mut [f64]) -> usize {
+    let model = wrappers::model(simulation);
+    let data = wrappers::data(simulation);
+    copy_into(
+        unsafe { wrappers::slice(data.sensordata, model.nsensordata as usize) },
+        out,
+    )
+}
+
+pub(crate) fn copy_cfrc_ext_into(simulation: &impl Backend, out: &mut [[f64; 6]]) -> usize {
+    let model = wrappers::model(simulation);
+    let cfrc_ext = unsafe {
+        wrappers::slice(
+            wrappers::data(simulation).cfrc_ext,
+            6 * model.nbody as usize,
+        )
+    };
+    let mut copied = 0;
+    for (dst, src) in out.iter_mut().zip(cfrc_ext.chunks_exact(6)) {
+        dst.copy_from_slice(src);
+        copied += 1;
+    }
+    copied
+}
+
+pub(crate) fn copy_act_into(simulation: &impl Backend, out: &mut [f64]) -> usize {
+    let model = wrappers::model(simulation);
+    copy_into(
+        unsafe { wrappers::slice(wrappers::data(simulation).act, model.na as usize) },
+        out,
+    )
+}
+
+pub(crate) fn copy_actuator_force_into(simulation: &impl Backend, out: &mut [f64]) -> usize {
+    let model = wrappers::model(simulation);
+    copy_into(
+        unsafe { wrappers::slice(wrappers::data(simulation).actuator_force, model.nu as usize) },
+        out,
+    )
+}
+
+pub(crate) fn copy_qfrc_actuator_into(simulation: &impl Backend, out: &mut [f64]) -> usize {
+    let model = wrappers::model(simulation);
+    copy_into(
+        unsafe { wrappers::slice(wrappers::data(simulation).qfrc_actuator, model.nv as usize) },
+        out,
+    )
+}
+
+impl MuJoCoSimulation {
+    /// Copy `qpos` into a caller-owned buffer without allocating, returns the number of values copied
+    pub fn copy_qpos_into(&self, out: &mut [f64]) -> usize {
+        copy_qpos_into(&self.lock().unwrap(), out)
+    }
+
+    /// Copy `qvel` into a caller-owned buffer without allocating, returns the number of values copied
+    pub fn copy_qvel_into(&self, out: &mut [f64]) -> usize {
+        copy_qvel_into(&self.lock().unwrap(), out)
+    }
+
+    /// Copy `sensordata` into a caller-owned buffer without allocating, returns the number of values copied
+    pub fn copy_sensordata_into(&self, out: &mut [f64]) -> usize {
+        copy_sensordata_into(&self.lock().unwrap(), out)
+    }
+
+    /// Copy `cfrc_ext` into a caller-owned buffer without allocating, returns the number of bodies copied
+    pub fn copy_cfrc_ext_into(&self, out: &mut [[f64; 6]]) -> usize {
+        copy_cfrc_ext_into(&self.lock().unwrap(), out)
+    }
+
+    /// Copy actuator activations `act` into a caller-owned buffer without allocating, returns the number of values copied
+    pub fn copy_act_into(&self, out: &mut [f64]) -> usize {
+        copy_act_into(&self.lock().unwrap(), out)
+    }
+
+    /// Copy `actuator_force` into a caller-owned buffer without allocating, returns the number of values copied
+    pub fn copy_actuator_force_into(&self, out: &mut [f64]) -> usize {
+        copy_actuator_force_into(&self.lock().unwrap(), out)
+    }
+
+    /// Copy `qfrc_actuator` into a caller-owned buffer without allocating, returns the number of values copied
+    pub fn copy_qfrc_actuator_into(&self, out: &mut [f64]) -> usize {
+        copy_qfrc_actuator_into(&self.lock().unwrap(), out)
+    }
+}
+
+/// Buffers sized for the loaded model that `simulate_physics` extracts the state into,
+/// so no allocations happen in steady state
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MuJoCoStateScratch {
+    pub qpos: Vec<f64>,
+    pub qvel: Vec<f64>,
+    pub sensor_data: Vec<f64>,
+    pub cfrc_ext: Vec<[f64; 6]>,
+    pub act: Vec<f64>,
+    pub actuator_force: Vec<f64>,
+    pub qfrc_actuator: Vec<f64>,
+}
+
+impl MuJoCoStateScratch {
+    pub(crate) fn from_simulation(simulation: &impl Backend) -> Self {
+        let model = wrappers::model(simulation);
+        MuJoCoStateScratch {
+            qpos: vec![0.0; model.nq as usize],
+            qvel: vec![0.0; model.nv as usize],
+            sensor_data: vec![0.0; model.nsensordata as usize],
+            cfrc_ext: vec![[0.0; 6]; model.nbody as usize],
+            act: vec![0.0; model.na as usize],
+            actuator_force: vec![0.0; model.nu as usize],
+            qfrc_actuator: vec![0.0; model.nv as usize],
+        }
+    }
+
+    /// Copy the current state into the buffers
+    pub(crate) fn extract(&mut self, simulation: &impl Backend) {
+        copy_qpos_into(simulation, &mut self.qpos);
+        copy_qvel_into(simulation, &mut self.qvel);
+        copy_sensordata_into(simulation, &mut self.sensor_data);
+        copy_cfrc_ext_into(simulation, &mut self.cfrc_ext);
+        copy_act_into(simulation, &mut self.act);
+        copy_actuator_force_into(simulation, &mut self.actuator_force);
+        copy_qfrc_actuator_into(simulation, &mut self.qfrc_actuator);
+    }
+}
@@ -0,0 +1,67 @@
+use std::sync::{Arc, Mutex};
+
+use arc_swap::ArcSwap;
+use bevy::prelude::*;
+
+/// Copy of the simulation state after a frame's steps, in MuJoCo coordinates
+#[derive(Debug, Clone, Default)]
+pub struct MuJoCoStateSnapshot {
+    /// Incremented on every publish
+    pub version: u64,
+    pub time: f64,
+    pub qpos: Vec<f64>,
+    pub qvel: Vec<f64>,
+    pub sensor_data: Vec<f64>,
+    pub cfrc_ext: Vec<[f64; 6]>,
+    pub act: Vec<f64>,
+    pub actuator_force: Vec<f64>,
+    pub qfrc_actuator: Vec<f64>,
+    /// Body positions in the world frame
+    pub xpos: Vec<[f64; 3]>,
+    /// Body orientations in the world frame, row-major rotation matrices
+    pub xmat: Vec<[f64; 9]>,
+    /// Body orientations in the world frame, quaternions (w, x, y, z)
+    pub xquat: Vec<[f64; 4]>,
+    /// Geoms of every active contact, -1 for flex contacts
+    pub contact_geoms: Vec<[i32; 2]>,
+    /// Positions of the active contacts in the world frame
+    pub contact_pos: Vec<[f64; 3]>,
+    /// Geom positions in the world frame, empty unless `MuJoCoPluginSettings::track_geoms`
+    pub geom_xpos: Vec<[f64; 3]>,
+    /// Geom orientations in the world frame, empty unless `MuJoCoPluginSettings::track_geoms`
+    pub geom_xmat: Vec<[f64; 9]>,
+}
+
+#[derive(Debug, Default)]
+struct StateBuffers {
+    front: ArcSwap<MuJoCoStateSnapshot>,
+    /// Previous front snapshot, refilled by the next publish. Only the writer locks it
+    back: Mutex<Arc<MuJoCoStateSnapshot>>,
+}
+
+/// Double-buffered state snapshots. `simulate_physics` fills the back snapshot and swaps it to
+/// the front after stepping, so readers (transform sync, UI, telemetry threads) never lock the
+/// simulation itself. Neither side blocks the other: readers take the front snapshot with an
+/// atomic load, the writer swaps it atomically. Clones share the same buffers and can be moved
+/// to other threads
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MuJoCoStateBuffer(Arc<StateBuffers>);
+
+impl MuJoCoStateBuffer {
+    /// Latest published snapshot. It can be kept as long as needed, later publishes don't
+    /// change it
+    pub fn front(&self) -> Arc<MuJoCoStateSnapshot> {
+        self.0.front.load_full()
+    }
+
+    /// Fill the back snapshot and make it the front one. The back snapshot's allocations are
+    /// reused unless a reader still holds it, then it is copied
+    pub(crate) fn publish(&self, fill: impl FnOnce(&mut MuJoCoStateSnapshot)) {
+        let version = self.0.front.load().version;
+        let mut back = self.0.back.lock().unwrap();
+        let snapshot = Arc::make_mut(&mut back);
+        fill(snapshot);
+        snapshot.version = version + 1;
+        *back = self.0.front.swap(std::mem::take(&mut *back));
+    }
+}
@@ -0,0 +1,28 @@
+use bevy::prelude::*;
+
+use crate::{adapters::rotation_from_xmat, UpAxis};
+
+/// Body poses converted to `f32` and to the bevy scene frame in bulk after every frame.
+/// Optional: when the app inits this resource `simulate_physics` fills it straight from
+/// `mjData` and transform sync reads it instead of converting the f64 state per body
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MuJoCoStateF32 {
+    pub positions: Vec<Vec3>,
+    pub rotations: Vec<Quat>,
+}
+
+impl MuJoCoStateF32 {
+    /// Convert `xpos` (x y z) and row-major `xmat` arrays
+    pub(crate) fn fill(&mut self, up_axis: UpAxis, xpos: &[f64], xmat: &[f64]) {
+        let basis = up_axis.basis();
+        self.positions.clear();
+        self.positions.extend(
+            xpos.chunks_exact(3)
+                .map(|p| basis.mul_vec3(Vec3::new(p[0] as f32, p[1] as f32, p[2] as f32))),
+        );
+
+        self.rotations.clear();
+        self.rotations
+            .extend(xmat.chunks_exact(9).map(|m| basis * rotation_from_xmat(m)));
+    }
+}
@@ -0,0 +1,88 @@
+//! Controllers running inside every `mj_step` through MuJoCo's global `mjcb_control` hook.
+//! Closures are registered per simulation, keyed by its `mjData`, so other simulations
+//! (e.g. batch envs) stepping in the same process aren't affected. Each closure has its own
+//! lock, simulations stepping on different threads run their controllers in parallel
+
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::{
+    sys::{mjData, mjModel},
+    wrappers, Backend, MuJoCoSimulation,
+};
+
+/// State and outputs of a simulation inside `mj_step`, after the position and velocity stages
+/// and before actuation. MuJoCo coordinates
+pub struct StepControl<'a> {
+    pub time: f64,
+    pub qpos: &'a [f64],
+    pub qvel: &'a [f64],
+    /// Sensor values, only position and velocity stage sensors are up to date
+    pub sensor_data: &'a [f64],
+    /// Controls the plugin set before the step, overwrite them to control in-step
+    pub ctrl: &'a mut [f64],
+    /// Generalized forces applied directly, bypassing actuators
+    pub qfrc_applied: &'a mut [f64],
+}
+
+type StepController = Arc<Mutex<dyn FnMut(&mut StepControl) + Send>>;
+
+/// Registered controllers by `mjData` address. Only held to look a controller up, never while
+/// one runs
+static STEP_CONTROLLERS: RwLock<Vec<(usize, StepController)>> = RwLock::new(Vec::new());
+
+unsafe extern "C" fn run_step_controller(m: *const mjModel, d: *mut mjData) {
+    let controller = STEP_CONTROLLERS
+        .read()
+        .unwrap()
+        .iter()
+        .find(|(data, _)| *data == d as usize)
+        .map(|(_, controller)| controller.clone());
+    let Some(controller) = controller else {
+        return;
+    };
+    // a controller that panicked before is still usable, the panic was reported
+    let mut controller = controller
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let (model, data) = (&*m, &mut *d);
+    let mut control = StepControl {
+        time: data.time,
+        qpos: wrappers::slice(data.qpos, model.nq as usize),
+        qvel: wrappers::slice(data.qvel, model.nv as usize),
+        sensor_data: wrappers::slice(data.sensordata, model.nsensordata as usize),
+        ctrl: wrappers::slice_mut(data.ctrl, model.nu as usize),
+        qfrc_applied: wrappers::slice_mut(data.qfrc_applied, model.nv as usize),
+    };
+    // unwinding out of an extern "C" function aborts
+    let result =
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| controller(&mut control)));
+    if result.is_err() {
+        bevy::log::error!("step controller panicked at t={}", data.time);
+    }
+}
+
+/// Forget the controller of a simulation whose data is about to be freed
+pub(crate) fn remove_step_controller(data: *mut mjData) {
+    STEP_CONTROLLERS
+        .write()
+        .unwrap()
+        .retain(|(registered, _)| *registered != data as usize);
+}
+
+impl MuJoCoSimulation {
+    /// Run `controller` inside every step of this simulation, replacing the previous one.
+    /// It must not call back into this simulation, which is locked while it steps
+    pub fn set_step_controller(&self, controller: impl FnMut(&mut StepControl) + Send + 'static) {
+        let simulation = self.lock().unwrap();
+        let data = simulation.data_ptr();
+        let mut controllers = STEP_CONTROLLERS.write().unwrap();
+        controllers.retain(|(registered, _)| *registered != data as usize);
+        controllers.push((data as usize, Arc::new(Mutex::new(controller))));
+        unsafe { crate::sys::no_render::mjcb_control = Some(run_step_controller) };
+    }
+
+    pub fn clear_step_controller(&self) {
+        let simulation = self.lock().unwrap();
+        remove_step_controller(simulation.data_ptr());
+    }
+}
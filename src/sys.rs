@@ -0,0 +1,34 @@
+//! The raw MuJoCo bindings the crate is built with: `mujoco-rs-sys`, which describes MuJoCo
+//! 2.x, or with the `mujoco3` feature bindings that the build script generates from the
+//! installed MuJoCo 3.x headers. Both expose the functions under `no_render`
+
+#[cfg(not(mujoco3))]
+pub(crate) use mujoco_rs_sys::no_render;
+#[cfg(not(mujoco3))]
+pub use mujoco_rs_sys::{mjContact, mjData, mjModel};
+
+#[cfg(mujoco3)]
+#[allow(
+    non_camel_case_types,
+    non_snake_case,
+    non_upper_case_globals,
+    dead_code,
+    clippy::all
+)]
+mod bindings {
+    include!(concat!(env!("OUT_DIR"), "/mujoco3.rs"));
+}
+
+#[cfg(mujoco3)]
+pub use bindings::{mjContact, mjData, mjModel};
+
+#[cfg(mujoco3)]
+pub(crate) use bindings::{mjSENS_FRAMEQUAT, mjVERSION_HEADER};
+
+#[cfg(mujoco31)]
+pub(crate) use bindings::{mjENBL_ISLAND, mjThreadPool};
+
+#[cfg(mujoco3)]
+pub(crate) mod no_render {
+    pub(crate) use super::bindings::*;
+}
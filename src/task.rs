@@ -0,0 +1,118 @@
+//! Reward and termination of a learning task, evaluated on `MuJoCoState` after every frame that
+//! stepped the simulation and published as `MuJoCoTaskStep` events
+
+use bevy::prelude::*;
+
+use crate::{
+    divergence::reset_simulation, simulate_physics, DivergenceReset, MuJoCoResources,
+    MuJoCoSimulation, MuJoCoState, MuJoCoTime, ResetOptions,
+};
+
+/// A task defined on the simulation state
+pub trait MuJoCoTask: Send + Sync + 'static {
+    fn reward(&self, state: &MuJoCoState) -> f64;
+    fn terminated(&self, state: &MuJoCoState) -> bool;
+}
+
+/// Evaluate a `MuJoCoTask` after every step
+#[derive(Clone)]
+pub struct MuJoCoTaskPlugin<T: MuJoCoTask + Clone> {
+    pub task: T,
+    /// Where a terminated episode restarts, `DivergenceReset::None` leaves the simulation as is
+    pub reset: DivergenceReset,
+}
+
+impl<T: MuJoCoTask + Clone> MuJoCoTaskPlugin<T> {
+    pub fn new(task: T) -> Self {
+        MuJoCoTaskPlugin {
+            task,
+            reset: DivergenceReset::Initial,
+        }
+    }
+
+    pub fn with_reset(mut self, reset: DivergenceReset) -> Self {
+        self.reset = reset;
+        self
+    }
+}
+
+impl<T: MuJoCoTask + Clone> Plugin for MuJoCoTaskPlugin<T> {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TaskSettings(self.clone()));
+        app.init_resource::<MuJoCoEpisode>();
+        app.add_event::<MuJoCoTaskStep>();
+        app.add_systems(
+            Update,
+            evaluate_task::<T>
+                .after(simulate_physics)
+                .run_if(resource_exists::<MuJoCoResources>),
+        );
+    }
+}
+
+#[derive(Resource)]
+struct TaskSettings<T: MuJoCoTask + Clone>(MuJoCoTaskPlugin<T>);
+
+/// Result of evaluating the task on the state after a frame's steps
+#[derive(Event, Debug, Clone, Copy)]
+pub struct MuJoCoTaskStep {
+    /// Simulation time of the evaluated state
+    pub time: f64,
+    pub reward: f64,
+    pub terminated: bool,
+    /// Sum of rewards since the episode started, including this one
+    pub episode_return: f64,
+    /// Number of evaluations since the episode started, including this one
+    pub episode_length: usize,
+}
+
+/// Progress of the current episode
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MuJoCoEpisode {
+    /// Number of finished episodes
+    pub count: usize,
+    pub episode_return: f64,
+    pub length: usize,
+}
+
+fn evaluate_task<T: MuJoCoTask + Clone>(
+    settings: Res<TaskSettings<T>>,
+    mujoco: Res<MuJoCoSimulation>,
+    mujoco_resources: Res<MuJoCoResources>,
+    sim_time: Res<MuJoCoTime>,
+    reset_options: Res<ResetOptions>,
+    mut episode: ResMut<MuJoCoEpisode>,
+    mut task_steps: EventWriter<MuJoCoTaskStep>,
+) {
+    if sim_time.steps == 0 {
+        return;
+    }
+
+    let state = &mujoco_resources.state;
+    let task = &settings.0.task;
+    let reward = task.reward(state);
+    let terminated = task.terminated(state);
+    episode.episode_return += reward;
+    episode.length += 1;
+
+    task_steps.send(MuJoCoTaskStep {
+        time: sim_time.elapsed,
+        reward,
+        terminated,
+        episode_return: episode.episode_return,
+        episode_length: episode.length,
+    });
+
+    if !terminated {
+        return;
+    }
+    episode.count += 1;
+    episode.episode_return = 0.0;
+    episode.length = 0;
+    let mujoco = mujoco.lock().unwrap();
+    match settings.0.reset {
+        DivergenceReset::None => {}
+        DivergenceReset::Initial => reset_simulation(&mujoco, None, &reset_options),
+        DivergenceReset::Keyframe(key) => reset_simulation(&mujoco, Some(key), &reset_options),
+    }
+}
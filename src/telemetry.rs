@@ -0,0 +1,146 @@
+//! WebSocket telemetry: streams `MuJoCoState` as JSON text messages for browser dashboards.
+//! Each message is the serialized state plus the simulation `time`, limited to the selected fields
+
+use std::net::TcpListener;
+use std::sync::mpsc::{sync_channel, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use bevy::prelude::*;
+use tungstenite::Message;
+
+use crate::{simulate_physics, MuJoCoResources, MuJoCoStateBuffer};
+
+/// Serve `MuJoCoState` over WebSocket
+#[derive(Clone)]
+pub struct MuJoCoTelemetryPlugin {
+    pub address: String,
+    /// Maximum number of messages per second
+    pub rate: f64,
+    /// `MuJoCoState` fields to include, all fields when empty
+    pub fields: Vec<String>,
+}
+
+impl Default for MuJoCoTelemetryPlugin {
+    fn default() -> Self {
+        MuJoCoTelemetryPlugin {
+            address: "127.0.0.1:9001".to_string(),
+            rate: 30.0,
+            fields: vec![],
+        }
+    }
+}
+
+impl MuJoCoTelemetryPlugin {
+    pub fn with_fields(mut self, fields: &[&str]) -> Self {
+        self.fields = fields.iter().map(|field| field.to_string()).collect();
+        self
+    }
+
+    pub fn with_rate(mut self, rate: f64) -> Self {
+        self.rate = rate;
+        self
+    }
+}
+
+/// Messages a client may fall behind by before it is dropped
+const CLIENT_QUEUE: usize = 64;
+
+#[derive(Resource)]
+struct TelemetryServer {
+    /// Queues of the clients' writer threads
+    clients: Arc<Mutex<Vec<SyncSender<Arc<str>>>>>,
+    timer: Timer,
+    fields: Vec<String>,
+}
+
+impl Plugin for MuJoCoTelemetryPlugin {
+    fn build(&self, app: &mut App) {
+        let listener = match TcpListener::bind(&self.address) {
+            Ok(listener) => listener,
+            Err(err) => {
+                error!("MuJoCo telemetry can't listen on {}: {err}", self.address);
+                return;
+            }
+        };
+        info!("MuJoCo telemetry serving on ws://{}", self.address);
+
+        let clients = Arc::new(Mutex::new(vec![]));
+        {
+            let clients = clients.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    let mut socket = match tungstenite::accept(stream) {
+                        Ok(socket) => socket,
+                        Err(err) => {
+                            warn!("MuJoCo telemetry handshake failed: {err}");
+                            continue;
+                        }
+                    };
+
+                    // messages are sent on the client's own thread, a slow browser never
+                    // stalls the app
+                    let (sender, messages) = sync_channel::<Arc<str>>(CLIENT_QUEUE);
+                    clients.lock().unwrap().push(sender);
+                    thread::spawn(move || {
+                        for message in messages {
+                            if socket.send(Message::text(message.to_string())).is_err() {
+                                return;
+                            }
+                        }
+                        let _ = socket.close(None);
+                    });
+                }
+            });
+        }
+
+        app.insert_resource(TelemetryServer {
+            clients,
+            timer: Timer::from_seconds((1.0 / self.rate) as f32, TimerMode::Repeating),
+            fields: self.fields.clone(),
+        });
+        app.add_systems(
+            Update,
+            stream_state
+                .after(simulate_physics)
+                .run_if(resource_exists::<MuJoCoResources>),
+        );
+    }
+}
+
+fn stream_state(
+    mut server: ResMut<TelemetryServer>,
+    time: Res<Time>,
+    state_buffer: Res<MuJoCoStateBuffer>,
+    mujoco_resources: Res<MuJoCoResources>,
+) {
+    if !server.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let clients = server.clients.clone();
+    let mut clients = clients.lock().unwrap();
+    if clients.is_empty() {
+        return;
+    }
+
+    let Ok(serde_json::Value::Object(mut state)) = serde_json::to_value(&mujoco_resources.state)
+    else {
+        return;
+    };
+    if !server.fields.is_empty() {
+        state.retain(|field, _| server.fields.contains(field));
+    }
+    state.insert("time".to_string(), state_buffer.front().time.into());
+
+    let message: Arc<str> = serde_json::Value::Object(state).to_string().into();
+    // drop clients that disconnected or fell too far behind
+    clients.retain(|client| match client.try_send(message.clone()) {
+        Ok(()) => true,
+        Err(TrySendError::Full(_)) => {
+            warn!("dropping MuJoCo telemetry client that can't keep up");
+            false
+        }
+        Err(TrySendError::Disconnected(_)) => false,
+    });
+}
@@ -0,0 +1,216 @@
+use bevy::prelude::*;
+
+use crate::{
+    adapters::position_bevy_2_mujoco, apply_control_events, apply_pd_controller, wrappers,
+    MuJoCoModelInfo, MuJoCoResources, MuJoCoSimulation, MuJoCoSystemSet,
+};
+
+/// Input driving a teleoperation binding, read as a value in `[-1, 1]`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TeleopInput {
+    Axis(GamepadAxis),
+    Keys {
+        positive: KeyCode,
+        negative: KeyCode,
+    },
+}
+
+/// What a teleoperation binding controls
+#[derive(Debug, Clone, PartialEq)]
+pub enum TeleopTarget {
+    /// Set the control of the named actuator to `input * scale`
+    Actuator(String),
+    /// Move the named mocap body along `direction` (bevy scene frame) at `input * scale` m/s
+    Mocap { body: String, direction: Vec3 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TeleopBinding {
+    pub input: TeleopInput,
+    pub target: TeleopTarget,
+    pub scale: f64,
+}
+
+/// Mapping of gamepad axes and keyboard keys to actuators and mocap bodies
+///
+/// ```ignore
+/// TeleopMap::new()
+///     .axis(GamepadAxis::LeftStickX, "steer")
+///     .keys(KeyCode::KeyW, KeyCode::KeyS, "throttle")
+///     .scale(2.0)
+/// ```
+#[derive(Resource, Debug, Clone, Default)]
+pub struct TeleopMap {
+    pub bindings: Vec<TeleopBinding>,
+}
+
+impl TeleopMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn bind(mut self, input: TeleopInput, target: TeleopTarget) -> Self {
+        self.bindings.push(TeleopBinding {
+            input,
+            target,
+            scale: 1.0,
+        });
+        self
+    }
+
+    /// Drive an actuator with a gamepad axis
+    pub fn axis(self, axis: GamepadAxis, actuator: &str) -> Self {
+        self.bind(
+            TeleopInput::Axis(axis),
+            TeleopTarget::Actuator(actuator.to_string()),
+        )
+    }
+
+    /// Drive an actuator with a pair of keys
+    pub fn keys(self, positive: KeyCode, negative: KeyCode, actuator: &str) -> Self {
+        self.bind(
+            TeleopInput::Keys { positive, negative },
+            TeleopTarget::Actuator(actuator.to_string()),
+        )
+    }
+
+    /// Move a mocap body along a direction with a gamepad axis
+    pub fn mocap_axis(self, axis: GamepadAxis, body: &str, direction: Vec3) -> Self {
+        self.bind(
+            TeleopInput::Axis(axis),
+            TeleopTarget::Mocap {
+                body: body.to_string(),
+                direction,
+            },
+        )
+    }
+
+    /// Move a mocap body along a direction with a pair of keys
+    pub fn mocap_keys(
+        self,
+        positive: KeyCode,
+        negative: KeyCode,
+        body: &str,
+        direction: Vec3,
+    ) -> Self {
+        self.bind(
+            TeleopInput::Keys { positive, negative },
+            TeleopTarget::Mocap {
+                body: body.to_string(),
+                direction,
+            },
+        )
+    }
+
+    /// Scale the value of the last added binding
+    pub fn scale(mut self, scale: f64) -> Self {
+        if let Some(binding) = self.bindings.last_mut() {
+            binding.scale = scale;
+        }
+        self
+    }
+}
+
+/// Drive actuators and mocap bodies from gamepad and keyboard input. Actuators are only written
+/// while their binding has input and once when it's released, otherwise other control sources
+/// keep them
+pub struct MuJoCoTeleopPlugin {
+    pub map: TeleopMap,
+}
+
+impl MuJoCoTeleopPlugin {
+    pub fn new(map: TeleopMap) -> Self {
+        MuJoCoTeleopPlugin { map }
+    }
+}
+
+impl Plugin for MuJoCoTeleopPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.map.clone());
+        app.add_systems(
+            Update,
+            // bindings set actuators after the joint feedback controllers, control events of
+            // the frame override them
+            teleoperate
+                .in_set(MuJoCoSystemSet::Control)
+                .after(apply_pd_controller)
+                .before(apply_control_events)
+                .run_if(resource_exists::<MuJoCoResources>),
+        );
+    }
+}
+
+fn input_value(
+    input: &TeleopInput,
+    gamepads: &Query<&Gamepad>,
+    keys: &ButtonInput<KeyCode>,
+) -> f64 {
+    match input {
+        TeleopInput::Axis(axis) => gamepads
+            .iter()
+            .filter_map(|gamepad| gamepad.get(*axis))
+            .find(|value| *value != 0.0)
+            .unwrap_or_default() as f64,
+        TeleopInput::Keys { positive, negative } => {
+            keys.pressed(*positive) as i32 as f64 - keys.pressed(*negative) as i32 as f64
+        }
+    }
+}
+
+fn teleoperate(
+    mujoco: Res<MuJoCoSimulation>,
+    model_info: Res<MuJoCoModelInfo>,
+    map: Res<TeleopMap>,
+    gamepads: Query<&Gamepad>,
+    keys: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    mut mujoco_resources: ResMut<MuJoCoResources>,
+    mut last_values: Local<Vec<f64>>,
+) {
+    // locked only once a mocap body moves, actuator controls go through `MuJoCoResources`
+    let mut simulation = None;
+    let control = &mut mujoco_resources.control;
+    last_values.resize(map.bindings.len(), 0.0);
+
+    for (binding, last_value) in map.bindings.iter().zip(last_values.iter_mut()) {
+        let value = input_value(&binding.input, &gamepads, &keys) * binding.scale;
+        let previous = std::mem::replace(last_value, value);
+        if value == 0.0 && previous == 0.0 {
+            continue;
+        }
+
+        match &binding.target {
+            TeleopTarget::Actuator(actuator) => {
+                let Some(id) = model_info.actuator_id(actuator) else {
+                    continue;
+                };
+                control.data.resize(control.number_of_controls, 0.0);
+                control.data[id] = value;
+            }
+            TeleopTarget::Mocap { body, direction } => {
+                let Some(mocap_id) = model_info
+                    .body_id(body)
+                    .and_then(|body| usize::try_from(model_info.body_mocapid[body]).ok())
+                else {
+                    continue;
+                };
+
+                let offset = position_bevy_2_mujoco(
+                    model_info.up_axis,
+                    *direction * (value * time.delta_secs_f64()) as f32,
+                );
+                let simulation = simulation.get_or_insert_with(|| mujoco.lock().unwrap());
+                let mocap_pos = unsafe {
+                    wrappers::slice_mut(
+                        wrappers::data(&*simulation).mocap_pos,
+                        3 * model_info.nmocap,
+                    )
+                };
+                let mocap_pos = &mut mocap_pos[3 * mocap_id..3 * mocap_id + 3];
+                for (p, d) in mocap_pos.iter_mut().zip(offset) {
+                    *p += d;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+    },
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{wrappers, Backend, GeomType, MjcfElement, MuJoCoSimulation};
+
+/// Shape of a procedural terrain. Heights are in meters, slopes and stairs rise along +X
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TerrainProfile {
+    Flat,
+    /// Fractal Perlin noise
+    Perlin {
+        amplitude: f64,
+        /// Features per meter of the first octave
+        frequency: f64,
+        octaves: u32,
+        seed: u64,
+    },
+    Stairs {
+        step_length: f64,
+        step_height: f64,
+    },
+    /// Incline in radians
+    Slope {
+        angle: f64,
+    },
+}
+
+/// Grid of terrain heights in meters, laid out like MuJoCo `hfield_data`: `nrow` rows along Y,
+/// `ncol` columns along X, covering `[-size[0], size[0]] x [-size[1], size[1]]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeightField {
+    pub nrow: usize,
+    pub ncol: usize,
+    pub size: [f64; 2],
+    pub heights: Vec<f64>,
+}
+
+impl HeightField {
+    pub fn generate(profile: TerrainProfile, nrow: usize, ncol: usize, size: [f64; 2]) -> Self {
+        let noise = match profile {
+            TerrainProfile::Perlin { seed, .. } => Some(Perlin::new(seed)),
+            _ => None,
+        };
+
+        let heights = (0..nrow * ncol)
+            .map(|i| {
+                let (row, col) = (i / ncol, i % ncol);
+                let x = -size[0] + 2.0 * size[0] * col as f64 / (ncol.max(2) - 1) as f64;
+                let y = -size[1] + 2.0 * size[1] * row as f64 / (nrow.max(2) - 1) as f64;
+
+                match profile {
+                    TerrainProfile::Flat => 0.0,
+                    TerrainProfile::Perlin {
+                        amplitude,
+                        frequency,
+                        octaves,
+                        ..
+                    } => {
+                        let noise = noise.as_ref().unwrap();
+                        (0..octaves)
+                            .map(|octave| {
+                                let scale = 2f64.powi(octave as i32);
+                                noise.sample(x * frequency * scale, y * frequency * scale) / scale
+                            })
+                            .sum::<f64>()
+                            * amplitude
+                    }
+                    TerrainProfile::Stairs {
+                        step_length,
+                        step_height,
+                    } => ((x + size[0]) / step_length).floor() * step_height,
+                    TerrainProfile::Slope { angle } => (x + size[0]) * angle.tan(),
+                }
+            })
+            .collect();
+
+        HeightField {
+            nrow,
+            ncol,
+            size,
+            heights,
+        }
+    }
+
+    /// Lowest and highest point
+    pub fn range(&self) -> (f64, f64) {
+        self.heights
+            .iter()
+            .fold((f64::MAX, f64::MIN), |(min, max), h| {
+                (min.min(*h), max.max(*h))
+            })
+    }
+
+    /// `<hfield>` asset declaring the grid, to be filled with `MuJoCoSimulation::set_hfield`
+    /// once compiled. `base` is the depth of the box under the terrain
+    pub fn mjcf_asset(&self, name: &str, base: f64) -> MjcfElement {
+        let (min, max) = self.range();
+        MjcfElement::new("hfield")
+            .with_attribute("name", name)
+            .with_attribute("nrow", self.nrow)
+            .with_attribute("ncol", self.ncol)
+            .with_attribute(
+                "size",
+                format!(
+                    "{} {} {} {}",
+                    self.size[0],
+                    self.size[1],
+                    (max - min).max(1e-6),
+                    base
+                ),
+            )
+    }
+
+    /// Surface mesh in the frame of a geom using this height field
+    pub fn mesh(&self) -> Mesh {
+        let (min, _) = self.range();
+        let normalized: Vec<f32> = self.heights.iter().map(|h| (h - min) as f32).collect();
+        hfield_mesh(self.nrow, self.ncol, self.size, 1.0, &normalized)
+    }
+}
+
+/// Triangulated height field surface. Vertices are in the geom frame of non-mesh geoms,
+/// which is rotated so bevy Y is MuJoCo Z
+pub(crate) fn hfield_mesh(
+    nrow: usize,
+    ncol: usize,
+    size: [f64; 2],
+    elevation: f32,
+    data: &[f32],
+) -> Mesh {
+    let (sx, sy) = (size[0] as f32, size[1] as f32);
+    let positions: Vec<[f32; 3]> = (0..nrow * ncol)
+        .map(|i| {
+            let (row, col) = (i / ncol, i % ncol);
+            let x = -sx + 2.0 * sx * col as f32 / (ncol.max(2) - 1) as f32;
+            let y = -sy + 2.0 * sy * row as f32 / (nrow.max(2) - 1) as f32;
+            [x, data[i] * elevation, -y]
+        })
+        .collect();
+
+    let mut indices = Vec::with_capacity(6 * nrow.saturating_sub(1) * ncol.saturating_sub(1));
+    for row in 0..nrow.saturating_sub(1) {
+        for col in 0..ncol.saturating_sub(1) {
+            let i = (row * ncol + col) as u32;
+            let (right, up) = (i + 1, i + ncol as u32);
+            indices.extend_from_slice(&[i, right, up, right, up + 1, up]);
+        }
+    }
+
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_indices(Indices::U32(indices));
+    mesh.compute_smooth_normals();
+    mesh
+}
+
+/// Surface mesh of a height field of the compiled model
+pub(crate) fn model_hfield_mesh(simulation: &impl Backend, hfield: usize) -> Mesh {
+    let model = wrappers::model(simulation);
+    let nhfield = model.nhfield as usize;
+    let nrow = unsafe { wrappers::slice(model.hfield_nrow, nhfield) }[hfield] as usize;
+    let ncol = unsafe { wrappers::slice(model.hfield_ncol, nhfield) }[hfield] as usize;
+    let adr = unsafe { wrappers::slice(model.hfield_adr, nhfield) }[hfield] as usize;
+    let size =
+        &unsafe { wrappers::slice(model.hfield_size, 4 * nhfield) }[4 * hfield..4 * hfield + 4];
+    let data = unsafe { wrappers::slice(model.hfield_data, model.nhfielddata as usize) };
+
+    hfield_mesh(
+        nrow,
+        ncol,
+        [size[0], size[1]],
+        size[2] as f32,
+        &data[adr..adr + nrow * ncol],
+    )
+}
+
+/// Copy a height field into the compiled model. The grid size has to match the `<hfield>`
+/// declaration, the elevation is set to the height range of the field
+pub(crate) fn write_hfield(simulation: &impl Backend, hfield: usize, field: &HeightField) -> bool {
+    let model = wrappers::model(simulation);
+    let nhfield = model.nhfield as usize;
+    let nrow = unsafe { wrappers::slice(model.hfield_nrow, nhfield) }[hfield] as usize;
+    let ncol = unsafe { wrappers::slice(model.hfield_ncol, nhfield) }[hfield] as usize;
+    if (nrow, ncol) != (field.nrow, field.ncol) {
+        warn!(
+            "height field is {}x{}, the model expects {}x{}",
+            field.nrow, field.ncol, nrow, ncol
+        );
+        return false;
+    }
+
+    let (min, max) = field.range();
+    let elevation = (max - min).max(1e-6);
+    let size = &mut unsafe { wrappers::slice_mut(model.hfield_size, 4 * nhfield) }
+        [4 * hfield..4 * hfield + 4];
+    size[2] = elevation;
+    let radius = (size[0] * size[0] + size[1] * size[1] + elevation * elevation).sqrt();
+
+    let adr = unsafe { wrappers::slice(model.hfield_adr, nhfield) }[hfield] as usize;
+    let data = unsafe { wrappers::slice_mut(model.hfield_data, model.nhfielddata as usize) };
+    for (dst, h) in data[adr..adr + nrow * ncol]
+        .iter_mut()
+        .zip(field.heights.iter())
+    {
+        *dst = ((h - min) / elevation) as f32;
+    }
+
+    // keep the broad phase bounds of geoms using the field up to date
+    let ngeom = model.ngeom as usize;
+    let geom_type = unsafe { wrappers::slice(model.geom_type, ngeom) };
+    let geom_dataid = unsafe { wrappers::slice(model.geom_dataid, ngeom) };
+    let geom_rbound = unsafe { wrappers::slice_mut(model.geom_rbound, ngeom) };
+    for geom in 0..ngeom {
+        if GeomType::from_raw(geom_type[geom]) == GeomType::HFIELD
+            && geom_dataid[geom] as usize == hfield
+        {
+            geom_rbound[geom] = radius;
+        }
+    }
+    true
+}
+
+impl MuJoCoSimulation {
+    /// Replace the data of a height field by name. Rendered terrain is updated with
+    /// `MuJoCoCommands::set_hfield`
+    pub fn set_hfield(&self, name: &str, field: &HeightField) -> bool {
+        let simulation = self.lock().unwrap();
+        let model = wrappers::model(&simulation);
+        let Some(hfield) = wrappers::name_to_id(model, model.name_hfieldadr, model.nhfield, name)
+        else {
+            return false;
+        };
+        write_hfield(&simulation, hfield, field)
+    }
+}
+
+/// Classic 2D Perlin noise with a seeded permutation table
+struct Perlin {
+    permutation: [u8; 512],
+}
+
+impl Perlin {
+    fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut table: Vec<u8> = (0..=255).collect();
+        for i in (1..256).rev() {
+            table.swap(i, rng.gen_range(0..=i));
+        }
+        let mut permutation = [0; 512];
+        for (i, value) in permutation.iter_mut().enumerate() {
+            *value = table[i % 256];
+        }
+        Perlin { permutation }
+    }
+
+    /// Noise value roughly in `[-1, 1]`
+    fn sample(&self, x: f64, y: f64) -> f64 {
+        let (xi, yi) = (x.floor() as i64 & 255, y.floor() as i64 & 255);
+        let (xf, yf) = (x - x.floor(), y - y.floor());
+        let fade = |t: f64| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+        let (u, v) = (fade(xf), fade(yf));
+
+        let p = &self.permutation;
+        let hash = |x: i64, y: i64| p[p[x as usize] as usize + y as usize];
+        let gradient = |hash: u8, x: f64, y: f64| match hash & 3 {
+            0 => x + y,
+            1 => -x + y,
+            2 => x - y,
+            _ => -x - y,
+        };
+        let lerp = |a: f64, b: f64, t: f64| a + t * (b - a);
+
+        let bottom = lerp(
+            gradient(hash(xi, yi), xf, yf),
+            gradient(hash(xi + 1, yi), xf - 1.0, yf),
+            u,
+        );
+        let top = lerp(
+            gradient(hash(xi, yi + 1), xf, yf - 1.0),
+            gradient(hash(xi + 1, yi + 1), xf - 1.0, yf - 1.0),
+            u,
+        );
+        lerp(bottom, top, v)
+    }
+}
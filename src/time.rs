@@ -0,0 +1,110 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+/// Simulation clock, updated by `simulate_physics` every frame
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MuJoCoTime {
+    /// Simulation time in seconds
+    pub elapsed: f64,
+    /// Simulated seconds advanced in the last frame
+    pub delta: f64,
+    /// Number of `mj_step` calls in the last frame
+    pub steps: usize,
+    /// Wall time spent stepping in the last frame
+    pub step_wall_time: Duration,
+    /// Wall time of a single step, smoothed over recent frames
+    pub average_step_time: Option<Duration>,
+}
+
+impl MuJoCoTime {
+    pub(crate) fn record(&mut self, elapsed: f64, delta: f64, steps: usize, wall_time: Duration) {
+        self.elapsed = elapsed;
+        self.delta = delta;
+        self.steps = steps;
+        self.step_wall_time = wall_time;
+        if let Some(step_time) = self.mean_step_time() {
+            self.average_step_time = Some(match self.average_step_time {
+                Some(average) => average.mul_f64(0.9) + step_time.mul_f64(0.1),
+                None => step_time,
+            });
+        }
+    }
+
+    /// Wall time of a single step averaged over the last frame
+    pub fn mean_step_time(&self) -> Option<Duration> {
+        (self.steps > 0).then(|| self.step_wall_time / self.steps as u32)
+    }
+}
+
+/// Limits on the stepping done in one frame in `StepMode::Realtime`, so a slow machine falls
+/// behind real time instead of spending ever longer frames catching up
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StepBudget {
+    /// Wall time stepping may take per frame, converted to a number of steps with
+    /// `MuJoCoTime::average_step_time`. `None` steps until simulation time catches up
+    pub max_wall_time: Option<Duration>,
+    /// Steps per frame at most
+    pub max_steps: Option<usize>,
+    /// Simulated seconds the simulation may fall behind, the rest of the backlog is dropped
+    pub max_lag: f64,
+}
+
+impl Default for StepBudget {
+    fn default() -> Self {
+        StepBudget {
+            max_wall_time: Some(Duration::from_millis(20)),
+            max_steps: None,
+            max_lag: 0.25,
+        }
+    }
+}
+
+impl StepBudget {
+    /// Steps allowed this frame given the measured wall time of a step
+    pub(crate) fn steps(&self, average_step_time: Option<Duration>) -> usize {
+        let by_wall_time = match (self.max_wall_time, average_step_time) {
+            (Some(budget), Some(step_time)) if !step_time.is_zero() => {
+                // always step at least once so the simulation advances
+                ((budget.as_secs_f64() / step_time.as_secs_f64()) as usize).max(1)
+            }
+            _ => usize::MAX,
+        };
+        by_wall_time.min(self.max_steps.unwrap_or(usize::MAX))
+    }
+
+    /// Steps of `timestep` a realtime frame takes to consume `time_budget`, the last one
+    /// overshooting by less than a step, within the budget
+    pub(crate) fn realtime_steps(
+        &self,
+        time_budget: f64,
+        timestep: f64,
+        average_step_time: Option<Duration>,
+    ) -> usize {
+        if time_budget <= 0.0 || timestep <= 0.0 {
+            return 0;
+        }
+        ((time_budget / timestep).ceil() as usize).min(self.steps(average_step_time))
+    }
+
+    /// Carry what is left of `time_budget` after stepping over to the next frame, dropping
+    /// the backlog beyond `max_lag`. Returns the lag when stepping fell behind
+    pub(crate) fn carry_over(&self, time_budget: &mut f64) -> Option<f64> {
+        if *time_budget <= 0.0 {
+            return None;
+        }
+        let lag = *time_budget;
+        *time_budget = lag.min(self.max_lag);
+        Some(lag)
+    }
+}
+
+/// Sent when the step budget stopped a frame's stepping before simulation time caught up with
+/// scaled real time
+#[derive(Event, Debug, Clone, Copy)]
+pub struct SimulationLagging {
+    /// Simulated seconds behind after the frame, before the backlog is capped to `max_lag`
+    pub lag: f64,
+    /// Steps done in the frame
+    pub steps: usize,
+}
@@ -0,0 +1,108 @@
+use crate::{wrappers, Backend, Geom, GeomType};
+use bevy::prelude::*;
+
+/// Model features that bevy_mujoco can't render (yet)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnsupportedFeatureKind {
+    Skin,
+    Plugin,
+    Texture,
+    UnknownGeom,
+}
+
+/// A single unsupported feature together with the names of affected model objects
+#[derive(Debug, Clone)]
+pub struct UnsupportedFeature {
+    pub kind: UnsupportedFeatureKind,
+    pub names: Vec<String>,
+}
+
+/// Consolidated report of unsupported features found in the loaded model.
+/// Available as a resource and sent once as an event after the model is loaded
+#[derive(Resource, Event, Debug, Clone, Default)]
+pub struct MuJoCoUnsupportedFeatures {
+    pub features: Vec<UnsupportedFeature>,
+}
+
+impl MuJoCoUnsupportedFeatures {
+    /// Scan a compiled model for features the renderer doesn't support
+    pub(crate) fn scan(simulation: &impl Backend, geoms: &[Geom]) -> Self {
+        let model = wrappers::model(simulation);
+        let mut features = vec![];
+        let mut push = |kind, names: Vec<String>| {
+            if !names.is_empty() {
+                features.push(UnsupportedFeature { kind, names });
+            }
+        };
+
+        push(
+            UnsupportedFeatureKind::Skin,
+            wrappers::names(model, model.name_skinadr, model.nskin),
+        );
+        push(
+            UnsupportedFeatureKind::Plugin,
+            wrappers::names(model, model.name_pluginadr, model.nplugin),
+        );
+        push(
+            UnsupportedFeatureKind::Texture,
+            wrappers::names(model, model.name_texadr, model.ntex),
+        );
+
+        let geom_names = |filter: fn(&Geom) -> bool| {
+            geoms
+                .iter()
+                .filter(|geom| filter(geom))
+                .map(|geom| geom.name.clone())
+                .collect::<Vec<String>>()
+        };
+        push(
+            UnsupportedFeatureKind::UnknownGeom,
+            geom_names(|geom| !is_known_geom_type(&geom.geom_type)),
+        );
+
+        MuJoCoUnsupportedFeatures { features }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.features.is_empty()
+    }
+
+    /// Log a single warning listing every unsupported feature
+    pub(crate) fn warn(&self) {
+        if self.is_empty() {
+            return;
+        }
+
+        let lines: Vec<String> = self
+            .features
+            .iter()
+            .map(|feature| {
+                format!(
+                    "  {:?} ({}): {}",
+                    feature.kind,
+                    feature.names.len(),
+                    feature.names.join(", ")
+                )
+            })
+            .collect();
+        warn!(
+            "MuJoCo model uses features that are not rendered by bevy_mujoco:\n{}",
+            lines.join("\n")
+        );
+    }
+}
+
+/// Geom types rendered by `geom_mesh` or `model_hfield_mesh`
+fn is_known_geom_type(geom_type: &GeomType) -> bool {
+    matches!(
+        geom_type,
+        GeomType::PLANE
+            | GeomType::BOX
+            | GeomType::SPHERE
+            | GeomType::CAPSULE
+            | GeomType::CYLINDER
+            | GeomType::MESH
+            | GeomType::HFIELD
+            | GeomType::ELLIPSOID
+    )
+}
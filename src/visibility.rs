@@ -0,0 +1,156 @@
+use bevy::prelude::*;
+
+use crate::{MuJoCoBody, MuJoCoGeom, MuJoCoModelInfo, MuJoCoResources};
+
+/// Visibility and highlight rules over geom and body names, e.g. to isolate one leg of a robot:
+/// `visibility.set("*", false); visibility.set("FR_*", true)`. Patterns are globs with `*` and
+/// `?` matched against the geom name and the name of the body owning it. Rules are applied in
+/// order, the last matching one wins, and keep applying to geoms spawned later
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MuJoCoVisibility {
+    visible: Vec<(String, bool)>,
+    highlights: Vec<(String, Option<Color>)>,
+}
+
+impl MuJoCoVisibility {
+    /// Show or hide the matching geoms
+    pub fn set(&mut self, pattern: &str, visible: bool) {
+        self.visible.push((pattern.to_string(), visible));
+    }
+
+    /// Tint the matching geoms with a color
+    pub fn highlight(&mut self, pattern: &str, color: Color) {
+        self.highlights.push((pattern.to_string(), Some(color)));
+    }
+
+    /// Restore the materials of the matching geoms
+    pub fn clear_highlight(&mut self, pattern: &str) {
+        self.highlights.push((pattern.to_string(), None));
+    }
+
+    /// Drop all rules, every geom is shown with its own material again
+    pub fn reset(&mut self) {
+        self.visible.clear();
+        self.highlights.clear();
+    }
+
+    /// Whether a geom of a body is shown
+    pub fn is_visible(&self, geom: &str, body: &str) -> bool {
+        last_match(&self.visible, geom, body).unwrap_or(true)
+    }
+
+    /// Tint of a geom of a body
+    pub fn highlight_color(&self, geom: &str, body: &str) -> Option<Color> {
+        last_match(&self.highlights, geom, body).flatten()
+    }
+}
+
+fn last_match<T: Copy>(rules: &[(String, T)], geom: &str, body: &str) -> Option<T> {
+    rules
+        .iter()
+        .rev()
+        .find(|(pattern, _)| glob_match(pattern, geom) || glob_match(pattern, body))
+        .map(|(_, value)| *value)
+}
+
+/// Match a name against a pattern where `*` stands for any run of characters and `?` for one
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name): (Vec<char>, Vec<char>) =
+        (pattern.chars().collect(), name.chars().collect());
+    let (mut p, mut n) = (0, 0);
+    // position of the last `*` and of the name character it currently stands in front of
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Geom entity with its material swapped for a tinted copy
+#[derive(Component)]
+pub(crate) struct Tinted {
+    color: Color,
+    material: Handle<StandardMaterial>,
+}
+
+/// Apply `MuJoCoVisibility` to geom entities when the rules change or geoms get materials
+#[allow(clippy::type_complexity)]
+pub(crate) fn apply_visibility(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    visibility: Res<MuJoCoVisibility>,
+    model_info: Res<MuJoCoModelInfo>,
+    mujoco_resources: Res<MuJoCoResources>,
+    bodies: Query<&MuJoCoBody>,
+    added: Query<(), (With<MuJoCoGeom>, Added<MeshMaterial3d<StandardMaterial>>)>,
+    mut geoms: Query<(
+        Entity,
+        &MuJoCoGeom,
+        &Parent,
+        &mut Visibility,
+        Option<&mut MeshMaterial3d<StandardMaterial>>,
+        Option<&Tinted>,
+    )>,
+) {
+    if !visibility.is_changed() && added.is_empty() {
+        return;
+    }
+
+    for (entity, geom, parent, mut geom_visibility, material, tinted) in geoms.iter_mut() {
+        let geom_name = &mujoco_resources.geoms[geom.id as usize].name;
+        let body_name = bodies
+            .get(parent.get())
+            .ok()
+            .and_then(|body| model_info.body_names.get(body.id as usize))
+            .map_or("", String::as_str);
+
+        let shown = match visibility.is_visible(geom_name, body_name) {
+            true => Visibility::Inherited,
+            false => Visibility::Hidden,
+        };
+        geom_visibility.set_if_neq(shown);
+
+        let Some(mut material) = material else {
+            continue;
+        };
+        let color = visibility.highlight_color(geom_name, body_name);
+        if color == tinted.map(|tinted| tinted.color) {
+            continue;
+        }
+        let original = match tinted {
+            Some(tinted) => tinted.material.clone(),
+            None => material.0.clone(),
+        };
+        let Some(color) = color else {
+            material.0 = original;
+            commands.entity(entity).remove::<Tinted>();
+            continue;
+        };
+        let Some(mut copy) = materials.get(&original).cloned() else {
+            continue;
+        };
+        copy.base_color = color;
+        material.0 = materials.add(copy);
+        commands.entity(entity).insert(Tinted {
+            color,
+            material: original,
+        });
+    }
+}
@@ -0,0 +1,222 @@
+//! Global visual options of the model: `<visual>` colors, fog, headlight and quality settings
+//! plus the skybox texture, applied to bevy's clear color, lighting and 3d cameras
+
+use bevy::{
+    core_pipeline::Skybox,
+    pbr::{DirectionalLightShadowMap, DistanceFog, FogFalloff},
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{
+            Extent3d, TextureDimension, TextureFormat, TextureViewDescriptor, TextureViewDimension,
+        },
+    },
+};
+
+use crate::{wrappers, Backend, MuJoCoResources, MuJoCoSimulation};
+
+/// `mjTEXTURE_SKYBOX` (`mjtTexture`)
+const TEXTURE_SKYBOX: i32 = 2;
+
+/// Configure the scene from the model's visual options, the way MuJoCo's viewer draws it
+#[derive(Clone)]
+pub struct MuJoCoVisualPlugin {
+    /// Add distance fog to 3d cameras. MuJoCo's viewer has fog off unless enabled
+    pub fog: bool,
+    /// Draw the model's skybox texture on 3d cameras
+    pub skybox: bool,
+}
+
+impl Default for MuJoCoVisualPlugin {
+    fn default() -> Self {
+        MuJoCoVisualPlugin {
+            fog: false,
+            skybox: true,
+        }
+    }
+}
+
+impl Plugin for MuJoCoVisualPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(VisualSettings(self.clone()));
+        app.add_systems(
+            Update,
+            (
+                load_visual.run_if(resource_added::<MuJoCoResources>),
+                apply_visual_to_cameras.run_if(resource_exists::<MuJoCoVisual>),
+            )
+                .chain(),
+        );
+    }
+}
+
+#[derive(Resource)]
+struct VisualSettings(MuJoCoVisualPlugin);
+
+/// Visual options read from the compiled model
+#[derive(Resource, Debug, Clone)]
+pub struct MuJoCoVisual {
+    /// `<visual><rgba haze>`, used as the clear color
+    pub haze: Color,
+    /// `<visual><rgba fog>`
+    pub fog_color: Color,
+    /// `<visual><map fogstart fogend>` scaled by the model extent
+    pub fog_start: f32,
+    pub fog_end: f32,
+    /// `<visual><headlight ambient>`
+    pub ambient: Color,
+    /// `<visual><quality shadowsize>`
+    pub shadow_size: usize,
+    /// Cube map built from the `<texture type="skybox">` asset
+    pub skybox: Option<Handle<Image>>,
+}
+
+impl MuJoCoVisual {
+    pub(crate) fn from_simulation(simulation: &impl Backend, images: &mut Assets<Image>) -> Self {
+        let model = wrappers::model(simulation);
+        let vis = &model.vis;
+        let extent = model.stat.extent as f32;
+        let rgba = |c: [f32; 4]| Color::srgba(c[0], c[1], c[2], c[3]);
+
+        MuJoCoVisual {
+            haze: rgba(vis.rgba.haze),
+            fog_color: rgba(vis.rgba.fog),
+            fog_start: vis.map.fogstart * extent,
+            fog_end: vis.map.fogend * extent,
+            ambient: Color::srgb(
+                vis.headlight.ambient[0],
+                vis.headlight.ambient[1],
+                vis.headlight.ambient[2],
+            ),
+            shadow_size: vis.quality.shadowsize.max(1) as usize,
+            skybox: skybox_image(model).map(|image| images.add(image)),
+        }
+    }
+}
+
+/// RGB pixels of a texture
+#[cfg(mujoco3)]
+fn texture_rgb(model: &crate::sys::mjModel, texture: usize, pixels: usize) -> Option<Vec<u8>> {
+    let ntex = model.ntex as usize;
+    let adr = unsafe { wrappers::slice(model.tex_adr, ntex) }[texture] as usize;
+    let nchannel = unsafe { wrappers::slice(model.tex_nchannel, ntex) }[texture] as usize;
+    let data = unsafe { wrappers::slice(model.tex_data, model.ntexdata as usize) };
+    let data = data.get(adr..adr + nchannel * pixels)?;
+    Some(
+        data.chunks_exact(nchannel)
+            .flat_map(|pixel| [0, 1, 2].map(|c| pixel[c.min(nchannel - 1)]))
+            .collect(),
+    )
+}
+
+/// RGB pixels of a texture
+#[cfg(not(mujoco3))]
+fn texture_rgb(model: &crate::sys::mjModel, texture: usize, pixels: usize) -> Option<Vec<u8>> {
+    let adr = unsafe { wrappers::slice(model.tex_adr, model.ntex as usize) }[texture] as usize;
+    let data = unsafe { wrappers::slice(model.tex_rgb, model.ntexdata as usize) };
+    Some(data.get(adr..adr + 3 * pixels)?.to_vec())
+}
+
+/// Cube map of the first skybox texture. MuJoCo stores cube textures as six square faces
+/// stacked vertically (right, left, up, down, front, back), a single square image is used
+/// for every face
+fn skybox_image(model: &crate::sys::mjModel) -> Option<Image> {
+    let ntex = model.ntex as usize;
+    let texture = unsafe { wrappers::slice(model.tex_type, ntex) }
+        .iter()
+        .position(|tex_type| *tex_type == TEXTURE_SKYBOX)?;
+    let width = unsafe { wrappers::slice(model.tex_width, ntex) }[texture] as usize;
+    let height = unsafe { wrappers::slice(model.tex_height, ntex) }[texture] as usize;
+    if width == 0 || height == 0 {
+        return None;
+    }
+    let rgb = texture_rgb(model, texture, width * height)?;
+
+    let face = width * width * 4;
+    let faces = if height == 6 * width { 6 } else { 1 };
+    let rgba: Vec<u8> = rgb
+        .chunks_exact(3)
+        .take(faces * width * width)
+        .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+        .collect();
+    if rgba.len() < faces * face {
+        return None;
+    }
+    let data = if faces == 6 {
+        rgba
+    } else {
+        rgba[..face].repeat(6)
+    };
+
+    let mut image = Image::new(
+        Extent3d {
+            width: width as u32,
+            height: (6 * width) as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    image.reinterpret_stacked_2d_as_array(6);
+    image.texture_view_descriptor = Some(TextureViewDescriptor {
+        dimension: Some(TextureViewDimension::Cube),
+        ..default()
+    });
+    Some(image)
+}
+
+fn load_visual(
+    mut commands: Commands,
+    mujoco: Res<MuJoCoSimulation>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let visual = MuJoCoVisual::from_simulation(&mujoco.lock().unwrap(), &mut images);
+
+    commands.insert_resource(ClearColor(visual.haze));
+    commands.insert_resource(AmbientLight {
+        color: visual.ambient,
+        ..default()
+    });
+    commands.insert_resource(DirectionalLightShadowMap {
+        size: visual.shadow_size,
+    });
+    commands.insert_resource(visual);
+}
+
+/// Add fog and skybox to 3d cameras, again for every camera once a model is (re)loaded
+fn apply_visual_to_cameras(
+    mut commands: Commands,
+    settings: Res<VisualSettings>,
+    visual: Res<MuJoCoVisual>,
+    cameras: Query<Entity, With<Camera3d>>,
+    added_cameras: Query<Entity, Added<Camera3d>>,
+) {
+    let cameras: Vec<Entity> = if visual.is_changed() {
+        cameras.iter().collect()
+    } else {
+        added_cameras.iter().collect()
+    };
+
+    for camera in cameras {
+        let mut camera = commands.entity(camera);
+        if settings.0.fog {
+            camera.insert(DistanceFog {
+                color: visual.fog_color,
+                falloff: FogFalloff::Linear {
+                    start: visual.fog_start,
+                    end: visual.fog_end,
+                },
+                ..default()
+            });
+        }
+        if let Some(image) = visual.skybox.as_ref().filter(|_| settings.0.skybox) {
+            camera.insert(Skybox {
+                image: image.clone(),
+                brightness: 1000.0,
+                rotation: Quat::IDENTITY,
+            });
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use bevy::prelude::*;
+
+use crate::{wrappers, MuJoCoSimulation};
+
+/// Kinds of runtime warnings MuJoCo counts in `mjData.warning` (`mjtWarning`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MuJoCoWarningKind {
+    /// A body has (nearly) zero mass or inertia
+    Inertia,
+    /// More contacts than fit in the arena
+    ContactFull,
+    /// More constraints than fit in the arena
+    ConstraintFull,
+    /// Too many visual geoms
+    VisualGeomFull,
+    /// Bad qpos, MuJoCo reset the data
+    BadQpos,
+    /// Bad qvel, MuJoCo reset the data
+    BadQvel,
+    /// Bad qacc, MuJoCo reset the data
+    BadQacc,
+    /// Bad control value
+    BadCtrl,
+}
+
+impl MuJoCoWarningKind {
+    pub const ALL: [MuJoCoWarningKind; 8] = [
+        MuJoCoWarningKind::Inertia,
+        MuJoCoWarningKind::ContactFull,
+        MuJoCoWarningKind::ConstraintFull,
+        MuJoCoWarningKind::VisualGeomFull,
+        MuJoCoWarningKind::BadQpos,
+        MuJoCoWarningKind::BadQvel,
+        MuJoCoWarningKind::BadQacc,
+        MuJoCoWarningKind::BadCtrl,
+    ];
+
+    /// Index into `mjData.warning`
+    pub(crate) fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Sent after a frame in which MuJoCo raised a warning
+#[derive(Event, Debug, Clone)]
+pub struct MuJoCoWarning {
+    pub kind: MuJoCoWarningKind,
+    /// Number of times the warning was raised during the frame
+    pub count: i32,
+    /// Number of times the warning was raised since the last reset
+    pub total: i32,
+    /// `lastinfo` of the warning: a body, dof, actuator id or a buffer size depending on the kind
+    pub info: i32,
+    /// Name of the body or actuator `info` refers to, when it refers to one
+    pub object: Option<String>,
+}
+
+/// Name of the body or actuator a warning's `lastinfo` points at
+fn warning_object(
+    model: &crate::sys::mjModel,
+    kind: MuJoCoWarningKind,
+    info: i32,
+) -> Option<String> {
+    let index = usize::try_from(info).ok()?;
+    let body_name = |body: i32| -> Option<String> {
+        let body = usize::try_from(body).ok()?;
+        let adr =
+            *unsafe { wrappers::slice(model.name_bodyadr, model.nbody as usize) }.get(body)?;
+        Some(wrappers::name(model, adr))
+    };
+
+    match kind {
+        MuJoCoWarningKind::Inertia => body_name(info),
+        MuJoCoWarningKind::BadQpos => {
+            // the joint whose qpos range contains the index
+            let njnt = model.njnt as usize;
+            let joint = unsafe { wrappers::slice(model.jnt_qposadr, njnt) }
+                .iter()
+                .rposition(|adr| *adr as usize <= index)?;
+            body_name(unsafe { wrappers::slice(model.jnt_bodyid, njnt) }[joint])
+        }
+        MuJoCoWarningKind::BadQvel | MuJoCoWarningKind::BadQacc => {
+            body_name(*unsafe { wrappers::slice(model.dof_bodyid, model.nv as usize) }.get(index)?)
+        }
+        MuJoCoWarningKind::BadCtrl => Some(wrappers::name(
+            model,
+            *unsafe { wrappers::slice(model.name_actuatoradr, model.nu as usize) }.get(index)?,
+        )),
+        _ => None,
+    }
+}
+
+/// Send a `MuJoCoWarning` for every warning counter that increased since the last frame
+pub fn report_warnings(
+    mujoco: Res<MuJoCoSimulation>,
+    mut previous: Local<[i32; 8]>,
+    mut warnings: EventWriter<MuJoCoWarning>,
+) {
+    let mujoco = mujoco.lock().unwrap();
+    let model = wrappers::model(&mujoco);
+    let data = wrappers::data(&mujoco);
+
+    for kind in MuJoCoWarningKind::ALL {
+        let warning = &data.warning[kind.index()];
+        // counters start from zero again after a reset
+        let before = if warning.number < previous[kind.index()] {
+            0
+        } else {
+            previous[kind.index()]
+        };
+        previous[kind.index()] = warning.number;
+        if warning.number == before {
+            continue;
+        }
+
+        warnings.send(MuJoCoWarning {
+            kind,
+            count: warning.number - before,
+            total: warning.number,
+            info: warning.lastinfo,
+            object: warning_object(model, kind, warning.lastinfo),
+        });
+    }
+}
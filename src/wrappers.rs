@@ -0,0 +1,201 @@
+//! Raw accessors for the `mjModel` / `mjData` fields of a `Backend`
+
+use std::ffi::{c_char, CStr};
+use std::sync::Mutex;
+
+use crate::{
+    sys::{mjData, mjModel},
+    Backend,
+};
+
+/// Borrow the compiled model behind a simulation
+pub(crate) fn model<B: Backend + ?Sized>(sim: &B) -> &mjModel {
+    unsafe { &*sim.model_ptr() }
+}
+
+/// Mutably borrow the compiled model behind a simulation
+///
+/// # Safety
+///
+/// The caller must have exclusive access to the simulation, i.e. hold its lock or a `&mut`,
+/// and use no other reference to the model, e.g. from `model` or a previous `model_mut`, for
+/// as long as the returned one is used
+pub(crate) unsafe fn model_mut<'a, B: Backend + ?Sized>(sim: &B) -> &'a mut mjModel {
+    &mut *sim.model_ptr()
+}
+
+/// Borrow the simulation state behind a simulation
+pub(crate) fn data<B: Backend + ?Sized>(sim: &B) -> &mjData {
+    unsafe { &*sim.data_ptr() }
+}
+
+/// Mutably borrow the simulation state behind a simulation
+///
+/// # Safety
+///
+/// Same as `model_mut`, for the state: no other reference to it, e.g. from `data`, may be used
+/// for as long as the returned one is
+pub(crate) unsafe fn data_mut<'a, B: Backend + ?Sized>(sim: &B) -> &'a mut mjData {
+    &mut *sim.data_ptr()
+}
+
+/// View a raw model/data array as a slice
+///
+/// # Safety
+///
+/// A non-null `ptr` must point to `len` initialized elements, e.g. an `mjModel` / `mjData`
+/// array of the simulation the caller holds locked, that stay alive and aren't written to for
+/// as long as the returned slice is used
+pub(crate) unsafe fn slice<'a, T>(ptr: *const T, len: usize) -> &'a [T] {
+    if ptr.is_null() || len == 0 {
+        return &[];
+    }
+    std::slice::from_raw_parts(ptr, len)
+}
+
+/// View a raw model/data array as a mutable slice
+///
+/// # Safety
+///
+/// Same as `slice`, and no other slice or reference to the same elements may be used for as
+/// long as the returned slice is, so don't view an array twice at the same time
+pub(crate) unsafe fn slice_mut<'a, T>(ptr: *mut T, len: usize) -> &'a mut [T] {
+    if ptr.is_null() || len == 0 {
+        return &mut [];
+    }
+    std::slice::from_raw_parts_mut(ptr, len)
+}
+
+/// Activation flags of equality constraints, stored in `mjData` since MuJoCo 3.0
+///
+/// # Safety
+///
+/// Same as `data_mut`
+#[cfg(mujoco3)]
+pub(crate) unsafe fn eq_active<'a, B: Backend + ?Sized>(sim: &B) -> &'a mut [u8] {
+    let neq = model(sim).neq as usize;
+    slice_mut(data_mut(sim).eq_active, neq)
+}
+
+/// Activation flags of equality constraints, stored in `mjModel` before MuJoCo 3.0
+///
+/// # Safety
+///
+/// Same as `model_mut`
+#[cfg(not(mujoco3))]
+pub(crate) unsafe fn eq_active<'a, B: Backend + ?Sized>(sim: &B) -> &'a mut [u8] {
+    let model = model(sim);
+    slice_mut(model.eq_active, model.neq as usize)
+}
+
+/// MuJoCo versions whose `mjModel` / `mjData` layout the bindings describe: `mujoco-rs-sys`
+/// binds MuJoCo 2.3
+#[cfg(not(mujoco3))]
+const BUILT_FOR: std::ops::RangeInclusive<i32> = 230..=239;
+
+/// `mujoco3` bindings are generated from the installed headers, whose layout changes between
+/// minor versions
+#[cfg(mujoco3)]
+const BUILT_FOR: std::ops::RangeInclusive<i32> =
+    crate::sys::mjVERSION_HEADER as i32..=crate::sys::mjVERSION_HEADER as i32;
+
+fn version_string(version: i32) -> String {
+    format!("{}.{}.{}", version / 100, version / 10 % 10, version % 10)
+}
+
+/// Check that the MuJoCo library loaded at runtime has the `mjModel` / `mjData` layout the crate
+/// was built for
+pub(crate) fn check_library_version() -> Result<(), String> {
+    let version = unsafe { crate::sys::no_render::mj_version() };
+    if BUILT_FOR.contains(&version) {
+        return Ok(());
+    }
+    let (first, last) = (*BUILT_FOR.start(), *BUILT_FOR.end());
+    let built_for = if first == last {
+        version_string(first)
+    } else {
+        format!("{} to {}", version_string(first), version_string(last))
+    };
+    Err(format!(
+        "MuJoCo library version {} doesn't match MuJoCo {built_for} the crate was built for",
+        version_string(version)
+    ))
+}
+
+/// Read an object name from the model `names` buffer at a given address
+pub(crate) fn name(model: &mjModel, adr: i32) -> String {
+    if model.names.is_null() || adr < 0 || adr >= model.nnames {
+        return String::new();
+    }
+    unsafe { CStr::from_ptr(model.names.add(adr as usize)) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+static CAPTURED_WARNINGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+unsafe extern "C" fn capture_warning(message: *const c_char) {
+    let message = CStr::from_ptr(message).to_string_lossy().into_owned();
+    CAPTURED_WARNINGS.lock().unwrap().push(message);
+}
+
+/// Run `f` while collecting every warning MuJoCo reports through `mju_user_warning`
+pub(crate) fn capture_warnings<R>(f: impl FnOnce() -> R) -> (R, Vec<String>) {
+    let previous = unsafe { crate::sys::no_render::mju_user_warning };
+    unsafe { crate::sys::no_render::mju_user_warning = Some(capture_warning) };
+    let result = f();
+    unsafe { crate::sys::no_render::mju_user_warning = previous };
+
+    let warnings = std::mem::take(&mut *CAPTURED_WARNINGS.lock().unwrap());
+    (result, warnings)
+}
+
+/// Names of all objects of a kind, given the kind's `name_*adr` array
+pub(crate) fn names(model: &mjModel, adr: *const i32, count: i32) -> Vec<String> {
+    unsafe { slice(adr, count as usize) }
+        .iter()
+        .map(|adr| name(model, *adr))
+        .collect()
+}
+
+/// Id of the object of a kind with the given name, given the kind's `name_*adr` array
+pub(crate) fn name_to_id(
+    model: &mjModel,
+    adr: *const i32,
+    count: i32,
+    name: &str,
+) -> Option<usize> {
+    unsafe { slice(adr, count as usize) }
+        .iter()
+        .position(|adr| self::name(model, *adr) == name)
+}
+
+/// Id of a body by name
+pub(crate) fn body_id(model: &mjModel, name: &str) -> Option<usize> {
+    name_to_id(model, model.name_bodyadr, model.nbody, name)
+}
+
+/// Name of an object of any type (`mjtObj`) by id, `None` for unnamed objects
+pub(crate) fn object_name(model: &mjModel, object_type: i32, id: i32) -> Option<String> {
+    let name = unsafe { crate::sys::no_render::mj_id2name(model, object_type, id) };
+    if name.is_null() {
+        return None;
+    }
+    Some(
+        unsafe { CStr::from_ptr(name) }
+            .to_string_lossy()
+            .into_owned(),
+    )
+}
+
+/// Geoms of a contact, -1 for flex contacts
+#[cfg(mujoco3)]
+pub(crate) fn contact_geoms(contact: &crate::sys::mjContact) -> [i32; 2] {
+    contact.geom
+}
+
+/// Geoms of a contact
+#[cfg(not(mujoco3))]
+pub(crate) fn contact_geoms(contact: &crate::sys::mjContact) -> [i32; 2] {
+    [contact.geom1, contact.geom2]
+}
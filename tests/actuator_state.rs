@@ -0,0 +1,35 @@
+//! Actuator force readback in actuation space (`actuator_force`) and joint space
+//! (`qfrc_actuator`)
+
+use bevy_mujoco::*;
+
+/// One hinge driven by a motor with gear 2
+fn geared_hinge() -> MuJoCoSimulation {
+    MjcfBuilder::new()
+        .worldbody()
+        .add_body("arm", [0.0, 0.0, 1.0])
+        .add_joint("hinge", "hinge", [0.0, 1.0, 0.0])
+        .add_geom("arm", "capsule", &[0.05, 0.2])
+        .end()
+        .actuator()
+        .add_motor("motor", "hinge", 2.0)
+        .load()
+        .expect("geared hinge compiles")
+}
+
+#[test]
+fn motor_forces_read_back_in_actuation_and_joint_space() {
+    let mujoco = geared_hinge();
+    {
+        let mut simulation = mujoco.lock().unwrap();
+        simulation.control(&[1.5]);
+        simulation.step();
+    }
+
+    let (mut actuator_force, mut qfrc_actuator) = ([0.0; 1], [0.0; 1]);
+    assert_eq!(mujoco.copy_actuator_force_into(&mut actuator_force), 1);
+    assert_eq!(mujoco.copy_qfrc_actuator_into(&mut qfrc_actuator), 1);
+    // a motor's force is its control, the joint receives it times the gear
+    assert!((actuator_force[0] - 1.5).abs() < 1e-9, "{actuator_force:?}");
+    assert!((qfrc_actuator[0] - 3.0).abs() < 1e-9, "{qfrc_actuator:?}");
+}
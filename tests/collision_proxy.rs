@@ -0,0 +1,131 @@
+//! Collision proxies: a bevy entity mirrored into the simulation pushes a free body and hears
+//! about the contacts
+
+use bevy::prelude::*;
+use bevy_mujoco::*;
+
+/// A ball floating without gravity next to one proxy pool body
+fn ball_and_proxy_pool() -> MuJoCoSimulation {
+    MjcfBuilder::new()
+        .option("gravity", "0 0 0")
+        .worldbody()
+        .add_body("ball", [0.0, 0.0, 1.0])
+        .add_freejoint()
+        .add_geom("ball", "sphere", &[0.1])
+        .end()
+        .add(
+            MjcfElement::new("body")
+                .with_attribute("name", format!("{COLLISION_PROXY_PREFIX}0"))
+                .with_attribute("mocap", "true")
+                .with_child(
+                    MjcfElement::new("geom")
+                        .with_attribute("name", "proxy")
+                        .with_attribute("type", "sphere")
+                        .with_attribute("size", "0.05"),
+                ),
+        )
+        .load()
+        .expect("proxy model compiles")
+}
+
+/// Contacts triggered on proxy entities
+#[derive(Resource, Default)]
+struct Contacts(Vec<MuJoCoProxyContact>);
+
+fn app() -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins((
+            AssetPlugin::default(),
+            TransformPlugin,
+            HierarchyPlugin,
+            bevy::gizmos::GizmoPlugin,
+        ))
+        .init_asset::<Mesh>()
+        .init_asset::<StandardMaterial>()
+        .insert_resource(MuJoCoPluginSettings {
+            spawn_mode: SpawnMode::TransformOnly,
+            ..default()
+        })
+        .insert_resource(ball_and_proxy_pool())
+        .init_resource::<Contacts>()
+        .add_plugins((MuJoCoPlugin, MuJoCoCollisionProxyPlugin))
+        .add_observer(
+            |trigger: Trigger<MuJoCoProxyContact>, mut contacts: ResMut<Contacts>| {
+                contacts.0.push(trigger.event().clone());
+            },
+        );
+    app
+}
+
+#[test]
+fn proxy_pushes_free_body_and_reports_contacts() {
+    let mut app = app();
+    // overlapping the ball on its -x side, MuJoCo (-0.1, 0, 1) in the bevy scene frame
+    let proxy = app
+        .world_mut()
+        .spawn((
+            MuJoCoCollisionProxy::new(FreeBodyShape::Sphere { radius: 0.05 }),
+            Transform::from_xyz(-0.1, 1.0, 0.0),
+        ))
+        .id();
+    for _ in 0..20 {
+        app.update();
+    }
+
+    let world = app.world();
+    assert!(world
+        .resource::<MuJoCoCollisionProxies>()
+        .geom(proxy)
+        .is_some());
+    let ball = world.resource::<MuJoCoEntityMap>().body_by_name("ball");
+    let contacts = &world.resource::<Contacts>().0;
+    let contact = contacts
+        .iter()
+        .find(|contact| contact.proxy == proxy)
+        .expect("proxy touched the ball");
+    assert_eq!(contact.other, ball);
+    // from the proxy towards the ball
+    assert!(contact.normal.x > 0.9, "normal {}", contact.normal);
+
+    let mujoco = world.resource::<MuJoCoSimulation>();
+    let ball = mujoco
+        .free_bodies()
+        .into_iter()
+        .find(|body| body.name == "ball")
+        .unwrap();
+    assert!(
+        ball.velocity.linear[0] > 0.0,
+        "ball pushed away, velocity {:?}",
+        ball.velocity.linear
+    );
+}
+
+#[test]
+fn removed_proxies_return_to_the_pool() {
+    let mut app = app();
+    let proxy = app
+        .world_mut()
+        .spawn((
+            MuJoCoCollisionProxy::new(FreeBodyShape::Box {
+                half_extents: [0.1, 0.1, 0.1],
+            }),
+            Transform::from_xyz(2.0, 0.0, 0.0),
+        ))
+        .id();
+    for _ in 0..3 {
+        app.update();
+    }
+    assert_eq!(
+        app.world().resource::<MuJoCoCollisionProxies>().available(),
+        0
+    );
+
+    app.world_mut()
+        .entity_mut(proxy)
+        .remove::<MuJoCoCollisionProxy>();
+    app.update();
+    let proxies = app.world().resource::<MuJoCoCollisionProxies>();
+    assert_eq!(proxies.available(), 1);
+    assert_eq!(proxies.geom(proxy), None);
+}
@@ -0,0 +1,91 @@
+//! Impulses and pose readback of passive free bodies, checked against rigid-body mechanics
+
+use bevy_mujoco::*;
+
+const TOLERANCE: f64 = 1e-6;
+
+/// A free sphere and a free box floating without gravity
+fn floating_bodies() -> MuJoCoSimulation {
+    MjcfBuilder::new()
+        .option("gravity", "0 0 0")
+        .worldbody()
+        .add_body("ball", [0.0, 0.0, 1.0])
+        .add_freejoint()
+        .add_geom("ball", "sphere", &[0.1])
+        .end()
+        .add_body("crate", [1.0, 0.0, 1.0])
+        .add_freejoint()
+        .add_geom("crate", "box", &[0.1, 0.2, 0.3])
+        .end()
+        .load()
+        .expect("floating bodies compile")
+}
+
+fn free_body(mujoco: &MuJoCoSimulation, name: &str) -> FreeBody {
+    mujoco
+        .free_bodies()
+        .into_iter()
+        .find(|body| body.name == name)
+        .unwrap_or_else(|| panic!("{name} is a free body"))
+}
+
+fn assert_close(actual: [f64; 3], expected: [f64; 3], what: &str) {
+    for axis in 0..3 {
+        assert!(
+            (actual[axis] - expected[axis]).abs() < TOLERANCE,
+            "{what}: {actual:?}, expected {expected:?}"
+        );
+    }
+}
+
+#[test]
+fn free_bodies_report_their_poses() {
+    let mujoco = floating_bodies();
+    let bodies = mujoco.free_bodies();
+    assert_eq!(bodies.len(), 2);
+    assert_close(free_body(&mujoco, "ball").position, [0.0, 0.0, 1.0], "ball");
+    assert_close(
+        free_body(&mujoco, "crate").position,
+        [1.0, 0.0, 1.0],
+        "crate",
+    );
+}
+
+#[test]
+fn impulse_at_center_of_mass_changes_linear_velocity() {
+    let mujoco = floating_bodies();
+    let ball = mujoco.body_id("ball").unwrap();
+    let mass = mujoco.body_mass(ball).unwrap();
+
+    assert!(mujoco.apply_impulse(ball, [0.5, -1.0, 2.0], [0.0, 0.0, 1.0]));
+    let velocity = free_body(&mujoco, "ball").velocity;
+    assert_close(
+        velocity.linear,
+        [0.5 / mass, -1.0 / mass, 2.0 / mass],
+        "linear velocity",
+    );
+    assert_close(velocity.angular, [0.0; 3], "angular velocity");
+}
+
+#[test]
+fn angular_impulse_spins_around_the_principal_axes() {
+    let mujoco = floating_bodies();
+    let id = mujoco.body_id("crate").unwrap();
+    let inertia = mujoco.body_inertia(id).unwrap();
+
+    assert!(mujoco.apply_angular_impulse(id, [0.0, 0.0, 0.3]));
+    let velocity = free_body(&mujoco, "crate").velocity;
+    assert_close(
+        velocity.angular,
+        [0.0, 0.0, 0.3 / inertia[2]],
+        "angular velocity",
+    );
+    assert_close(velocity.linear, [0.0; 3], "linear velocity");
+}
+
+#[test]
+fn impulses_on_unknown_bodies_are_rejected() {
+    let mujoco = floating_bodies();
+    assert!(!mujoco.apply_impulse(0, [1.0, 0.0, 0.0], [0.0; 3]));
+    assert!(!mujoco.apply_impulse(100, [1.0, 0.0, 0.0], [0.0; 3]));
+}
@@ -0,0 +1,258 @@
+//! Golden images of the geom primitives. Each geom's mesh is rasterized on the CPU where the
+//! plugin placed it and compared with `tests/golden/<geom>.png`, so changes to mesh
+//! generation or geom transforms that change how primitives look fail here, without a GPU.
+//!
+//! A missing golden image fails the test. The images aren't in the tree yet: record them with
+//! `UPDATE_GOLDEN=1 cargo test --test golden_images` against the MuJoCo version CI uses and
+//! commit `tests/golden`, and record them again after an intended change. Failed comparisons
+//! write the rendered image next to the test binaries (`target/tmp/golden`)
+
+use std::path::{Path, PathBuf};
+
+use bevy::{prelude::*, render::mesh::VertexAttributeValues};
+use bevy_mujoco::*;
+use image::GrayImage;
+
+const SIZE: u32 = 96;
+/// Half width of the square seen by the camera, in meters
+const EXTENT: f32 = 0.5;
+/// Gray levels two pixels may differ by and still match
+const LEVEL_TOLERANCE: u8 = 8;
+/// Share of pixels allowed to differ, for rasterization differences on triangle edges
+const PIXEL_TOLERANCE: f32 = 0.01;
+
+/// Geoms rendered by the plugin, each in its own static body spaced along MuJoCo's x axis
+const GEOMS: [(&str, &str, &str); 7] = [
+    ("plane", "plane", "0.4 0.3 0.1"),
+    ("box", "box", "0.3 0.2 0.1"),
+    ("sphere", "sphere", "0.25"),
+    ("capsule", "capsule", "0.12 0.2"),
+    ("ellipsoid", "ellipsoid", "0.3 0.15 0.2"),
+    ("cylinder", "cylinder", "0.15 0.2"),
+    ("mesh", "mesh", ""),
+];
+
+const SPACING: f64 = 2.0;
+const HEIGHT: f64 = 0.5;
+
+fn primitives() -> MuJoCoSimulation {
+    let mut builder = MjcfBuilder::new().asset().add(
+        MjcfElement::new("mesh")
+            .with_attribute("name", "tetrahedron")
+            .with_attribute("vertex", "0 0 0  0.4 0 0  0 0.3 0  0 0 0.35"),
+    );
+    builder = builder.worldbody();
+    for (index, (name, geom_type, size)) in GEOMS.iter().enumerate() {
+        let mut geom = MjcfElement::new("geom")
+            .with_attribute("name", name)
+            .with_attribute("type", geom_type)
+            // turned so every primitive shows three sides
+            .with_attribute("euler", "25 -15 40");
+        geom = match *geom_type {
+            "mesh" => geom.with_attribute("mesh", "tetrahedron"),
+            _ => geom.with_attribute("size", size),
+        };
+        builder = builder
+            .add_body(name, [SPACING * index as f64, 0.0, HEIGHT])
+            .add(geom)
+            .end();
+    }
+    builder.load().expect("primitives compile")
+}
+
+fn app() -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins((
+            AssetPlugin::default(),
+            TransformPlugin,
+            HierarchyPlugin,
+            bevy::gizmos::GizmoPlugin,
+        ))
+        .init_asset::<Mesh>()
+        .init_asset::<StandardMaterial>()
+        .init_asset::<Image>()
+        .insert_resource(MuJoCoPluginSettings {
+            spawn_mode: SpawnMode::Full,
+            pause_simulation: true,
+            ..default()
+        })
+        .insert_resource(primitives())
+        .add_plugins(MuJoCoPlugin);
+    // spawn the scene and attach meshes
+    for _ in 0..3 {
+        app.update();
+    }
+    app
+}
+
+/// Orthographic view of the square around `target`, looking down diagonally
+struct View {
+    target: Vec3,
+    right: Vec3,
+    up: Vec3,
+    back: Vec3,
+}
+
+impl View {
+    fn new(target: Vec3) -> Self {
+        let back = Vec3::new(1.0, 1.2, 2.0).normalize();
+        let right = Vec3::Y.cross(back).normalize();
+        View {
+            target,
+            right,
+            up: back.cross(right),
+            back,
+        }
+    }
+
+    /// Pixel coordinates and depth, larger is closer
+    fn project(&self, point: Vec3) -> Vec3 {
+        let point = point - self.target;
+        let scale = SIZE as f32 / (2.0 * EXTENT);
+        Vec3::new(
+            (point.dot(self.right) + EXTENT) * scale,
+            (EXTENT - point.dot(self.up)) * scale,
+            point.dot(self.back),
+        )
+    }
+}
+
+/// Flat shaded triangles with a depth buffer, background black
+fn rasterize(view: &View, triangles: &[[Vec3; 3]]) -> GrayImage {
+    let light = Vec3::new(0.3, 1.0, 0.5).normalize();
+    let mut depth = vec![f32::NEG_INFINITY; (SIZE * SIZE) as usize];
+    let mut image = GrayImage::new(SIZE, SIZE);
+
+    for triangle in triangles {
+        let normal = (triangle[1] - triangle[0]).cross(triangle[2] - triangle[0]);
+        if normal.length_squared() < 1e-12 {
+            continue;
+        }
+        let shade = (40.0 + 215.0 * normal.normalize().dot(light).abs()) as u8;
+        let [a, b, c] = triangle.map(|vertex| view.project(vertex));
+        let area = (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x);
+        if area.abs() < 1e-9 {
+            continue;
+        }
+
+        let (min_x, max_x) = (a.x.min(b.x).min(c.x), a.x.max(b.x).max(c.x));
+        let (min_y, max_y) = (a.y.min(b.y).min(c.y), a.y.max(b.y).max(c.y));
+        let clamp = |value: f32| value.clamp(0.0, SIZE as f32 - 1.0) as u32;
+        for y in clamp(min_y.floor())..=clamp(max_y.ceil()) {
+            for x in clamp(min_x.floor())..=clamp(max_x.ceil()) {
+                let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+                let wa = ((b.x - px) * (c.y - py) - (b.y - py) * (c.x - px)) / area;
+                let wb = ((c.x - px) * (a.y - py) - (c.y - py) * (a.x - px)) / area;
+                let wc = 1.0 - wa - wb;
+                if wa < 0.0 || wb < 0.0 || wc < 0.0 {
+                    continue;
+                }
+                let z = wa * a.z + wb * b.z + wc * c.z;
+                let index = (y * SIZE + x) as usize;
+                if z > depth[index] {
+                    depth[index] = z;
+                    image.put_pixel(x, y, image::Luma([shade]));
+                }
+            }
+        }
+    }
+    image
+}
+
+/// Triangles of an entity's mesh in world coordinates
+fn world_triangles(mesh: &Mesh, transform: &GlobalTransform) -> Vec<[Vec3; 3]> {
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        return vec![];
+    };
+    let positions: Vec<Vec3> = positions
+        .iter()
+        .map(|position| transform.transform_point(Vec3::from(*position)))
+        .collect();
+    let indices: Vec<usize> = match mesh.indices() {
+        Some(indices) => indices.iter().collect(),
+        None => (0..positions.len()).collect(),
+    };
+    indices
+        .chunks_exact(3)
+        .map(|t| [positions[t[0]], positions[t[1]], positions[t[2]]])
+        .collect()
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.png"))
+}
+
+/// Compare with the golden image, recording it when asked to
+fn assert_matches_golden(name: &str, image: &GrayImage) {
+    let path = golden_path(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        image.save(&path).unwrap();
+        return;
+    }
+    assert!(
+        path.exists(),
+        "{name}: no golden image at {}, record it with UPDATE_GOLDEN=1",
+        path.display()
+    );
+
+    let golden = image::open(&path).unwrap().to_luma8();
+    assert_eq!(
+        golden.dimensions(),
+        image.dimensions(),
+        "{name}: image size"
+    );
+    let differing = golden
+        .pixels()
+        .zip(image.pixels())
+        .filter(|(a, b)| a.0[0].abs_diff(b.0[0]) > LEVEL_TOLERANCE)
+        .count();
+    let share = differing as f32 / (SIZE * SIZE) as f32;
+    if share > PIXEL_TOLERANCE {
+        let actual = Path::new(env!("CARGO_TARGET_TMPDIR"))
+            .join("golden")
+            .join(format!("{name}.png"));
+        std::fs::create_dir_all(actual.parent().unwrap()).unwrap();
+        image.save(&actual).unwrap();
+        panic!(
+            "{name}: {:.1}% of pixels differ from {}, rendered image written to {}",
+            100.0 * share,
+            path.display(),
+            actual.display()
+        );
+    }
+}
+
+#[test]
+fn geom_primitives_match_golden_images() {
+    let mut app = app();
+    let world = app.world_mut();
+    let mut geoms = world.query::<(&Mesh3d, &GlobalTransform)>();
+    let world = &*world;
+    let entity_map = world.resource::<MuJoCoEntityMap>();
+    let meshes = world.resource::<Assets<Mesh>>();
+
+    for (index, (name, ..)) in GEOMS.iter().enumerate() {
+        let entity = entity_map
+            .geom_by_name(name)
+            .unwrap_or_else(|| panic!("{name}: no geom entity"));
+        let (mesh, transform) = geoms
+            .get(world, entity)
+            .unwrap_or_else(|_| panic!("{name}: geom entity without a mesh"));
+        let mesh = meshes.get(&mesh.0).unwrap();
+
+        // MuJoCo's z axis is bevy's y axis
+        let target = Vec3::new((SPACING * index as f64) as f32, HEIGHT as f32, 0.0);
+        let image = rasterize(&View::new(target), &world_triangles(mesh, transform));
+        assert!(
+            image.pixels().any(|pixel| pixel.0[0] > 0),
+            "{name}: nothing rendered"
+        );
+        assert_matches_golden(name, &image);
+    }
+}
@@ -0,0 +1,25 @@
+use bevy_mujoco::*;
+
+fn ball() -> MjcfBuilder {
+    MjcfBuilder::new()
+        .worldbody()
+        .add_geom("floor", "plane", &[1.0, 1.0, 0.1])
+        .add_body("ball", [0.0, 0.0, 0.5])
+        .add_freejoint()
+        .add_geom("ball", "sphere", &[0.05])
+        .end()
+}
+
+#[test]
+fn repeated_load_unload_frees_simulations() {
+    let before = MuJoCoSimulation::live_count();
+
+    for _ in 0..50 {
+        let simulation = ball().load().expect("model compiles");
+        assert_eq!(MuJoCoSimulation::live_count(), before + 1);
+
+        simulation.lock().unwrap().step();
+        drop(simulation);
+        assert_eq!(MuJoCoSimulation::live_count(), before);
+    }
+}
@@ -0,0 +1,279 @@
+//! Steps models headless and compares the world transforms of body and geom entities with
+//! MuJoCo's `xpos` / `xmat` and `geom_xpos` / `geom_xmat`, so changes to the conversion math
+//! that move rendered bodies away from the simulated ones fail here
+
+use bevy::prelude::*;
+use bevy_mujoco::*;
+
+const FRAMES: usize = 40;
+const STEPS_PER_FRAME: usize = 5;
+/// f32 transforms of f64 poses, a few meters from the origin
+const POSITION_TOLERANCE: f32 = 1e-4;
+const ROTATION_TOLERANCE: f32 = 1e-3;
+
+fn app(simulation: MuJoCoSimulation, world_transform: Transform) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins((
+            AssetPlugin::default(),
+            TransformPlugin,
+            HierarchyPlugin,
+            bevy::gizmos::GizmoPlugin,
+        ))
+        .init_asset::<Mesh>()
+        .init_asset::<StandardMaterial>()
+        .insert_resource(MuJoCoPluginSettings {
+            spawn_mode: SpawnMode::TransformOnly,
+            step_mode: StepMode::Manual,
+            world_transform,
+            ..default()
+        })
+        .insert_resource(simulation)
+        .add_plugins(MuJoCoPlugin)
+        .add_systems(Update, |mut steps: EventWriter<MuJoCoStepRequest>| {
+            steps.send(MuJoCoStepRequest {
+                n_steps: STEPS_PER_FRAME,
+            });
+        });
+    app
+}
+
+/// Rotation of a row-major MuJoCo matrix, in the bevy scene frame
+fn rotation_mujoco_2_bevy(xmat: &[f64]) -> Quat {
+    let xmat: Vec<f32> = xmat.iter().map(|value| *value as f32).collect();
+    let rotation = Quat::from_mat3(&Mat3::from_cols_slice(&xmat).transpose());
+    Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2) * rotation
+}
+
+fn position_mujoco_2_bevy(xpos: &[f64]) -> Vec3 {
+    Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2).mul_vec3(Vec3::new(
+        xpos[0] as f32,
+        xpos[1] as f32,
+        xpos[2] as f32,
+    ))
+}
+
+fn slice<'a, T>(ptr: *const T, len: usize) -> &'a [T] {
+    unsafe { std::slice::from_raw_parts(ptr, len) }
+}
+
+/// Compare every body and geom entity with the simulation, in the scene root frame
+fn assert_poses_match(app: &mut App, model_name: &str) {
+    let world = app.world_mut();
+    let mut roots = world.query_filtered::<&GlobalTransform, With<MuJoCoSceneRoot>>();
+    let mut transforms = world.query::<&GlobalTransform>();
+    let world = &*world;
+    let root = *roots.single(world);
+    let entity_map = world.resource::<MuJoCoEntityMap>();
+
+    let simulation = world.resource::<MuJoCoSimulation>().lock().unwrap();
+    let (model, data): (&mjModel, &mjData) =
+        unsafe { (&*simulation.model_ptr(), &*simulation.data_ptr()) };
+    let (nbody, ngeom) = (model.nbody as usize, model.ngeom as usize);
+    let xpos = slice(data.xpos, 3 * nbody);
+    let xmat = slice(data.xmat, 9 * nbody);
+    let geom_xpos = slice(data.geom_xpos, 3 * ngeom);
+    let geom_xmat = slice(data.geom_xmat, 9 * ngeom);
+    let geom_type = slice(model.geom_type, ngeom);
+    let time = data.time;
+
+    for (&id, &entity) in entity_map.bodies.iter() {
+        let id = id as usize;
+        let rendered = transforms.get(world, entity).unwrap().reparented_to(&root);
+        let position = position_mujoco_2_bevy(&xpos[3 * id..3 * id + 3]);
+        let rotation = rotation_mujoco_2_bevy(&xmat[9 * id..9 * id + 9]);
+        assert!(
+            rendered.translation.distance(position) < POSITION_TOLERANCE,
+            "{model_name}: body {id} at t = {time:.3} rendered at {}, simulated at {position}",
+            rendered.translation,
+        );
+        assert!(
+            rendered.rotation.angle_between(rotation) < ROTATION_TOLERANCE,
+            "{model_name}: body {id} at t = {time:.3} rendered rotated {}, simulated {rotation}",
+            rendered.rotation,
+        );
+    }
+
+    for (&id, &entity) in entity_map.geoms.iter() {
+        let id = id as usize;
+        let rendered = transforms.get(world, entity).unwrap().reparented_to(&root);
+        let position = position_mujoco_2_bevy(&geom_xpos[3 * id..3 * id + 3]);
+        let mut rotation = rotation_mujoco_2_bevy(&geom_xmat[9 * id..9 * id + 9]);
+        // primitive meshes are built along bevy's Y axis
+        if GeomType::from_raw(geom_type[id]) != GeomType::MESH {
+            rotation *= Quat::from_rotation_x(std::f32::consts::FRAC_PI_2);
+        }
+        assert!(
+            rendered.translation.distance(position) < POSITION_TOLERANCE,
+            "{model_name}: geom {id} at t = {time:.3} rendered at {}, simulated at {position}",
+            rendered.translation,
+        );
+        assert!(
+            rendered.rotation.angle_between(rotation) < ROTATION_TOLERANCE,
+            "{model_name}: geom {id} at t = {time:.3} rendered rotated {}, simulated {rotation}",
+            rendered.rotation,
+        );
+    }
+}
+
+fn run(model_name: &str, simulation: MuJoCoSimulation) {
+    // the scene root moved and turned, poses are compared relative to it
+    let world_transform =
+        Transform::from_xyz(1.0, 0.5, -2.0).with_rotation(Quat::from_rotation_y(0.7));
+    run_app(app(simulation, world_transform), model_name);
+}
+
+fn run_app(mut app: App, model_name: &str) {
+    for frame in 0..FRAMES {
+        app.update();
+        // the scene is spawned during the first frames
+        if frame >= 2 && frame % 5 == 0 {
+            assert_poses_match(&mut app, model_name);
+        }
+    }
+    assert_poses_match(&mut app, model_name);
+}
+
+/// A chain of hinged links turned against each other, spheres offset from the body frames
+fn pendulum_chain() -> MuJoCoSimulation {
+    fn link(index: usize, child: Option<MjcfElement>) -> MjcfElement {
+        let mut link = MjcfElement::new("body")
+            .with_attribute("name", format!("link_{index}"))
+            .with_attribute("pos", "0.1 0.05 -0.3")
+            .with_attribute("euler", "20 -35 60")
+            .with_child(
+                MjcfElement::new("joint")
+                    .with_attribute("name", format!("hinge_{index}"))
+                    .with_attribute("type", "hinge")
+                    .with_attribute("axis", "0.3 1 0.2"),
+            )
+            .with_child(
+                MjcfElement::new("geom")
+                    .with_attribute("name", format!("bob_{index}"))
+                    .with_attribute("type", "sphere")
+                    .with_attribute("size", "0.05")
+                    .with_attribute("pos", "0.02 -0.04 -0.15"),
+            );
+        if let Some(child) = child {
+            link = link.with_child(child);
+        }
+        link
+    }
+
+    let chain = (0..4)
+        .rev()
+        .fold(None, |child, index| Some(link(index, child)));
+    MjcfBuilder::new()
+        .worldbody()
+        .add_body("base", [0.0, 0.0, 2.0])
+        .add_geom("base", "sphere", &[0.05])
+        .add(chain.unwrap())
+        .end()
+        .load()
+        .expect("pendulum chain compiles")
+}
+
+/// Free spheres tumbling onto a plane
+fn falling_spheres() -> MuJoCoSimulation {
+    let mut builder = MjcfBuilder::new()
+        .worldbody()
+        .add_geom("floor", "plane", &[5.0, 5.0, 0.1]);
+    for i in 0..6 {
+        builder = builder
+            .add_body(
+                &format!("ball_{i}"),
+                [0.3 * i as f64, 0.1 * i as f64, 0.5 + 0.2 * i as f64],
+            )
+            .add_freejoint()
+            .add_geom(&format!("ball_{i}"), "sphere", &[0.1])
+            .end();
+    }
+    builder.load().expect("falling spheres compile")
+}
+
+#[test]
+fn pendulum_chain_poses_match_mujoco() {
+    run("pendulum chain", pendulum_chain());
+}
+
+#[test]
+fn falling_sphere_poses_match_mujoco() {
+    run("falling spheres", falling_spheres());
+}
+
+#[test]
+fn tracked_geom_poses_match_mujoco() {
+    let mut app = app(pendulum_chain(), Transform::IDENTITY);
+    app.world_mut()
+        .resource_mut::<MuJoCoPluginSettings>()
+        .track_geoms = true;
+    run_app(app, "pendulum chain, tracked geoms");
+}
+
+#[test]
+fn example_model_poses_match_mujoco() {
+    for path in [
+        "assets/mjcf/simple_1.xml",
+        "assets/mjcf/simple_3.xml",
+        "assets/mjcf/simple_4.xml",
+        "assets/mjcf/fromto.xml",
+    ] {
+        run(
+            path,
+            MuJoCoSimulation::load(path).expect("example model loads"),
+        );
+    }
+}
+
+#[test]
+fn fromto_geoms_lie_along_their_segment() {
+    let path = "assets/mjcf/fromto.xml";
+    let simulation = MuJoCoSimulation::load(path).expect("fromto model loads");
+    let mut app = app(simulation, Transform::IDENTITY);
+    for _ in 0..3 {
+        app.update();
+    }
+
+    let world = app.world_mut();
+    let mut transforms = world.query::<&GlobalTransform>();
+    let world = &*world;
+    let entity = world
+        .resource::<MuJoCoEntityMap>()
+        .geom_by_name("strut")
+        .expect("strut geom spawned");
+    let strut = transforms.get(world, entity).unwrap();
+
+    // fromto="-0.3 -0.2 0 0.3 0.2 0.2" on a static body, the mesh axis is bevy's Y
+    let segment = position_mujoco_2_bevy(&[0.6, 0.4, 0.2]).normalize();
+    let axis = strut.rotation() * Vec3::Y;
+    assert!(
+        axis.dot(segment).abs() > 0.9999,
+        "strut rendered along {axis}, segment along {segment}"
+    );
+    let center = position_mujoco_2_bevy(&[0.0, 0.0, 0.6]);
+    assert!(
+        strut.translation().distance(center) < POSITION_TOLERANCE,
+        "strut rendered at {}, segment center at {center}",
+        strut.translation()
+    );
+}
+
+#[test]
+fn static_fixtures_are_left_out_of_sync() {
+    let path = "assets/mjcf/fromto.xml";
+    let simulation = MuJoCoSimulation::load(path).expect("fromto model loads");
+    let mut app = app(simulation, Transform::IDENTITY);
+    for _ in 0..3 {
+        app.update();
+    }
+
+    let world = app.world();
+    let entity_map = world.resource::<MuJoCoEntityMap>();
+    let is_static = |entity: Option<Entity>| world.get::<MuJoCoStatic>(entity.unwrap()).is_some();
+    assert!(is_static(entity_map.body_by_name("strut")));
+    assert!(is_static(entity_map.geom_by_name("strut")));
+    for (body, geom) in [("arm", "upper_arm"), ("forearm", "forearm"), ("log", "log")] {
+        assert!(!is_static(entity_map.body_by_name(body)), "{body}");
+        assert!(!is_static(entity_map.geom_by_name(geom)), "{geom}");
+    }
+}
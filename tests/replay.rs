@@ -0,0 +1,123 @@
+//! Records a falling sphere headless and checks that the `.mjreplay` text round-trips and plays
+//! back the synced body transforms
+
+use bevy::prelude::*;
+use bevy_mujoco::*;
+
+const FRAMES: usize = 20;
+const STEPS_PER_FRAME: usize = 5;
+const TOLERANCE: f32 = 1e-5;
+/// MuJoCo's default timestep
+const TIMESTEP: f64 = 0.002;
+
+fn falling_sphere() -> MuJoCoSimulation {
+    MjcfBuilder::new()
+        .worldbody()
+        .add_body("ball", [0.0, 0.0, 1.0])
+        .add_freejoint()
+        .add_geom("ball", "sphere", &[0.1])
+        .end()
+        .load()
+        .expect("falling sphere compiles")
+}
+
+/// The recording, the simulation time it ended at and the ball's transform
+fn record() -> (MuJoCoReplay, f64, Transform) {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins((
+            AssetPlugin::default(),
+            TransformPlugin,
+            HierarchyPlugin,
+            bevy::gizmos::GizmoPlugin,
+        ))
+        .init_asset::<Mesh>()
+        .init_asset::<StandardMaterial>()
+        .insert_resource(MuJoCoPluginSettings {
+            spawn_mode: SpawnMode::TransformOnly,
+            step_mode: StepMode::Manual,
+            ..default()
+        })
+        .insert_resource(falling_sphere())
+        .insert_resource(MuJoCoReplayRecorder::new())
+        .add_plugins(MuJoCoPlugin)
+        .add_systems(Update, |mut steps: EventWriter<MuJoCoStepRequest>| {
+            steps.send(MuJoCoStepRequest {
+                n_steps: STEPS_PER_FRAME,
+            });
+        });
+
+    for _ in 0..FRAMES {
+        app.update();
+    }
+    let ball = app
+        .world_mut()
+        .query::<(&Name, &Transform)>()
+        .iter(app.world())
+        .find_map(|(name, transform)| (name.as_str() == "MuJoCo::body_ball").then_some(*transform))
+        .expect("ball entity");
+    (
+        app.world()
+            .resource::<MuJoCoReplayRecorder>()
+            .replay
+            .clone(),
+        app.world().resource::<MuJoCoTime>().elapsed,
+        ball,
+    )
+}
+
+fn ball_index(replay: &MuJoCoReplay) -> usize {
+    replay
+        .body_names
+        .iter()
+        .position(|name| name == "MuJoCo::body_ball")
+        .expect("ball is recorded")
+}
+
+#[test]
+fn replay_records_every_stepped_frame() {
+    let (replay, elapsed, _) = record();
+
+    let ball = ball_index(&replay);
+    // the scene is spawned during the first frames, every later one steps
+    assert!(replay.frames.len() >= FRAMES - 2);
+    assert!(replay.frames.windows(2).all(|frames| {
+        (frames[1].time - frames[0].time - STEPS_PER_FRAME as f64 * TIMESTEP).abs() < 1e-9
+    }));
+    let (first, last) = (&replay.frames[0], &replay.frames[replay.frames.len() - 1]);
+    assert!((last.time - elapsed).abs() < 1e-9);
+    // the sphere falls
+    assert!(last.poses[ball].translation.y < first.poses[ball].translation.y);
+}
+
+#[test]
+fn replay_text_round_trips() {
+    let (replay, _, _) = record();
+    let parsed = MuJoCoReplay::parse(&replay.to_text()).expect("recorded replay parses");
+    assert_eq!(parsed, replay);
+}
+
+#[test]
+fn replay_samples_synced_transforms() {
+    let (replay, _, ball) = record();
+
+    let sampled = replay.sample(replay.duration())[ball_index(&replay)];
+    assert!(sampled.translation.distance(ball.translation) < TOLERANCE);
+    assert!(sampled.rotation.angle_between(ball.rotation) < TOLERANCE);
+
+    // halfway between two frames the sphere is halfway between their positions
+    let (a, b) = (&replay.frames[0], &replay.frames[1]);
+    let index = ball_index(&replay);
+    let halfway = replay.sample((b.time - a.time) / 2.0)[index].translation;
+    let expected = a.poses[index]
+        .translation
+        .lerp(b.poses[index].translation, 0.5);
+    assert!(halfway.distance(expected) < TOLERANCE);
+}
+
+#[test]
+fn replay_rejects_frames_missing_bodies() {
+    let text = "mjreplay 1\nbody MuJoCo::body_ball\nframe 0 0 0 0\n";
+    assert!(MuJoCoReplay::parse(text).is_err());
+    assert!(MuJoCoReplay::parse("not a replay\n").is_err());
+}
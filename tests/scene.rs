@@ -0,0 +1,87 @@
+//! Composes scenes from model files on disk and checks that every model's names, and the
+//! references to them, get the model's prefix
+
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use bevy_mujoco::*;
+
+/// A model whose mesh is named after its file, as MuJoCo does for assets without a `name`
+const MODEL: &str = r#"<mujoco>
+  <asset>
+    <mesh file="tetrahedron.stl"/>
+  </asset>
+  <worldbody>
+    <body name="prop">
+      <geom type="mesh" mesh="tetrahedron"/>
+    </body>
+  </worldbody>
+</mujoco>
+"#;
+
+/// Binary STL of a tetrahedron
+fn tetrahedron_stl() -> Vec<u8> {
+    let vertices = [
+        [0.0f32, 0.0, 0.0],
+        [0.1, 0.0, 0.0],
+        [0.0, 0.1, 0.0],
+        [0.0, 0.0, 0.1],
+    ];
+    let faces = [[0, 2, 1], [0, 1, 3], [0, 3, 2], [1, 2, 3]];
+    let mut stl = vec![0; 80];
+    stl.extend((faces.len() as u32).to_le_bytes());
+    for face in faces {
+        // MuJoCo computes normals itself
+        stl.extend([0; 12]);
+        for vertex in face {
+            for value in vertices[vertex] {
+                stl.extend(value.to_le_bytes());
+            }
+        }
+        stl.extend([0; 2]);
+    }
+    stl
+}
+
+/// A directory with the model and its mesh file
+fn model_directory(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("bevy_mujoco_{name}_{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(directory.join("prop.xml"), MODEL).unwrap();
+    std::fs::write(directory.join("tetrahedron.stl"), tetrahedron_stl()).unwrap();
+    directory
+}
+
+fn scene(directory: &std::path::Path) -> SceneBuilder {
+    let model = directory.join("prop.xml");
+    let model = model.to_str().unwrap();
+    SceneBuilder::new()
+        .add(model, "a/", Transform::IDENTITY)
+        .add(model, "b/", Transform::from_xyz(1.0, 0.0, 0.0))
+}
+
+#[test]
+fn scene_names_file_assets_before_prefixing() {
+    let directory = model_directory("scene_names");
+    let scene = scene(&directory).build(None).expect("scene builds");
+
+    for prefix in ["a/", "b/"] {
+        let mesh = format!("{prefix}tetrahedron");
+        assert!(scene.find("mesh", &mesh).is_some(), "no mesh {mesh}");
+        let body = scene
+            .find("body", &format!("{prefix}prop"))
+            .expect("prefixed body");
+        assert_eq!(body.children[0].attribute("mesh"), Some(mesh.as_str()));
+    }
+}
+
+#[test]
+fn scene_with_file_named_meshes_compiles() {
+    let directory = model_directory("scene_compiles");
+    let path = directory.join("scene.xml");
+    scene(&directory).write(None, &path).expect("scene writes");
+
+    let mujoco = MuJoCoSimulation::load(path.to_str().unwrap()).expect("scene compiles");
+    assert!(mujoco.body_id("a/prop").is_some());
+    assert!(mujoco.body_id("b/prop").is_some());
+}